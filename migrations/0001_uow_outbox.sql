@@ -0,0 +1,10 @@
+-- Messages written in a unit of work and delivered by an OutboxRelay after it commits
+CREATE TABLE IF NOT EXISTS uow_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    topic TEXT NOT NULL,
+    payload BYTEA NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    published_at TIMESTAMPTZ
+);
+
+CREATE INDEX IF NOT EXISTS uow_outbox_unpublished ON uow_outbox (id) WHERE published_at IS NULL;
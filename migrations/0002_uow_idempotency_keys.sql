@@ -0,0 +1,8 @@
+-- Keys claimed by PostgresUnitOfWorkSession::idempotency_guard, kept until they expire
+CREATE TABLE IF NOT EXISTS uow_idempotency_keys (
+    key TEXT PRIMARY KEY,
+    first_seen TIMESTAMPTZ NOT NULL DEFAULT now(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS uow_idempotency_keys_expires_at ON uow_idempotency_keys (expires_at);
@@ -0,0 +1,10 @@
+-- Background jobs written by PostgresUnitOfWorkSession::enqueue_job and taken by claim_jobs
+CREATE TABLE IF NOT EXISTS uow_jobs (
+    id BIGSERIAL PRIMARY KEY,
+    queue TEXT NOT NULL,
+    payload BYTEA NOT NULL,
+    run_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS uow_jobs_due ON uow_jobs (queue, run_at, id);
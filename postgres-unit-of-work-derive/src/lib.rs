@@ -0,0 +1,252 @@
+//! Derive macro for the `TransactionAware` trait of `postgres-unit-of-work`,
+//! and the `#[transactional]` attribute for service methods.
+//!
+//! Use them through the `derive` and `macros` features of
+//! `postgres-unit-of-work` rather than depending on this crate directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::Parser;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, FnArg, Index, ItemFn, LitInt, LitStr, Member, Meta, ReturnType,
+    Token,
+};
+
+/// Implement `TransactionAware` by forwarding every callback to the fields
+/// marked `#[transaction_aware]`, in declaration order.
+///
+/// `after_begin` and `before_commit` stop at the first failing field; the
+/// completion callbacks notify every field and aggregate their errors into
+/// `TransactionError::ObserverErrors`. The struct is idempotent only if all
+/// marked fields are.
+///
+/// ```ignore
+/// #[derive(TransactionAware)]
+/// struct OrderService {
+///     #[transaction_aware]
+///     users: Arc<UserRepository>,
+///     #[transaction_aware]
+///     orders: Arc<OrderRepository>,
+///     pricing: PricingClient,
+/// }
+/// ```
+#[proc_macro_derive(TransactionAware, attributes(transaction_aware))]
+pub fn derive_transaction_aware(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(Error::into_compile_error).into()
+}
+
+/// Run an async `&self` method in a unit of work, committing when it returns
+/// `Ok` and rolling back when it returns `Err` or panics.
+///
+/// The method's type must have a `unit_of_work()` method returning a
+/// `&PostgresUnitOfWork` (or something dereferencing to one, like an `Arc`),
+/// and the body can use the session as `session`. The error type must convert
+/// from `TransactionError` and implement `AsTransactionError`, as for
+/// `PostgresUnitOfWork::run`.
+///
+/// - `isolation = "read_committed" | "repeatable_read" | "serializable"`
+///   begins the session with that isolation level.
+/// - `retry`, or `retry = <attempts>`, retries transient failures like
+///   `PostgresUnitOfWork::run_with_retry` with the default `RetryPolicy`, or
+///   one making at most that many attempts. The body may then run several
+///   times, so it only borrows the method's arguments.
+///
+/// ```ignore
+/// impl TransferService {
+///     fn unit_of_work(&self) -> &PostgresUnitOfWork {
+///         &self.uow
+///     }
+///
+///     #[transactional(isolation = "serializable", retry)]
+///     async fn transfer(&self, from: Uuid, to: Uuid, amount: i64) -> TransactionResult<()> {
+///         let accounts = AccountRepository::new(session.executor().clone());
+///         accounts.withdraw(from, amount).await?;
+///         accounts.deposit(to, amount).await
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn transactional(args: TokenStream, item: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(item as ItemFn);
+    expand_transactional(args.into(), item)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand_transactional(args: TokenStream2, item: ItemFn) -> syn::Result<TokenStream2> {
+    let krate = quote!(::postgres_unit_of_work);
+    let mut isolation = None;
+    let mut retry = None;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("isolation") {
+            let level: LitStr = meta.value()?.parse()?;
+            let variant = match level.value().as_str() {
+                "read_committed" => quote!(ReadCommitted),
+                "repeatable_read" => quote!(RepeatableRead),
+                "serializable" => quote!(Serializable),
+                _ => {
+                    return Err(Error::new_spanned(
+                        level,
+                        "unknown isolation level; expected \"read_committed\", \"repeatable_read\" or \"serializable\"",
+                    ))
+                }
+            };
+            isolation = Some(quote!(.isolation_level(#krate::IsolationLevel::#variant)));
+            Ok(())
+        } else if meta.path.is_ident("retry") {
+            retry = Some(if meta.input.peek(Token![=]) {
+                let attempts: LitInt = meta.value()?.parse()?;
+                quote!(#krate::RetryPolicy::new(#attempts))
+            } else {
+                quote!(#krate::RetryPolicy::default())
+            });
+            Ok(())
+        } else {
+            Err(meta.error("unknown `#[transactional]` argument; expected `isolation` or `retry`"))
+        }
+    });
+    parser.parse2(args)?;
+
+    let ItemFn { attrs, vis, sig, block } = item;
+    if sig.asyncness.is_none() {
+        return Err(Error::new_spanned(sig.fn_token, "`#[transactional]` can only be used on async fns"));
+    }
+    if !matches!(sig.inputs.first(), Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_none())
+    {
+        return Err(Error::new_spanned(&sig.ident, "`#[transactional]` methods must take `&self`"));
+    }
+    let ReturnType::Type(_, output) = &sig.output else {
+        return Err(Error::new_spanned(&sig.ident, "`#[transactional]` methods must return a `Result`"));
+    };
+
+    let options = quote!(#krate::TransactionOptions::new() #isolation);
+    let run = match retry {
+        Some(policy) => quote! {
+            #krate::__private::transactional_with_retry(&*self.unit_of_work(), #options, #policy, |session, _| {
+                ::std::boxed::Box::pin(async {
+                    let output: #output = #block;
+                    output
+                })
+            })
+        },
+        None => quote! {
+            #krate::__private::transactional(&*self.unit_of_work(), #options, |session, _| {
+                ::std::boxed::Box::pin(async move {
+                    let output: #output = #block;
+                    output
+                })
+            })
+        },
+    };
+    Ok(quote! {
+        #(#attrs)*
+        #vis #sig {
+            #run.await
+        }
+    })
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let members = marked_members(input)?;
+    let name = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let krate = quote!(::postgres_unit_of_work);
+    let members = quote! {
+        [#(&self.#members as &dyn #krate::TransactionAware),*]
+    };
+
+    Ok(quote! {
+        #[#krate::__private::async_trait]
+        impl #impl_generics #krate::TransactionAware for #name #type_generics #where_clause {
+            async fn after_begin(&self, executor: &#krate::Executor) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_until_error(&#members, |member| member.after_begin(executor)).await
+            }
+
+            async fn before_commit(&self, executor: &#krate::Executor) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_until_error(&#members, |member| member.before_commit(executor)).await
+            }
+
+            async fn on_commit(&self) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_to_all(&#members, |member| member.on_commit()).await
+            }
+
+            async fn on_rollback(&self) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_to_all(&#members, |member| member.on_rollback()).await
+            }
+
+            async fn on_rollback_failure(
+                &self,
+                error: &#krate::TransactionError,
+            ) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_to_all(&#members, |member| member.on_rollback_failure(error)).await
+            }
+
+            async fn on_commit_with(
+                &self,
+                context: &#krate::TransactionContext,
+            ) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_to_all(&#members, |member| member.on_commit_with(context)).await
+            }
+
+            async fn on_rollback_with(
+                &self,
+                context: &#krate::TransactionContext,
+            ) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_to_all(&#members, |member| member.on_rollback_with(context)).await
+            }
+
+            async fn on_rollback_failure_with(
+                &self,
+                context: &#krate::TransactionContext,
+                error: &#krate::TransactionError,
+            ) -> #krate::TransactionResult<()> {
+                #krate::__private::forward_to_all(&#members, |member| {
+                    member.on_rollback_failure_with(context, error)
+                })
+                .await
+            }
+
+            fn is_idempotent(&self) -> bool {
+                #members.iter().all(|member| member.is_idempotent())
+            }
+        }
+    })
+}
+
+/// The fields marked `#[transaction_aware]`, in declaration order.
+fn marked_members(input: &DeriveInput) -> syn::Result<Vec<Member>> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "TransactionAware can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut members = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let Some(attr) = field.attrs.iter().find(|attr| attr.path().is_ident("transaction_aware")) else {
+            continue;
+        };
+        if !matches!(attr.meta, Meta::Path(_)) {
+            return Err(Error::new_spanned(attr, "`#[transaction_aware]` takes no arguments"));
+        }
+        members.push(match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(Index::from(index)),
+        });
+    }
+
+    if members.is_empty() {
+        let message = "no fields are marked `#[transaction_aware]`; mark the fields to forward callbacks to";
+        return Err(match fields {
+            Fields::Unit => Error::new_spanned(&input.ident, message),
+            _ => Error::new_spanned(fields, message),
+        });
+    }
+    Ok(members)
+}
\ No newline at end of file
@@ -0,0 +1,119 @@
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{TransactionAware, TransactionError, TransactionResult};
+use std::sync::Arc;
+
+type CallLog = Arc<Mutex<Vec<String>>>;
+
+struct Recorder {
+    name: &'static str,
+    log: CallLog,
+    fail: bool,
+}
+
+impl Recorder {
+    fn new(name: &'static str, log: &CallLog) -> Self {
+        Self { name, log: log.clone(), fail: false }
+    }
+
+    fn failing(name: &'static str, log: &CallLog) -> Self {
+        Self { name, log: log.clone(), fail: true }
+    }
+
+    fn record(&self, event: &str) -> TransactionResult<()> {
+        self.log.lock().push(format!("{}:{}", self.name, event));
+        if self.fail {
+            return Err(TransactionError::CommitFailed(format!("{} failed", self.name)));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransactionAware for Recorder {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.record("commit")
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.record("rollback")
+    }
+
+    fn name(&self) -> &str {
+        self.name
+    }
+}
+
+#[derive(TransactionAware)]
+struct Service {
+    #[transaction_aware]
+    users: Recorder,
+    #[allow(dead_code)]
+    client: String,
+    #[transaction_aware]
+    orders: Arc<Recorder>,
+    #[transaction_aware]
+    audit: Recorder,
+}
+
+#[derive(TransactionAware)]
+struct Pair(#[transaction_aware] Recorder, #[transaction_aware] Recorder);
+
+fn service(log: &CallLog, failing_orders: bool) -> Service {
+    let orders = if failing_orders {
+        Recorder::failing("orders", log)
+    } else {
+        Recorder::new("orders", log)
+    };
+    Service {
+        users: Recorder::new("users", log),
+        client: "pricing".to_string(),
+        orders: Arc::new(orders),
+        audit: Recorder::failing("audit", log),
+    }
+}
+
+#[tokio::test]
+async fn test_derive_forwards_in_declaration_order() {
+    let log: CallLog = Arc::default();
+    let service = service(&log, false);
+
+    let _ = service.on_rollback().await;
+    let pair = Pair(Recorder::new("first", &log), Recorder::new("second", &log));
+    pair.on_commit().await.expect("Pair should forward successfully");
+
+    assert_eq!(
+        *log.lock(),
+        vec!["users:rollback", "orders:rollback", "audit:rollback", "first:commit", "second:commit"]
+    );
+}
+
+#[tokio::test]
+async fn test_derive_aggregates_errors() {
+    let log: CallLog = Arc::default();
+    let service = service(&log, true);
+
+    let error = service.on_commit().await.expect_err("Failing fields should be reported");
+
+    assert_eq!(*log.lock(), vec!["users:commit", "orders:commit", "audit:commit"]);
+    match error {
+        TransactionError::ObserverErrors(errors) => {
+            let observers: Vec<_> = errors
+                .iter()
+                .map(|error| match error {
+                    TransactionError::ObserverFailed { observer, .. } => observer.as_str(),
+                    other => panic!("Expected ObserverFailed, got {other:?}"),
+                })
+                .collect();
+            assert_eq!(observers, vec!["orders", "audit"]);
+        }
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+    assert!(!service.is_idempotent());
+}
+
+#[test]
+fn test_derive_misuse_fails_to_compile() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
\ No newline at end of file
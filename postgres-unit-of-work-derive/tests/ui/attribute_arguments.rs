@@ -0,0 +1,10 @@
+use postgres_unit_of_work::TransactionAware;
+use std::sync::Arc;
+
+#[derive(TransactionAware)]
+struct Service {
+    #[transaction_aware(skip)]
+    inner: Arc<dyn TransactionAware>,
+}
+
+fn main() {}
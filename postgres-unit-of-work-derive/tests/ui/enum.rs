@@ -0,0 +1,8 @@
+use postgres_unit_of_work::TransactionAware;
+
+#[derive(TransactionAware)]
+enum Observers {
+    First,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use postgres_unit_of_work::TransactionAware;
+
+#[derive(TransactionAware)]
+struct Service {
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use postgres_unit_of_work::{transactional, PostgresUnitOfWork};
+
+struct Service {
+    uow: PostgresUnitOfWork,
+}
+
+impl Service {
+    fn unit_of_work(&self) -> &PostgresUnitOfWork {
+        &self.uow
+    }
+
+    #[transactional(timeout = 5)]
+    async fn create(&self) -> postgres_unit_of_work::TransactionResult<()> {
+        Ok(())
+    }
+
+    #[transactional(isolation = "snapshot")]
+    async fn update(&self) -> postgres_unit_of_work::TransactionResult<()> {
+        Ok(())
+    }
+}
+
+fn main() {}
\ No newline at end of file
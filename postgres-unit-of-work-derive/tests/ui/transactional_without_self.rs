@@ -0,0 +1,19 @@
+use postgres_unit_of_work::{transactional, PostgresUnitOfWork};
+
+struct Service {
+    uow: PostgresUnitOfWork,
+}
+
+impl Service {
+    fn unit_of_work(&self) -> &PostgresUnitOfWork {
+        &self.uow
+    }
+
+    #[transactional]
+    async fn create(service: &Service) -> postgres_unit_of_work::TransactionResult<()> {
+        let _ = service.unit_of_work();
+        Ok(())
+    }
+}
+
+fn main() {}
\ No newline at end of file
@@ -0,0 +1,90 @@
+use sqlx::postgres::PgArguments;
+use sqlx::query::Query;
+use sqlx::{Encode, Postgres, Type};
+
+/// The parameters of one run of the statement given to `Executor::execute_many`,
+/// or one row given to `Executor::bulk_insert`.
+///
+/// Implemented for tuples of up to eight values, bound to `$1`, `$2`, ... in
+/// order. Implement it for a record type to bind its fields.
+pub trait BindRow<'q> {
+    /// Binds the row's values to `query`.
+    fn bind_to(self, query: Query<'q, Postgres, PgArguments>) -> Query<'q, Postgres, PgArguments>;
+}
+
+macro_rules! bind_row {
+    ($(($($value:ident),+);)*) => {
+        $(
+            impl<'q, $($value),+> BindRow<'q> for ($($value,)+)
+            where
+                $($value: 'q + Encode<'q, Postgres> + Type<Postgres>,)+
+            {
+                #[allow(non_snake_case)]
+                fn bind_to(self, query: Query<'q, Postgres, PgArguments>) -> Query<'q, Postgres, PgArguments> {
+                    let ($($value,)+) = self;
+                    query$(.bind($value))+
+                }
+            }
+        )*
+    };
+}
+
+bind_row! {
+    (A);
+    (A, B);
+    (A, B, C);
+    (A, B, C, D);
+    (A, B, C, D, E);
+    (A, B, C, D, E, F);
+    (A, B, C, D, E, F, G);
+    (A, B, C, D, E, F, G, H);
+}
+
+/// Most bind parameters one statement may have in the PostgreSQL protocol.
+pub(crate) const MAX_BIND_PARAMETERS: usize = u16::MAX as usize;
+
+/// Options of `Executor::bulk_insert_with`.
+#[derive(Clone, Debug, Default)]
+pub struct BulkInsertOptions {
+    pub(crate) on_conflict_do_nothing: bool,
+}
+
+impl BulkInsertOptions {
+    /// Create options failing the insert on a conflicting row.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Skip rows conflicting with existing ones, with `ON CONFLICT DO
+    /// NOTHING`, instead of failing; they are not counted as inserted.
+    pub fn on_conflict_do_nothing(mut self) -> Self {
+        self.on_conflict_do_nothing = true;
+        self
+    }
+}
+
+/// An `INSERT` of `rows` rows into `columns` of `table`, as one multi-row
+/// `VALUES` list with the parameters numbered row by row.
+///
+/// `table` and `columns` are put into the SQL unquoted, so they must be
+/// trusted identifiers, as `Executor::bulk_insert` documents.
+pub(crate) fn bulk_insert_sql(table: &str, columns: &[&str], rows: usize, options: &BulkInsertOptions) -> String {
+    let mut sql = format!("INSERT INTO {table} ({}) VALUES ", columns.join(", "));
+    for row in 0..rows {
+        if row > 0 {
+            sql.push_str(", ");
+        }
+        sql.push('(');
+        for column in 0..columns.len() {
+            if column > 0 {
+                sql.push_str(", ");
+            }
+            sql.push_str(&format!("${}", row * columns.len() + column + 1));
+        }
+        sql.push(')');
+    }
+    if options.on_conflict_do_nothing {
+        sql.push_str(" ON CONFLICT DO NOTHING");
+    }
+    sql
+}
\ No newline at end of file
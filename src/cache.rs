@@ -0,0 +1,226 @@
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+use std::sync::Arc;
+
+use crate::{TransactionAware, TransactionResult};
+
+/// A cache shared across transactions, such as an in-process map or a client
+/// of an external cache, that a `TxCache` promotes committed entries to.
+///
+/// `RwLock<HashMap<K, V>>` is one.
+#[async_trait]
+pub trait SharedCache<K, V>: Send + Sync {
+    /// The cached value of `key`, if any.
+    async fn get(&self, key: &K) -> Option<V>;
+
+    /// Cache `value` under `key`, replacing any previous value.
+    async fn put(&self, key: K, value: V);
+
+    /// Forget the value cached under `key`, if any.
+    async fn remove(&self, key: &K);
+}
+
+#[async_trait]
+impl<K, V> SharedCache<K, V> for RwLock<HashMap<K, V>>
+where
+    K: Eq + Hash + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    async fn get(&self, key: &K) -> Option<V> {
+        self.read().get(key).cloned()
+    }
+
+    async fn put(&self, key: K, value: V) {
+        self.write().insert(key, value);
+    }
+
+    async fn remove(&self, key: &K) {
+        self.write().remove(key);
+    }
+}
+
+/// A read-your-writes cache for one transaction, in front of a `SharedCache`.
+///
+/// `put` and `remove` only change an overlay private to the transaction, which
+/// `get` consults before the shared cache, so other transactions never see
+/// values that may still be rolled back. Register the cache with the session
+/// as an observer: on commit the overlay is applied to the shared cache,
+/// removals included, and on rollback it is dropped.
+pub struct TxCache<K, V> {
+    shared: Arc<dyn SharedCache<K, V>>,
+    /// Values written in the transaction; `None` marks a removal.
+    overlay: Mutex<HashMap<K, Option<V>>>,
+}
+
+impl<K, V> TxCache<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    /// Create a cache for one transaction in front of `shared`.
+    pub fn new(shared: Arc<dyn SharedCache<K, V>>) -> Arc<Self> {
+        Arc::new(Self {
+            shared,
+            overlay: Mutex::default(),
+        })
+    }
+
+    /// The value of `key` as the transaction sees it: written or removed in
+    /// the transaction, or else from the shared cache.
+    pub async fn get(&self, key: &K) -> Option<V> {
+        if let Some(entry) = self.overlay.lock().get(key) {
+            return entry.clone();
+        }
+        self.shared.get(key).await
+    }
+
+    /// Cache `value` under `key` for the transaction, and for everyone once it
+    /// commits.
+    pub fn put(&self, key: K, value: V) {
+        self.overlay.lock().insert(key, Some(value));
+    }
+
+    /// Hide the value of `key` from the transaction, and remove it from the
+    /// shared cache once the transaction commits.
+    pub fn remove(&self, key: K) {
+        self.overlay.lock().insert(key, None);
+    }
+}
+
+#[async_trait]
+impl<K, V> TransactionAware for TxCache<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    async fn on_commit(&self) -> TransactionResult<()> {
+        let overlay = std::mem::take(&mut *self.overlay.lock());
+        for (key, entry) in overlay {
+            match entry {
+                Some(value) => self.shared.put(key, value).await,
+                None => self.shared.remove(&key).await,
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.overlay.lock().clear();
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "transaction cache"
+    }
+
+    /// Applying the same entries again leaves the shared cache as it was.
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
+
+/// Keys marked at most by default by an `InvalidationObserver` before it
+/// asks for everything to be invalidated.
+const DEFAULT_MAX_KEYS: usize = 1000;
+
+/// What an `InvalidationObserver` asks its `Invalidator` to invalidate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Invalidation {
+    /// The keys marked in the transaction, each once, in sorted order.
+    Keys(Vec<String>),
+    /// Everything, as more keys were marked than the observer collects.
+    All,
+}
+
+/// Invalidates entries of a cache kept outside of the database, e.g. by
+/// deleting them from Redis or publishing them to the other instances.
+#[async_trait]
+pub trait Invalidator: Send + Sync {
+    /// Invalidate the entries of one committed transaction.
+    async fn invalidate(&self, invalidation: Invalidation) -> TransactionResult<()>;
+}
+
+/// Observer collecting the cache keys a transaction touched, to invalidate
+/// them all at once after it commits; a lighter alternative to `TxCache` for
+/// caches that are filled on read.
+///
+/// Repositories `mark` keys as they write. On commit the `Invalidator` is
+/// called once with the marked keys, if there are any; on rollback they are
+/// forgotten. Past `max_keys` the observer stops collecting and asks for
+/// everything to be invalidated instead.
+pub struct InvalidationObserver {
+    invalidator: Arc<dyn Invalidator>,
+    max_keys: usize,
+    keys: Mutex<Marked>,
+}
+
+#[derive(Default)]
+struct Marked {
+    keys: BTreeSet<String>,
+    overflowed: bool,
+}
+
+impl InvalidationObserver {
+    /// Create an observer handing the keys marked in its transaction to
+    /// `invalidator` after commit.
+    pub fn new(invalidator: Arc<dyn Invalidator>) -> Self {
+        Self {
+            invalidator,
+            max_keys: DEFAULT_MAX_KEYS,
+            keys: Mutex::default(),
+        }
+    }
+
+    /// Most distinct keys collected, 1000 by default; marking more turns the
+    /// invalidation into `Invalidation::All`.
+    pub fn max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys;
+        self
+    }
+
+    /// Invalidate `key` once the transaction commits; marking a key again
+    /// changes nothing.
+    pub fn mark(&self, key: impl Into<String>) {
+        let mut marked = self.keys.lock();
+        if marked.overflowed {
+            return;
+        }
+        marked.keys.insert(key.into());
+        if marked.keys.len() > self.max_keys {
+            tracing::debug!(max_keys = self.max_keys, "Too many cache keys marked, invalidating everything instead");
+            marked.keys.clear();
+            marked.overflowed = true;
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionAware for InvalidationObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        let marked = std::mem::take(&mut *self.keys.lock());
+        let invalidation = if marked.overflowed {
+            Invalidation::All
+        } else if marked.keys.is_empty() {
+            return Ok(());
+        } else {
+            Invalidation::Keys(marked.keys.into_iter().collect())
+        };
+        self.invalidator.invalidate(invalidation).await
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        *self.keys.lock() = Marked::default();
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "cache invalidation"
+    }
+
+    /// Invalidating the same entries again is harmless.
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
\ No newline at end of file
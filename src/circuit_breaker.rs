@@ -0,0 +1,189 @@
+use parking_lot::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{TransactionError, TransactionResult};
+
+/// Where a unit of work's circuit breaker is; see
+/// `PostgresUnitOfWorkBuilder::circuit_breaker`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Sessions begin as usual.
+    Closed,
+    /// Too many consecutive connection failures: `begin()` fails right away
+    /// with `TransactionError::CircuitOpen` until the cool-down has passed.
+    Open,
+    /// The cool-down has passed and one `begin()` is let through to probe
+    /// the database; the others still fail right away.
+    HalfOpen,
+}
+
+/// When a circuit breaker opens and for how long.
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    failure_threshold: u32,
+    cool_down: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cool_down: Duration::from_secs(30),
+        }
+    }
+}
+
+impl CircuitBreakerConfig {
+    /// Create a config opening after 5 consecutive failures for 30 seconds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Consecutive begin or commit failures classified as connection errors
+    /// after which the breaker opens.
+    pub fn failure_threshold(mut self, failures: u32) -> Self {
+        self.failure_threshold = failures.max(1);
+        self
+    }
+
+    /// How long the breaker stays open before it lets a probe through.
+    pub fn cool_down(mut self, cool_down: Duration) -> Self {
+        self.cool_down = cool_down;
+        self
+    }
+}
+
+/// A change of `CircuitState`, to be reported to the listeners.
+pub(crate) type Transition = (CircuitState, CircuitState);
+
+/// Counts the connection failures of a unit of work's begins and commits and
+/// fails begins fast while the database looks unreachable.
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Instant,
+    /// Whether the half-open probe was let through and has not finished.
+    probing: bool,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(Inner {
+                state: CircuitState::Closed,
+                consecutive_failures: 0,
+                opened_at: Instant::now(),
+                probing: false,
+            }),
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        self.inner.lock().state
+    }
+
+    /// Lets a begin through, or fails it with `CircuitOpen`. Returns the
+    /// permit to report the begin's outcome with, and the transition to
+    /// half-open if this begin is the probe.
+    pub(crate) fn admit(&self) -> TransactionResult<(Permit<'_>, Option<Transition>)> {
+        let mut inner = self.inner.lock();
+        let mut transition = None;
+        match inner.state {
+            CircuitState::Closed => {}
+            CircuitState::Open => {
+                let retry_after = self.config.cool_down.saturating_sub(inner.opened_at.elapsed());
+                if !retry_after.is_zero() {
+                    return Err(TransactionError::CircuitOpen { retry_after });
+                }
+                inner.state = CircuitState::HalfOpen;
+                inner.probing = true;
+                transition = Some((CircuitState::Open, CircuitState::HalfOpen));
+            }
+            CircuitState::HalfOpen if inner.probing => {
+                return Err(TransactionError::CircuitOpen {
+                    retry_after: Duration::ZERO,
+                });
+            }
+            CircuitState::HalfOpen => inner.probing = true,
+        }
+        let probe = inner.state == CircuitState::HalfOpen;
+        Ok((Permit { breaker: self, probe }, transition))
+    }
+
+    /// Count the outcome of a begin or commit, returning the state change it
+    /// caused, if any.
+    ///
+    /// Errors other than connection errors say nothing about the database's
+    /// reachability and count neither way.
+    pub(crate) fn record<T>(&self, result: &TransactionResult<T>) -> Option<Transition> {
+        match result {
+            Ok(_) => self.record_success(),
+            Err(error) if error.is_connection_error() => self.record_failure(),
+            Err(_) => None,
+        }
+    }
+
+    fn record_success(&self) -> Option<Transition> {
+        let mut inner = self.inner.lock();
+        inner.consecutive_failures = 0;
+        let from = std::mem::replace(&mut inner.state, CircuitState::Closed);
+        if from == CircuitState::Closed {
+            return None;
+        }
+        tracing::info!("Database reachable again, closing the circuit breaker");
+        Some((from, CircuitState::Closed))
+    }
+
+    fn record_failure(&self) -> Option<Transition> {
+        let mut inner = self.inner.lock();
+        inner.consecutive_failures = inner.consecutive_failures.saturating_add(1);
+        let opens = match inner.state {
+            CircuitState::Closed => inner.consecutive_failures >= self.config.failure_threshold,
+            CircuitState::HalfOpen => true,
+            CircuitState::Open => false,
+        };
+        if !opens {
+            return None;
+        }
+        let from = std::mem::replace(&mut inner.state, CircuitState::Open);
+        inner.opened_at = Instant::now();
+        tracing::warn!(
+            failures = inner.consecutive_failures,
+            cool_down = ?self.config.cool_down,
+            "Opening the circuit breaker after repeated connection failures"
+        );
+        Some((from, CircuitState::Open))
+    }
+}
+
+/// A begin let through by the circuit breaker.
+///
+/// A probe dropped without reporting, e.g. because the begin was cancelled,
+/// lets the next begin probe instead.
+pub(crate) struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+}
+
+impl Permit<'_> {
+    /// Count the begin's outcome; see `CircuitBreaker::record`.
+    pub(crate) fn finish<T>(self, result: &TransactionResult<T>) -> Option<Transition> {
+        self.breaker.record(result)
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if self.probe {
+            self.breaker.inner.lock().probing = false;
+        }
+    }
+}
\ No newline at end of file
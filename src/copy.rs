@@ -0,0 +1,273 @@
+use bytes::{BufMut, Bytes, BytesMut};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, BoxFuture, Either};
+use futures::SinkExt;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use uuid::Uuid;
+
+use crate::{TransactionError, TransactionResult};
+
+/// Size of the chunks `CopyInSink::read_from` reads its source in.
+const READ_CHUNK_SIZE: usize = 64 * 1024;
+
+/// An in-flight `COPY ... FROM STDIN`, started by `Executor::copy_in`.
+///
+/// The Executor stays locked until the sink is finished or dropped. Dropping
+/// it without calling `finish` aborts the copy, which fails the transaction.
+pub struct CopyInSink<'a> {
+    chunks: mpsc::Sender<Bytes>,
+    /// Forwards `chunks` to the server; None once it failed.
+    copy: Option<BoxFuture<'a, TransactionResult<u64>>>,
+}
+
+impl<'a> CopyInSink<'a> {
+    /// Waits for the server to accept the COPY statement driven by `copy`.
+    pub(crate) async fn start(
+        chunks: mpsc::Sender<Bytes>,
+        mut copy: BoxFuture<'a, TransactionResult<u64>>,
+        started: oneshot::Receiver<()>,
+    ) -> TransactionResult<Self> {
+        match future::select(started, &mut copy).await {
+            Either::Left((Ok(()), _)) => Ok(Self { chunks, copy: Some(copy) }),
+            Either::Left((Err(_), _)) => Err(failure(copy.await)),
+            Either::Right((result, _)) => Err(failure(result)),
+        }
+    }
+
+    /// Sends a chunk of data in the format named by the COPY statement.
+    ///
+    /// Chunks need not align with rows.
+    pub async fn send(&mut self, data: impl Into<Bytes>) -> TransactionResult<()> {
+        let copy = self.copy.as_mut().ok_or_else(already_failed)?;
+        let error = match future::select(self.chunks.send(data.into()), copy).await {
+            Either::Left((Ok(()), _)) => return Ok(()),
+            Either::Left((Err(_), copy)) => failure(copy.await),
+            Either::Right((result, _)) => failure(result),
+        };
+        self.copy = None;
+        Err(error)
+    }
+
+    /// Sends everything `source` yields until it reaches end of file.
+    pub async fn read_from(&mut self, mut source: impl AsyncRead + Unpin) -> TransactionResult<()> {
+        loop {
+            let mut chunk = BytesMut::with_capacity(READ_CHUNK_SIZE);
+            let read = source
+                .read_buf(&mut chunk)
+                .await
+                .map_err(|error| TransactionError::DatabaseError(sqlx::Error::Io(error)))?;
+            if read == 0 {
+                return Ok(());
+            }
+            self.send(chunk.freeze()).await?;
+        }
+    }
+
+    /// Completes the copy and returns the number of rows copied.
+    pub async fn finish(mut self) -> TransactionResult<u64> {
+        self.chunks.close_channel();
+        match self.copy.take() {
+            Some(copy) => copy.await,
+            None => Err(already_failed()),
+        }
+    }
+}
+
+/// The error of a copy that ended before all of its data was sent.
+fn failure(result: TransactionResult<u64>) -> TransactionError {
+    result.err().unwrap_or_else(already_failed)
+}
+
+fn already_failed() -> TransactionError {
+    TransactionError::DatabaseError(sqlx::Error::Protocol("COPY FROM STDIN has already failed".to_string()))
+}
+
+/// Microseconds between the Unix epoch and the PostgreSQL epoch, 2000-01-01.
+const PG_EPOCH_OFFSET_MICROS: i64 = 946_684_800_000_000;
+
+/// Size at which `BinaryCopyWriter` sends its buffered rows.
+const BINARY_FLUSH_SIZE: usize = 64 * 1024;
+
+/// PostgreSQL column types supported by `BinaryCopyWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyType {
+    Int2,
+    Int4,
+    Int8,
+    Float4,
+    Float8,
+    Bool,
+    Text,
+    Uuid,
+    Timestamp,
+    Timestamptz,
+}
+
+impl CopyType {
+    /// The PostgreSQL name of the type.
+    pub fn name(self) -> &'static str {
+        match self {
+            CopyType::Int2 => "int2",
+            CopyType::Int4 => "int4",
+            CopyType::Int8 => "int8",
+            CopyType::Float4 => "float4",
+            CopyType::Float8 => "float8",
+            CopyType::Bool => "bool",
+            CopyType::Text => "text",
+            CopyType::Uuid => "uuid",
+            CopyType::Timestamp => "timestamp",
+            CopyType::Timestamptz => "timestamptz",
+        }
+    }
+}
+
+impl std::fmt::Display for CopyType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A Rust value that can be written to a binary COPY column.
+///
+/// `Option<T>` writes None as NULL.
+pub trait CopyValue: Send + Sync {
+    /// The column type this value encodes to.
+    fn copy_type(&self) -> CopyType;
+
+    /// Appends the binary encoding of the value, or returns false for NULL.
+    fn encode(&self, buf: &mut BytesMut) -> bool;
+}
+
+macro_rules! copy_value {
+    ($($ty:ty => $copy_type:ident, |$value:ident, $buf:ident| $encode:expr;)*) => {
+        $(
+            impl CopyValue for $ty {
+                fn copy_type(&self) -> CopyType {
+                    CopyType::$copy_type
+                }
+
+                fn encode(&self, $buf: &mut BytesMut) -> bool {
+                    let $value = self;
+                    $encode;
+                    true
+                }
+            }
+        )*
+    };
+}
+
+copy_value! {
+    i16 => Int2, |value, buf| buf.put_i16(*value);
+    i32 => Int4, |value, buf| buf.put_i32(*value);
+    i64 => Int8, |value, buf| buf.put_i64(*value);
+    f32 => Float4, |value, buf| buf.put_f32(*value);
+    f64 => Float8, |value, buf| buf.put_f64(*value);
+    bool => Bool, |value, buf| buf.put_u8(u8::from(*value));
+    str => Text, |value, buf| buf.put_slice(value.as_bytes());
+    String => Text, |value, buf| buf.put_slice(value.as_bytes());
+    Uuid => Uuid, |value, buf| buf.put_slice(value.as_bytes());
+    NaiveDateTime => Timestamp, |value, buf| buf.put_i64(pg_micros(value.and_utc()));
+    DateTime<Utc> => Timestamptz, |value, buf| buf.put_i64(pg_micros(*value));
+}
+
+impl<T: CopyValue + ?Sized> CopyValue for &T {
+    fn copy_type(&self) -> CopyType {
+        (**self).copy_type()
+    }
+
+    fn encode(&self, buf: &mut BytesMut) -> bool {
+        (**self).encode(buf)
+    }
+}
+
+impl<T: CopyValue + Default> CopyValue for Option<T> {
+    fn copy_type(&self) -> CopyType {
+        match self {
+            Some(value) => value.copy_type(),
+            None => T::default().copy_type(),
+        }
+    }
+
+    fn encode(&self, buf: &mut BytesMut) -> bool {
+        self.as_ref().is_some_and(|value| value.encode(buf))
+    }
+}
+
+/// Microseconds since the PostgreSQL epoch.
+fn pg_micros(value: DateTime<Utc>) -> i64 {
+    value.timestamp_micros() - PG_EPOCH_OFFSET_MICROS
+}
+
+/// Encodes rows in the PostgreSQL binary COPY format onto a `CopyInSink`.
+///
+/// The statement passed to `Executor::copy_in` must use `WITH (FORMAT binary)`
+/// and list the columns in the order given to `new`. Rows whose values do not
+/// match the declared column types are rejected before anything is sent.
+pub struct BinaryCopyWriter<'a> {
+    sink: CopyInSink<'a>,
+    columns: Vec<CopyType>,
+    buf: BytesMut,
+}
+
+impl<'a> BinaryCopyWriter<'a> {
+    /// Starts a binary copy of rows with the given column types.
+    pub fn new(sink: CopyInSink<'a>, columns: &[CopyType]) -> Self {
+        let mut buf = BytesMut::with_capacity(BINARY_FLUSH_SIZE);
+        buf.put_slice(b"PGCOPY\n\xff\r\n\0");
+        // Flags and header extension length
+        buf.put_i32(0);
+        buf.put_i32(0);
+        Self {
+            sink,
+            columns: columns.to_vec(),
+            buf,
+        }
+    }
+
+    /// Encodes one row, sending the buffered rows once enough have accumulated.
+    pub async fn write_row(&mut self, row: &[&dyn CopyValue]) -> TransactionResult<()> {
+        if row.len() != self.columns.len() {
+            return Err(TransactionError::InvalidCopyRow(format!(
+                "expected {} values, got {}",
+                self.columns.len(),
+                row.len()
+            )));
+        }
+        for (index, (value, column)) in row.iter().zip(&self.columns).enumerate() {
+            if value.copy_type() != *column {
+                return Err(TransactionError::InvalidCopyRow(format!(
+                    "column {index} is {column} but the value is {}",
+                    value.copy_type()
+                )));
+            }
+        }
+
+        let start = self.buf.len();
+        self.buf.put_i16(row.len() as i16);
+        for value in row {
+            let length_at = self.buf.len();
+            self.buf.put_i32(-1);
+            if value.encode(&mut self.buf) {
+                let length = self.buf.len() - length_at - 4;
+                let Ok(length) = i32::try_from(length) else {
+                    self.buf.truncate(start);
+                    return Err(TransactionError::InvalidCopyRow(format!("value of {length} bytes is too large")));
+                };
+                self.buf[length_at..length_at + 4].copy_from_slice(&length.to_be_bytes());
+            }
+        }
+
+        if self.buf.len() >= BINARY_FLUSH_SIZE {
+            self.sink.send(self.buf.split().freeze()).await?;
+        }
+        Ok(())
+    }
+
+    /// Writes the trailer, completes the copy and returns the number of rows copied.
+    pub async fn finish(mut self) -> TransactionResult<u64> {
+        self.buf.put_i16(-1);
+        self.sink.send(self.buf.split().freeze()).await?;
+        self.sink.finish().await
+    }
+}
\ No newline at end of file
@@ -0,0 +1,58 @@
+use sqlx::postgres::PgRow;
+
+use crate::{Executor, TransactionResult};
+
+/// A server-side cursor declared by `Executor::cursor`, for reading a large
+/// result in batches.
+///
+/// The cursor lives in the Executor's transaction and is gone once it commits
+/// or rolls back; dropping it without calling `close` leaves it open until
+/// then. The transaction is only locked while a batch is being fetched, so
+/// other statements can run between batches.
+#[derive(Debug)]
+pub struct Cursor {
+    executor: Executor,
+    name: String,
+    exhausted: bool,
+}
+
+impl Cursor {
+    pub(crate) fn new(executor: Executor, name: String) -> Self {
+        Self {
+            executor,
+            name,
+            exhausted: false,
+        }
+    }
+
+    /// Name the cursor was declared with, for use in hand-written SQL such as
+    /// `UPDATE ... WHERE CURRENT OF`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Fetches the next `count` rows, or fewer once the result runs out.
+    ///
+    /// An exhausted cursor returns no rows without asking the server again.
+    pub async fn fetch(&mut self, count: u32) -> TransactionResult<Vec<PgRow>> {
+        if self.exhausted {
+            return Ok(Vec::new());
+        }
+        let sql = format!("FETCH FORWARD {count} FROM {}", self.name);
+        let rows = self.executor.fetch_all(sqlx::query(&sql).persistent(false)).await?;
+        self.exhausted = rows.len() < count as usize;
+        Ok(rows)
+    }
+
+    /// Whether a fetch has returned fewer rows than asked for.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+
+    /// Closes the cursor, releasing its resources on the server.
+    pub async fn close(self) -> TransactionResult<()> {
+        let sql = format!("CLOSE {}", self.name);
+        self.executor.execute(sqlx::query(&sql).persistent(false)).await?;
+        Ok(())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,168 @@
+use sqlx::postgres::PgDatabaseError;
+use sqlx::{PgPool, Row};
+use std::time::Duration;
+
+/// How long capturing the diagnostics of a deadlock may take, including
+/// waiting for a connection, before the deadlock is reported without them.
+const CAPTURE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Most backends and most locks a snapshot keeps.
+const MAX_ROWS: i64 = 32;
+
+/// Length past which the queries of the backends are cut off.
+const MAX_QUERY_LEN: i32 = 1024;
+
+/// What the server knew about a deadlock, attached to
+/// `TransactionError::Deadlock` by the `deadlock-diagnostics` feature.
+///
+/// The snapshot is taken on a separate connection once the deadlock is
+/// reported, so by then the other sessions may have moved on; `pids` comes
+/// from the error itself and always names every process of the cycle.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeadlockDiagnostics {
+    /// The processes of the cycle, in the order the server's `DETAIL` names
+    /// them.
+    pub pids: Vec<i32>,
+    /// Those processes and the ones blocking or blocked by them.
+    pub backends: Vec<DeadlockBackend>,
+    /// The locks held or awaited by `backends`, awaited ones first.
+    pub locks: Vec<DeadlockLock>,
+    /// Whether backends or locks were left out past the first 32 of each.
+    pub truncated: bool,
+}
+
+/// A server process involved in a deadlock, from `pg_stat_activity`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeadlockBackend {
+    pub pid: i32,
+    /// e.g. `active` or `idle in transaction (aborted)`.
+    pub state: Option<String>,
+    /// What the process waits for, e.g. `Lock:transactionid`.
+    pub wait_event: Option<String>,
+    /// The process's current or last statement, cut off after 1024
+    /// characters.
+    pub query: Option<String>,
+    /// The processes it waits for, from `pg_blocking_pids`.
+    pub blocked_by: Vec<i32>,
+}
+
+/// A lock held or awaited by a backend involved in a deadlock, from
+/// `pg_locks`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DeadlockLock {
+    pub pid: i32,
+    /// What is locked, e.g. `relation`, `tuple` or `transactionid`.
+    pub locktype: String,
+    /// e.g. `RowExclusiveLock`.
+    pub mode: String,
+    /// False while the process waits for the lock.
+    pub granted: bool,
+    /// The locked table, for locks on one.
+    pub relation: Option<String>,
+}
+
+/// Takes a snapshot of the backends and locks of the deadlock `error`
+/// reports, on a connection of `pool`; None if it takes longer than a second
+/// or fails.
+pub(crate) async fn capture(pool: &PgPool, error: &sqlx::Error) -> Option<DeadlockDiagnostics> {
+    let detail = error
+        .as_database_error()
+        .and_then(|db_error| db_error.try_downcast_ref::<PgDatabaseError>())
+        .and_then(PgDatabaseError::detail);
+    let pids = detail.map(parse_pids).unwrap_or_default();
+    match tokio::time::timeout(CAPTURE_TIMEOUT, snapshot(pool, pids)).await {
+        Ok(Ok(diagnostics)) => Some(diagnostics),
+        Ok(Err(error)) => {
+            tracing::debug!(%error, "Failed to capture deadlock diagnostics");
+            None
+        }
+        Err(_) => {
+            tracing::debug!(timeout = ?CAPTURE_TIMEOUT, "Timed out capturing deadlock diagnostics");
+            None
+        }
+    }
+}
+
+async fn snapshot(pool: &PgPool, pids: Vec<i32>) -> Result<DeadlockDiagnostics, sqlx::Error> {
+    let mut conn = pool.acquire().await?;
+    let mut backends: Vec<DeadlockBackend> = sqlx::query(
+        "WITH involved AS (
+             SELECT unnest($1::int4[]) AS pid
+             UNION SELECT unnest(pg_blocking_pids(pid)) FROM unnest($1::int4[]) AS pid
+             UNION SELECT pid FROM pg_stat_activity WHERE pg_blocking_pids(pid) && $1::int4[]
+         )
+         SELECT pid, state, wait_event_type || ':' || wait_event, left(query, $2), pg_blocking_pids(pid)
+         FROM pg_stat_activity JOIN involved USING (pid)
+         ORDER BY array_position($1::int4[], pid) NULLS LAST, pid
+         LIMIT $3",
+    )
+    .bind(&pids)
+    .bind(MAX_QUERY_LEN)
+    .bind(MAX_ROWS + 1)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| DeadlockBackend {
+        pid: row.get(0),
+        state: row.get(1),
+        wait_event: row.get(2),
+        query: row.get(3),
+        blocked_by: row.get(4),
+    })
+    .collect();
+    let mut truncated = backends.len() > MAX_ROWS as usize;
+    backends.truncate(MAX_ROWS as usize);
+
+    let backend_pids: Vec<i32> = backends.iter().map(|backend| backend.pid).collect();
+    let mut locks: Vec<DeadlockLock> = sqlx::query(
+        "SELECT pid, locktype, mode, granted, relation::regclass::text
+         FROM pg_locks
+         WHERE pid = ANY($1) AND locktype <> 'virtualxid'
+         ORDER BY granted, array_position($1, pid), locktype, mode
+         LIMIT $2",
+    )
+    .bind(&backend_pids)
+    .bind(MAX_ROWS + 1)
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|row| DeadlockLock {
+        pid: row.get(0),
+        locktype: row.get(1),
+        mode: row.get(2),
+        granted: row.get(3),
+        relation: row.get(4),
+    })
+    .collect();
+    truncated |= locks.len() > MAX_ROWS as usize;
+    locks.truncate(MAX_ROWS as usize);
+
+    Ok(DeadlockDiagnostics {
+        pids,
+        backends,
+        locks,
+        truncated,
+    })
+}
+
+/// The process ids in the `DETAIL` of a deadlock, e.g. 101 and 102 in
+/// "Process 101 waits for ShareLock on transaction 7; blocked by process 102."
+fn parse_pids(detail: &str) -> Vec<i32> {
+    let mut pids = Vec::new();
+    let mut words = detail.split_whitespace();
+    while let Some(word) = words.next() {
+        if !word.eq_ignore_ascii_case("process") {
+            continue;
+        }
+        let Some(pid) = words.next().and_then(|pid| pid.trim_end_matches(['.', ':', ';']).parse().ok()) else {
+            continue;
+        };
+        if !pids.contains(&pid) {
+            pids.push(pid);
+        }
+    }
+    pids
+}
@@ -0,0 +1,104 @@
+use async_trait::async_trait;
+use std::sync::{Arc, Weak};
+
+use crate::{Executor, ObserverHandle, TransactionAware, TransactionResult, UnitOfWork, UnitOfWorkSession};
+
+/// Object-safe counterpart of `UnitOfWork`, for holding a unit of work as an
+/// `Arc<dyn DynUnitOfWork>`, e.g. in a dependency injection container.
+///
+/// Implemented for every `UnitOfWork`; its sessions are boxed as
+/// `DynSession`s.
+#[async_trait]
+pub trait DynUnitOfWork: Send + Sync {
+    /// Begin a new transaction session.
+    async fn begin(&self) -> TransactionResult<Box<dyn DynSession>>;
+}
+
+#[async_trait]
+impl<U> DynUnitOfWork for U
+where
+    U: UnitOfWork,
+    U::Session: 'static,
+{
+    async fn begin(&self) -> TransactionResult<Box<dyn DynSession>> {
+        let session = UnitOfWork::begin(self).await?;
+        Ok(Box::new(session))
+    }
+}
+
+/// Object-safe counterpart of `UnitOfWorkSession`, begun by a
+/// `DynUnitOfWork`.
+///
+/// Implemented for every `UnitOfWorkSession`; see its methods for how they
+/// behave.
+#[async_trait]
+pub trait DynSession: Send + Sync {
+    /// Get the executor for this session (provides access to the transaction).
+    fn executor(&self) -> &Executor;
+
+    /// Register a component that needs to be notified of transaction events.
+    async fn register_transaction_aware(&self, observer: Arc<dyn TransactionAware>) -> TransactionResult<ObserverHandle>;
+
+    /// Register a component with an explicit notification priority.
+    async fn register_transaction_aware_with_priority(
+        &self,
+        observer: Arc<dyn TransactionAware>,
+        priority: i32,
+    ) -> TransactionResult<ObserverHandle>;
+
+    /// Register a component without keeping it alive.
+    async fn register_transaction_aware_weak(
+        &self,
+        observer: Weak<dyn TransactionAware>,
+    ) -> TransactionResult<ObserverHandle>;
+
+    /// Register a component that is notified of at most one completion event.
+    async fn register_once(&self, observer: Arc<dyn TransactionAware>) -> TransactionResult<ObserverHandle>;
+
+    /// Commit the transaction and notify all registered observers.
+    async fn commit(self: Box<Self>) -> TransactionResult<()>;
+
+    /// Rollback the transaction and notify all registered observers.
+    async fn rollback(self: Box<Self>) -> TransactionResult<()>;
+}
+
+#[async_trait]
+impl<S> DynSession for S
+where
+    S: UnitOfWorkSession + 'static,
+{
+    fn executor(&self) -> &Executor {
+        UnitOfWorkSession::executor(self)
+    }
+
+    async fn register_transaction_aware(&self, observer: Arc<dyn TransactionAware>) -> TransactionResult<ObserverHandle> {
+        UnitOfWorkSession::register_transaction_aware(self, observer).await
+    }
+
+    async fn register_transaction_aware_with_priority(
+        &self,
+        observer: Arc<dyn TransactionAware>,
+        priority: i32,
+    ) -> TransactionResult<ObserverHandle> {
+        UnitOfWorkSession::register_transaction_aware_with_priority(self, observer, priority).await
+    }
+
+    async fn register_transaction_aware_weak(
+        &self,
+        observer: Weak<dyn TransactionAware>,
+    ) -> TransactionResult<ObserverHandle> {
+        UnitOfWorkSession::register_transaction_aware_weak(self, observer).await
+    }
+
+    async fn register_once(&self, observer: Arc<dyn TransactionAware>) -> TransactionResult<ObserverHandle> {
+        UnitOfWorkSession::register_once(self, observer).await
+    }
+
+    async fn commit(self: Box<Self>) -> TransactionResult<()> {
+        UnitOfWorkSession::commit(*self).await
+    }
+
+    async fn rollback(self: Box<Self>) -> TransactionResult<()> {
+        UnitOfWorkSession::rollback(*self).await
+    }
+}
\ No newline at end of file
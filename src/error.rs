@@ -0,0 +1,362 @@
+use sqlx::postgres::PgDatabaseError;
+use std::time::Duration;
+
+/// Classes of PostgreSQL errors that applications commonly branch on.
+///
+/// See <https://www.postgresql.org/docs/current/errcodes-appendix.html> for
+/// the underlying SQLSTATE codes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PgErrorKind {
+    /// `23505 unique_violation`
+    UniqueViolation,
+    /// `23503 foreign_key_violation`
+    ForeignKeyViolation,
+    /// `23514 check_violation`
+    CheckViolation,
+    /// `23502 not_null_violation`
+    NotNullViolation,
+    /// `40001 serialization_failure`
+    SerializationFailure,
+    /// `40P01 deadlock_detected`
+    Deadlock,
+    /// `55P03 lock_not_available`, raised by `lock_timeout` and `NOWAIT`
+    LockNotAvailable,
+    /// `57014 query_canceled`, raised by `statement_timeout` and cancel requests
+    QueryCanceled,
+    /// `42501 insufficient_privilege`
+    InsufficientPrivilege,
+    /// `42P01 undefined_table`
+    UndefinedTable,
+    /// Any code in class `08` (connection exception), or an I/O failure talking
+    /// to the server
+    ConnectionError,
+    /// Any other SQLSTATE, carrying the raw code
+    Other(String),
+}
+
+impl PgErrorKind {
+    /// Map a raw SQLSTATE code onto its error kind.
+    pub fn from_sqlstate(code: &str) -> Self {
+        match code {
+            "23505" => PgErrorKind::UniqueViolation,
+            "23503" => PgErrorKind::ForeignKeyViolation,
+            "23514" => PgErrorKind::CheckViolation,
+            "23502" => PgErrorKind::NotNullViolation,
+            "40001" => PgErrorKind::SerializationFailure,
+            "40P01" => PgErrorKind::Deadlock,
+            "55P03" => PgErrorKind::LockNotAvailable,
+            "57014" => PgErrorKind::QueryCanceled,
+            "42501" => PgErrorKind::InsufficientPrivilege,
+            "42P01" => PgErrorKind::UndefinedTable,
+            code if code.starts_with("08") => PgErrorKind::ConnectionError,
+            code => PgErrorKind::Other(code.to_string()),
+        }
+    }
+
+    /// The error kind of a sqlx error, if it came from the server or the connection.
+    pub(crate) fn of(error: &sqlx::Error) -> Option<Self> {
+        match error {
+            sqlx::Error::Io(_) => Some(PgErrorKind::ConnectionError),
+            error => error
+                .as_database_error()
+                .and_then(|db_error| db_error.code())
+                .map(|code| PgErrorKind::from_sqlstate(&code)),
+        }
+    }
+}
+
+/// Error type for transaction-aware operations
+#[derive(Debug, thiserror::Error)]
+pub enum TransactionError {
+    #[error("Transaction commit failed: {0}")]
+    CommitFailed(String),
+    
+    #[error("Transaction rollback failed: {0}")]
+    RollbackFailed(String),
+    
+    #[error("Database error: {0}")]
+    DatabaseError(#[source] sqlx::Error),
+    
+    #[error("Transaction commit failed and the transaction was rolled back: {commit_error}")]
+    CommitFailedRolledBack {
+        #[source]
+        commit_error: Box<TransactionError>,
+        /// Error from the ROLLBACK sent after the failed commit.
+        rollback_error: Option<Box<TransactionError>>,
+        /// Error from notifying observers of the rollback, as passed on by the
+        /// observer error policy.
+        observer_error: Option<Box<TransactionError>>,
+    },
+    
+    #[error("Transaction commit was vetoed by an observer: {source}")]
+    CommitVetoed {
+        #[source]
+        source: Box<TransactionError>,
+        /// Error from rolling back the vetoed transaction.
+        rollback_error: Option<Box<TransactionError>>,
+        /// Error from notifying observers of the rollback, as passed on by the
+        /// observer error policy.
+        observer_error: Option<Box<TransactionError>>,
+    },
+    
+    #[error("Transaction has already been committed")]
+    AlreadyCommitted,
+    
+    #[error("Transaction has already been rolled back")]
+    AlreadyRolledBack,
+    
+    #[error("Statement timed out: {source}")]
+    StatementTimeout {
+        #[source]
+        source: sqlx::Error,
+        /// The `statement_timeout` configured for the session, or the timeout
+        /// given to the helper, if known.
+        timeout: Option<Duration>,
+    },
+    
+    #[error("Statement cancelled")]
+    Cancelled,
+    
+    #[cfg(feature = "deadlock-diagnostics")]
+    #[error("Deadlock detected: {source}")]
+    Deadlock {
+        #[source]
+        source: sqlx::Error,
+        /// The backends and locks involved, captured by the session that hit
+        /// the deadlock; None if capturing them failed or took too long, or
+        /// for errors not raised by a session's helpers.
+        diagnostics: Option<Box<crate::deadlock::DeadlockDiagnostics>>,
+    },
+    
+    #[error("Lock wait timed out: {source}")]
+    LockTimeout {
+        #[source]
+        source: sqlx::Error,
+        /// The `lock_timeout` configured for the session, if known.
+        timeout: Option<Duration>,
+    },
+    
+    #[error("Refusing to retry: {observers} non-idempotent observer(s) were registered by the failed attempt: {source}")]
+    RetryUnsafe {
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+        /// Number of registered observers that did not declare themselves idempotent.
+        observers: usize,
+    },
+    
+    #[error("Transaction has already completed")]
+    TransactionAlreadyCompleted,
+    
+    #[error("The unit of work is shutting down and begins no new sessions")]
+    ShuttingDown,
+    
+    #[error("The circuit breaker is open after repeated connection failures; retry after {retry_after:?}")]
+    CircuitOpen {
+        /// How long until the breaker lets a probe through; zero while a probe
+        /// is under way.
+        retry_after: Duration,
+    },
+    
+    #[error("No session is attached to the current task; run it inside `attach`")]
+    NoCurrentSession,
+    
+    #[error("Executor is busy: held by {holder}")]
+    ExecutorBusy {
+        /// The Executor call holding the transaction and for how long,
+        /// e.g. "fetch_stream since 2.3s ago".
+        holder: String,
+    },
+    
+    #[error("Executor used again by the task holding it: held by {holder}")]
+    ReentrantExecutorUse {
+        /// The Executor call holding the transaction, as for `ExecutorBusy`.
+        holder: String,
+    },
+    
+    #[error("Statement {index} of the batch failed: {source}")]
+    BatchStatementFailed {
+        /// Zero-based position of the failing statement in the batch.
+        index: usize,
+        #[source]
+        source: Box<TransactionError>,
+    },
+    
+    #[error("Statement returned {rows} rows, expected {expected}")]
+    UnexpectedRowCount {
+        /// How many rows the helper accepts, e.g. "exactly one".
+        expected: &'static str,
+        /// Number of rows the statement returned.
+        rows: usize,
+    },
+    
+    #[error("Column {column} of `{sql}` failed to decode from {pg_type}: {source}")]
+    Decode {
+        /// Name of the column that failed to decode.
+        column: String,
+        /// The Rust type it was decoded into, if sqlx reported it.
+        rust_type: Option<String>,
+        /// The PostgreSQL type of the column, e.g. `INT8`.
+        pg_type: String,
+        /// The statement that returned the row.
+        sql: String,
+        #[source]
+        source: Box<sqlx::Error>,
+    },
+    
+    #[error("Invalid binary COPY row: {0}")]
+    InvalidCopyRow(String),
+    
+    #[error("Migration failed: {0}")]
+    MigrationFailed(#[source] Box<sqlx::migrate::MigrateError>),
+    
+    #[error("Migration {version} ({description}) opts out of transactions and cannot run in a session")]
+    MigrationNotTransactional {
+        /// Version of the migration.
+        version: i64,
+        /// Description of the migration, from its file name.
+        description: String,
+    },
+    
+    #[error("Transaction observer {observer} failed: {source}")]
+    ObserverFailed {
+        observer: String,
+        #[source]
+        source: Box<TransactionError>,
+    },
+    
+    #[error("Transaction observer {observer} timed out after {elapsed:?}")]
+    ObserverTimeout { observer: String, elapsed: Duration },
+    
+    #[error("Transaction observer {observer} panicked: {message}")]
+    ObserverPanicked { observer: String, message: String },
+    
+    #[cfg(feature = "http")]
+    #[error("Webhook to {url} failed after {attempts} attempt(s): {reason}")]
+    WebhookFailed {
+        url: String,
+        /// How many times delivery was tried.
+        attempts: u32,
+        /// The last response status, or why there was no response.
+        reason: String,
+    },
+    
+    #[error("{} transaction observer(s) failed", .0.len())]
+    ObserverErrors(Vec<TransactionError>),
+}
+
+impl TransactionError {
+    /// Classify a database error by its SQLSTATE.
+    ///
+    /// The configured session timeouts are attached to the timeout variants so
+    /// callers can tell which budget was exceeded.
+    pub(crate) fn classify(
+        error: sqlx::Error,
+        statement_timeout: Option<Duration>,
+        lock_timeout: Option<Duration>,
+    ) -> Self {
+        match PgErrorKind::of(&error) {
+            Some(PgErrorKind::QueryCanceled) => TransactionError::StatementTimeout {
+                source: error,
+                timeout: statement_timeout,
+            },
+            Some(PgErrorKind::LockNotAvailable) => TransactionError::LockTimeout {
+                source: error,
+                timeout: lock_timeout,
+            },
+            #[cfg(feature = "deadlock-diagnostics")]
+            Some(PgErrorKind::Deadlock) => TransactionError::Deadlock {
+                source: error,
+                diagnostics: None,
+            },
+            _ => TransactionError::DatabaseError(error),
+        }
+    }
+    
+    /// The class of the underlying PostgreSQL error, if this error wraps one.
+    pub fn pg_kind(&self) -> Option<PgErrorKind> {
+        self.sqlx_error().and_then(PgErrorKind::of)
+    }
+    
+    /// Whether this error says the database could not be reached: an I/O or
+    /// TLS failure, a SQLSTATE of class `08`, or a timeout waiting for a
+    /// connection from the pool, including as the cause of a failed commit.
+    ///
+    /// These are the failures the circuit breaker counts.
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            TransactionError::DatabaseError(sqlx::Error::PoolTimedOut | sqlx::Error::Tls(_)) => true,
+            TransactionError::DatabaseError(error) => PgErrorKind::of(error) == Some(PgErrorKind::ConnectionError),
+            TransactionError::CommitFailedRolledBack { commit_error, .. } => commit_error.is_connection_error(),
+            _ => false,
+        }
+    }
+    
+    /// The raw SQLSTATE code of the underlying PostgreSQL error, if any.
+    pub fn sqlstate(&self) -> Option<&str> {
+        self.pg_database_error().map(|db_error| db_error.code())
+    }
+    
+    /// The PostgreSQL `DETAIL` field of the underlying error, if any.
+    pub fn detail(&self) -> Option<&str> {
+        self.pg_database_error()?.detail()
+    }
+    
+    /// The PostgreSQL `HINT` field of the underlying error, if any.
+    pub fn hint(&self) -> Option<&str> {
+        self.pg_database_error()?.hint()
+    }
+    
+    /// The table the underlying PostgreSQL error relates to, if any.
+    pub fn table(&self) -> Option<&str> {
+        self.pg_database_error()?.table()
+    }
+    
+    /// The column the underlying PostgreSQL error relates to, if any.
+    pub fn column(&self) -> Option<&str> {
+        self.pg_database_error()?.column()
+    }
+    
+    /// The innermost-wrapped sqlx error, found by walking the `source()` chain.
+    fn sqlx_error(&self) -> Option<&sqlx::Error> {
+        let mut current: Option<&(dyn std::error::Error + 'static)> = Some(self);
+        while let Some(error) = current {
+            if let Some(sqlx_error) = error.downcast_ref::<sqlx::Error>() {
+                return Some(sqlx_error);
+            }
+            current = error.source();
+        }
+        None
+    }
+    
+    /// The PostgreSQL error reported by the server, for variants that carry one.
+    fn pg_database_error(&self) -> Option<&PgDatabaseError> {
+        self.sqlx_error()?
+            .as_database_error()?
+            .try_downcast_ref::<PgDatabaseError>()
+    }
+}
+
+impl From<sqlx::Error> for TransactionError {
+    fn from(error: sqlx::Error) -> Self {
+        Self::classify(error, None, None)
+    }
+}
+
+/// Access to the `TransactionError` carried by an application error type.
+///
+/// `PostgresUnitOfWork::run_with_retry` uses this to decide whether a failure
+/// returned by the closure is retryable. Implement it for error enums that
+/// wrap `TransactionError`, returning `None` for pure domain failures.
+pub trait AsTransactionError {
+    /// The wrapped transaction error, if this error carries one.
+    fn as_transaction_error(&self) -> Option<&TransactionError>;
+}
+
+impl AsTransactionError for TransactionError {
+    fn as_transaction_error(&self) -> Option<&TransactionError> {
+        Some(self)
+    }
+}
+
+/// Result type for transaction-aware operations
+pub type TransactionResult<T> = Result<T, TransactionError>;
\ No newline at end of file
@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use std::any::{type_name, Any};
+use std::future::Future;
+use std::sync::Arc;
+
+use crate::{TransactionAware, TransactionResult};
+
+/// A staged domain event, erased over its type.
+type StagedEvent = Arc<dyn Any + Send + Sync>;
+
+/// Domain events staged on a session, in staging order.
+pub(crate) type EventBuffer = Arc<Mutex<Vec<StagedEvent>>>;
+
+/// Handler closure that runs for events of its type and skips the rest.
+type ErasedHandler = dyn Fn(StagedEvent) -> Option<BoxFuture<'static, TransactionResult<()>>> + Send + Sync;
+
+/// An event handler registered on a unit of work.
+#[derive(Clone)]
+pub(crate) struct EventHandler {
+    name: Arc<str>,
+    handle: Arc<ErasedHandler>,
+}
+
+impl EventHandler {
+    pub(crate) fn new<E, F, Fut>(handler: F) -> Self
+    where
+        E: Send + Sync + 'static,
+        F: Fn(Arc<E>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        let handle = move |event: StagedEvent| {
+            let event = event.downcast::<E>().ok()?;
+            Some(Box::pin(handler(event)) as BoxFuture<'static, TransactionResult<()>>)
+        };
+        Self {
+            name: format!("event handler for {}", type_name::<E>()).into(),
+            handle: Arc::new(handle),
+        }
+    }
+
+    /// Observer delivering the events staged in `events` to this handler once
+    /// the session commits.
+    pub(crate) fn observer(&self, events: EventBuffer) -> Arc<dyn TransactionAware> {
+        Arc::new(EventObserver {
+            handler: self.clone(),
+            events,
+        })
+    }
+}
+
+/// Per-session observer dispatching committed events to one handler.
+///
+/// Staged events are simply dropped with the session on rollback.
+struct EventObserver {
+    handler: EventHandler,
+    events: EventBuffer,
+}
+
+#[async_trait]
+impl TransactionAware for EventObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        let events = self.events.lock().clone();
+        for event in events {
+            if let Some(delivery) = (self.handler.handle)(event) {
+                delivery.await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.handler.name
+    }
+
+    /// Events are only delivered after a commit, so a failed attempt that is
+    /// retried has delivered nothing.
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
\ No newline at end of file
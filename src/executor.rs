@@ -1,27 +1,2064 @@
-use sqlx::{Postgres, Transaction};
+use bytes::Bytes;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{BoxFuture, FutureExt, TryFutureExt};
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use parking_lot::{Mutex as SyncMutex, RwLock};
+use sqlx::postgres::{PgArguments, PgConnection, PgQueryResult, PgRow, PgStatement, PgTypeInfo};
+use sqlx::query::{Query, QueryAs};
+use sqlx::pool::PoolConnection;
+use sqlx::{
+    Arguments, Column, Connection, Describe, Either, Execute, FromRow, PgPool, Postgres, Row, Transaction, TypeInfo,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::fmt;
+use std::future::Future;
+use std::ops::{Deref, DerefMut};
+use tokio::sync::{Mutex, MutexGuard};
+use tokio_util::sync::CancellationToken;
+
+use crate::bind::{bulk_insert_sql, BindRow, BulkInsertOptions, MAX_BIND_PARAMETERS};
+use crate::copy::CopyInSink;
+use crate::cursor::Cursor;
+use crate::instrumentation::{ExecutorMetrics, Instrumentation, QueryHook, Rows, SessionStats};
+use crate::sql_comment::SqlComment;
+use crate::statement_log::StatementLog;
+use crate::{PgErrorKind, ReadOnlyExecutor, TransactionError, TransactionOptions, TransactionResult, UowMetrics};
+
+/// How long an interrupted statement's cancel request may wait for a pooled
+/// connection to be sent on.
+const CANCEL_TIMEOUT: Duration = Duration::from_secs(5);
+
+tokio::task_local! {
+    /// The Executor attached to the running task by `Executor::attach`.
+    static CURRENT: Executor;
+    
+    /// The transactions locked by the `with_tx` closures the running task is
+    /// inside of, by the address of `Executor::tx`.
+    static LOCKED: Vec<usize>;
+}
+
+/// Where the session behind an Executor is in its lifecycle, as reported by
+/// `PostgresUnitOfWorkSession::state` and `Executor::session_state`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SessionState {
+    /// The transaction is open.
+    Active,
+    /// `commit` was called: the `before_commit` observers are running or
+    /// COMMIT is in flight.
+    Committing,
+    /// The transaction was committed.
+    Committed,
+    /// `rollback` was called and ROLLBACK is in flight.
+    RollingBack,
+    /// The transaction was rolled back, or its COMMIT failed.
+    RolledBack,
+    /// A statement was cancelled, leaving the transaction to be rolled back,
+    /// or ROLLBACK failed and the outcome is unknown.
+    Poisoned,
+}
+
+/// What an Executor runs its statements on, as reported by `Executor::state`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExecutorState {
+    /// The transaction is open.
+    Active,
+    /// The transaction was committed, rolled back or taken out of the Executor.
+    Completed,
+    /// The Executor runs statements on a pool outside of any transaction; see
+    /// `Executor::from_pool`.
+    PoolMode,
+}
+
+/// A snapshot of what an Executor is doing, as returned by `Executor::status`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ExecutorStatus {
+    /// Whether the transaction is locked, by a helper or directly through `tx`.
+    pub locked: bool,
+    /// The helper holding the lock, e.g. `"fetch_one"`, or None if it is free
+    /// or locked directly.
+    pub holder: Option<&'static str>,
+    /// How long the helper has been holding the lock.
+    pub held_for: Option<Duration>,
+    /// Statements run through the helpers so far.
+    pub statements: u64,
+    /// Whether the transaction was committed, rolled back or taken out of `tx`.
+    pub transaction_taken: bool,
+}
 
 /// Executor wraps a database transaction for use by repositories.
 ///
 /// This struct provides a shared reference to a PostgreSQL transaction
-/// that can be passed to multiple repositories within a unit of work.
-#[derive(Clone, Debug)]
+/// that can be passed to multiple repositories within a unit of work. An
+/// Executor created with `from_pool` runs the same helpers outside of any
+/// transaction instead.
+///
+/// The helpers lock the transaction one at a time; helpers polled
+/// concurrently, e.g. with `join!`, wait for each other. A helper called from
+/// inside a `with_tx` closure, or by the task holding the guard of `lock`,
+/// `acquire` or `copy_in`, fails with `ReentrantExecutorUse` instead of
+/// deadlocking.
+#[derive(Clone)]
 pub struct Executor {
+    /// The transaction, or None once it completed.
+    ///
+    /// Locking it directly bypasses the helpers' re-entrancy check: a task
+    /// that holds this lock and then calls a helper deadlocks.
     pub tx: Arc<Mutex<Option<Transaction<'static, Postgres>>>>,
+    pool: Option<Arc<PgPool>>,
+    /// Pool for sending the cancel requests of interrupted statements on.
+    cancel_pool: Option<Arc<PgPool>>,
+    options: Arc<TransactionOptions>,
+    state: Arc<RwLock<SessionState>>,
+    streaming: Arc<AtomicBool>,
+    /// Number of cursors declared so far, for naming the next one.
+    cursors: Arc<AtomicU64>,
+    holder: Arc<Holder>,
+    instrumentation: Instrumentation,
+    /// Whether the helpers append the SQL comment, if one is set; turned off
+    /// by `without_sql_comments`.
+    sql_comments: bool,
 }
 
 impl Executor {
     /// Creates a new Executor from a PostgreSQL transaction.
     pub fn new(tx: Transaction<'static, Postgres>) -> Self {
+        Self::with_options(tx, TransactionOptions::default())
+    }
+    
+    /// Creates a new Executor for a transaction that was started with `options`.
+    pub(crate) fn with_options(tx: Transaction<'static, Postgres>, options: TransactionOptions) -> Self {
         Self {
             tx: Arc::new(Mutex::new(Some(tx))),
+            pool: None,
+            cancel_pool: None,
+            options: Arc::new(options),
+            state: Arc::new(RwLock::new(SessionState::Active)),
+            streaming: Arc::new(AtomicBool::new(false)),
+            cursors: Arc::new(AtomicU64::new(0)),
+            holder: Arc::new(Holder::new()),
+            instrumentation: Instrumentation::default(),
+            sql_comments: true,
+        }
+    }
+    
+    /// Creates an Executor that runs each helper's statement on its own pooled
+    /// connection, auto-committed, for code paths that never open a transaction
+    /// such as health checks and simple lookups.
+    ///
+    /// Repositories can take either kind of Executor. There is no session, so
+    /// `TransactionAware` callbacks are never invoked, and `tx` is always None.
+    /// `with_tx` runs its closure in a transaction of its own, committed when
+    /// the closure succeeds.
+    pub fn from_pool(pool: Arc<PgPool>) -> Self {
+        Self {
+            tx: Arc::new(Mutex::new(None)),
+            pool: Some(pool),
+            cancel_pool: None,
+            options: Arc::new(TransactionOptions::default()),
+            state: Arc::new(RwLock::new(SessionState::Active)),
+            streaming: Arc::new(AtomicBool::new(false)),
+            cursors: Arc::new(AtomicU64::new(0)),
+            holder: Arc::new(Holder::new()),
+            instrumentation: Instrumentation::default(),
+            sql_comments: true,
+        }
+    }
+    
+    /// Converts a database error into a `TransactionError`.
+    ///
+    /// Unlike the plain `From` conversion, timeout errors carry the timeouts
+    /// configured for this transaction. Serialization failures and deadlocks
+    /// are reported to `UowMetrics::record_conflict` of the unit of work the
+    /// Executor belongs to.
+    pub fn classify_error(&self, error: sqlx::Error) -> TransactionError {
+        let error = TransactionError::classify(error, self.options.statement_timeout, self.options.lock_timeout);
+        self.instrumentation.record_conflict(&error);
+        error
+    }
+    
+    /// The isolation level the transaction was begun with, if not the default.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn isolation_level(&self) -> Option<crate::IsolationLevel> {
+        self.options.isolation_level
+    }
+    
+    /// The error to report when the transaction is no longer available.
+    ///
+    /// Repositories holding a clone of the Executor after the session completed
+    /// get `AlreadyCommitted` or `AlreadyRolledBack` depending on the outcome,
+    /// or `TransactionAlreadyCompleted` while it is being completed, if its
+    /// outcome is unknown or if the transaction was taken directly.
+    pub fn completed_error(&self) -> TransactionError {
+        match *self.state.read() {
+            SessionState::Committed => TransactionError::AlreadyCommitted,
+            SessionState::RolledBack => TransactionError::AlreadyRolledBack,
+            SessionState::Active | SessionState::Committing | SessionState::RollingBack | SessionState::Poisoned => {
+                TransactionError::TransactionAlreadyCompleted
+            }
+        }
+    }
+    
+    /// Where the session behind the Executor is in its lifecycle.
+    ///
+    /// Pool-backed Executors have no session and are always `Active`, as is a
+    /// transaction taken directly through `tx`.
+    pub fn session_state(&self) -> SessionState {
+        *self.state.read()
+    }
+    
+    /// Whether the Executor is still inside a live transaction.
+    ///
+    /// Pool-backed Executors are never active.
+    pub fn is_active(&self) -> bool {
+        self.state() == ExecutorState::Active
+    }
+    
+    /// What the Executor runs its statements on.
+    ///
+    /// This does not wait for the transaction lock. While it is held elsewhere
+    /// a transaction taken directly through `tx` still reports as `Active`.
+    pub fn state(&self) -> ExecutorState {
+        if self.pool.is_some() {
+            ExecutorState::PoolMode
+        } else if self.is_completed() {
+            ExecutorState::Completed
+        } else {
+            ExecutorState::Active
+        }
+    }
+    
+    /// What the Executor is doing, for diagnosing a hang.
+    ///
+    /// This never waits for the transaction lock, nor for the record of its
+    /// holder, which is read from an atomic, so it can be called while the
+    /// Executor is stuck. Pool-backed Executors are never locked.
+    pub fn status(&self) -> ExecutorStatus {
+        let holder = self.holder.snapshot();
+        let tx = self.tx.try_lock();
+        ExecutorStatus {
+            locked: holder.is_some() || tx.is_err(),
+            holder: holder.map(|(label, _)| label),
+            held_for: holder.map(|(_, held_for)| held_for),
+            statements: self.instrumentation.stats().statements,
+            transaction_taken: self.pool.is_none()
+                && (self.has_outcome() || tx.is_ok_and(|tx| tx.is_none())),
+        }
+    }
+    
+    /// Reports every statement run through the query helpers of this Executor
+    /// and its clones to `hook`, replacing any previous hook.
+    pub fn set_query_hook(&self, hook: Arc<dyn QueryHook>) {
+        self.instrumentation.set_query_hook(hook);
+    }
+    
+    /// Reports the latency of every statement run through the query helpers
+    /// of this Executor and its clones to `metrics`, replacing any previous
+    /// configuration.
+    pub fn set_metrics(&self, metrics: ExecutorMetrics) {
+        self.instrumentation.set_metrics(metrics);
+    }
+    
+    /// Appends `comment` to the statements of the query helpers of this
+    /// Executor and its clones.
+    pub(crate) fn set_sql_comment(&self, comment: SqlComment) {
+        self.instrumentation.set_sql_comment(comment);
+    }
+    
+    /// Reports the serialization failures and deadlocks classified by this
+    /// Executor and its clones to `metrics`.
+    pub(crate) fn set_uow_metrics(&self, metrics: Arc<dyn UowMetrics>) {
+        self.instrumentation.set_uow_metrics(metrics);
+    }
+    
+    /// Logs the statements of the query helpers of this Executor and its
+    /// clones as configured by `log`.
+    pub(crate) fn set_statement_log(&self, log: StatementLog) {
+        self.instrumentation.set_statement_log(log);
+    }
+    
+    /// A clone of this Executor whose helpers run statements without the
+    /// comment configured with `PostgresUnitOfWorkBuilder::sql_comments`,
+    /// e.g. for a hot statement that should stay in the statement cache.
+    pub fn without_sql_comments(&self) -> Executor {
+        Executor {
+            sql_comments: false,
+            ..self.clone()
+        }
+    }
+    
+    /// Runs `query` on the transaction and returns its result, including the
+    /// number of rows affected.
+    ///
+    /// Fails with `completed_error()` once the transaction is gone; database
+    /// errors are classified as by `classify_error`.
+    pub async fn execute(&self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<PgQueryResult> {
+        self.lock_as("execute").await?.execute(query).await
+    }
+    
+    /// Runs a string of semicolon-separated statements in order, using the
+    /// simple query protocol.
+    ///
+    /// Statements take no parameters. If one fails the rest are skipped and
+    /// the error is `BatchStatementFailed`, carrying the statement's index.
+    pub async fn execute_batch(&self, sql: &str) -> TransactionResult<()> {
+        self.lock_as("execute_batch").await?.execute_batch(sql).await
+    }
+    
+    /// Runs independent queries in order under a single lock of the
+    /// transaction and returns their results in order.
+    ///
+    /// The first failure skips the remaining queries and is reported as
+    /// `BatchStatementFailed` with the query's index. sqlx 0.8 does not expose
+    /// protocol-level pipelining, so each query still waits for its result;
+    /// the call saves the per-query locking and keeps other repositories from
+    /// interleaving.
+    pub async fn execute_pipelined(
+        &self,
+        queries: Vec<Query<'_, Postgres, PgArguments>>,
+    ) -> TransactionResult<Vec<PgQueryResult>> {
+        let mut guard = self.lock_as("execute_pipelined").await?;
+        let mut results = Vec::with_capacity(queries.len());
+        for (index, query) in queries.into_iter().enumerate() {
+            let result = guard.execute(query).await.map_err(|error| TransactionError::BatchStatementFailed {
+                index,
+                source: Box::new(error),
+            })?;
+            results.push(result);
         }
+        Ok(results)
+    }
+    
+    /// Runs `sql` once for each row of parameters under a single lock of the
+    /// transaction, and returns the total number of rows affected.
+    ///
+    /// The statement is prepared once and reused from the connection's cache,
+    /// unless the session turned the cache off. The first failure skips the
+    /// remaining rows and is reported as `BatchStatementFailed` with the row's
+    /// index; the earlier rows stay in the transaction.
+    pub async fn execute_many<'q, I>(&self, sql: &'q str, rows: I) -> TransactionResult<u64>
+    where
+        I: IntoIterator,
+        I::Item: BindRow<'q>,
+    {
+        let mut guard = self.lock_as("execute_many").await?;
+        let mut rows_affected = 0;
+        for (index, row) in rows.into_iter().enumerate() {
+            let result = guard
+                .execute(row.bind_to(sqlx::query(sql)))
+                .await
+                .map_err(|error| TransactionError::BatchStatementFailed {
+                    index,
+                    source: Box::new(error),
+                })?;
+            rows_affected += result.rows_affected();
+        }
+        Ok(rows_affected)
+    }
+    
+    /// Inserts `rows` into `columns` of `table` with multi-row `INSERT`s under a
+    /// single lock of the transaction, and returns how many rows were inserted.
+    ///
+    /// Each row binds one value per column, in order; rows binding a different
+    /// number of values fail the insert before their chunk is sent. The rows
+    /// are sent in as few statements as the limit of 65535 bind parameters per
+    /// statement allows. `table` and `columns` are put into the SQL as they
+    /// are, so they must be trusted identifiers, quoted where needed. See
+    /// `bulk_insert_with` for skipping conflicting rows.
+    pub async fn bulk_insert<'q, I>(&self, table: &str, columns: &[&str], rows: I) -> TransactionResult<u64>
+    where
+        I: IntoIterator,
+        I::Item: BindRow<'q>,
+    {
+        self.bulk_insert_with(table, columns, rows, BulkInsertOptions::default()).await
+    }
+    
+    /// Like `bulk_insert`, with `options`.
+    pub async fn bulk_insert_with<'q, I>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        rows: I,
+        options: BulkInsertOptions,
+    ) -> TransactionResult<u64>
+    where
+        I: IntoIterator,
+        I::Item: BindRow<'q>,
+    {
+        if columns.is_empty() || columns.len() > MAX_BIND_PARAMETERS {
+            return Err(TransactionError::DatabaseError(sqlx::Error::Configuration(
+                format!(
+                    "bulk insert into {table} needs between 1 and {MAX_BIND_PARAMETERS} columns, got {}",
+                    columns.len()
+                )
+                .into(),
+            )));
+        }
+        let chunk_size = MAX_BIND_PARAMETERS / columns.len();
+        let mut guard = self.lock_as("bulk_insert").await?;
+        let mut rows = rows.into_iter().enumerate().peekable();
+        let mut rows_inserted = 0;
+        while rows.peek().is_some() {
+            // The rows are bound to a placeholder first, since the statement
+            // for them is only known once they are counted
+            let mut arguments = PgArguments::default();
+            let mut count = 0;
+            while count < chunk_size {
+                let Some((index, row)) = rows.next() else { break };
+                let bound = arguments.len();
+                arguments = row
+                    .bind_to(sqlx::query_with("", arguments))
+                    .take_arguments()
+                    .map_err(|error| TransactionError::DatabaseError(sqlx::Error::Encode(error)))?
+                    .unwrap_or_default();
+                // A row binding too few or too many values would shift every
+                // later row's values into the wrong columns
+                if arguments.len() - bound != columns.len() {
+                    return Err(TransactionError::DatabaseError(sqlx::Error::Configuration(
+                        format!(
+                            "bulk insert into {table}: row {index} bound {} values, expected one for each of {} column(s)",
+                            arguments.len() - bound,
+                            columns.len()
+                        )
+                        .into(),
+                    )));
+                }
+                count += 1;
+            }
+            // Only the statement for full chunks is worth keeping prepared
+            let sql = bulk_insert_sql(table, columns, count, &options);
+            let query = sqlx::query_with(&sql, arguments).persistent(count == chunk_size);
+            rows_inserted += guard.execute(query).await?.rows_affected();
+        }
+        Ok(rows_inserted)
+    }
+    
+    /// Runs `query` and returns its only row.
+    ///
+    /// A query returning no rows fails with `DatabaseError(sqlx::Error::RowNotFound)`.
+    pub async fn fetch_one(&self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<PgRow> {
+        self.lock_as("fetch_one").await?.fetch_one(query).await
+    }
+    
+    /// Runs `query` and returns its first row, if any.
+    pub async fn fetch_optional(&self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<Option<PgRow>> {
+        self.lock_as("fetch_optional").await?.fetch_optional(query).await
+    }
+    
+    /// Runs `query` and returns all of its rows.
+    pub async fn fetch_all(&self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<Vec<PgRow>> {
+        self.lock_as("fetch_all").await?.fetch_all(query).await
+    }
+    
+    /// Runs a `sqlx::query_as` query and maps its only row to `T`.
+    ///
+    /// A query returning no rows fails with `DatabaseError(sqlx::Error::RowNotFound)`.
+    pub async fn fetch_one_as<T>(&self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<T>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        self.lock_as("fetch_one_as").await?.fetch_one_as(query).await
+    }
+    
+    /// Runs a `sqlx::query_as` query and maps its first row, if any, to `T`.
+    pub async fn fetch_optional_as<T>(&self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        self.lock_as("fetch_optional_as").await?.fetch_optional_as(query).await
+    }
+    
+    /// Runs a `sqlx::query_as` query and maps every row to `T`.
+    pub async fn fetch_all_as<T>(&self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        self.lock_as("fetch_all_as").await?.fetch_all_as(query).await
+    }
+    
+    /// Runs an `INSERT ... RETURNING` statement, with its parameters bound by
+    /// `bind`, and maps the row it returned to `T`.
+    ///
+    /// Fails with `UnexpectedRowCount` unless the statement returned exactly
+    /// one row, e.g. when `ON CONFLICT DO NOTHING` skipped the row; use
+    /// `insert_returning_optional` for those.
+    pub async fn insert_returning<'q, T, B>(&self, sql: &'q str, bind: B) -> TransactionResult<T>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        B: FnOnce(QueryAs<'q, Postgres, T, PgArguments>) -> QueryAs<'q, Postgres, T, PgArguments>,
+    {
+        self.lock_as("insert_returning").await?.insert_returning(sql, bind).await
+    }
+    
+    /// Like `insert_returning`, but returns None if the statement returned no
+    /// row, as `INSERT ... ON CONFLICT DO NOTHING RETURNING` does for a
+    /// conflicting row.
+    pub async fn insert_returning_optional<'q, T, B>(&self, sql: &'q str, bind: B) -> TransactionResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        B: FnOnce(QueryAs<'q, Postgres, T, PgArguments>) -> QueryAs<'q, Postgres, T, PgArguments>,
+    {
+        self.lock_as("insert_returning_optional")
+            .await?
+            .insert_returning_optional(sql, bind)
+            .await
+    }
+    
+    /// Declares a server-side cursor for `sql`, with its parameters bound by
+    /// `bind`, for fetching its rows in batches with `Cursor::fetch`.
+    ///
+    /// The cursor is `NO SCROLL` and `WITHOUT HOLD`, so it only lives as long
+    /// as the transaction; pool-backed Executors cannot declare one.
+    pub async fn cursor<'q, B>(&self, sql: &'q str, bind: B) -> TransactionResult<Cursor>
+    where
+        B: FnOnce(Query<'q, Postgres, PgArguments>) -> Query<'q, Postgres, PgArguments>,
+    {
+        let name = format!(
+            "postgres_unit_of_work_cursor_{}",
+            self.cursors.fetch_add(1, Ordering::Relaxed)
+        );
+        let arguments = bind(sqlx::query(sql))
+            .take_arguments()
+            .map_err(|error| TransactionError::DatabaseError(sqlx::Error::Encode(error)))?
+            .unwrap_or_default();
+        let declare = format!("DECLARE {name} NO SCROLL CURSOR WITHOUT HOLD FOR {sql}");
+        self.execute(sqlx::query_with(&declare, arguments).persistent(false)).await?;
+        Ok(Cursor::new(self.clone(), name))
+    }
+    
+    /// Like `execute`, but gives up on the statement after `timeout`; see
+    /// `fetch_one_with_timeout`.
+    pub async fn execute_with_timeout(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        timeout: Duration,
+    ) -> TransactionResult<PgQueryResult> {
+        let sql = query.sql();
+        self.run_interruptible("execute_with_timeout", sql, Interrupt::Timeout(timeout), |guard| {
+            Box::pin(guard.execute(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_one`, but gives up on the statement after `timeout`, which
+    /// includes neither waiting for the lock nor the statement cache.
+    ///
+    /// A statement that runs out of time is cancelled on the server and fails
+    /// with `StatementTimeout`. It runs inside a savepoint that is rolled back
+    /// once the cancel went through, so the transaction stays usable. Cancel
+    /// requests are sent on a pooled connection, which Executors created with
+    /// `Executor::new` do not have: their timed out statement keeps running
+    /// and the next statement waits for it.
+    pub async fn fetch_one_with_timeout(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        timeout: Duration,
+    ) -> TransactionResult<PgRow> {
+        let sql = query.sql();
+        self.run_interruptible("fetch_one_with_timeout", sql, Interrupt::Timeout(timeout), |guard| {
+            Box::pin(guard.fetch_one(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_optional`, but gives up on the statement after `timeout`; see
+    /// `fetch_one_with_timeout`.
+    pub async fn fetch_optional_with_timeout(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        timeout: Duration,
+    ) -> TransactionResult<Option<PgRow>> {
+        let sql = query.sql();
+        self.run_interruptible("fetch_optional_with_timeout", sql, Interrupt::Timeout(timeout), |guard| {
+            Box::pin(guard.fetch_optional(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_all`, but gives up on the statement after `timeout`; see
+    /// `fetch_one_with_timeout`.
+    pub async fn fetch_all_with_timeout(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        timeout: Duration,
+    ) -> TransactionResult<Vec<PgRow>> {
+        let sql = query.sql();
+        self.run_interruptible("fetch_all_with_timeout", sql, Interrupt::Timeout(timeout), |guard| {
+            Box::pin(guard.fetch_all(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_one_as`, but gives up on the statement after `timeout`; see
+    /// `fetch_one_with_timeout`.
+    pub async fn fetch_one_as_with_timeout<T>(
+        &self,
+        query: QueryAs<'_, Postgres, T, PgArguments>,
+        timeout: Duration,
+    ) -> TransactionResult<T>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let sql = query.sql();
+        self.run_interruptible("fetch_one_as_with_timeout", sql, Interrupt::Timeout(timeout), |guard| {
+            Box::pin(guard.fetch_one_as(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_optional_as`, but gives up on the statement after `timeout`; see
+    /// `fetch_one_with_timeout`.
+    pub async fn fetch_optional_as_with_timeout<T>(
+        &self,
+        query: QueryAs<'_, Postgres, T, PgArguments>,
+        timeout: Duration,
+    ) -> TransactionResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let sql = query.sql();
+        self.run_interruptible("fetch_optional_as_with_timeout", sql, Interrupt::Timeout(timeout), |guard| {
+            Box::pin(guard.fetch_optional_as(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_all_as`, but gives up on the statement after `timeout`; see
+    /// `fetch_one_with_timeout`.
+    pub async fn fetch_all_as_with_timeout<T>(
+        &self,
+        query: QueryAs<'_, Postgres, T, PgArguments>,
+        timeout: Duration,
+    ) -> TransactionResult<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let sql = query.sql();
+        self.run_interruptible("fetch_all_as_with_timeout", sql, Interrupt::Timeout(timeout), |guard| {
+            Box::pin(guard.fetch_all_as(query))
+        })
+        .await
+    }
+    
+    /// Like `execute`, but stops the statement once `token` is cancelled; see
+    /// `fetch_one_with_cancellation`.
+    pub async fn execute_with_cancellation(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        token: &CancellationToken,
+    ) -> TransactionResult<PgQueryResult> {
+        let sql = query.sql();
+        self.run_interruptible("execute_with_cancellation", sql, Interrupt::Cancellation(token), |guard| {
+            Box::pin(guard.execute(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_one`, but stops the statement once `token` is cancelled,
+    /// e.g. when the request it serves was abandoned.
+    ///
+    /// The statement is cancelled on the server and the helper fails with
+    /// `Cancelled`, also if `token` was cancelled before it started. The
+    /// transaction then has to be rolled back: committing it rolls it back
+    /// and fails with `Cancelled`. Like `fetch_one_with_timeout`, Executors
+    /// created with `Executor::new` cannot cancel the statement on the server.
+    pub async fn fetch_one_with_cancellation(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        token: &CancellationToken,
+    ) -> TransactionResult<PgRow> {
+        let sql = query.sql();
+        self.run_interruptible("fetch_one_with_cancellation", sql, Interrupt::Cancellation(token), |guard| {
+            Box::pin(guard.fetch_one(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_optional`, but stops the statement once `token` is cancelled;
+    /// see `fetch_one_with_cancellation`.
+    pub async fn fetch_optional_with_cancellation(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        token: &CancellationToken,
+    ) -> TransactionResult<Option<PgRow>> {
+        let sql = query.sql();
+        self.run_interruptible("fetch_optional_with_cancellation", sql, Interrupt::Cancellation(token), |guard| {
+            Box::pin(guard.fetch_optional(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_all`, but stops the statement once `token` is cancelled; see
+    /// `fetch_one_with_cancellation`.
+    pub async fn fetch_all_with_cancellation(
+        &self,
+        query: Query<'_, Postgres, PgArguments>,
+        token: &CancellationToken,
+    ) -> TransactionResult<Vec<PgRow>> {
+        let sql = query.sql();
+        self.run_interruptible("fetch_all_with_cancellation", sql, Interrupt::Cancellation(token), |guard| {
+            Box::pin(guard.fetch_all(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_one_as`, but stops the statement once `token` is cancelled; see
+    /// `fetch_one_with_cancellation`.
+    pub async fn fetch_one_as_with_cancellation<T>(
+        &self,
+        query: QueryAs<'_, Postgres, T, PgArguments>,
+        token: &CancellationToken,
+    ) -> TransactionResult<T>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let sql = query.sql();
+        self.run_interruptible("fetch_one_as_with_cancellation", sql, Interrupt::Cancellation(token), |guard| {
+            Box::pin(guard.fetch_one_as(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_optional_as`, but stops the statement once `token` is cancelled;
+    /// see `fetch_one_with_cancellation`.
+    pub async fn fetch_optional_as_with_cancellation<T>(
+        &self,
+        query: QueryAs<'_, Postgres, T, PgArguments>,
+        token: &CancellationToken,
+    ) -> TransactionResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let sql = query.sql();
+        self.run_interruptible("fetch_optional_as_with_cancellation", sql, Interrupt::Cancellation(token), |guard| {
+            Box::pin(guard.fetch_optional_as(query))
+        })
+        .await
+    }
+    
+    /// Like `fetch_all_as`, but stops the statement once `token` is cancelled; see
+    /// `fetch_one_with_cancellation`.
+    pub async fn fetch_all_as_with_cancellation<T>(
+        &self,
+        query: QueryAs<'_, Postgres, T, PgArguments>,
+        token: &CancellationToken,
+    ) -> TransactionResult<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let sql = query.sql();
+        self.run_interruptible("fetch_all_as_with_cancellation", sql, Interrupt::Cancellation(token), |guard| {
+            Box::pin(guard.fetch_all_as(query))
+        })
+        .await
+    }
+    
+    /// Runs the statement `run` starts on the locked connection, cancelling it
+    /// on the server once `interrupt` fires; the body of the `*_with_timeout`
+    /// and `*_with_cancellation` helpers.
+    ///
+    /// A timed out statement runs inside a savepoint, keeping the transaction
+    /// usable, while a cancelled one leaves it to be rolled back.
+    async fn run_interruptible<'a, T, F>(
+        &'a self,
+        label: &'static str,
+        sql: &str,
+        interrupt: Interrupt<'_>,
+        run: F,
+    ) -> TransactionResult<T>
+    where
+        F: for<'g> FnOnce(&'g mut ExecutorGuard<'a>) -> BoxFuture<'g, TransactionResult<T>>,
+    {
+        if let Interrupt::Cancellation(token) = interrupt {
+            if token.is_cancelled() {
+                return Err(TransactionError::Cancelled);
+            }
+        }
+        let mut guard = self.lock_as(label).await?;
+        let statement = guard
+            .start_interruptible(matches!(interrupt, Interrupt::Timeout(_)))
+            .await?;
+        let running = run(&mut guard);
+        let result = match interrupt {
+            Interrupt::Timeout(timeout) => tokio::time::timeout(timeout, running).await.ok(),
+            Interrupt::Cancellation(token) => token.run_until_cancelled(running).await,
+        };
+        guard
+            .finish_interruptible(statement, sql, result, |source| match interrupt {
+                Interrupt::Timeout(timeout) => timed_out(source, timeout),
+                Interrupt::Cancellation(_) => self.cancelled_error(),
+            })
+            .await
+    }
+    
+    /// The Executor attached to the running task with `attach`, for code that
+    /// is not handed one, such as argument-free repository functions.
+    ///
+    /// Fails with `TransactionError::NoCurrentSession` outside of any
+    /// attachment.
+    pub fn current() -> TransactionResult<Executor> {
+        CURRENT
+            .try_with(Executor::clone)
+            .map_err(|_| TransactionError::NoCurrentSession)
+    }
+    
+    /// Runs `future` with this Executor as `Executor::current`.
+    ///
+    /// Attachments nest: inside an inner one, `current` returns the inner
+    /// Executor until it ends. The attachment is local to the task polling
+    /// `future`, so tasks it spawns do not see it; attach them too.
+    pub fn attach<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        CURRENT.scope(self.clone(), future)
+    }
+    
+    /// A view of this Executor offering only the fetch helpers, for code that
+    /// must not modify data.
+    pub fn read_only(&self) -> ReadOnlyExecutor {
+        ReadOnlyExecutor::new(self.clone())
+    }
+    
+    /// Locks the transaction once for running many statements through the
+    /// returned guard, without the per-call locking of the helpers.
+    ///
+    /// The other helpers wait until the guard is dropped. A pool-backed
+    /// Executor's guard holds one pooled connection instead.
+    pub async fn lock(&self) -> TransactionResult<ExecutorGuard<'_>> {
+        let guard = self.lock_as("lock").await?;
+        self.hand_out();
+        Ok(guard)
+    }
+    
+    /// Runs `f` with the transaction locked for its whole duration.
+    ///
+    /// Use it for statements that must not interleave with other repositories'
+    /// queries, such as a SELECT followed by an UPDATE of the same rows.
+    /// Database errors returned by `f` are not classified.
+    pub async fn with_tx<F, T>(&self, f: F) -> TransactionResult<T>
+    where
+        F: for<'t> FnOnce(&'t mut Transaction<'static, Postgres>) -> BoxFuture<'t, TransactionResult<T>>,
+    {
+        if let Some(pool) = &self.pool {
+            return self.with_pool_tx(pool, f).await;
+        }
+        let mut tx = self.lock_tx("with_tx").await?;
+        let locked = self.locked_with_this();
+        LOCKED.scope(locked, f(&mut tx)).await
+    }
+    
+    /// Like `with_tx`, but fails with `ExecutorBusy` instead of waiting when
+    /// the transaction is locked elsewhere.
+    pub async fn try_with_tx<F, T>(&self, f: F) -> TransactionResult<T>
+    where
+        F: for<'t> FnOnce(&'t mut Transaction<'static, Postgres>) -> BoxFuture<'t, TransactionResult<T>>,
+    {
+        if let Some(pool) = &self.pool {
+            return self.with_pool_tx(pool, f).await;
+        }
+        let tx_guard = self.tx.try_lock().map_err(|_| self.busy_error())?;
+        let mut tx = self.guard(tx_guard, "try_with_tx")?;
+        let locked = self.locked_with_this();
+        LOCKED.scope(locked, f(&mut tx)).await
+    }
+    
+    /// Locks the transaction and returns it as a connection for running sqlx
+    /// queries directly, including the `query!` macros.
+    ///
+    /// `&mut ExecutorConn` is a `sqlx::Executor`, and it derefs to `PgConnection`.
+    /// The other helpers wait until it is dropped. A pool-backed Executor
+    /// returns a pooled connection instead.
+    pub async fn acquire(&self) -> TransactionResult<ExecutorConn<'_>> {
+        let conn = self.lock_conn("acquire").await?;
+        self.hand_out();
+        Ok(ExecutorConn(conn))
+    }
+    
+    /// Starts a `COPY ... FROM STDIN` statement on the transaction.
+    ///
+    /// The Executor is locked until the returned sink is finished or dropped,
+    /// and the copied rows roll back with the transaction.
+    pub async fn copy_in(&self, statement: &str) -> TransactionResult<CopyInSink<'_>> {
+        let mut conn = self.lock_conn("copy_in").await?;
+        self.hand_out();
+        let statement = statement.to_string();
+        let (chunks, mut received) = mpsc::channel::<Bytes>(0);
+        let (started, on_started) = oneshot::channel();
+        let instrumentation = &self.instrumentation;
+        instrumentation.count_statements(1);
+        let copy = async move {
+            let mut copy = conn.copy_in_raw(&statement).await?;
+            let _ = started.send(());
+            while let Some(chunk) = received.next().await {
+                copy.send(chunk).await?;
+            }
+            let rows = copy.finish().await?;
+            instrumentation.count_rows(Rows::Affected, rows);
+            Ok(rows)
+        };
+        let copy = copy.map_err(|error| self.classify_error(error)).boxed();
+        CopyInSink::start(chunks, copy, on_started).await
+    }
+    
+    /// Runs a `COPY ... TO STDOUT` statement and yields the data as the server
+    /// sends it, in the format the statement names.
+    ///
+    /// The copy sees the transaction's own writes. Like `fetch_stream`, the
+    /// stream holds the transaction and the other helpers fail with
+    /// `ExecutorBusy` until it is dropped.
+    ///
+    /// sqlx 0.8 leaves the connection out of step with the server when the
+    /// statement itself is rejected (e.g. an unknown table), so only pass
+    /// statements known to be valid. Errors while the copy runs are safe.
+    pub async fn copy_out(
+        &self,
+        statement: &str,
+    ) -> TransactionResult<impl Stream<Item = TransactionResult<Bytes>> + Send + '_> {
+        let mut conn = self.lock_conn("copy_out").await?;
+        let streaming = match conn {
+            ConnGuard::Pooled(_) => None,
+            ConnGuard::Transaction(_) => {
+                Some(StreamingGuard::acquire(&self.streaming).ok_or_else(|| self.busy_error())?)
+            }
+        };
+        let statement = statement.to_string();
+        self.instrumentation.count_statements(1);
+        let chunks = async_stream::stream! {
+            let _streaming = streaming;
+            let mut chunks = match conn.copy_out_raw(&statement).await {
+                Ok(chunks) => chunks,
+                Err(error) => {
+                    yield Err(self.classify_error(error));
+                    return;
+                }
+            };
+            while let Some(chunk) = chunks.next().await {
+                yield chunk.map_err(|error| self.classify_error(error));
+            }
+        };
+        
+        // Surface a rejected statement here rather than as the first chunk
+        let mut chunks = Box::pin(chunks.peekable());
+        if let Some(Err(error)) = chunks.as_mut().next_if(Result::is_err).await {
+            return Err(error);
+        }
+        Ok(chunks)
+    }
+    
+    /// Runs `query` and yields its rows as they arrive from the server.
+    ///
+    /// The stream holds the transaction for as long as it is alive: the other
+    /// helpers, `commit()` and a second stream fail with `ExecutorBusy` until
+    /// it is dropped. Locking `tx` directly while streaming on the same task
+    /// deadlocks.
+    pub fn fetch_stream<'q>(
+        &self,
+        query: Query<'q, Postgres, PgArguments>,
+    ) -> impl Stream<Item = TransactionResult<PgRow>> + Send + 'q {
+        let executor = self.clone();
+        async_stream::stream! {
+            // Declared in drop order: the lock record is cleared before the lock
+            // is released, and the streaming mark after
+            let _streaming;
+            let mut tx_guard;
+            let _holder;
+            let mut pooled;
+            let conn: &mut PgConnection = match &executor.pool {
+                Some(pool) => {
+                    pooled = match pool.acquire().await {
+                        Ok(conn) => conn,
+                        Err(error) => {
+                            yield Err(executor.classify_error(error));
+                            return;
+                        }
+                    };
+                    &mut pooled
+                }
+                None => {
+                    let Some(streaming) = StreamingGuard::acquire(&executor.streaming) else {
+                        yield Err(executor.busy_error());
+                        return;
+                    };
+                    _streaming = streaming;
+                    if let Err(error) = executor.check_reentrant() {
+                        yield Err(error);
+                        return;
+                    }
+                    tx_guard = executor.tx.clone().lock_owned().await;
+                    _holder = LockRecord::new(&executor.holder, "fetch_stream");
+                    let Some(tx) = tx_guard.as_mut() else {
+                        yield Err(executor.completed_error());
+                        return;
+                    };
+                    tx
+                }
+            };
+            let mut commented = String::new();
+            let (query, parameters) = match executor.prepare(query, &mut commented) {
+                Ok(prepared) => prepared,
+                Err(error) => {
+                    yield Err(error);
+                    return;
+                }
+            };
+            let sql = query.sql();
+            let started = Instant::now();
+            let mut returned = 0;
+            executor.instrumentation.count_statements(1);
+            let mut rows = query.fetch(conn);
+            while let Some(row) = rows.next().await {
+                match row {
+                    Ok(row) => {
+                        returned += 1;
+                        executor.instrumentation.count_rows(Rows::Fetched, 1);
+                        yield Ok(row);
+                    }
+                    Err(error) => {
+                        let elapsed = started.elapsed();
+                        let error = executor.diagnose(executor.classify_error(error)).await;
+                        executor.instrumentation.record(
+                            sql,
+                            parameters.as_deref(),
+                            elapsed,
+                            Err(&error),
+                        );
+                        yield Err(error);
+                        return;
+                    }
+                }
+            }
+            executor.instrumentation.record(sql, parameters.as_deref(), started.elapsed(), Ok(returned));
+        }
+    }
+    
+    /// Readies a helper's query to run: appends the SQL comment, if any,
+    /// keeping the commented statement in `commented`, and turns off
+    /// statement caching if the session options or a `traceparent` tag ask
+    /// for it. Returns the bind parameters as rendered for the statement log
+    /// alongside, if statements are logged.
+    fn prepare<'q, 's>(
+        &self,
+        mut query: Query<'q, Postgres, PgArguments>,
+        commented: &'s mut String,
+    ) -> TransactionResult<(PreparedQuery<'q, 's>, Option<String>)> {
+        let comment = self.sql_comments.then(|| self.instrumentation.sql_comment()).flatten();
+        let comment = comment.and_then(|comment| comment.apply(query.sql()));
+        let log = self.instrumentation.statement_log();
+        if comment.is_none() && log.is_none() {
+            return Ok((PreparedQuery::Plain(self.apply_statement_cache(query)), None));
+        }
+        let sql = query.sql();
+        let persistent = Execute::persistent(&query);
+        let arguments = query
+            .take_arguments()
+            .map_err(|error| TransactionError::DatabaseError(sqlx::Error::Encode(error)))?
+            .unwrap_or_default();
+        let parameters = log.map(|log| log.parameters(&arguments));
+        let Some((sql, per_request)) = comment else {
+            let query = sqlx::query_with(sql, arguments).persistent(persistent);
+            return Ok((PreparedQuery::Plain(self.apply_statement_cache(query)), parameters));
+        };
+        *commented = sql;
+        let commented: &'s String = commented;
+        let query = sqlx::query_with(commented, arguments).persistent(persistent && !per_request);
+        Ok((PreparedQuery::Commented(self.apply_statement_cache(query)), parameters))
+    }
+    
+    /// Turns off statement caching for a helper's query if the session
+    /// options ask for it.
+    fn apply_statement_cache<Q: SessionQuery>(&self, query: Q) -> Q {
+        if self.options.caches_statements() {
+            query
+        } else {
+            query.uncached()
+        }
+    }
+    
+    /// Classifies the outcome of a helper's statement, counts it with the
+    /// number of rows `count` finds in it, and reports it to the query hook
+    /// and metrics.
+    async fn finish_query<T>(
+        &self,
+        sql: &str,
+        parameters: Option<&str>,
+        started: Instant,
+        result: Result<T, sqlx::Error>,
+        rows: Rows,
+        count: impl FnOnce(&T) -> u64,
+    ) -> TransactionResult<T> {
+        let elapsed = started.elapsed();
+        let result = match result {
+            Ok(value) => Ok(value),
+            Err(error) => Err(self.diagnose(self.classify_error(error)).await),
+        };
+        let counted = result.as_ref().map(count);
+        self.instrumentation.count_statements(1);
+        self.instrumentation.count_rows(rows, counted.as_ref().copied().unwrap_or(0));
+        self.instrumentation.record(sql, parameters, elapsed, counted);
+        result
+    }
+    
+    /// With the `deadlock-diagnostics` feature, attaches a snapshot of the
+    /// backends and locks involved to a deadlock, taken on a connection of
+    /// the pool the Executor belongs to.
+    async fn diagnose(&self, error: TransactionError) -> TransactionError {
+        #[cfg(feature = "deadlock-diagnostics")]
+        if let TransactionError::Deadlock { source, diagnostics: None } = error {
+            let diagnostics = match self.pool.as_ref().or(self.cancel_pool.as_ref()) {
+                Some(pool) => crate::deadlock::capture(pool, &source).await.map(Box::new),
+                None => None,
+            };
+            return TransactionError::Deadlock { source, diagnostics };
+        }
+        error
+    }
+    
+    /// Locks the connection for one of the query helpers, recording `label`
+    /// as the holder.
+    async fn lock_as(&self, label: &'static str) -> TransactionResult<ExecutorGuard<'_>> {
+        Ok(ExecutorGuard {
+            executor: self,
+            conn: self.lock_conn(label).await?,
+        })
+    }
+    
+    /// The connection for one of the query helpers: the locked transaction,
+    /// or a pooled connection for a pool-backed Executor.
+    async fn lock_conn(&self, label: &'static str) -> TransactionResult<ConnGuard<'_>> {
+        match &self.pool {
+            Some(pool) => {
+                let conn = pool.acquire().await.map_err(|error| self.classify_error(error))?;
+                Ok(ConnGuard::Pooled(conn))
+            }
+            None => Ok(ConnGuard::Transaction(self.lock_tx(label).await?)),
+        }
+    }
+    
+    /// Runs a `with_tx` closure in a transaction of its own, committing it if
+    /// the closure succeeds.
+    async fn with_pool_tx<F, T>(&self, pool: &PgPool, f: F) -> TransactionResult<T>
+    where
+        F: for<'t> FnOnce(&'t mut Transaction<'static, Postgres>) -> BoxFuture<'t, TransactionResult<T>>,
+    {
+        let mut tx = pool.begin().await.map_err(|error| self.classify_error(error))?;
+        let value = f(&mut tx).await?;
+        tx.commit().await.map_err(|error| self.classify_error(error))?;
+        Ok(value)
+    }
+    
+    /// Locks the transaction for one of the query helpers.
+    ///
+    /// `label` names the helper in `ExecutorBusy` errors while it holds the lock.
+    async fn lock_tx(&self, label: &'static str) -> TransactionResult<TxGuard<'_>> {
+        if self.streaming.load(Ordering::Acquire) {
+            return Err(self.busy_error());
+        }
+        self.check_reentrant()?;
+        let tx_guard = self.tx.lock().await;
+        self.guard(tx_guard, label)
+    }
+    
+    /// Fails with `ReentrantExecutorUse` if the current task runs inside a
+    /// `with_tx` closure of this transaction or holds a guard handed out to
+    /// it, and would otherwise wait on itself forever.
+    ///
+    /// A lock taken by another helper of the task is not re-entry: helpers
+    /// polled concurrently within one task wait for each other.
+    fn check_reentrant(&self) -> TransactionResult<()> {
+        let in_with_tx = LOCKED
+            .try_with(|locked| locked.contains(&self.lock_id()))
+            .unwrap_or(false);
+        match &*self.holder.current.lock() {
+            Some(holder) if in_with_tx || holder.owner == Some(LockOwner::current()) => {
+                Err(TransactionError::ReentrantExecutorUse {
+                    holder: holder.to_string(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Identifies the transaction shared by this Executor and its clones.
+    fn lock_id(&self) -> usize {
+        Arc::as_ptr(&self.tx) as usize
+    }
+    
+    /// The transactions the current task's `with_tx` closures lock, with this
+    /// one added, for the scope of a new closure.
+    fn locked_with_this(&self) -> Vec<usize> {
+        let mut locked = LOCKED.try_with(Vec::clone).unwrap_or_default();
+        locked.push(self.lock_id());
+        locked
+    }
+    
+    /// Records that the current task holds the lock through a guard returned
+    /// to it, rather than within a helper.
+    fn hand_out(&self) {
+        if let Some(holder) = self.holder.current.lock().as_mut() {
+            holder.owner = Some(LockOwner::current());
+        }
+    }
+    
+    /// Records `label` as the holder of a freshly locked transaction.
+    fn guard<'a>(
+        &'a self,
+        tx_guard: MutexGuard<'a, Option<Transaction<'static, Postgres>>>,
+        label: &'static str,
+    ) -> TransactionResult<TxGuard<'a>> {
+        if tx_guard.is_none() {
+            return Err(self.completed_error());
+        }
+        Ok(TxGuard {
+            _holder: LockRecord::new(&self.holder, label),
+            tx: tx_guard,
+        })
+    }
+    
+    /// The error for a transaction locked elsewhere, naming the holder if known.
+    fn busy_error(&self) -> TransactionError {
+        let holder = match &*self.holder.current.lock() {
+            Some(holder) => holder.to_string(),
+            None => "a direct lock of `Executor::tx`".to_string(),
+        };
+        TransactionError::ExecutorBusy { holder }
+    }
+    
+    /// Whether the transaction has been committed, rolled back, or taken out
+    /// of the Executor by other means.
+    pub(crate) fn is_completed(&self) -> bool {
+        self.has_outcome() || self.tx.try_lock().is_ok_and(|tx| tx.is_none())
+    }
+    
+    /// Whether the session recorded that the transaction was committed or
+    /// rolled back.
+    fn has_outcome(&self) -> bool {
+        matches!(*self.state.read(), SessionState::Committed | SessionState::RolledBack)
     }
     
-    /// Takes ownership of the transaction, leaving None in its place.
+    /// The error of a statement cancelled through its token, marking the
+    /// transaction as needing a rollback.
+    fn cancelled_error(&self) -> TransactionError {
+        if self.pool.is_none() {
+            self.set_state(SessionState::Poisoned);
+        }
+        TransactionError::Cancelled
+    }
+    
+    /// Sends the cancel requests of interrupted statements on `pool`.
+    pub(crate) fn set_cancel_pool(&mut self, pool: Arc<PgPool>) {
+        self.cancel_pool = Some(pool);
+    }
+    
+    /// The work done through the query helpers of this Executor and its clones.
+    pub(crate) fn stats(&self) -> SessionStats {
+        self.instrumentation.stats()
+    }
+    
+    /// Fails if the transaction cannot be completed right now: a stream from
+    /// `fetch_stream` is still open, or the caller already holds it.
+    pub(crate) fn check_completable(&self) -> TransactionResult<()> {
+        if self.streaming.load(Ordering::Acquire) {
+            return Err(self.busy_error());
+        }
+        self.check_reentrant()
+    }
+    
+    /// Takes ownership of the transaction, leaving None in its place, and
+    /// records that the caller is about to commit or roll it back.
     /// This should only be called when committing or rolling back.
-    pub(crate) async fn take_transaction(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
-        self.tx.lock().await.take().ok_or(sqlx::Error::PoolClosed)
+    pub(crate) async fn take_transaction(
+        &self,
+        completing: SessionState,
+    ) -> TransactionResult<Transaction<'static, Postgres>> {
+        self.check_completable()?;
+        let mut tx_guard = self.tx.lock().await;
+        let tx = tx_guard.take().ok_or_else(|| self.completed_error())?;
+        self.set_state(completing);
+        Ok(tx)
+    }
+    
+    /// The lifecycle state shared by the Executor and its clones.
+    pub(crate) fn shared_state(&self) -> Arc<RwLock<SessionState>> {
+        self.state.clone()
+    }
+    
+    /// Records where the session is in its lifecycle.
+    pub(crate) fn set_state(&self, state: SessionState) {
+        *self.state.write() = state;
+    }
+}
+
+impl fmt::Debug for Executor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Executor")
+            .field("state", &self.state())
+            .field("status", &self.status())
+            .field("options", &self.options)
+            .field("instrumentation", &self.instrumentation)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The query types accepted by the Executor's helpers.
+trait SessionQuery {
+    /// The query, run as an unnamed statement that is not cached.
+    fn uncached(self) -> Self;
+}
+
+impl SessionQuery for Query<'_, Postgres, PgArguments> {
+    fn uncached(self) -> Self {
+        self.persistent(false)
+    }
+}
+
+impl<T> SessionQuery for QueryAs<'_, Postgres, T, PgArguments> {
+    fn uncached(self) -> Self {
+        self.persistent(false)
+    }
+}
+
+/// A helper's query, as passed in or rebuilt with the SQL comment appended.
+///
+/// Queries are invariant in the lifetime of their SQL, so the commented SQL,
+/// which lives shorter than the caller's, needs a query of its own.
+enum PreparedQuery<'q, 's> {
+    Plain(Query<'q, Postgres, PgArguments>),
+    Commented(Query<'s, Postgres, PgArguments>),
+}
+
+impl<'q: 's, 's> PreparedQuery<'q, 's> {
+    /// The SQL that is run.
+    fn sql(&self) -> &'s str {
+        match self {
+            PreparedQuery::Plain(query) => query.sql(),
+            PreparedQuery::Commented(query) => query.sql(),
+        }
+    }
+    
+    async fn execute(self, conn: &mut PgConnection) -> Result<PgQueryResult, sqlx::Error> {
+        match self {
+            PreparedQuery::Plain(query) => query.execute(conn).await,
+            PreparedQuery::Commented(query) => query.execute(conn).await,
+        }
+    }
+    
+    async fn fetch_one(self, conn: &mut PgConnection) -> Result<PgRow, sqlx::Error> {
+        match self {
+            PreparedQuery::Plain(query) => query.fetch_one(conn).await,
+            PreparedQuery::Commented(query) => query.fetch_one(conn).await,
+        }
+    }
+    
+    async fn fetch_optional(self, conn: &mut PgConnection) -> Result<Option<PgRow>, sqlx::Error> {
+        match self {
+            PreparedQuery::Plain(query) => query.fetch_optional(conn).await,
+            PreparedQuery::Commented(query) => query.fetch_optional(conn).await,
+        }
+    }
+    
+    async fn fetch_all(self, conn: &mut PgConnection) -> Result<Vec<PgRow>, sqlx::Error> {
+        match self {
+            PreparedQuery::Plain(query) => query.fetch_all(conn).await,
+            PreparedQuery::Commented(query) => query.fetch_all(conn).await,
+        }
+    }
+    
+    fn fetch<'e>(self, conn: &'e mut PgConnection) -> BoxStream<'e, Result<PgRow, sqlx::Error>>
+    where
+        's: 'e,
+    {
+        match self {
+            PreparedQuery::Plain(query) => query.fetch(conn),
+            PreparedQuery::Commented(query) => query.fetch(conn),
+        }
+    }
+}
+
+/// What stops the statement of an interruptible helper.
+#[derive(Clone, Copy)]
+enum Interrupt<'a> {
+    /// Running for longer than the timeout.
+    Timeout(Duration),
+    /// The token being cancelled.
+    Cancellation(&'a CancellationToken),
+}
+
+/// The Executor call currently holding the transaction lock.
+#[derive(Debug)]
+struct LockHolder {
+    label: &'static str,
+    since: Instant,
+    /// The task holding a guard handed out by `lock`, `acquire` or `copy_in`.
+    owner: Option<LockOwner>,
+}
+
+impl fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} since {:.1?} ago", self.label, self.since.elapsed())
+    }
+}
+
+/// The task that took a lock, or the thread for code running outside of a
+/// task, such as the future passed to `Runtime::block_on`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum LockOwner {
+    Task(tokio::task::Id),
+    Thread(std::thread::ThreadId),
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        tokio::task::try_id().map_or_else(|| LockOwner::Thread(std::thread::current().id()), LockOwner::Task)
+    }
+}
+
+/// The labels the helpers record as lock holders, by which `Holder` packs
+/// them into an atomic.
+const HOLDER_LABELS: &[&str] = &[
+    "acquire",
+    "bulk_insert",
+    "copy_in",
+    "copy_out",
+    "execute",
+    "execute_batch",
+    "execute_many",
+    "execute_pipelined",
+    "execute_with_cancellation",
+    "execute_with_timeout",
+    "fetch_all",
+    "fetch_all_as",
+    "fetch_all_as_with_cancellation",
+    "fetch_all_as_with_timeout",
+    "fetch_all_with_cancellation",
+    "fetch_all_with_timeout",
+    "fetch_one",
+    "fetch_one_as",
+    "fetch_one_as_with_cancellation",
+    "fetch_one_as_with_timeout",
+    "fetch_one_with_cancellation",
+    "fetch_one_with_timeout",
+    "fetch_optional",
+    "fetch_optional_as",
+    "fetch_optional_as_with_cancellation",
+    "fetch_optional_as_with_timeout",
+    "fetch_optional_with_cancellation",
+    "fetch_optional_with_timeout",
+    "fetch_stream",
+    "insert_returning",
+    "insert_returning_optional",
+    "lock",
+    "try_with_tx",
+    "with_tx",
+];
+
+/// The bits of `Holder::packed` holding when the lock was taken.
+const SINCE_MASK: u64 = (1 << 56) - 1;
+
+/// The Executor call holding the transaction lock, shared by an Executor's
+/// clones.
+struct Holder {
+    /// The holder in full, for errors and the re-entrancy check.
+    current: SyncMutex<Option<LockHolder>>,
+    /// The holder's label and start, for `Executor::status` to read without
+    /// taking `current`: the position of the label in `HOLDER_LABELS` plus
+    /// one in the top byte, or 0 while free, and the microseconds from
+    /// `epoch` to the start in the rest.
+    packed: AtomicU64,
+    epoch: Instant,
+}
+
+impl Holder {
+    fn new() -> Self {
+        Self {
+            current: SyncMutex::new(None),
+            packed: AtomicU64::new(0),
+            epoch: Instant::now(),
+        }
+    }
+    
+    /// The label of the holder and how long it has held the lock, if any.
+    fn snapshot(&self) -> Option<(&'static str, Duration)> {
+        let packed = self.packed.load(Ordering::Acquire);
+        let label = HOLDER_LABELS.get(usize::try_from(packed >> 56).ok()?.checked_sub(1)?)?;
+        let since = self.epoch + Duration::from_micros(packed & SINCE_MASK);
+        Some((label, since.elapsed()))
+    }
+}
+
+/// Records a lock holder until dropped.
+struct LockRecord(Arc<Holder>);
+
+impl LockRecord {
+    fn new(holder: &Arc<Holder>, label: &'static str) -> Self {
+        let since = Instant::now();
+        *holder.current.lock() = Some(LockHolder {
+            label,
+            since,
+            owner: None,
+        });
+        let index = HOLDER_LABELS.iter().position(|known| *known == label);
+        debug_assert!(index.is_some(), "{label} is missing from HOLDER_LABELS");
+        let micros = u64::try_from(since.duration_since(holder.epoch).as_micros()).unwrap_or(SINCE_MASK);
+        let packed = index.map_or(0, |index| (index as u64 + 1) << 56) | (micros & SINCE_MASK);
+        holder.packed.store(packed, Ordering::Release);
+        Self(holder.clone())
+    }
+}
+
+impl Drop for LockRecord {
+    fn drop(&mut self) {
+        self.0.packed.store(0, Ordering::Release);
+        *self.0.current.lock() = None;
+    }
+}
+
+/// A locked transaction that is known to be present.
+struct TxGuard<'a> {
+    // Declared first so the record is cleared before the lock is released
+    _holder: LockRecord,
+    tx: MutexGuard<'a, Option<Transaction<'static, Postgres>>>,
+}
+
+impl Deref for TxGuard<'_> {
+    type Target = Transaction<'static, Postgres>;
+    
+    fn deref(&self) -> &Self::Target {
+        self.tx.as_ref().expect("TxGuard holds a transaction")
+    }
+}
+
+impl DerefMut for TxGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.tx.as_mut().expect("TxGuard holds a transaction")
+    }
+}
+
+/// The connection a query helper runs on.
+enum ConnGuard<'a> {
+    Transaction(TxGuard<'a>),
+    Pooled(PoolConnection<Postgres>),
+}
+
+impl Deref for ConnGuard<'_> {
+    type Target = PgConnection;
+    
+    fn deref(&self) -> &Self::Target {
+        match self {
+            ConnGuard::Transaction(tx) => tx,
+            ConnGuard::Pooled(conn) => conn,
+        }
+    }
+}
+
+impl DerefMut for ConnGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            ConnGuard::Transaction(tx) => tx,
+            ConnGuard::Pooled(conn) => conn,
+        }
+    }
+}
+
+/// Marks an Executor as streaming until dropped.
+struct StreamingGuard(Arc<AtomicBool>);
+
+impl StreamingGuard {
+    /// Marks `streaming`, or returns None if another stream already did.
+    fn acquire(streaming: &Arc<AtomicBool>) -> Option<Self> {
+        (!streaming.swap(true, Ordering::AcqRel)).then(|| Self(streaming.clone()))
+    }
+}
+
+impl Drop for StreamingGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+/// The transaction of an Executor, locked by `Executor::lock` for running
+/// the query helpers on it without locking again for each statement.
+pub struct ExecutorGuard<'a> {
+    executor: &'a Executor,
+    conn: ConnGuard<'a>,
+}
+
+impl ExecutorGuard<'_> {
+    /// Like `Executor::execute`.
+    pub async fn execute(&mut self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<PgQueryResult> {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(query, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.execute(&mut self.conn).await;
+        self.executor
+            .finish_query(
+                sql,
+                parameters.as_deref(),
+                started,
+                result,
+                Rows::Affected,
+                PgQueryResult::rows_affected,
+            )
+            .await
+    }
+    
+    /// Like `Executor::execute_batch`.
+    pub async fn execute_batch(&mut self, sql: &str) -> TransactionResult<()> {
+        let instrumentation = &self.executor.instrumentation;
+        let started = Instant::now();
+        let mut results = sqlx::raw_sql(sql).execute_many(&mut *self.conn);
+        let mut index = 0;
+        let mut rows_affected = 0;
+        let result = loop {
+            match results.next().await {
+                Some(Ok(result)) => {
+                    instrumentation.count_statements(1);
+                    instrumentation.count_rows(Rows::Affected, result.rows_affected());
+                    rows_affected += result.rows_affected();
+                }
+                Some(Err(error)) => {
+                    instrumentation.count_statements(1);
+                    break Err(TransactionError::BatchStatementFailed {
+                        index,
+                        source: Box::new(self.executor.diagnose(self.executor.classify_error(error)).await),
+                    })
+                }
+                None => break Ok(rows_affected),
+            }
+            index += 1;
+        };
+        instrumentation.record(sql, None, started.elapsed(), result.as_ref().copied());
+        result.map(drop)
+    }
+    
+    /// Like `Executor::fetch_one`.
+    pub async fn fetch_one(&mut self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<PgRow> {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(query, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.fetch_one(&mut self.conn).await;
+        self.executor.finish_query(sql, parameters.as_deref(), started, result, Rows::Fetched, |_| 1).await
+    }
+    
+    /// Like `Executor::fetch_optional`.
+    pub async fn fetch_optional(&mut self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<Option<PgRow>> {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(query, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.fetch_optional(&mut self.conn).await;
+        self.executor
+            .finish_query(
+                sql,
+                parameters.as_deref(),
+                started,
+                result,
+                Rows::Fetched,
+                |row| u64::from(row.is_some()),
+            )
+            .await
+    }
+    
+    /// Like `Executor::fetch_all`.
+    pub async fn fetch_all(&mut self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<Vec<PgRow>> {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(query, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.fetch_all(&mut self.conn).await;
+        self.executor
+            .finish_query(
+                sql,
+                parameters.as_deref(),
+                started,
+                result,
+                Rows::Fetched,
+                |rows| rows.len() as u64,
+            )
+            .await
+    }
+    
+    /// Like `Executor::fetch_one_as`.
+    pub async fn fetch_one_as<T>(&mut self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<T>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(untyped(query)?, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.fetch_one(&mut self.conn).await;
+        let row = self.executor.finish_query(
+            sql,
+            parameters.as_deref(),
+            started,
+            result,
+            Rows::Fetched,
+            |_| 1,
+        ).await?;
+        decode(&row, sql)
+    }
+    
+    /// Like `Executor::fetch_optional_as`.
+    pub async fn fetch_optional_as<T>(
+        &mut self,
+        query: QueryAs<'_, Postgres, T, PgArguments>,
+    ) -> TransactionResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(untyped(query)?, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.fetch_optional(&mut self.conn).await;
+        let row = self
+            .executor
+            .finish_query(
+                sql,
+                parameters.as_deref(),
+                started,
+                result,
+                Rows::Fetched,
+                |row| u64::from(row.is_some()),
+            ).await?;
+        row.map(|row| decode(&row, sql)).transpose()
+    }
+    
+    /// Like `Executor::fetch_all_as`.
+    pub async fn fetch_all_as<T>(&mut self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(untyped(query)?, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.fetch_all(&mut self.conn).await;
+        let rows = self
+            .executor
+            .finish_query(
+                sql,
+                parameters.as_deref(),
+                started,
+                result,
+                Rows::Fetched,
+                |rows| rows.len() as u64,
+            ).await?;
+        rows.iter().map(|row| decode(row, sql)).collect()
+    }
+    
+    /// Like `Executor::insert_returning`.
+    pub async fn insert_returning<'q, T, B>(&mut self, sql: &'q str, bind: B) -> TransactionResult<T>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        B: FnOnce(QueryAs<'q, Postgres, T, PgArguments>) -> QueryAs<'q, Postgres, T, PgArguments>,
+    {
+        let mut rows = self.returning(sql, bind).await?;
+        match rows.len() {
+            1 => Ok(rows.remove(0)),
+            rows => Err(TransactionError::UnexpectedRowCount {
+                expected: "exactly one",
+                rows,
+            }),
+        }
+    }
+    
+    /// Like `Executor::insert_returning_optional`.
+    pub async fn insert_returning_optional<'q, T, B>(&mut self, sql: &'q str, bind: B) -> TransactionResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        B: FnOnce(QueryAs<'q, Postgres, T, PgArguments>) -> QueryAs<'q, Postgres, T, PgArguments>,
+    {
+        let mut rows = self.returning(sql, bind).await?;
+        match rows.len() {
+            0 => Ok(None),
+            1 => Ok(rows.pop()),
+            rows => Err(TransactionError::UnexpectedRowCount {
+                expected: "at most one",
+                rows,
+            }),
+        }
+    }
+    
+    /// Runs a statement returning rows that count as affected, such as an
+    /// `INSERT ... RETURNING`.
+    async fn returning<'q, T, B>(&mut self, sql: &'q str, bind: B) -> TransactionResult<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+        B: FnOnce(QueryAs<'q, Postgres, T, PgArguments>) -> QueryAs<'q, Postgres, T, PgArguments>,
+    {
+        let mut commented = String::new();
+        let (query, parameters) = self.executor.prepare(untyped(bind(sqlx::query_as(sql)))?, &mut commented)?;
+        let sql = query.sql();
+        let started = Instant::now();
+        let result = query.fetch_all(&mut self.conn).await;
+        let rows = self
+            .executor
+            .finish_query(
+                sql,
+                parameters.as_deref(),
+                started,
+                result,
+                Rows::Affected,
+                |rows| rows.len() as u64,
+            ).await?;
+        rows.iter().map(|row| decode(row, sql)).collect()
+    }
+    
+    /// Prepares the connection for a statement that may be interrupted: sets a
+    /// savepoint if asked to inside a transaction, and looks up the server
+    /// process to cancel.
+    async fn start_interruptible(&mut self, savepoint: bool) -> TransactionResult<InterruptibleStatement> {
+        let savepoint = savepoint && matches!(self.conn, ConnGuard::Transaction(_));
+        let sql = if savepoint {
+            "SAVEPOINT postgres_unit_of_work_interrupt; SELECT pg_backend_pid()"
+        } else {
+            "SELECT pg_backend_pid()"
+        };
+        let backend_pid = sqlx::raw_sql(sql)
+            .fetch_one(&mut *self.conn)
+            .await
+            .and_then(|row| row.try_get(0))
+            .map_err(|error| self.executor.classify_error(error))?;
+        Ok(InterruptibleStatement {
+            backend_pid,
+            savepoint,
+            started: Instant::now(),
+        })
+    }
+    
+    /// Completes a statement that may have been interrupted, as shown by a
+    /// `result` of None. An interrupted statement is cancelled and fails with
+    /// the error `interrupted` makes of the server's cancellation error.
+    async fn finish_interruptible<T>(
+        &mut self,
+        statement: InterruptibleStatement,
+        sql: &str,
+        result: Option<TransactionResult<T>>,
+        interrupted: impl FnOnce(Option<sqlx::Error>) -> TransactionError,
+    ) -> TransactionResult<T> {
+        match result {
+            Some(Ok(value)) if statement.savepoint => {
+                sqlx::raw_sql("RELEASE SAVEPOINT postgres_unit_of_work_interrupt")
+                    .execute(&mut *self.conn)
+                    .await
+                    .map_err(|error| self.executor.classify_error(error))?;
+                Ok(value)
+            }
+            Some(result) => result,
+            None => {
+                let source = match self.cancel_statement(&statement).await {
+                    Ok(source) => source,
+                    Err(reason) => {
+                        tracing::warn!(sql = %sql, reason = %reason, "Failed to cancel interrupted statement");
+                        if let ConnGuard::Pooled(conn) = &mut self.conn {
+                            conn.close_on_drop();
+                        }
+                        None
+                    }
+                };
+                let error = interrupted(source);
+                let instrumentation = &self.executor.instrumentation;
+                instrumentation.count_statements(1);
+                instrumentation.record(sql, None, statement.started.elapsed(), Err(&error));
+                Err(error)
+            }
+        }
+    }
+    
+    /// Cancels an interrupted statement and waits for the connection to
+    /// settle, rolling back to the statement's savepoint if it has one.
+    /// Returns the server's cancellation error, which is None if the statement
+    /// completed before the cancel arrived.
+    async fn cancel_statement(&mut self, statement: &InterruptibleStatement) -> Result<Option<sqlx::Error>, String> {
+        let pool = self
+            .executor
+            .pool
+            .as_ref()
+            .or(self.executor.cancel_pool.as_ref())
+            .ok_or("no pool to send the cancel request on")?;
+        let cancel = sqlx::query_scalar("SELECT pg_cancel_backend($1)")
+            .bind(statement.backend_pid)
+            .fetch_one(&**pool);
+        let cancelled: bool = tokio::time::timeout(CANCEL_TIMEOUT, cancel)
+            .await
+            .map_err(|_| "timed out sending the cancel request".to_string())?
+            .map_err(|error| error.to_string())?;
+        if !cancelled {
+            return Err("the server did not accept the cancel request".to_string());
+        }
+        // The cancelled statement's error surfaces on the next use of the
+        // connection, and only the use after that completes
+        let mut source = None;
+        if let Err(error) = self.conn.ping().await {
+            if PgErrorKind::of(&error) != Some(PgErrorKind::QueryCanceled) {
+                return Err(error.to_string());
+            }
+            source = Some(error);
+            self.conn.ping().await.map_err(|error| error.to_string())?;
+        }
+        if statement.savepoint {
+            sqlx::raw_sql(
+                "ROLLBACK TO SAVEPOINT postgres_unit_of_work_interrupt; RELEASE SAVEPOINT postgres_unit_of_work_interrupt",
+            )
+            .execute(&mut *self.conn)
+            .await
+            .map_err(|error| error.to_string())?;
+        }
+        Ok(source)
+    }
+    
+    /// The locked transaction, for running sqlx queries on it directly, or
+    /// None for a pool-backed Executor.
+    pub fn transaction(&mut self) -> Option<&mut Transaction<'static, Postgres>> {
+        match &mut self.conn {
+            ConnGuard::Transaction(tx) => Some(tx),
+            ConnGuard::Pooled(_) => None,
+        }
+    }
+}
+
+/// A statement that may be interrupted, started by
+/// `ExecutorGuard::start_interruptible`.
+struct InterruptibleStatement {
+    /// Server process running the statement.
+    backend_pid: i32,
+    /// Whether the statement runs inside a savepoint.
+    savepoint: bool,
+    started: Instant,
+}
+
+/// The plain query behind a `query_as` query, for the typed helpers to decode
+/// its rows themselves and report decoding errors with the row's columns.
+fn untyped<'q, T>(mut query: QueryAs<'q, Postgres, T, PgArguments>) -> TransactionResult<Query<'q, Postgres, PgArguments>>
+where
+    T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+{
+    let sql = query.sql();
+    let persistent = Execute::persistent(&query);
+    let arguments = query
+        .take_arguments()
+        .map_err(|error| TransactionError::DatabaseError(sqlx::Error::Encode(error)))?
+        .unwrap_or_default();
+    Ok(sqlx::query_with(sql, arguments).persistent(persistent))
+}
+
+/// Maps a row returned by `sql` to `T`, failing with `Decode` if a column
+/// does not decode.
+fn decode<T>(row: &PgRow, sql: &str) -> TransactionResult<T>
+where
+    T: for<'r> FromRow<'r, PgRow>,
+{
+    T::from_row(row).map_err(|error| {
+        let sqlx::Error::ColumnDecode { index, source } = &error else {
+            return TransactionError::DatabaseError(error);
+        };
+        // sqlx formats the index with Debug: a quoted name or a position
+        let column = match index.parse::<usize>() {
+            Ok(position) => row.try_column(position),
+            Err(_) => row.try_column(index.trim_matches('"')),
+        };
+        let Ok(column) = column else {
+            return TransactionError::DatabaseError(error);
+        };
+        // Type mismatches are only described in the message
+        let message = source.to_string();
+        let rust_type = message
+            .split_once("Rust type `")
+            .and_then(|(_, rest)| rest.split_once('`'))
+            .map(|(rust_type, _)| rust_type.to_string());
+        TransactionError::Decode {
+            column: column.name().to_string(),
+            rust_type,
+            pg_type: column.type_info().name().to_string(),
+            sql: sql.to_string(),
+            source: Box::new(error),
+        }
+    })
+}
+
+/// The error of a statement that ran out of `timeout`, caused by the
+/// server's cancellation error if it was cancelled.
+fn timed_out(source: Option<sqlx::Error>, timeout: Duration) -> TransactionError {
+    let source = source.unwrap_or_else(|| {
+        sqlx::Error::Io(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("statement exceeded its timeout of {timeout:?}"),
+        ))
+    });
+    TransactionError::StatementTimeout {
+        source,
+        timeout: Some(timeout),
+    }
+}
+
+impl fmt::Debug for ExecutorGuard<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutorGuard").finish_non_exhaustive()
+    }
+}
+
+/// The transaction of an Executor, locked by `Executor::acquire`, or a
+/// pooled connection of a pool-backed Executor.
+pub struct ExecutorConn<'a>(ConnGuard<'a>);
+
+impl Deref for ExecutorConn<'_> {
+    type Target = PgConnection;
+    
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for ExecutorConn<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl fmt::Debug for ExecutorConn<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutorConn").finish_non_exhaustive()
+    }
+}
+
+impl<'c> sqlx::Executor<'c> for &'c mut ExecutorConn<'_> {
+    type Database = Postgres;
+    
+    fn fetch_many<'e, 'q: 'e, E>(self, query: E) -> BoxStream<'e, Result<Either<PgQueryResult, PgRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, Postgres>,
+    {
+        (&mut **self).fetch_many(query)
+    }
+    
+    fn fetch_optional<'e, 'q: 'e, E>(self, query: E) -> BoxFuture<'e, Result<Option<PgRow>, sqlx::Error>>
+    where
+        'c: 'e,
+        E: 'q + Execute<'q, Postgres>,
+    {
+        (&mut **self).fetch_optional(query)
+    }
+    
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [PgTypeInfo],
+    ) -> BoxFuture<'e, Result<PgStatement<'q>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        (&mut **self).prepare_with(sql, parameters)
+    }
+    
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<Postgres>, sqlx::Error>>
+    where
+        'c: 'e,
+    {
+        (&mut **self).describe(sql)
     }
 }
\ No newline at end of file
@@ -0,0 +1,62 @@
+use parking_lot::RwLock;
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Values stashed on a session by type, e.g. the request id or the
+/// authenticated user, as returned by `PostgresUnitOfWorkSession::extensions`.
+///
+/// Each type holds at most one value. Clones share the same values, so
+/// observers read what the application inserted through the
+/// `TransactionContext` they are passed.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    values: Arc<RwLock<HashMap<TypeId, Arc<dyn Any + Send + Sync>>>>,
+}
+
+impl Extensions {
+    /// Stores `value`, returning the value of the same type it replaces.
+    pub fn insert<T: Send + Sync + 'static>(&self, value: T) -> Option<Arc<T>> {
+        let previous = self.values.write().insert(TypeId::of::<T>(), Arc::new(value));
+        previous.and_then(downcast)
+    }
+
+    /// The value of type `T`, if one was inserted.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let value = self.values.read().get(&TypeId::of::<T>()).cloned();
+        value.and_then(downcast)
+    }
+
+    /// Removes and returns the value of type `T`.
+    pub fn remove<T: Send + Sync + 'static>(&self) -> Option<Arc<T>> {
+        let value = self.values.write().remove(&TypeId::of::<T>());
+        value.and_then(downcast)
+    }
+
+    /// Whether a value of type `T` was inserted.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.values.read().contains_key(&TypeId::of::<T>())
+    }
+
+    /// Number of values stored.
+    pub fn len(&self) -> usize {
+        self.values.read().len()
+    }
+
+    /// Whether no values are stored.
+    pub fn is_empty(&self) -> bool {
+        self.values.read().is_empty()
+    }
+}
+
+/// A stored value as the type it is keyed by.
+fn downcast<T: Send + Sync + 'static>(value: Arc<dyn Any + Send + Sync>) -> Option<Arc<T>> {
+    value.downcast().ok()
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions").field("len", &self.len()).finish()
+    }
+}
\ No newline at end of file
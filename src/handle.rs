@@ -0,0 +1,150 @@
+use parking_lot::{Mutex, RwLock};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+use crate::SessionState;
+
+/// A cheap, cloneable view of a session's progress, created with
+/// `PostgresUnitOfWorkSession::handle`, for code that needs to know whether a
+/// unit of work finished without owning it, such as shutdown logic.
+///
+/// The handle does not keep the transaction alive.
+#[derive(Clone, Debug)]
+pub struct SessionHandle {
+    id: Uuid,
+    started: Instant,
+    started_at: SystemTime,
+    label: Arc<Mutex<Option<String>>>,
+    state: Arc<RwLock<SessionState>>,
+    completion: Arc<Completion>,
+}
+
+impl SessionHandle {
+    pub(crate) fn new(
+        id: Uuid,
+        started: Instant,
+        started_at: SystemTime,
+        label: Arc<Mutex<Option<String>>>,
+        state: Arc<RwLock<SessionState>>,
+        completion: Arc<Completion>,
+    ) -> Self {
+        Self {
+            id,
+            started,
+            started_at,
+            label,
+            state,
+            completion,
+        }
+    }
+
+    /// Identifier of the session.
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// The session's label, if one was set.
+    pub fn label(&self) -> Option<String> {
+        self.label.lock().clone()
+    }
+
+    /// Wall-clock time the session began.
+    pub fn started_at(&self) -> SystemTime {
+        self.started_at
+    }
+
+    /// Where the session is in its lifecycle.
+    pub fn state(&self) -> SessionState {
+        *self.state.read()
+    }
+
+    /// How long ago the session began.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Whether the session has ended; see `wait_for_completion`.
+    pub fn is_completed(&self) -> bool {
+        self.completion.is_done()
+    }
+
+    /// Waits until the session has ended and returns the state it ended in.
+    ///
+    /// A session ends once it committed, rolled back, failed to roll back
+    /// (`Poisoned`) or was dropped; a session dropped while open ends once the
+    /// rollback that follows has run.
+    pub async fn wait_for_completion(&self) -> SessionState {
+        loop {
+            let notified = self.completion.notify.notified();
+            if self.completion.is_done() {
+                return self.state();
+            }
+            notified.await;
+        }
+    }
+}
+
+/// A snapshot of a session that is still open, as listed by
+/// `PostgresUnitOfWork::active_sessions`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SessionInfo {
+    /// Identifier of the session.
+    pub id: Uuid,
+    /// The session's label, if one was set.
+    pub label: Option<String>,
+    /// Wall-clock time the session began.
+    pub started_at: SystemTime,
+    /// Where the session was in its lifecycle when the snapshot was taken.
+    pub state: SessionState,
+}
+
+impl From<&SessionHandle> for SessionInfo {
+    fn from(handle: &SessionHandle) -> Self {
+        Self {
+            id: handle.id,
+            label: handle.label(),
+            started_at: handle.started_at,
+            state: handle.state(),
+        }
+    }
+}
+
+/// How the sessions open when `PostgresUnitOfWork::shutdown` was called
+/// ended.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ShutdownReport {
+    /// Sessions that committed, rolled back or were dropped within the grace
+    /// period.
+    pub completed: usize,
+    /// Sessions still open when the grace period ran out.
+    pub abandoned: usize,
+}
+
+/// Handles of the open sessions of a `PostgresUnitOfWork`, which each session
+/// removes itself from when it is dropped.
+pub(crate) type SessionRegistry = Mutex<HashMap<Uuid, SessionHandle>>;
+
+/// Signals the end of a session to its handles.
+#[derive(Debug, Default)]
+pub(crate) struct Completion {
+    done: AtomicBool,
+    notify: Notify,
+}
+
+impl Completion {
+    /// Marks the session as ended, waking every waiting handle.
+    pub(crate) fn complete(&self) {
+        self.done.store(true, Ordering::Release);
+        self.notify.notify_waiters();
+    }
+
+    fn is_done(&self) -> bool {
+        self.done.load(Ordering::Acquire)
+    }
+}
\ No newline at end of file
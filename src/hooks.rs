@@ -0,0 +1,272 @@
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use parking_lot::Mutex;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::unit_of_work::call_observer;
+use crate::{
+    Executor, TransactionAware, TransactionContext, TransactionError, TransactionOutcome, TransactionResult,
+};
+
+/// A boxed one-shot hook closure.
+type Hook = Box<dyn FnOnce(TransactionOutcome) -> BoxFuture<'static, TransactionResult<()>> + Send>;
+
+/// Which outcomes a closure hook runs for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum HookTrigger {
+    Commit,
+    Rollback,
+    Complete,
+}
+
+impl HookTrigger {
+    /// Name reported for hooks registered without a label.
+    fn default_name(self) -> &'static str {
+        match self {
+            HookTrigger::Commit => "on_commit hook",
+            HookTrigger::Rollback => "on_rollback hook",
+            HookTrigger::Complete => "on_complete hook",
+        }
+    }
+
+    fn matches(self, outcome: TransactionOutcome) -> bool {
+        match self {
+            HookTrigger::Commit => outcome == TransactionOutcome::Committed,
+            HookTrigger::Rollback => outcome == TransactionOutcome::RolledBack,
+            HookTrigger::Complete => true,
+        }
+    }
+}
+
+/// One-shot observer adapter running a closure when the transaction completes.
+///
+/// The closure is consumed by the first completion notification, and dropped
+/// unrun if that outcome does not match its trigger.
+pub(crate) struct ClosureHook {
+    trigger: HookTrigger,
+    label: Option<String>,
+    hook: Mutex<Option<Hook>>,
+}
+
+impl ClosureHook {
+    pub(crate) fn new<F, Fut>(trigger: HookTrigger, label: Option<String>, hook: F) -> Self
+    where
+        F: FnOnce(TransactionOutcome) -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        let hook: Hook = Box::new(move |outcome| Box::pin(hook(outcome)));
+        Self {
+            trigger,
+            label,
+            hook: Mutex::new(Some(hook)),
+        }
+    }
+
+    async fn fire(&self, outcome: TransactionOutcome) -> TransactionResult<()> {
+        let hook = self.hook.lock().take();
+        match hook {
+            Some(hook) if self.trigger.matches(outcome) => hook(outcome).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionAware for ClosureHook {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.fire(TransactionOutcome::Committed).await
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.fire(TransactionOutcome::RolledBack).await
+    }
+
+    async fn on_rollback_failure(&self, _error: &TransactionError) -> TransactionResult<()> {
+        self.fire(TransactionOutcome::Failed).await
+    }
+
+    fn name(&self) -> &str {
+        self.label.as_deref().unwrap_or(self.trigger.default_name())
+    }
+
+    /// A failed attempt drops its commit hooks unrun, so retrying is safe.
+    fn is_idempotent(&self) -> bool {
+        self.trigger == HookTrigger::Commit
+    }
+}
+
+/// A boxed compensating action.
+type Compensation = Box<dyn FnOnce() -> BoxFuture<'static, TransactionResult<()>> + Send>;
+
+/// Observer running the compensating actions registered with a session once
+/// its transaction has been rolled back, most recently registered first.
+///
+/// Every action runs even if an earlier one fails, panics or times out;
+/// failures are collected into `TransactionError::ObserverErrors` like a
+/// composite observer's. The actions are dropped unrun if the session
+/// commits.
+#[derive(Default)]
+pub(crate) struct Compensations {
+    actions: Mutex<Vec<(String, Option<Duration>, Compensation)>>,
+}
+
+impl Compensations {
+    /// Add an action given `timeout` to run in, returning whether it is the
+    /// first one.
+    pub(crate) fn push<F, Fut>(&self, name: String, timeout: Option<Duration>, action: F) -> bool
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        let action: Compensation = Box::new(move || Box::pin(action()));
+        let mut actions = self.actions.lock();
+        actions.push((name, timeout, action));
+        actions.len() == 1
+    }
+
+    async fn run(&self) -> TransactionResult<()> {
+        let actions = std::mem::take(&mut *self.actions.lock());
+        let mut errors = Vec::new();
+        for (name, timeout, action) in actions.into_iter().rev() {
+            match call_observer(&name, timeout, action()).await {
+                Ok(()) => {}
+                Err(error @ (TransactionError::ObserverPanicked { .. } | TransactionError::ObserverTimeout { .. })) => {
+                    errors.push(error);
+                }
+                Err(error) => errors.push(TransactionError::ObserverFailed {
+                    observer: name,
+                    source: Box::new(error),
+                }),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(TransactionError::ObserverErrors(errors))
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionAware for Compensations {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.actions.lock().clear();
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.run().await
+    }
+
+    /// Nothing was committed even if ROLLBACK failed, so compensate anyway.
+    async fn on_rollback_failure(&self, _error: &TransactionError) -> TransactionResult<()> {
+        self.run().await
+    }
+
+    fn name(&self) -> &str {
+        "compensations"
+    }
+}
+
+/// Observer wrapper forwarding at most one completion notification.
+///
+/// The wrapped observer is taken out by the first commit, rollback or
+/// rollback failure notification, so it cannot fire twice and is released as
+/// soon as it has run.
+pub(crate) struct OnceObserver {
+    name: String,
+    observer: Mutex<Option<Arc<dyn TransactionAware>>>,
+}
+
+impl OnceObserver {
+    pub(crate) fn new(observer: Arc<dyn TransactionAware>) -> Self {
+        Self {
+            name: observer.name().to_string(),
+            observer: Mutex::new(Some(observer)),
+        }
+    }
+
+    /// The observer, if it has not fired yet.
+    fn pending(&self) -> Option<Arc<dyn TransactionAware>> {
+        self.observer.lock().clone()
+    }
+
+    /// Take the observer out for its one completion notification.
+    fn take(&self) -> Option<Arc<dyn TransactionAware>> {
+        self.observer.lock().take()
+    }
+}
+
+#[async_trait]
+impl TransactionAware for OnceObserver {
+    async fn after_begin(&self, executor: &Executor) -> TransactionResult<()> {
+        match self.pending() {
+            Some(observer) => observer.after_begin(executor).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn before_commit(&self, executor: &Executor) -> TransactionResult<()> {
+        match self.pending() {
+            Some(observer) => observer.before_commit(executor).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_commit(&self) -> TransactionResult<()> {
+        match self.take() {
+            Some(observer) => observer.on_commit().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        match self.take() {
+            Some(observer) => observer.on_rollback().await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_rollback_failure(&self, error: &TransactionError) -> TransactionResult<()> {
+        match self.take() {
+            Some(observer) => observer.on_rollback_failure(error).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_commit_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        match self.take() {
+            Some(observer) => observer.on_commit_with(context).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_rollback_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        match self.take() {
+            Some(observer) => observer.on_rollback_with(context).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn on_rollback_failure_with(
+        &self,
+        context: &TransactionContext,
+        error: &TransactionError,
+    ) -> TransactionResult<()> {
+        match self.take() {
+            Some(observer) => observer.on_rollback_failure_with(context, error).await,
+            None => Ok(()),
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn is_idempotent(&self) -> bool {
+        self.pending().is_none_or(|observer| observer.is_idempotent())
+    }
+}
\ No newline at end of file
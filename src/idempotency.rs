@@ -0,0 +1,82 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Executor, TransactionResult};
+
+/// Creates the `uow_idempotency_keys` table `idempotency_guard` works on.
+///
+/// Run it once, e.g. with `sqlx::raw_sql`, or copy it from the crate's
+/// `migrations` directory into your own migrations.
+pub const IDEMPOTENCY_MIGRATION: &str = include_str!("../migrations/0002_uow_idempotency_keys.sql");
+
+/// Whether an idempotency key was claimed, as returned by
+/// `PostgresUnitOfWorkSession::idempotency_guard`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IdempotencyOutcome {
+    /// The key was claimed by this transaction: process the request.
+    New,
+    /// The key was claimed by a committed transaction before: the request was
+    /// already processed and should be skipped.
+    Duplicate {
+        /// When the key was first claimed.
+        first_seen: SystemTime,
+    },
+}
+
+impl IdempotencyOutcome {
+    /// Whether the request was already processed.
+    pub fn is_duplicate(&self) -> bool {
+        matches!(self, Self::Duplicate { .. })
+    }
+}
+
+/// Claims `key` in the transaction of `executor` until `ttl` has passed.
+///
+/// When another open transaction holds the key, this waits for it to end: the
+/// key stays claimed if it commits and is claimed here if it rolls back.
+pub(crate) async fn claim(executor: &Executor, key: &str, ttl: Duration) -> TransactionResult<IdempotencyOutcome> {
+    executor
+        .execute(sqlx::query("DELETE FROM uow_idempotency_keys WHERE key = $1 AND expires_at <= now()").bind(key))
+        .await?;
+    loop {
+        let inserted = executor
+            .execute(
+                sqlx::query(
+                    "INSERT INTO uow_idempotency_keys (key, expires_at) \
+                     VALUES ($1, now() + $2 * interval '1 second') ON CONFLICT (key) DO NOTHING",
+                )
+                .bind(key)
+                .bind(ttl.as_secs_f64()),
+            )
+            .await?;
+        if inserted.rows_affected() == 1 {
+            return Ok(IdempotencyOutcome::New);
+        }
+
+        let first_seen: Option<(i64,)> = executor
+            .fetch_optional_as(
+                sqlx::query_as(
+                    "SELECT (extract(epoch FROM first_seen) * 1000000)::bigint \
+                     FROM uow_idempotency_keys WHERE key = $1",
+                )
+                .bind(key),
+            )
+            .await?;
+        // The key may have been purged since the conflict; claim it again
+        if let Some((micros,)) = first_seen {
+            let first_seen = UNIX_EPOCH + Duration::from_micros(micros.max(0) as u64);
+            return Ok(IdempotencyOutcome::Duplicate { first_seen });
+        }
+    }
+}
+
+/// Deletes the idempotency keys whose time to live has passed, returning how
+/// many there were.
+///
+/// Expired keys no longer block a request, so this only keeps the table small;
+/// run it now and then, e.g. from a scheduled job.
+pub async fn purge_expired_keys(executor: &Executor) -> TransactionResult<u64> {
+    let result = executor
+        .execute(sqlx::query("DELETE FROM uow_idempotency_keys WHERE expires_at <= now()"))
+        .await?;
+    Ok(result.rows_affected())
+}
\ No newline at end of file
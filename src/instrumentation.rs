@@ -0,0 +1,361 @@
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::sql_comment::SqlComment;
+use crate::statement_log::StatementLog;
+use crate::unit_of_work::panic_message;
+use crate::{TransactionError, TransactionOutcome, UowMetrics};
+
+/// Length past which fingerprints are cut off.
+const MAX_FINGERPRINT_LEN: usize = 512;
+
+/// Sees every statement run through the Executor's query helpers.
+///
+/// Set it with `Executor::set_query_hook`, or with
+/// `PostgresUnitOfWorkBuilder::query_hook` for every session's Executor.
+/// Hooks run synchronously after each statement and cannot affect it; a
+/// panicking hook is logged and otherwise ignored.
+pub trait QueryHook: Send + Sync {
+    /// Called with the SQL of a finished statement, how long it took, and the
+    /// number of rows it affected or returned, or the error it failed with.
+    fn on_query(&self, sql: &str, duration: Duration, result: Result<u64, &TransactionError>);
+}
+
+/// Callback receiving a statement's fingerprint and duration.
+type TimingCallback = Arc<dyn Fn(&str, Duration) + Send + Sync>;
+
+/// Latency reporting for the statements run through the Executor's query
+/// helpers, set with `PostgresUnitOfWorkBuilder::executor_metrics`.
+///
+/// Statements are identified by a fingerprint: the SQL with string and
+/// numeric literals replaced by `?` and whitespace collapsed, cut off after
+/// 512 bytes. Bound parameters already keep values out of the SQL.
+#[derive(Clone, Default)]
+pub struct ExecutorMetrics {
+    on_query_timing: Option<TimingCallback>,
+    slow_query_threshold: Option<Duration>,
+}
+
+impl ExecutorMetrics {
+    /// Call `callback` with the fingerprint and duration of every statement,
+    /// including failed ones.
+    pub fn on_query_timing(mut self, callback: impl Fn(&str, Duration) + Send + Sync + 'static) -> Self {
+        self.on_query_timing = Some(Arc::new(callback));
+        self
+    }
+
+    /// Log statements taking longer than `threshold` as a warning, with their SQL.
+    pub fn slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+}
+
+impl fmt::Debug for ExecutorMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutorMetrics")
+            .field("on_query_timing", &self.on_query_timing.is_some())
+            .field("slow_query_threshold", &self.slow_query_threshold)
+            .finish()
+    }
+}
+
+/// A session that stayed open longer than the unit of work's
+/// `slow_transaction_threshold`, or that is still open past its
+/// `long_transaction_threshold`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct SlowTransaction {
+    /// Identifier of the session.
+    pub session_id: Uuid,
+    /// Label given to the session, if any.
+    pub label: Option<String>,
+    /// Metadata attached to the session.
+    pub metadata: HashMap<String, String>,
+    /// Backend pid of the session's connection, if it was asked for with
+    /// `PostgresUnitOfWorkSession::backend_pid`.
+    pub backend_pid: Option<i32>,
+    /// Number of statements run through the session's Executor helpers.
+    pub statements: u64,
+    /// How long the transaction was open.
+    pub elapsed: Duration,
+    /// How the transaction ended, or None if the session was dropped without
+    /// committing or rolling back.
+    pub outcome: Option<TransactionOutcome>,
+}
+
+/// What a committed session did, as returned by
+/// `PostgresUnitOfWorkSession::commit_with_report`.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct CommitReport {
+    /// Identifier of the session.
+    pub session_id: Uuid,
+    /// Label given to the session, if any.
+    pub label: Option<String>,
+    /// Metadata attached to the session.
+    pub metadata: HashMap<String, String>,
+    /// How long the transaction was open.
+    pub duration: Duration,
+    /// How long the server took to answer COMMIT, part of `duration`; long
+    /// under synchronous replication, however fast the statements were.
+    pub commit_duration: Duration,
+    /// Work the session did through its Executor's helpers.
+    pub stats: SessionStats,
+    /// Names of the observers notified of the commit, in notification order.
+    pub observers: Vec<String>,
+    /// Observer failures logged and ignored under
+    /// `ObserverErrorPolicy::LogAndIgnore`.
+    pub observer_errors: Vec<TransactionError>,
+}
+
+/// Result of a successful `PostgresUnitOfWork::health_check`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HealthReport {
+    /// How long acquiring a connection and running `SELECT 1` took.
+    pub latency: Duration,
+    /// Connections the pool holds, idle or in use.
+    pub size: u32,
+    /// Connections waiting in the pool to be used.
+    pub idle: u32,
+    /// Connections handed out by the pool, e.g. to open sessions.
+    pub in_use: u32,
+}
+
+/// How much work a session did through its Executor's helpers, as returned
+/// by `PostgresUnitOfWorkSession::stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SessionStats {
+    /// Statements run, including failed ones. Each statement of a batch counts.
+    pub statements: u64,
+    /// Rows inserted, updated or deleted, including rows loaded by `copy_in`.
+    pub rows_affected: u64,
+    /// Rows returned by the fetch helpers. Data from `copy_out` is not counted.
+    pub rows_fetched: u64,
+}
+
+/// Which counter the rows of a helper's statement add to.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Rows {
+    Affected,
+    Fetched,
+}
+
+/// Callback receiving the report of a slow transaction.
+pub(crate) type SlowTransactionCallback = Arc<dyn Fn(&SlowTransaction) + Send + Sync>;
+
+/// When and how to warn about sessions that are still open.
+#[derive(Clone, Default)]
+pub(crate) struct Watchdog {
+    pub(crate) threshold: Option<Duration>,
+    pub(crate) interval: Option<Duration>,
+    pub(crate) callback: Option<SlowTransactionCallback>,
+}
+
+impl fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("threshold", &self.threshold)
+            .field("interval", &self.interval)
+            .field("callback", &self.callback.is_some())
+            .finish()
+    }
+}
+
+/// The hooks shared by an Executor and its clones.
+#[derive(Clone, Default)]
+pub(crate) struct Instrumentation {
+    hooks: Arc<RwLock<Hooks>>,
+    counters: Arc<Counters>,
+}
+
+#[derive(Default)]
+struct Counters {
+    statements: AtomicU64,
+    rows_affected: AtomicU64,
+    rows_fetched: AtomicU64,
+}
+
+#[derive(Clone, Default)]
+struct Hooks {
+    query_hook: Option<Arc<dyn QueryHook>>,
+    metrics: ExecutorMetrics,
+    sql_comment: Option<SqlComment>,
+    statement_log: Option<StatementLog>,
+    uow_metrics: Option<Arc<dyn UowMetrics>>,
+}
+
+impl Instrumentation {
+    pub(crate) fn set_query_hook(&self, hook: Arc<dyn QueryHook>) {
+        self.hooks.write().query_hook = Some(hook);
+    }
+
+    pub(crate) fn set_metrics(&self, metrics: ExecutorMetrics) {
+        self.hooks.write().metrics = metrics;
+    }
+
+    pub(crate) fn set_sql_comment(&self, comment: SqlComment) {
+        self.hooks.write().sql_comment = Some(comment);
+    }
+
+    /// The comment to append to the helpers' statements, if any.
+    pub(crate) fn sql_comment(&self) -> Option<SqlComment> {
+        self.hooks.read().sql_comment.clone()
+    }
+
+    pub(crate) fn set_statement_log(&self, log: StatementLog) {
+        self.hooks.write().statement_log = Some(log);
+    }
+
+    pub(crate) fn set_uow_metrics(&self, metrics: Arc<dyn UowMetrics>) {
+        self.hooks.write().uow_metrics = Some(metrics);
+    }
+
+    /// Tells the unit of work's metrics, if set, about `error` if it is a
+    /// serialization failure or deadlock.
+    pub(crate) fn record_conflict(&self, error: &TransactionError) {
+        let Some(sqlstate) = crate::metrics::conflict(error) else {
+            return;
+        };
+        if let Some(metrics) = self.hooks.read().uow_metrics.clone() {
+            guard_panic("Metrics", || metrics.record_conflict(sqlstate));
+        }
+    }
+
+    /// How to log the helpers' statements, if at all.
+    pub(crate) fn statement_log(&self) -> Option<StatementLog> {
+        self.hooks.read().statement_log
+    }
+
+    /// The work counted so far.
+    pub(crate) fn stats(&self) -> SessionStats {
+        SessionStats {
+            statements: self.counters.statements.load(Ordering::Relaxed),
+            rows_affected: self.counters.rows_affected.load(Ordering::Relaxed),
+            rows_fetched: self.counters.rows_fetched.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Counts statements that were run.
+    pub(crate) fn count_statements(&self, statements: u64) {
+        self.counters.statements.fetch_add(statements, Ordering::Relaxed);
+    }
+
+    /// Counts rows affected or fetched by a statement.
+    pub(crate) fn count_rows(&self, rows: Rows, count: u64) {
+        let counter = match rows {
+            Rows::Affected => &self.counters.rows_affected,
+            Rows::Fetched => &self.counters.rows_fetched,
+        };
+        counter.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Reports a finished statement to the hook and the metrics, and logs it,
+    /// if set. `parameters` are its bind parameters as rendered for the log.
+    pub(crate) fn record(
+        &self,
+        sql: &str,
+        parameters: Option<&str>,
+        duration: Duration,
+        result: Result<u64, &TransactionError>,
+    ) {
+        let hooks = self.hooks.read().clone();
+        if let Some(log) = &hooks.statement_log {
+            log.log(sql, parameters, duration, result);
+        }
+        if hooks
+            .metrics
+            .slow_query_threshold
+            .is_some_and(|threshold| duration > threshold)
+        {
+            tracing::warn!(sql = %sql, elapsed = ?duration, "Slow query");
+        }
+        if let Some(callback) = &hooks.metrics.on_query_timing {
+            let fingerprint = fingerprint(sql);
+            guard_panic("Query timing callback", || callback(&fingerprint, duration));
+        }
+        if let Some(hook) = &hooks.query_hook {
+            guard_panic("Query hook", || hook.on_query(sql, duration, result));
+        }
+    }
+}
+
+impl fmt::Debug for Instrumentation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hooks = self.hooks.read();
+        f.debug_struct("Instrumentation")
+            .field("query_hook", &hooks.query_hook.is_some())
+            .field("metrics", &hooks.metrics)
+            .field("sql_comment", &hooks.sql_comment.is_some())
+            .field("statement_log", &hooks.statement_log)
+            .field("uow_metrics", &hooks.uow_metrics.is_some())
+            .field("stats", &self.stats())
+            .finish()
+    }
+}
+
+/// Runs a user callback, logging a panic instead of unwinding into the caller.
+pub(crate) fn guard_panic(name: &str, callback: impl FnOnce()) {
+    if let Err(panic) = std::panic::catch_unwind(AssertUnwindSafe(callback)) {
+        tracing::warn!(panic = %panic_message(panic.as_ref()), "{name} panicked");
+    }
+}
+
+/// The SQL with string and numeric literals replaced by `?` and runs of
+/// whitespace collapsed to a single space.
+///
+/// Digits that continue an identifier or a `$1` placeholder are kept, as is
+/// everything inside double-quoted identifiers.
+fn fingerprint(sql: &str) -> String {
+    let mut fingerprint = String::with_capacity(sql.len().min(MAX_FINGERPRINT_LEN));
+    let mut chars = sql.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // '' inside a literal is an escaped quote
+                while let Some(c) = chars.next() {
+                    if c == '\'' && chars.next_if_eq(&'\'').is_none() {
+                        break;
+                    }
+                }
+                fingerprint.push('?');
+            }
+            '"' => {
+                fingerprint.push(c);
+                for c in chars.by_ref() {
+                    fingerprint.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                }
+            }
+            c if c.is_ascii_digit() && !continues_token(&fingerprint) => {
+                while chars.next_if(|c| c.is_ascii_digit() || *c == '.').is_some() {}
+                fingerprint.push('?');
+            }
+            c if c.is_whitespace() => {
+                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                fingerprint.push(' ');
+            }
+            c => fingerprint.push(c),
+        }
+        if fingerprint.len() >= MAX_FINGERPRINT_LEN {
+            break;
+        }
+    }
+    fingerprint
+}
+
+/// Whether a digit appended to `fingerprint` would be part of an identifier
+/// or placeholder rather than start a number.
+fn continues_token(fingerprint: &str) -> bool {
+    fingerprint.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$')
+}
\ No newline at end of file
@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+
+use crate::{SessionInfo, TransactionOptions, TransactionResult};
+
+/// Middleware around the lifecycle of every session a unit of work begins,
+/// e.g. to force a tenant setting onto each transaction or to refuse commits
+/// outside of a maintenance window.
+///
+/// Interceptors are added with `PostgresUnitOfWorkBuilder::interceptor`. The
+/// `before_*` hooks run in the order the interceptors were added and the
+/// `after_*` hooks in reverse, so the first interceptor wraps all the others.
+/// Unlike listeners, interceptors can affect the transaction: an error from a
+/// `before_*` hook aborts the operation. All methods default to doing nothing.
+#[async_trait]
+pub trait SessionInterceptor: Send + Sync {
+    /// Called before the transaction begins, with the options it is about to
+    /// begin with; returns the options to begin with instead.
+    ///
+    /// An error fails `begin()` without beginning a transaction, and the
+    /// interceptors after this one are not called.
+    async fn before_begin(&self, options: &TransactionOptions) -> TransactionResult<TransactionOptions> {
+        Ok(options.clone())
+    }
+
+    /// Called once the session has begun, after its default observers were
+    /// registered.
+    async fn after_begin(&self, _session: &SessionInfo) {}
+
+    /// Called when the session is committed, before its observers'
+    /// `before_commit` hooks, while the transaction is still open.
+    ///
+    /// An error rolls the transaction back and fails the commit with
+    /// `TransactionError::CommitVetoed`.
+    async fn before_commit(&self, _session: &SessionInfo) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    /// Called after the session's transaction committed and its observers
+    /// were notified.
+    async fn after_commit(&self, _session: &SessionInfo) {}
+
+    /// Called after the session's transaction rolled back, explicitly, after
+    /// a failed or vetoed commit, or because the session was dropped. Also
+    /// called when the ROLLBACK itself failed, e.g. on a lost connection, as
+    /// the session is over either way.
+    async fn after_rollback(&self, _session: &SessionInfo) {}
+}
\ No newline at end of file
@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use sqlx::PgPool;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{Executor, TransactionAware, TransactionError, TransactionResult};
+
+/// Creates the `uow_jobs` table the job helpers work on.
+///
+/// Run it once, e.g. with `sqlx::raw_sql`, or copy it from the crate's
+/// `migrations` directory into your own migrations.
+pub const JOBS_MIGRATION: &str = include_str!("../migrations/0003_uow_jobs.sql");
+
+/// Channel notified with the queue's name once jobs enqueued on it are
+/// committed; workers LISTEN on it to wake up right away instead of polling.
+pub const JOBS_CHANNEL: &str = "uow_jobs";
+
+/// A job taken from the `uow_jobs` table by `claim_jobs`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Job {
+    /// Identifier of the job, in the order the jobs were enqueued.
+    pub id: i64,
+    /// Queue the job was enqueued on.
+    pub queue: String,
+    /// What to do, in whatever encoding the application chose.
+    pub payload: Vec<u8>,
+    /// When the job became due.
+    pub run_at: SystemTime,
+}
+
+/// Writes a job to the `uow_jobs` table in the transaction of `executor`,
+/// returning its id.
+pub(crate) async fn insert(executor: &Executor, queue: &str, payload: &[u8], run_at: SystemTime) -> TransactionResult<i64> {
+    let (id,): (i64,) = executor
+        .fetch_one_as(
+            sqlx::query_as("INSERT INTO uow_jobs (queue, payload, run_at) VALUES ($1, $2, to_timestamp($3)) RETURNING id")
+                .bind(queue)
+                .bind(payload)
+                .bind(epoch_seconds(run_at)),
+        )
+        .await?;
+    Ok(id)
+}
+
+/// Takes up to `limit` due jobs of `queue` in the transaction of `executor`,
+/// earliest first.
+///
+/// The jobs are locked with `FOR UPDATE SKIP LOCKED`, so workers claiming side
+/// by side never get the same job, and deleted: run them in the same
+/// transaction, and they are gone once it commits and back in the queue if it
+/// rolls back.
+pub async fn claim_jobs(executor: &Executor, queue: &str, limit: u32) -> TransactionResult<Vec<Job>> {
+    let rows: Vec<(i64, String, Vec<u8>, i64)> = executor
+        .fetch_all_as(
+            sqlx::query_as(
+                "WITH due AS (\
+                     SELECT id FROM uow_jobs WHERE queue = $1 AND run_at <= now() \
+                     ORDER BY run_at, id LIMIT $2 FOR UPDATE SKIP LOCKED\
+                 ) \
+                 DELETE FROM uow_jobs USING due WHERE uow_jobs.id = due.id \
+                 RETURNING uow_jobs.id, queue, payload, (extract(epoch FROM run_at) * 1000000)::bigint",
+            )
+            .bind(queue)
+            .bind(i64::from(limit)),
+        )
+        .await?;
+
+    let mut jobs: Vec<Job> = rows
+        .into_iter()
+        .map(|(id, queue, payload, run_at)| Job {
+            id,
+            queue,
+            payload,
+            run_at: UNIX_EPOCH + Duration::from_micros(run_at.max(0) as u64),
+        })
+        .collect();
+    // RETURNING does not keep the order of the CTE
+    jobs.sort_by_key(|job| (job.run_at, job.id));
+    Ok(jobs)
+}
+
+fn epoch_seconds(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH).map_or(0.0, |since| since.as_secs_f64())
+}
+
+/// Observer notifying `JOBS_CHANNEL` of the queues a session enqueued jobs on,
+/// once per queue, on a connection of the pool after the commit.
+pub(crate) struct JobWakeups {
+    pool: Arc<PgPool>,
+    queues: Mutex<BTreeSet<String>>,
+}
+
+impl JobWakeups {
+    pub(crate) fn new(pool: Arc<PgPool>) -> Self {
+        Self {
+            pool,
+            queues: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn push(&self, queue: &str) {
+        self.queues.lock().insert(queue.to_string());
+    }
+}
+
+#[async_trait]
+impl TransactionAware for JobWakeups {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        let queues = std::mem::take(&mut *self.queues.lock());
+        for queue in queues {
+            sqlx::query("SELECT pg_notify($1, $2)")
+                .bind(JOBS_CHANNEL)
+                .bind(&queue)
+                .execute(&*self.pool)
+                .await
+                .map_err(TransactionError::from)?;
+        }
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.queues.lock().clear();
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "job wake-ups"
+    }
+
+    /// Only a committed attempt wakes the workers, so retrying is safe.
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
\ No newline at end of file
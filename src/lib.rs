@@ -3,10 +3,98 @@
 //! This module provides transaction handling primitives for PostgreSQL database operations.
 //! It isolates transaction management from specific repository implementations.
 
+pub mod bind;
+pub mod cache;
+pub mod circuit_breaker;
+pub mod copy;
+pub mod cursor;
+#[cfg(feature = "deadlock-diagnostics")]
+pub mod deadlock;
+pub mod dyn_unit_of_work;
+pub mod error;
+mod events;
 pub mod executor;
+pub mod extensions;
+pub mod handle;
+mod hooks;
+pub mod idempotency;
+pub mod instrumentation;
+pub mod interceptor;
+pub mod jobs;
+pub mod listener;
+pub mod metrics;
+pub mod migrate;
+pub mod notifications;
+mod observer_registry;
+pub mod options;
+#[cfg(feature = "otel")]
+mod otel;
+pub mod outbox;
+pub mod policy;
+pub mod pool_stats;
+#[cfg(feature = "prometheus")]
+pub mod prometheus;
+pub mod read_only;
+pub mod retry;
+pub mod sql_comment;
+pub mod statement_log;
 pub mod transaction_aware;
 pub mod unit_of_work;
+#[cfg(feature = "http")]
+pub mod webhook;
 
-pub use executor::Executor;
-pub use transaction_aware::{TransactionAware, TransactionError, TransactionResult};
-pub use unit_of_work::{UnitOfWork, UnitOfWorkSession, PostgresUnitOfWork, PostgresUnitOfWorkSession};
+pub use bind::{BindRow, BulkInsertOptions};
+pub use cache::{Invalidation, InvalidationObserver, Invalidator, SharedCache, TxCache};
+pub use circuit_breaker::{CircuitBreakerConfig, CircuitState};
+pub use copy::{BinaryCopyWriter, CopyInSink, CopyType, CopyValue};
+pub use cursor::Cursor;
+#[cfg(feature = "deadlock-diagnostics")]
+pub use deadlock::{DeadlockBackend, DeadlockDiagnostics, DeadlockLock};
+pub use dyn_unit_of_work::{DynSession, DynUnitOfWork};
+pub use error::{AsTransactionError, PgErrorKind, TransactionError, TransactionResult};
+pub use executor::{Executor, ExecutorConn, ExecutorGuard, ExecutorState, ExecutorStatus, SessionState};
+pub use extensions::Extensions;
+pub use handle::{SessionHandle, SessionInfo, ShutdownReport};
+pub use idempotency::IdempotencyOutcome;
+pub use instrumentation::{
+    CommitReport, ExecutorMetrics, HealthReport, QueryHook, SessionStats, SlowTransaction,
+};
+pub use interceptor::SessionInterceptor;
+pub use jobs::Job;
+pub use listener::TransactionListener;
+pub use metrics::{ActiveSessionCount, BeginErrorKind, RetryReason, RollbackReason, UowMetrics};
+pub use migrate::MigrationReport;
+pub use notifications::NotificationStream;
+pub use observer_registry::ObserverHandle;
+pub use options::{IsolationLevel, TransactionOptions};
+pub use outbox::{OutboxMessage, OutboxPublisher, OutboxRelay, OutboxRelayConfig};
+pub use policy::{LeakPolicy, ObserverErrorPolicy};
+pub use pool_stats::{PoolSampler, PoolStats};
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusUowMetrics;
+pub use read_only::ReadOnlyExecutor;
+pub use retry::RetryPolicy;
+pub use sql_comment::SqlCommenter;
+pub use statement_log::LogLevel;
+pub use transaction_aware::{
+    SyncAdapter, SyncTransactionAware, TransactionAware, TransactionContext, TransactionOutcome,
+};
+#[cfg(feature = "http")]
+pub use webhook::{FailedWebhook, WebhookClient, WebhookObserver};
+#[cfg(feature = "derive")]
+pub use postgres_unit_of_work_derive::TransactionAware;
+#[cfg(feature = "macros")]
+pub use postgres_unit_of_work_derive::transactional;
+
+/// Support code for the `TransactionAware` derive and `#[transactional]`
+/// macros; not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::transaction_aware::{forward_to_all, forward_until_error};
+    pub use crate::unit_of_work::{transactional, transactional_with_retry};
+    pub use async_trait::async_trait;
+}
+
+pub use unit_of_work::{
+    UnitOfWork, UnitOfWorkSession, PostgresUnitOfWork, PostgresUnitOfWorkBuilder, PostgresUnitOfWorkSession, WorkStep,
+};
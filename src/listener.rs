@@ -0,0 +1,34 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{CircuitState, TransactionError};
+
+/// Process-wide hooks that see every transaction of a unit of work.
+///
+/// Listeners are added with `PostgresUnitOfWork::add_listener` and are called
+/// for every session it begins, independently of the observers registered per
+/// session. This is the place for audit logging and metrics. Listeners cannot
+/// affect the transaction; all methods default to doing nothing.
+#[async_trait]
+pub trait TransactionListener: Send + Sync {
+    /// Called when a session has begun its transaction.
+    async fn on_begin(&self, _session_id: Uuid) {}
+
+    /// Called after the session's transaction committed and its observers
+    /// were notified. `duration` covers begin to COMMIT.
+    async fn on_commit(&self, _session_id: Uuid, _duration: Duration) {}
+
+    /// Called after the session's transaction rolled back, explicitly or
+    /// after a failed or vetoed commit.
+    async fn on_rollback(&self, _session_id: Uuid, _duration: Duration) {}
+
+    /// Called when `commit()` or `rollback()` returns an error, after
+    /// `on_commit` or `on_rollback` if the transaction still ended.
+    async fn on_error(&self, _session_id: Uuid, _error: &TransactionError) {}
+
+    /// Called when the unit of work's circuit breaker changes state, e.g. to
+    /// alert while it is open. Only called when a circuit breaker is
+    /// configured.
+    async fn on_circuit_state_change(&self, _from: CircuitState, _to: CircuitState) {}
+}
\ No newline at end of file
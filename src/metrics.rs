@@ -0,0 +1,237 @@
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use crate::handle::SessionRegistry;
+use crate::{PgErrorKind, PoolStats, TransactionError};
+
+/// Counters and durations for the transactions of a unit of work, to be
+/// forwarded to whatever metrics library the application uses.
+///
+/// Set it with `PostgresUnitOfWorkBuilder::metrics`. Every session the unit
+/// of work begins reports exactly once how it ended, including sessions
+/// dropped without committing or rolling back. Methods are called
+/// synchronously on the session's task, so they should only update counters;
+/// a panicking method is logged and otherwise ignored. All methods default to
+/// doing nothing.
+pub trait UowMetrics: Send + Sync {
+    /// Called when a session has begun its transaction.
+    fn record_begin(&self) {}
+
+    /// Called when a session's transaction committed, with how long it was
+    /// open and the number of statements run through its Executor helpers.
+    fn record_commit(&self, _duration: Duration, _statements: u64) {}
+
+    /// Called when a session's transaction ended without committing, with how
+    /// long it was open and why it was rolled back.
+    fn record_rollback(&self, _duration: Duration, _reason: RollbackReason) {}
+
+    /// Called when COMMIT took longer than the unit of work's
+    /// `slow_commit_threshold` to answer, with how long it took, whether it
+    /// committed or not.
+    fn record_slow_commit(&self, _round_trip: Duration) {}
+
+    /// Called when ROLLBACK took longer than the unit of work's
+    /// `slow_commit_threshold` to answer, with how long it took.
+    fn record_slow_rollback(&self, _round_trip: Duration) {}
+
+    /// Called when `begin()` fails, with the kind of failure.
+    fn record_begin_error(&self, _kind: BeginErrorKind) {}
+
+    /// Called when `run_with_retry` or a `#[transactional(retry)]` method
+    /// retries a failed attempt, with the attempt's number, starting at 1,
+    /// and the SQLSTATE it failed with, if any; see `RetryReason::of_sqlstate`.
+    fn record_retry(&self, _attempt: u32, _sqlstate: Option<&str>) {}
+
+    /// Called when `run_with_retry` or a `#[transactional(retry)]` method
+    /// gives up on a unit of work that failed with a retryable error after
+    /// `attempts` attempts, the most the retry policy allows. Not called for
+    /// policies allowing a single attempt.
+    fn record_retry_exhausted(&self, _attempts: u32) {}
+
+    /// Called when a statement or COMMIT run by the unit of work's sessions
+    /// fails with `40001 serialization_failure` or `40P01 deadlock_detected`,
+    /// whether or not it is retried, with that SQLSTATE.
+    fn record_conflict(&self, _sqlstate: &str) {}
+
+    /// Called once when a unit of work is built with these metrics, with a
+    /// way to read how many of its sessions are open, e.g. for a gauge.
+    fn observe_active_sessions(&self, _sessions: ActiveSessionCount) {}
+
+    /// Called by the sampler started with
+    /// `PostgresUnitOfWork::spawn_pool_sampler` at every interval, with the
+    /// unit of work's pool connections, e.g. for gauges.
+    fn observe_pool(&self, _stats: &PoolStats) {}
+}
+
+/// The metrics used when none are configured.
+pub(crate) struct NoopMetrics;
+
+impl UowMetrics for NoopMetrics {}
+
+/// Why a session's transaction was rolled back, as passed to
+/// `UowMetrics::record_rollback`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RollbackReason {
+    /// `rollback()` was called.
+    Requested,
+    /// An interceptor or observer vetoed the commit.
+    Vetoed,
+    /// A statement of the session was cancelled, so `commit()` rolled back.
+    Cancelled,
+    /// COMMIT failed.
+    CommitFailed,
+    /// The session was dropped without committing or rolling back.
+    Dropped,
+}
+
+impl RollbackReason {
+    /// Every reason, e.g. to report zero for those that did not occur yet.
+    pub const ALL: [RollbackReason; 5] = [
+        RollbackReason::Requested,
+        RollbackReason::Vetoed,
+        RollbackReason::Cancelled,
+        RollbackReason::CommitFailed,
+        RollbackReason::Dropped,
+    ];
+
+    /// The reason as a metric label, e.g. `commit_failed`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RollbackReason::Requested => "requested",
+            RollbackReason::Vetoed => "vetoed",
+            RollbackReason::Cancelled => "cancelled",
+            RollbackReason::CommitFailed => "commit_failed",
+            RollbackReason::Dropped => "dropped",
+        }
+    }
+
+    /// Why a `commit()` that did not commit rolled back, given its error.
+    pub(crate) fn of_commit_error(error: &TransactionError) -> Self {
+        match error {
+            TransactionError::Cancelled => RollbackReason::Cancelled,
+            TransactionError::CommitVetoed { .. } => RollbackReason::Vetoed,
+            _ => RollbackReason::CommitFailed,
+        }
+    }
+}
+
+/// What made `begin()` fail, as passed to `UowMetrics::record_begin_error`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BeginErrorKind {
+    /// The unit of work is shutting down.
+    ShuttingDown,
+    /// The circuit breaker is open.
+    CircuitOpen,
+    /// The database could not be reached; see
+    /// `TransactionError::is_connection_error`.
+    Connection,
+    /// The database refused to begin or set up the transaction.
+    Database,
+    /// An interceptor or default observer refused the session.
+    Rejected,
+}
+
+impl BeginErrorKind {
+    /// Every kind, e.g. to report zero for those that did not occur yet.
+    pub const ALL: [BeginErrorKind; 5] = [
+        BeginErrorKind::ShuttingDown,
+        BeginErrorKind::CircuitOpen,
+        BeginErrorKind::Connection,
+        BeginErrorKind::Database,
+        BeginErrorKind::Rejected,
+    ];
+
+    /// The kind as a metric label, e.g. `circuit_open`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BeginErrorKind::ShuttingDown => "shutting_down",
+            BeginErrorKind::CircuitOpen => "circuit_open",
+            BeginErrorKind::Connection => "connection",
+            BeginErrorKind::Database => "database",
+            BeginErrorKind::Rejected => "rejected",
+        }
+    }
+
+    /// Classify the error a `begin()` failed with.
+    pub(crate) fn of(error: &TransactionError) -> Self {
+        match error {
+            TransactionError::ShuttingDown => BeginErrorKind::ShuttingDown,
+            TransactionError::CircuitOpen { .. } => BeginErrorKind::CircuitOpen,
+            error if error.is_connection_error() => BeginErrorKind::Connection,
+            TransactionError::DatabaseError(_) => BeginErrorKind::Database,
+            _ => BeginErrorKind::Rejected,
+        }
+    }
+}
+
+/// What a retried attempt failed with, as told by the SQLSTATE passed to
+/// `UowMetrics::record_retry`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RetryReason {
+    /// `40001 serialization_failure`
+    SerializationFailure,
+    /// `40P01 deadlock_detected`
+    Deadlock,
+    /// Any other error the retry policy retries.
+    Other,
+}
+
+impl RetryReason {
+    /// Every reason, e.g. to report zero for those that did not occur yet.
+    pub const ALL: [RetryReason; 3] = [RetryReason::SerializationFailure, RetryReason::Deadlock, RetryReason::Other];
+
+    /// The reason as a metric label, e.g. `serialization_failure`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RetryReason::SerializationFailure => "serialization_failure",
+            RetryReason::Deadlock => "deadlock",
+            RetryReason::Other => "other",
+        }
+    }
+
+    /// Classify the SQLSTATE of an attempt that is retried.
+    pub fn of_sqlstate(sqlstate: Option<&str>) -> Self {
+        match sqlstate {
+            Some(SERIALIZATION_FAILURE) => RetryReason::SerializationFailure,
+            Some(DEADLOCK_DETECTED) => RetryReason::Deadlock,
+            _ => RetryReason::Other,
+        }
+    }
+}
+
+/// The SQLSTATE of `serialization_failure`.
+const SERIALIZATION_FAILURE: &str = "40001";
+
+/// The SQLSTATE of `deadlock_detected`.
+const DEADLOCK_DETECTED: &str = "40P01";
+
+/// The SQLSTATE of `error` if it is a serialization failure or a deadlock,
+/// for `UowMetrics::record_conflict`.
+pub(crate) fn conflict(error: &TransactionError) -> Option<&str> {
+    match error.pg_kind() {
+        Some(PgErrorKind::SerializationFailure | PgErrorKind::Deadlock) => error.sqlstate(),
+        _ => None,
+    }
+}
+
+/// How many sessions of a unit of work are open, as passed to
+/// `UowMetrics::observe_active_sessions`.
+///
+/// Counts what `PostgresUnitOfWork::active_count` does, without keeping the
+/// unit of work alive; once it is dropped the count is zero.
+#[derive(Clone, Debug)]
+pub struct ActiveSessionCount(Weak<SessionRegistry>);
+
+impl ActiveSessionCount {
+    pub(crate) fn new(sessions: &Arc<SessionRegistry>) -> Self {
+        Self(Arc::downgrade(sessions))
+    }
+
+    /// The number of open sessions right now.
+    pub fn get(&self) -> usize {
+        self.0.upgrade().map_or(0, |sessions| sessions.lock().len())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,77 @@
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+use sqlx::PgConnection;
+use std::collections::HashMap;
+
+use crate::{TransactionError, TransactionResult};
+
+/// The migrations a `run_migrations` call found, by version.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MigrationReport {
+    /// Versions applied by this call, in the order they ran.
+    pub applied: Vec<i64>,
+    /// Versions that had been applied before.
+    pub already_applied: Vec<i64>,
+}
+
+fn failed(error: MigrateError) -> TransactionError {
+    TransactionError::MigrationFailed(Box::new(error))
+}
+
+/// Applies the pending migrations of `migrator` on `conn`, inside its open
+/// transaction.
+///
+/// Nothing is applied if a pending migration opted out of transactions, and
+/// the checks of `Migrator::run` on the applied migrations are made first.
+pub(crate) async fn run(conn: &mut PgConnection, migrator: &Migrator) -> TransactionResult<MigrationReport> {
+    // Held until the transaction ends, unlike the session lock of `Migrator::run`
+    if migrator.locking {
+        sqlx::query("SELECT pg_advisory_xact_lock(hashtext('_sqlx_migrations'))")
+            .execute(&mut *conn)
+            .await?;
+    }
+    conn.ensure_migrations_table().await.map_err(failed)?;
+    if let Some(version) = conn.dirty_version().await.map_err(failed)? {
+        return Err(failed(MigrateError::Dirty(version)));
+    }
+
+    let applied: HashMap<i64, Vec<u8>> = conn
+        .list_applied_migrations()
+        .await
+        .map_err(failed)?
+        .into_iter()
+        .map(|migration| (migration.version, migration.checksum.into_owned()))
+        .collect();
+    if !migrator.ignore_missing {
+        if let Some(version) = applied
+            .keys()
+            .find(|version| !migrator.iter().any(|migration| migration.version == **version))
+        {
+            return Err(failed(MigrateError::VersionMissing(*version)));
+        }
+    }
+
+    let mut report = MigrationReport::default();
+    let mut pending = Vec::new();
+    for migration in migrator.iter().filter(|migration| !migration.migration_type.is_down_migration()) {
+        match applied.get(&migration.version) {
+            Some(checksum) if *checksum != *migration.checksum => {
+                return Err(failed(MigrateError::VersionMismatch(migration.version)));
+            }
+            Some(_) => report.already_applied.push(migration.version),
+            None if migration.no_tx => {
+                return Err(TransactionError::MigrationNotTransactional {
+                    version: migration.version,
+                    description: migration.description.to_string(),
+                });
+            }
+            None => pending.push(migration),
+        }
+    }
+
+    for migration in pending {
+        conn.apply(migration).await.map_err(failed)?;
+        report.applied.push(migration.version);
+    }
+    Ok(report)
+}
\ No newline at end of file
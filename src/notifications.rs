@@ -0,0 +1,56 @@
+use futures::stream::BoxStream;
+use futures::{Stream, StreamExt};
+use sqlx::postgres::{PgListener, PgNotification};
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::TransactionResult;
+
+/// Notifications on the channels a session listened to, returned by
+/// `PostgresUnitOfWorkSession::commit_and_listen`.
+///
+/// The stream owns the connection that issued the LISTENs; dropping it runs
+/// UNLISTEN on that connection before returning it to the pool. It ends if
+/// the connection is lost, since notifications may have been missed while
+/// reconnecting.
+pub struct NotificationStream {
+    notifications: BoxStream<'static, TransactionResult<PgNotification>>,
+}
+
+impl NotificationStream {
+    pub(crate) fn new(listener: Option<PgListener>) -> Self {
+        let notifications = async_stream::stream! {
+            let Some(mut listener) = listener else {
+                return;
+            };
+            loop {
+                match listener.try_recv().await {
+                    Ok(Some(notification)) => yield Ok(notification),
+                    Ok(None) => break,
+                    Err(error) => {
+                        yield Err(error.into());
+                        break;
+                    }
+                }
+            }
+        };
+        Self {
+            notifications: notifications.boxed(),
+        }
+    }
+}
+
+impl Stream for NotificationStream {
+    type Item = TransactionResult<PgNotification>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.notifications.poll_next_unpin(cx)
+    }
+}
+
+impl fmt::Debug for NotificationStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotificationStream").finish_non_exhaustive()
+    }
+}
\ No newline at end of file
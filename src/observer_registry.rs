@@ -0,0 +1,135 @@
+use parking_lot::RwLock;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+
+use crate::TransactionAware;
+
+/// How the registry refers to an observer.
+pub(crate) enum ObserverRef {
+    Strong(Arc<dyn TransactionAware>),
+    /// Observers registered weakly are skipped once they have been dropped.
+    Weak(Weak<dyn TransactionAware>),
+}
+
+impl ObserverRef {
+    pub(crate) fn upgrade(&self) -> Option<Arc<dyn TransactionAware>> {
+        match self {
+            ObserverRef::Strong(observer) => Some(observer.clone()),
+            ObserverRef::Weak(observer) => observer.upgrade(),
+        }
+    }
+}
+
+/// A registered observer and its notification settings.
+struct Registration {
+    priority: i32,
+    timeout: Option<Duration>,
+    observer: ObserverRef,
+}
+
+/// A live observer due to be notified, with its registration's timeout.
+pub(crate) struct Registered {
+    pub(crate) observer: Arc<dyn TransactionAware>,
+    /// Overrides the session's default observer timeout when set.
+    pub(crate) timeout: Option<Duration>,
+}
+
+/// Observers registered with a session, keyed by registration sequence.
+///
+/// Observers with a lower priority are notified first on commit and last on
+/// rollback; observers with the same priority are always notified in
+/// registration order.
+#[derive(Default)]
+pub(crate) struct ObserverRegistry {
+    registrations: BTreeMap<u64, Registration>,
+    next_id: u64,
+}
+
+impl ObserverRegistry {
+    /// Add an observer, returning the key it was registered under.
+    pub(crate) fn push(&mut self, observer: ObserverRef, priority: i32, timeout: Option<Duration>) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.registrations.insert(
+            id,
+            Registration {
+                priority,
+                timeout,
+                observer,
+            },
+        );
+        id
+    }
+
+    pub(crate) fn remove(&mut self, id: u64) -> bool {
+        self.registrations.remove(&id).is_some()
+    }
+
+    /// Live registered observers that are not idempotent.
+    pub(crate) fn non_idempotent(&self) -> usize {
+        self.registrations
+            .values()
+            .filter_map(|registration| registration.observer.upgrade())
+            .filter(|observer| !observer.is_idempotent())
+            .count()
+    }
+
+    /// Live observers in the order they are notified before and after commit.
+    pub(crate) fn commit_order(&self) -> Vec<Registered> {
+        let mut registrations: Vec<&Registration> = self.registrations.values().collect();
+        registrations.sort_by_key(|registration| registration.priority);
+        Self::upgrade_all(registrations)
+    }
+
+    /// Live observers in the order they are notified after rollback.
+    pub(crate) fn rollback_order(&self) -> Vec<Registered> {
+        let mut registrations: Vec<&Registration> = self.registrations.values().collect();
+        registrations.sort_by_key(|registration| Reverse(registration.priority));
+        Self::upgrade_all(registrations)
+    }
+
+    fn upgrade_all(registrations: Vec<&Registration>) -> Vec<Registered> {
+        registrations
+            .into_iter()
+            .filter_map(|registration| {
+                Some(Registered {
+                    observer: registration.observer.upgrade()?,
+                    timeout: registration.timeout,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Handle to a single observer registration, returned by
+/// `register_transaction_aware`.
+///
+/// Dropping the handle keeps the observer registered.
+#[derive(Clone, Debug)]
+pub struct ObserverHandle {
+    registry: Weak<RwLock<ObserverRegistry>>,
+    id: u64,
+}
+
+impl ObserverHandle {
+    pub(crate) fn new(registry: &Arc<RwLock<ObserverRegistry>>, id: u64) -> Self {
+        Self {
+            registry: Arc::downgrade(registry),
+            id,
+        }
+    }
+
+    /// Remove this registration so the observer is not notified when the
+    /// session completes.
+    ///
+    /// Returns whether the registration was removed; deregistering after the
+    /// session has completed is a no-op returning false.
+    pub fn deregister(self) -> bool {
+        match self.registry.upgrade() {
+            Some(registry) => registry.write().remove(self.id),
+            None => false,
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,116 @@
+use std::time::Duration;
+
+/// Isolation level of a transaction; see the PostgreSQL documentation on
+/// transaction isolation for what each one guarantees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// Each statement sees the data committed before it began; the server
+    /// default.
+    ReadCommitted,
+    /// Every statement sees the data committed before the transaction's first
+    /// statement.
+    RepeatableRead,
+    /// Concurrent transactions that could not have run one after the other
+    /// fail with a serialization failure, which `RetryPolicy` retries.
+    Serializable,
+}
+
+impl IsolationLevel {
+    pub(crate) fn as_sql(self) -> &'static str {
+        match self {
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+/// Options applied to a transaction when it begins.
+///
+/// Timeouts are applied with `SET LOCAL`, so they only affect the transaction
+/// they were configured for.
+#[derive(Clone, Debug, Default)]
+pub struct TransactionOptions {
+    pub(crate) isolation_level: Option<IsolationLevel>,
+    pub(crate) statement_timeout: Option<Duration>,
+    pub(crate) lock_timeout: Option<Duration>,
+    pub(crate) statement_cache: Option<bool>,
+    pub(crate) settings: Vec<(String, String)>,
+}
+
+impl TransactionOptions {
+    /// Create options with the server defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the transaction at `level` instead of the server default.
+    pub fn isolation_level(mut self, level: IsolationLevel) -> Self {
+        self.isolation_level = Some(level);
+        self
+    }
+
+    /// Abort any statement that takes longer than `timeout`.
+    pub fn statement_timeout(mut self, timeout: Duration) -> Self {
+        self.statement_timeout = Some(timeout);
+        self
+    }
+
+    /// Abort any statement that waits longer than `timeout` to acquire a lock.
+    pub fn lock_timeout(mut self, timeout: Duration) -> Self {
+        self.lock_timeout = Some(timeout);
+        self
+    }
+
+    /// Whether the Executor's query helpers prepare their statements as named
+    /// statements cached on the connection, which sqlx does by default.
+    ///
+    /// Turn it off behind PgBouncer in transaction pooling mode or for dynamic
+    /// SQL that is rarely repeated. Either way a single query can opt out with
+    /// sqlx's `.persistent(false)`. SQL run through `with_tx` or `acquire` is
+    /// not affected.
+    pub fn statement_cache(mut self, enabled: bool) -> Self {
+        self.statement_cache = Some(enabled);
+        self
+    }
+
+    /// Set the configuration parameter `name` to `value` for the transaction,
+    /// e.g. `application_name` or a setting read by row level security
+    /// policies such as `app.tenant_id`.
+    ///
+    /// Applied with `set_config(name, value, true)` after the other options,
+    /// in the order they were added.
+    pub fn setting(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.settings.push((name.into(), value.into()));
+        self
+    }
+
+    /// Whether the query helpers may cache prepared statements.
+    pub(crate) fn caches_statements(&self) -> bool {
+        self.statement_cache.unwrap_or(true)
+    }
+
+    /// The statements needed to apply these options inside a new transaction.
+    pub(crate) fn setup_statements(&self) -> Vec<String> {
+        let mut statements = Vec::new();
+        // Only allowed before the transaction's first query, so it goes first
+        if let Some(level) = self.isolation_level {
+            statements.push(format!("SET TRANSACTION ISOLATION LEVEL {}", level.as_sql()));
+        }
+        if let Some(timeout) = self.statement_timeout {
+            statements.push(format!("SET LOCAL statement_timeout = {}", timeout.as_millis().max(1)));
+        }
+        if let Some(timeout) = self.lock_timeout {
+            statements.push(format!("SET LOCAL lock_timeout = {}", timeout.as_millis().max(1)));
+        }
+        for (name, value) in &self.settings {
+            statements.push(format!("SELECT set_config({}, {}, true)", quote(name), quote(value)));
+        }
+        statements
+    }
+}
+
+/// `text` as an SQL string literal.
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
\ No newline at end of file
@@ -0,0 +1,39 @@
+use opentelemetry::trace::{SpanContext, TraceContextExt};
+use opentelemetry::Context;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// Tag the session span `span` as a PostgreSQL one and make it a child of
+/// `context` if it has no tracing parent, so a session begun outside any
+/// tracing span still joins the caller's trace, e.g. one attached from
+/// incoming request headers.
+pub(crate) fn join(span: &tracing::Span, parent: &tracing::Span, context: &Context) {
+    span.set_attribute("db.system", "postgresql");
+    if parent.is_none() && context.has_active_span() {
+        span.set_parent(context.clone());
+    }
+}
+
+/// The trace `span` belongs to, as 32 lowercase hex digits, or None if the
+/// OpenTelemetry layer does not see it.
+pub(crate) fn trace_id(span: &tracing::Span) -> Option<String> {
+    span_context(span).map(|span_context| span_context.trace_id().to_string())
+}
+
+/// The W3C `traceparent` of `span`, or None if the OpenTelemetry layer does
+/// not see it.
+pub(crate) fn traceparent(span: &tracing::Span) -> Option<String> {
+    span_context(span).map(|span_context| {
+        format!(
+            "00-{}-{}-{:02x}",
+            span_context.trace_id(),
+            span_context.span_id(),
+            span_context.trace_flags().to_u8()
+        )
+    })
+}
+
+/// The OpenTelemetry span context of `span`, if valid.
+fn span_context(span: &tracing::Span) -> Option<SpanContext> {
+    let span_context = span.context().span().span_context().clone();
+    span_context.is_valid().then_some(span_context)
+}
@@ -0,0 +1,218 @@
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::{Executor, PostgresUnitOfWork, RetryPolicy, TransactionResult, UnitOfWork, UnitOfWorkSession};
+
+/// Creates the `uow_outbox` table the outbox helpers work on.
+///
+/// Run it once, e.g. with `sqlx::raw_sql`, or copy it from the crate's
+/// `migrations` directory into your own migrations.
+pub const OUTBOX_MIGRATION: &str = include_str!("../migrations/0001_uow_outbox.sql");
+
+/// A message written to the outbox, as handed to an `OutboxPublisher`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutboxMessage {
+    /// Position of the message in the outbox; messages are delivered in this
+    /// order.
+    pub id: i64,
+    /// What the message is about, e.g. the name of the event or the queue to
+    /// publish it to.
+    pub topic: String,
+    /// The message body, in whatever encoding the application chose.
+    pub payload: Vec<u8>,
+}
+
+/// Writes a message to the outbox in the transaction of `executor`, so that it
+/// is delivered by an `OutboxRelay` once, and only if, the transaction commits.
+///
+/// Returns the message's id.
+pub async fn enqueue(executor: &Executor, topic: &str, payload: &[u8]) -> TransactionResult<i64> {
+    let (id,): (i64,) = executor
+        .fetch_one_as(
+            sqlx::query_as("INSERT INTO uow_outbox (topic, payload) VALUES ($1, $2) RETURNING id")
+                .bind(topic)
+                .bind(payload),
+        )
+        .await?;
+    Ok(id)
+}
+
+/// Delivers outbox messages to where they are consumed, e.g. a message
+/// broker. Implemented by the application.
+#[async_trait]
+pub trait OutboxPublisher: Send + Sync {
+    /// Publishes a batch of messages, in order.
+    ///
+    /// On error the whole batch stays in the outbox and is handed over again
+    /// later, so messages published before the failure are delivered twice:
+    /// consumers must tolerate duplicates.
+    async fn publish(&self, messages: &[OutboxMessage]) -> TransactionResult<()>;
+}
+
+/// How an `OutboxRelay` polls the outbox.
+#[derive(Clone, Debug)]
+pub struct OutboxRelayConfig {
+    batch_size: i64,
+    poll_interval: Duration,
+    backoff: RetryPolicy,
+}
+
+impl Default for OutboxRelayConfig {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            backoff: RetryPolicy::default()
+                .initial_backoff(Duration::from_millis(100))
+                .max_backoff(Duration::from_secs(30)),
+        }
+    }
+}
+
+impl OutboxRelayConfig {
+    /// Create a config claiming 100 messages at a time and polling every second.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Most messages claimed and published at once.
+    pub fn batch_size(mut self, batch_size: u32) -> Self {
+        self.batch_size = i64::from(batch_size.max(1));
+        self
+    }
+
+    /// How long to wait before looking again once the outbox is empty.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Delay after the first failed batch, doubling with each further failure.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = self.backoff.initial_backoff(backoff);
+        self
+    }
+
+    /// Upper bound for the delay after failed batches.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = self.backoff.max_backoff(backoff);
+        self
+    }
+}
+
+/// A background task delivering outbox messages to an `OutboxPublisher`, at
+/// least once each.
+///
+/// Each batch is claimed with `FOR UPDATE SKIP LOCKED`, published and marked
+/// published in one unit of work, so relays running side by side, e.g. one per
+/// instance of the application, never hand the same message over at the same
+/// time. A single relay delivers the messages of a topic in order; relays
+/// side by side may deliver them out of order. Failed batches are retried with
+/// backoff. Dropping the relay stops it; `shutdown` also waits for the batch in
+/// flight.
+#[derive(Debug)]
+pub struct OutboxRelay {
+    shutdown: CancellationToken,
+    task: Option<JoinHandle<()>>,
+}
+
+impl OutboxRelay {
+    /// Start relaying the outbox of `uow`'s database to `publisher`.
+    pub fn spawn(uow: Arc<PostgresUnitOfWork>, publisher: Arc<dyn OutboxPublisher>, config: OutboxRelayConfig) -> Self {
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn(relay(uow, publisher, config, shutdown.clone()));
+        Self {
+            shutdown,
+            task: Some(task),
+        }
+    }
+
+    /// Stops the relay, waiting for the batch in flight to be committed or
+    /// rolled back.
+    pub async fn shutdown(mut self) {
+        self.shutdown.cancel();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for OutboxRelay {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+async fn relay(
+    uow: Arc<PostgresUnitOfWork>,
+    publisher: Arc<dyn OutboxPublisher>,
+    config: OutboxRelayConfig,
+    shutdown: CancellationToken,
+) {
+    let mut failures = 0;
+    while !shutdown.is_cancelled() {
+        let delay = match relay_batch(&uow, publisher.as_ref(), config.batch_size).await {
+            // A full batch suggests more are waiting
+            Ok(published) if published == config.batch_size => {
+                failures = 0;
+                continue;
+            }
+            Ok(_) => {
+                failures = 0;
+                config.poll_interval
+            }
+            Err(error) => {
+                failures += 1;
+                let delay = config.backoff.backoff(failures);
+                tracing::warn!(%error, failures, backoff = ?delay, "Relaying the outbox failed");
+                delay
+            }
+        };
+        let _ = shutdown.run_until_cancelled(tokio::time::sleep(delay)).await;
+    }
+}
+
+/// Publishes the next batch of messages in a unit of work of its own,
+/// returning how many there were.
+async fn relay_batch(uow: &PostgresUnitOfWork, publisher: &dyn OutboxPublisher, batch_size: i64) -> TransactionResult<i64> {
+    let session = uow.begin().await?;
+    match publish_batch(session.executor(), publisher, batch_size).await {
+        Ok(published) => {
+            session.commit().await?;
+            Ok(published)
+        }
+        Err(error) => {
+            let _ = session.rollback().await;
+            Err(error)
+        }
+    }
+}
+
+async fn publish_batch(executor: &Executor, publisher: &dyn OutboxPublisher, batch_size: i64) -> TransactionResult<i64> {
+    let rows: Vec<(i64, String, Vec<u8>)> = executor
+        .fetch_all_as(
+            sqlx::query_as(
+                "SELECT id, topic, payload FROM uow_outbox WHERE published_at IS NULL \
+                 ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED",
+            )
+            .bind(batch_size),
+        )
+        .await?;
+    if rows.is_empty() {
+        return Ok(0);
+    }
+
+    let messages: Vec<OutboxMessage> = rows
+        .into_iter()
+        .map(|(id, topic, payload)| OutboxMessage { id, topic, payload })
+        .collect();
+    publisher.publish(&messages).await?;
+    let ids: Vec<i64> = messages.iter().map(|message| message.id).collect();
+    executor
+        .execute(sqlx::query("UPDATE uow_outbox SET published_at = now() WHERE id = ANY($1)").bind(&ids))
+        .await?;
+    Ok(messages.len() as i64)
+}
\ No newline at end of file
@@ -0,0 +1,54 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::TransactionError;
+
+/// What a session does with observer failures reported after the transaction
+/// has already committed or rolled back.
+///
+/// The failure cannot undo the transaction, so some applications prefer
+/// `commit()` to succeed and handle the error out of band.
+#[derive(Clone, Default)]
+pub enum ObserverErrorPolicy {
+    /// Return the aggregated `ObserverErrors` from `commit()` or `rollback()`.
+    #[default]
+    Propagate,
+    /// Log the failure as a warning and report success.
+    LogAndIgnore,
+    /// Pass the failure to a handler and report success.
+    Callback(Arc<dyn Fn(&TransactionError) + Send + Sync>),
+}
+
+impl ObserverErrorPolicy {
+    /// Route observer failures to `handler`.
+    pub fn callback(handler: impl Fn(&TransactionError) + Send + Sync + 'static) -> Self {
+        Self::Callback(Arc::new(handler))
+    }
+}
+
+impl fmt::Debug for ObserverErrorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Propagate => f.write_str("Propagate"),
+            Self::LogAndIgnore => f.write_str("LogAndIgnore"),
+            Self::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// What a session does when it is dropped while its transaction is still
+/// open, i.e. without `commit()` or `rollback()`, which is almost always a bug.
+///
+/// The session rolls the transaction back either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LeakPolicy {
+    /// Log a warning naming the session, its label and how long it was open,
+    /// with the backtrace of its creation if the `backtrace` feature is on.
+    #[default]
+    Warn,
+    /// Panic in builds with debug assertions, e.g. to fail tests, and warn
+    /// otherwise. A session dropped while unwinding from a panic only warns.
+    PanicInDebug,
+    /// Say nothing.
+    Ignore,
+}
\ No newline at end of file
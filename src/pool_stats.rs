@@ -0,0 +1,131 @@
+use parking_lot::Mutex;
+use sqlx::PgPool;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+
+use crate::instrumentation::guard_panic;
+use crate::UowMetrics;
+
+/// How many of the latest waits for a connection the percentiles cover.
+const ACQUIRE_WAIT_SAMPLES: usize = 1024;
+
+/// The connections of a unit of work's pool, as returned by
+/// `PostgresUnitOfWork::pool_stats`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct PoolStats {
+    /// Connections the pool holds, idle or in use.
+    pub size: u32,
+    /// Connections waiting in the pool to be used.
+    pub idle: u32,
+    /// Connections handed out by the pool, e.g. to open sessions.
+    pub in_use: u32,
+    /// Most connections the pool opens.
+    pub max: u32,
+    /// Median time `begin()` waited for a connection, over its latest 1024
+    /// begins; None before the first.
+    pub acquire_wait_p50: Option<Duration>,
+    /// 99th percentile of the time `begin()` waited for a connection, over
+    /// its latest 1024 begins; None before the first.
+    pub acquire_wait_p99: Option<Duration>,
+}
+
+impl PoolStats {
+    pub(crate) fn of(pool: &PgPool, waits: &AcquireWaits) -> Self {
+        let size = pool.size();
+        let idle = u32::try_from(pool.num_idle()).unwrap_or(u32::MAX).min(size);
+        let (acquire_wait_p50, acquire_wait_p99) = waits.percentiles();
+        Self {
+            size,
+            idle,
+            in_use: size - idle,
+            max: pool.options().get_max_connections(),
+            acquire_wait_p50,
+            acquire_wait_p99,
+        }
+    }
+}
+
+/// The latest times `begin()` waited for a connection from the pool.
+#[derive(Debug, Default)]
+pub(crate) struct AcquireWaits(Mutex<VecDeque<Duration>>);
+
+impl AcquireWaits {
+    pub(crate) fn record(&self, wait: Duration) {
+        let mut waits = self.0.lock();
+        if waits.len() == ACQUIRE_WAIT_SAMPLES {
+            waits.pop_front();
+        }
+        waits.push_back(wait);
+    }
+
+    /// The median and 99th percentile of the waits, by nearest rank.
+    fn percentiles(&self) -> (Option<Duration>, Option<Duration>) {
+        let mut waits: Vec<Duration> = self.0.lock().iter().copied().collect();
+        waits.sort_unstable();
+        let percentile = |p: usize| {
+            let rank = (waits.len() * p).div_ceil(100).max(1);
+            waits.get(rank - 1).copied()
+        };
+        (percentile(50), percentile(99))
+    }
+}
+
+/// A background task reporting the `PoolStats` of a unit of work to
+/// `UowMetrics::observe_pool` at a fixed interval, started with
+/// `PostgresUnitOfWork::spawn_pool_sampler`.
+///
+/// Dropping the sampler stops it; `shutdown` also waits for the task to end.
+#[derive(Debug)]
+pub struct PoolSampler {
+    shutdown: CancellationToken,
+    task: Option<JoinHandle<()>>,
+}
+
+impl PoolSampler {
+    pub(crate) fn spawn(
+        pool: Arc<PgPool>,
+        waits: Arc<AcquireWaits>,
+        metrics: Arc<dyn UowMetrics>,
+        interval: Duration,
+    ) -> Self {
+        let shutdown = CancellationToken::new();
+        let task = tokio::spawn(sample(pool, waits, metrics, interval, shutdown.clone()));
+        Self {
+            shutdown,
+            task: Some(task),
+        }
+    }
+
+    /// Stops the sampler, waiting for the task to end.
+    pub async fn shutdown(mut self) {
+        self.shutdown.cancel();
+        if let Some(task) = self.task.take() {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Drop for PoolSampler {
+    fn drop(&mut self) {
+        self.shutdown.cancel();
+    }
+}
+
+async fn sample(
+    pool: Arc<PgPool>,
+    waits: Arc<AcquireWaits>,
+    metrics: Arc<dyn UowMetrics>,
+    interval: Duration,
+    shutdown: CancellationToken,
+) {
+    let mut ticks = tokio::time::interval(interval.max(Duration::from_millis(1)));
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    while shutdown.run_until_cancelled(ticks.tick()).await.is_some() {
+        let stats = PoolStats::of(&pool, &waits);
+        guard_panic("Metrics", || metrics.observe_pool(&stats));
+    }
+}
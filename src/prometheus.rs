@@ -0,0 +1,242 @@
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::{ActiveSessionCount, BeginErrorKind, PoolStats, RetryReason, RollbackReason, UowMetrics};
+
+/// Bucket bounds of the duration histogram unless configured otherwise, in
+/// seconds; the Prometheus client defaults.
+const DEFAULT_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// `UowMetrics` keeping a ready-made set of metrics and rendering them in the
+/// Prometheus text exposition format, e.g. for a `/metrics` endpoint:
+///
+/// - `uow_transactions_total{outcome}`, sessions committed or rolled back
+/// - `uow_rollbacks_total{reason}`, rollbacks by `RollbackReason`
+/// - `uow_begin_errors_total{kind}`, failed begins by `BeginErrorKind`
+/// - `uow_transaction_duration_seconds`, a histogram of how long sessions were open
+/// - `uow_statements_total`, statements run by committed sessions
+/// - `uow_active_sessions`, a gauge of the sessions open right now
+/// - `uow_retries_total{reason}`, retried attempts by `RetryReason`
+/// - `uow_retries_exhausted_total`, units of work that failed all their attempts
+/// - `uow_conflicts_total{sqlstate}`, serialization failures (`40001`) and
+///   deadlocks (`40P01`), retried or not
+/// - `uow_pool_connections{state}`, idle and in-use pool connections,
+///   `uow_pool_max_connections` and `uow_pool_acquire_wait_seconds{quantile}`,
+///   as of the latest sample of `PostgresUnitOfWork::spawn_pool_sampler`
+/// - `uow_slow_completions_total{statement}`, COMMITs and ROLLBACKs slower
+///   than the `slow_commit_threshold`
+///
+/// Every label value is reported from the start, at zero until it occurs.
+/// One instance can be shared by several units of work; their sessions add up.
+pub struct PrometheusUowMetrics {
+    buckets: Vec<f64>,
+    series: Mutex<Series>,
+    sessions: Mutex<Vec<ActiveSessionCount>>,
+}
+
+#[derive(Default)]
+struct Series {
+    committed: u64,
+    rollbacks: HashMap<RollbackReason, u64>,
+    begin_errors: HashMap<BeginErrorKind, u64>,
+    /// Observations per bucket, not cumulative; the last one is `+Inf`.
+    duration_buckets: Vec<u64>,
+    duration_sum: f64,
+    statements: u64,
+    retries: HashMap<RetryReason, u64>,
+    retries_exhausted: u64,
+    serialization_failures: u64,
+    deadlocks: u64,
+    pool: PoolStats,
+    slow_commits: u64,
+    slow_rollbacks: u64,
+}
+
+impl Default for PrometheusUowMetrics {
+    fn default() -> Self {
+        Self::with_buckets(DEFAULT_BUCKETS.to_vec())
+    }
+}
+
+impl PrometheusUowMetrics {
+    /// Create metrics with the default duration buckets, from 5ms to 10s.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create metrics whose duration histogram has the upper bounds
+    /// `buckets`, in seconds; they are sorted and deduplicated, and `+Inf` is
+    /// always added.
+    pub fn with_buckets(mut buckets: Vec<f64>) -> Self {
+        buckets.retain(|bound| bound.is_finite());
+        buckets.sort_by(f64::total_cmp);
+        buckets.dedup();
+        let series = Series {
+            duration_buckets: vec![0; buckets.len() + 1],
+            ..Series::default()
+        };
+        Self {
+            buckets,
+            series: Mutex::new(series),
+            sessions: Mutex::default(),
+        }
+    }
+
+    /// The metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let active: usize = self.sessions.lock().iter().map(ActiveSessionCount::get).sum();
+        let series = self.series.lock();
+        let rolled_back: u64 = series.rollbacks.values().sum();
+        let mut out = String::new();
+
+        header(&mut out, "uow_transactions_total", "counter", "Transactions ended, by outcome.");
+        let _ = writeln!(out, "uow_transactions_total{{outcome=\"committed\"}} {}", series.committed);
+        let _ = writeln!(out, "uow_transactions_total{{outcome=\"rolled_back\"}} {rolled_back}");
+
+        header(&mut out, "uow_rollbacks_total", "counter", "Transactions rolled back, by reason.");
+        for reason in RollbackReason::ALL {
+            let count = series.rollbacks.get(&reason).copied().unwrap_or(0);
+            let _ = writeln!(out, "uow_rollbacks_total{{reason=\"{}\"}} {count}", reason.as_str());
+        }
+
+        header(&mut out, "uow_begin_errors_total", "counter", "Failed begins, by kind.");
+        for kind in BeginErrorKind::ALL {
+            let count = series.begin_errors.get(&kind).copied().unwrap_or(0);
+            let _ = writeln!(out, "uow_begin_errors_total{{kind=\"{}\"}} {count}", kind.as_str());
+        }
+
+        header(
+            &mut out,
+            "uow_transaction_duration_seconds",
+            "histogram",
+            "How long transactions were open, from begin to commit or rollback.",
+        );
+        let mut cumulative = 0;
+        let bounds = self.buckets.iter().map(f64::to_string).chain(["+Inf".to_string()]);
+        for (bound, count) in bounds.zip(&series.duration_buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "uow_transaction_duration_seconds_bucket{{le=\"{bound}\"}} {cumulative}");
+        }
+        let _ = writeln!(out, "uow_transaction_duration_seconds_sum {}", series.duration_sum);
+        let _ = writeln!(out, "uow_transaction_duration_seconds_count {cumulative}");
+
+        header(&mut out, "uow_statements_total", "counter", "Statements run by committed transactions.");
+        let _ = writeln!(out, "uow_statements_total {}", series.statements);
+
+        header(&mut out, "uow_active_sessions", "gauge", "Sessions begun and not yet ended.");
+        let _ = writeln!(out, "uow_active_sessions {active}");
+
+        header(&mut out, "uow_retries_total", "counter", "Failed attempts retried, by reason.");
+        for reason in RetryReason::ALL {
+            let count = series.retries.get(&reason).copied().unwrap_or(0);
+            let _ = writeln!(out, "uow_retries_total{{reason=\"{}\"}} {count}", reason.as_str());
+        }
+
+        header(
+            &mut out,
+            "uow_retries_exhausted_total",
+            "counter",
+            "Units of work given up after failing every attempt the retry policy allows.",
+        );
+        let _ = writeln!(out, "uow_retries_exhausted_total {}", series.retries_exhausted);
+
+        header(&mut out, "uow_conflicts_total", "counter", "Serialization failures and deadlocks, by SQLSTATE.");
+        let _ = writeln!(out, "uow_conflicts_total{{sqlstate=\"40001\"}} {}", series.serialization_failures);
+        let _ = writeln!(out, "uow_conflicts_total{{sqlstate=\"40P01\"}} {}", series.deadlocks);
+
+        header(&mut out, "uow_pool_connections", "gauge", "Pool connections, by state.");
+        let _ = writeln!(out, "uow_pool_connections{{state=\"idle\"}} {}", series.pool.idle);
+        let _ = writeln!(out, "uow_pool_connections{{state=\"in_use\"}} {}", series.pool.in_use);
+
+        header(&mut out, "uow_pool_max_connections", "gauge", "Most connections the pool opens.");
+        let _ = writeln!(out, "uow_pool_max_connections {}", series.pool.max);
+
+        header(
+            &mut out,
+            "uow_pool_acquire_wait_seconds",
+            "gauge",
+            "Time begin waited for a pool connection over the latest begins, by quantile.",
+        );
+        for (quantile, wait) in [("0.5", series.pool.acquire_wait_p50), ("0.99", series.pool.acquire_wait_p99)] {
+            let seconds = wait.unwrap_or_default().as_secs_f64();
+            let _ = writeln!(out, "uow_pool_acquire_wait_seconds{{quantile=\"{quantile}\"}} {seconds}");
+        }
+
+        header(
+            &mut out,
+            "uow_slow_completions_total",
+            "counter",
+            "COMMITs and ROLLBACKs slower than the slow commit threshold.",
+        );
+        let _ = writeln!(out, "uow_slow_completions_total{{statement=\"commit\"}} {}", series.slow_commits);
+        let _ = writeln!(out, "uow_slow_completions_total{{statement=\"rollback\"}} {}", series.slow_rollbacks);
+        out
+    }
+
+    /// Count a transaction's duration in the histogram.
+    fn observe_duration(&self, series: &mut Series, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        let bucket = self.buckets.partition_point(|bound| *bound < seconds);
+        series.duration_buckets[bucket] += 1;
+        series.duration_sum += seconds;
+    }
+}
+
+impl UowMetrics for PrometheusUowMetrics {
+    fn record_commit(&self, duration: Duration, statements: u64) {
+        let mut series = self.series.lock();
+        series.committed += 1;
+        series.statements += statements;
+        self.observe_duration(&mut series, duration);
+    }
+
+    fn record_rollback(&self, duration: Duration, reason: RollbackReason) {
+        let mut series = self.series.lock();
+        *series.rollbacks.entry(reason).or_default() += 1;
+        self.observe_duration(&mut series, duration);
+    }
+
+    fn record_slow_commit(&self, _round_trip: Duration) {
+        self.series.lock().slow_commits += 1;
+    }
+
+    fn record_slow_rollback(&self, _round_trip: Duration) {
+        self.series.lock().slow_rollbacks += 1;
+    }
+
+    fn record_begin_error(&self, kind: BeginErrorKind) {
+        *self.series.lock().begin_errors.entry(kind).or_default() += 1;
+    }
+
+    fn record_retry(&self, _attempt: u32, sqlstate: Option<&str>) {
+        *self.series.lock().retries.entry(RetryReason::of_sqlstate(sqlstate)).or_default() += 1;
+    }
+
+    fn record_retry_exhausted(&self, _attempts: u32) {
+        self.series.lock().retries_exhausted += 1;
+    }
+
+    fn record_conflict(&self, sqlstate: &str) {
+        let mut series = self.series.lock();
+        match RetryReason::of_sqlstate(Some(sqlstate)) {
+            RetryReason::Deadlock => series.deadlocks += 1,
+            _ => series.serialization_failures += 1,
+        }
+    }
+
+    fn observe_active_sessions(&self, sessions: ActiveSessionCount) {
+        self.sessions.lock().push(sessions);
+    }
+
+    fn observe_pool(&self, stats: &PoolStats) {
+        self.series.lock().pool = *stats;
+    }
+}
+
+/// Write the `HELP` and `TYPE` lines of a metric.
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
\ No newline at end of file
@@ -0,0 +1,83 @@
+use futures::Stream;
+use sqlx::postgres::{PgArguments, PgRow};
+use sqlx::query::{Query, QueryAs};
+use sqlx::{FromRow, Postgres};
+
+use crate::{Executor, ExecutorState, TransactionResult};
+
+/// A view of an Executor exposing only its fetch helpers, created with
+/// `Executor::read_only`.
+///
+/// Hand it to code that should only read, such as reports: it offers no
+/// `execute`, batches, `copy_in` or access to the transaction, and the
+/// Executor it wraps cannot be recovered from it. It does not stop a fetched
+/// statement from writing, e.g. a `WITH ... DELETE ... RETURNING`; run
+/// `SET TRANSACTION READ ONLY` first to have the server reject writes too.
+#[derive(Clone, Debug)]
+pub struct ReadOnlyExecutor {
+    executor: Executor,
+}
+
+impl ReadOnlyExecutor {
+    pub(crate) fn new(executor: Executor) -> Self {
+        Self { executor }
+    }
+
+    /// Whether the wrapped Executor is still inside a live transaction.
+    pub fn is_active(&self) -> bool {
+        self.executor.is_active()
+    }
+
+    /// What the wrapped Executor runs its statements on.
+    pub fn state(&self) -> ExecutorState {
+        self.executor.state()
+    }
+
+    /// Runs `query` and returns its only row; see `Executor::fetch_one`.
+    pub async fn fetch_one(&self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<PgRow> {
+        self.executor.fetch_one(query).await
+    }
+
+    /// Runs `query` and returns its first row, if any.
+    pub async fn fetch_optional(&self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<Option<PgRow>> {
+        self.executor.fetch_optional(query).await
+    }
+
+    /// Runs `query` and returns all of its rows.
+    pub async fn fetch_all(&self, query: Query<'_, Postgres, PgArguments>) -> TransactionResult<Vec<PgRow>> {
+        self.executor.fetch_all(query).await
+    }
+
+    /// Runs a `sqlx::query_as` query and maps its only row to `T`.
+    pub async fn fetch_one_as<T>(&self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<T>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        self.executor.fetch_one_as(query).await
+    }
+
+    /// Runs a `sqlx::query_as` query and maps its first row, if any, to `T`.
+    pub async fn fetch_optional_as<T>(&self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<Option<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        self.executor.fetch_optional_as(query).await
+    }
+
+    /// Runs a `sqlx::query_as` query and maps every row to `T`.
+    pub async fn fetch_all_as<T>(&self, query: QueryAs<'_, Postgres, T, PgArguments>) -> TransactionResult<Vec<T>>
+    where
+        T: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        self.executor.fetch_all_as(query).await
+    }
+
+    /// Runs `query` and yields its rows as they arrive; see
+    /// `Executor::fetch_stream`.
+    pub fn fetch_stream<'q>(
+        &self,
+        query: Query<'q, Postgres, PgArguments>,
+    ) -> impl Stream<Item = TransactionResult<PgRow>> + Send + 'q {
+        self.executor.fetch_stream(query)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use crate::{PgErrorKind, TransactionError};
+
+/// Policy controlling how `PostgresUnitOfWork::run_with_retry` retries a unit of work.
+///
+/// Only transient concurrency failures (serialization failures and deadlocks)
+/// are retried. Between attempts the policy backs off exponentially, starting
+/// at `initial_backoff` and doubling up to `max_backoff`.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub(crate) max_attempts: u32,
+    pub(crate) initial_backoff: Duration,
+    pub(crate) max_backoff: Duration,
+    pub(crate) allow_observer_retry: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            allow_observer_retry: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy that runs the unit of work at most `max_attempts` times.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            ..Self::default()
+        }
+    }
+
+    /// Delay before the first retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// Upper bound for the delay between attempts.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// Retry even when a failed attempt registered observers that are not idempotent.
+    ///
+    /// By default such attempts are not retried, since those observers may have
+    /// already acted on the failed attempt and would be notified again.
+    pub fn allow_observer_retry(mut self, allow: bool) -> Self {
+        self.allow_observer_retry = allow;
+        self
+    }
+
+    /// Whether `error` is a transient failure worth retrying.
+    pub fn is_retryable(&self, error: &TransactionError) -> bool {
+        matches!(
+            error.pg_kind(),
+            Some(PgErrorKind::SerializationFailure | PgErrorKind::Deadlock)
+        )
+    }
+
+    /// Delay after the given (1-based) failed attempt.
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,186 @@
+use parking_lot::Mutex;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// sqlcommenter tags appended to the statements of the Executor helpers, set
+/// with `PostgresUnitOfWorkBuilder::sql_comments`, so slow query logs and
+/// `pg_stat_activity` can be correlated with the requests that ran them:
+///
+/// ```text
+/// SELECT 1 /*application='checkout',controller='place_order',traceparent='00-4bf9...-01'*/
+/// ```
+///
+/// - `application`, as configured
+/// - `controller`, the session's label
+/// - the entries of the session's metadata listed with `metadata_key`
+/// - `traceparent`, the W3C trace context of the current span, or else of the
+///   session's span; only with the `otel` feature and an OpenTelemetry layer
+///
+/// Tags are sorted by key, and keys and values are URL-encoded, so no value
+/// can end the comment early. Statements that already contain a comment are
+/// left as they are, which is one way to opt a statement out;
+/// `Executor::without_sql_comments` opts out every statement run through the
+/// returned Executor. `execute_batch`, `copy_in` and `copy_out` are never
+/// commented.
+///
+/// The SQL of a statement is the key of the connection's prepared statement
+/// cache, so every distinct comment is another statement for the server to
+/// parse. A `traceparent` differs with every request, so statements carrying
+/// one run unnamed rather than evicting the cached ones, and are parsed each
+/// time. Tags that take few values, like the label, only add one cached
+/// statement per value. Turn `traceparent` off where parsing shows up.
+#[derive(Clone, Debug)]
+pub struct SqlCommenter {
+    application: Option<String>,
+    metadata_keys: Vec<String>,
+    traceparent: bool,
+}
+
+impl Default for SqlCommenter {
+    fn default() -> Self {
+        Self {
+            application: None,
+            metadata_keys: Vec::new(),
+            traceparent: true,
+        }
+    }
+}
+
+impl SqlCommenter {
+    /// Tag statements with the session label and, with the `otel` feature,
+    /// the `traceparent`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tag statements with `application`, e.g. the name of the service.
+    pub fn application(mut self, application: impl Into<String>) -> Self {
+        self.application = Some(application.into());
+        self
+    }
+
+    /// Tag statements with the session's metadata under `key`, if set.
+    ///
+    /// Metadata is only tagged when listed, since it may hold values that
+    /// should not end up in the server's logs.
+    pub fn metadata_key(mut self, key: impl Into<String>) -> Self {
+        self.metadata_keys.push(key.into());
+        self
+    }
+
+    /// Whether to tag statements with the `traceparent` of the current span.
+    /// On by default; it has no effect without the `otel` feature.
+    pub fn traceparent(mut self, traceparent: bool) -> Self {
+        self.traceparent = traceparent;
+        self
+    }
+}
+
+/// The commenter of an Executor, with the label, metadata and span of the
+/// session it belongs to, if any.
+#[derive(Clone)]
+pub(crate) struct SqlComment {
+    commenter: Arc<SqlCommenter>,
+    label: Arc<Mutex<Option<String>>>,
+    metadata: Arc<Mutex<BTreeMap<String, String>>>,
+    /// The session's span, for the `traceparent` outside of any other span.
+    #[cfg_attr(not(feature = "otel"), allow(dead_code))]
+    span: tracing::Span,
+}
+
+impl SqlComment {
+    /// Comments for an Executor outside of any session.
+    pub(crate) fn new(commenter: Arc<SqlCommenter>) -> Self {
+        Self {
+            commenter,
+            label: Arc::default(),
+            metadata: Arc::default(),
+            span: tracing::Span::none(),
+        }
+    }
+
+    /// Comments for the Executor of a session.
+    pub(crate) fn for_session(
+        commenter: Arc<SqlCommenter>,
+        label: Arc<Mutex<Option<String>>>,
+        metadata: Arc<Mutex<BTreeMap<String, String>>>,
+        span: tracing::Span,
+    ) -> Self {
+        Self {
+            commenter,
+            label,
+            metadata,
+            span,
+        }
+    }
+
+    /// `sql` with the comment appended, and whether it carries a
+    /// `traceparent`; None if there is nothing to tag or `sql` already has a
+    /// comment.
+    pub(crate) fn apply(&self, sql: &str) -> Option<(String, bool)> {
+        if sql.contains("/*") || sql.contains("--") {
+            return None;
+        }
+        let mut tags = BTreeMap::new();
+        let metadata = self.metadata.lock();
+        for key in &self.commenter.metadata_keys {
+            if let Some(value) = metadata.get(key) {
+                tags.insert(key.as_str(), value.clone());
+            }
+        }
+        drop(metadata);
+        if let Some(label) = self.label.lock().clone() {
+            tags.insert("controller", label);
+        }
+        if let Some(application) = &self.commenter.application {
+            tags.insert("application", application.clone());
+        }
+        #[cfg(feature = "otel")]
+        if self.commenter.traceparent {
+            let traceparent = crate::otel::traceparent(&tracing::Span::current())
+                .or_else(|| crate::otel::traceparent(&self.span));
+            if let Some(traceparent) = traceparent {
+                tags.insert("traceparent", traceparent);
+            }
+        }
+        if tags.is_empty() {
+            return None;
+        }
+        let comment = serialize(&tags);
+        let statement = sql.trim_end();
+        let commented = match statement.strip_suffix(';') {
+            Some(statement) => format!("{} {comment};", statement.trim_end()),
+            None => format!("{statement} {comment}"),
+        };
+        Some((commented, tags.contains_key("traceparent")))
+    }
+}
+
+/// The tags as a sqlcommenter comment: `key='value'` pairs in key order,
+/// separated by commas, with keys and values URL-encoded and quotes escaped.
+fn serialize(tags: &BTreeMap<&str, String>) -> String {
+    let pairs: Vec<String> = tags
+        .iter()
+        .map(|(key, value)| format!("{}='{}'", escape(&url_encode(key)), escape(&url_encode(value))))
+        .collect();
+    format!("/*{}*/", pairs.join(","))
+}
+
+/// `text` with every byte but the unreserved characters of RFC 3986
+/// percent-encoded.
+fn url_encode(text: &str) -> String {
+    let mut encoded = String::with_capacity(text.len());
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => encoded.push(char::from(byte)),
+            _ => encoded.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    encoded
+}
+
+/// `text` with single quotes escaped by a backslash; URL-encoding already
+/// removes them, but the format asks for it regardless.
+fn escape(text: &str) -> String {
+    text.replace('\'', "\\'")
+}
@@ -0,0 +1,236 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use sqlx::postgres::PgArguments;
+use sqlx::Arguments;
+use std::fmt::Write;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::TransactionError;
+
+/// Length past which logged SQL is cut off.
+const MAX_SQL_LEN: usize = 2048;
+
+/// Length past which logged bind values are cut off.
+const MAX_VALUE_LEN: usize = 128;
+
+/// Level of the events logged by `PostgresUnitOfWorkBuilder::log_statements`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+/// How statements are logged, set with `PostgresUnitOfWorkBuilder::log_statements`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct StatementLog {
+    pub(crate) level: LogLevel,
+    pub(crate) bind_values: bool,
+}
+
+impl StatementLog {
+    /// The bind parameters of a statement as logged: `$1=<redacted:uuid>`
+    /// placeholders naming each type, or with `bind_values` the values
+    /// themselves, e.g. `$1=42, $2='alice'`.
+    ///
+    /// sqlx keeps the parameters' types and encoded values to itself, so they
+    /// are read from its `Debug` output; parameters it cannot be read from
+    /// show as `<redacted>`.
+    pub(crate) fn parameters(&self, arguments: &PgArguments) -> String {
+        let debug = format!("{arguments:?}");
+        let types = parse_types(&debug).unwrap_or_default();
+        let values = if self.bind_values { parse_values(&debug) } else { None };
+        let mut rendered = String::new();
+        for index in 0..arguments.len() {
+            if index > 0 {
+                rendered.push_str(", ");
+            }
+            let type_name = types.get(index).map(String::as_str);
+            let value = match (type_name, values.as_ref().and_then(|values| values.get(index))) {
+                (Some(type_name), Some(value)) => render_value(type_name, value.as_deref()),
+                (Some(type_name), None) => format!("<redacted:{type_name}>"),
+                (None, _) => "<redacted>".to_string(),
+            };
+            let _ = write!(rendered, "${}={value}", index + 1);
+        }
+        rendered
+    }
+
+    /// Log a finished statement at the configured level.
+    pub(crate) fn log(
+        &self,
+        sql: &str,
+        parameters: Option<&str>,
+        duration: Duration,
+        result: Result<u64, &TransactionError>,
+    ) {
+        let sql = truncate(sql, MAX_SQL_LEN);
+        let parameters = parameters.unwrap_or_default();
+        let (rows, error) = match result {
+            Ok(rows) => (Some(rows), None),
+            Err(error) => (None, Some(tracing::field::display(error))),
+        };
+        macro_rules! log {
+            ($level:expr) => {
+                tracing::event!(
+                    $level,
+                    sql = %sql,
+                    parameters = %parameters,
+                    elapsed = ?duration,
+                    rows,
+                    error,
+                    "Statement"
+                )
+            };
+        }
+        match self.level {
+            LogLevel::Trace => log!(tracing::Level::TRACE),
+            LogLevel::Debug => log!(tracing::Level::DEBUG),
+            LogLevel::Info => log!(tracing::Level::INFO),
+            LogLevel::Warn => log!(tracing::Level::WARN),
+            LogLevel::Error => log!(tracing::Level::ERROR),
+        }
+    }
+}
+
+/// `text` cut off after `max` bytes, noting how long it was.
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... ({} bytes)", &text[..end], text.len())
+}
+
+/// The PostgreSQL type names of the parameters in the `Debug` output of
+/// `PgArguments`, e.g. `int4` for `PgTypeInfo(Int4)` and `int4[]` for
+/// `PgTypeInfo(Int4Array)`.
+fn parse_types(debug: &str) -> Option<Vec<String>> {
+    let start = debug.find("types: [")? + "types: [".len();
+    let end = start + debug[start..].find("], buffer:")?;
+    let mut types = Vec::new();
+    let mut depth = 0;
+    let mut item = String::new();
+    for c in debug[start..end].chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                types.push(type_name(item.trim()));
+                item.clear();
+                continue;
+            }
+            _ => {}
+        }
+        item.push(c);
+    }
+    if !item.trim().is_empty() {
+        types.push(type_name(item.trim()));
+    }
+    Some(types)
+}
+
+/// The type name of one `PgTypeInfo(...)` of the `Debug` output.
+fn type_name(debug: &str) -> String {
+    let variant = debug
+        .strip_prefix("PgTypeInfo(")
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(debug);
+    // Custom and named types carry their name as a string
+    if variant.contains('(') {
+        return match variant.split('"').nth(1) {
+            Some(name) => name.to_string(),
+            None => "unknown".to_string(),
+        };
+    }
+    match variant.strip_suffix("Array") {
+        Some(element) => format!("{}[]", element.to_lowercase()),
+        None => variant.to_lowercase(),
+    }
+}
+
+/// The encoded values of the parameters in the `Debug` output of
+/// `PgArguments`, None for NULL.
+fn parse_values(debug: &str) -> Option<Vec<Option<Vec<u8>>>> {
+    let start = debug.find("buffer: [")? + "buffer: [".len();
+    let end = start + debug[start..].find(']')?;
+    let bytes = debug[start..end]
+        .split(", ")
+        .filter(|byte| !byte.is_empty())
+        .map(str::parse)
+        .collect::<Result<Vec<u8>, _>>()
+        .ok()?;
+    let mut values = Vec::new();
+    let mut rest = bytes.as_slice();
+    while let Some((length, tail)) = rest.split_first_chunk::<4>() {
+        let length = i32::from_be_bytes(*length);
+        if length < 0 {
+            values.push(None);
+            rest = tail;
+            continue;
+        }
+        let (value, tail) = tail.split_at_checked(usize::try_from(length).ok()?)?;
+        values.push(Some(value.to_vec()));
+        rest = tail;
+    }
+    Some(values)
+}
+
+/// A parameter's value in its binary encoding, rendered like a SQL literal
+/// where the type is known, and by its size otherwise.
+fn render_value(type_name: &str, value: Option<&[u8]>) -> String {
+    let Some(value) = value else {
+        return "NULL".to_string();
+    };
+    decode(type_name, value).unwrap_or_else(|| format!("<{type_name}, {} bytes>", value.len()))
+}
+
+fn decode(type_name: &str, value: &[u8]) -> Option<String> {
+    let rendered = match type_name {
+        "bool" => (value.first()? != &0).to_string(),
+        "int2" => i16::from_be_bytes(value.try_into().ok()?).to_string(),
+        "int4" => i32::from_be_bytes(value.try_into().ok()?).to_string(),
+        "int8" => i64::from_be_bytes(value.try_into().ok()?).to_string(),
+        "float4" => f32::from_be_bytes(value.try_into().ok()?).to_string(),
+        "float8" => f64::from_be_bytes(value.try_into().ok()?).to_string(),
+        "uuid" => format!("'{}'", Uuid::from_slice(value).ok()?),
+        "text" | "varchar" | "bpchar" | "name" | "json" => quote(std::str::from_utf8(value).ok()?),
+        // jsonb is sent with a version byte
+        "jsonb" => quote(std::str::from_utf8(value.strip_prefix(&[1])?).ok()?),
+        "bytea" => {
+            let mut hex = String::from("'\\x");
+            for byte in value.iter().take(MAX_VALUE_LEN / 2) {
+                let _ = write!(hex, "{byte:02x}");
+            }
+            hex.push('\'');
+            hex
+        }
+        "date" => {
+            let days = i32::from_be_bytes(value.try_into().ok()?);
+            let epoch = NaiveDate::from_ymd_opt(2000, 1, 1)?;
+            format!("'{}'", epoch.checked_add_signed(chrono::Duration::days(days.into()))?)
+        }
+        "timestamp" | "timestamptz" => {
+            let micros = i64::from_be_bytes(value.try_into().ok()?);
+            let epoch = NaiveDate::from_ymd_opt(2000, 1, 1)?.and_hms_opt(0, 0, 0)?;
+            let timestamp: NaiveDateTime = epoch.checked_add_signed(chrono::Duration::microseconds(micros))?;
+            if type_name == "timestamptz" {
+                format!("'{}'", DateTime::<Utc>::from_naive_utc_and_offset(timestamp, Utc).to_rfc3339())
+            } else {
+                format!("'{timestamp}'")
+            }
+        }
+        _ => return None,
+    };
+    Some(rendered)
+}
+
+/// `text` as a quoted SQL string literal, cut off if long.
+fn quote(text: &str) -> String {
+    format!("'{}'", truncate(text, MAX_VALUE_LEN).replace('\'', "''"))
+}
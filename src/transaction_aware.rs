@@ -1,20 +1,57 @@
 use async_trait::async_trait;
+use futures::future::BoxFuture;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
 
-/// Error type for transaction-aware operations
-#[derive(Debug, thiserror::Error)]
-pub enum TransactionError {
-    #[error("Transaction commit failed: {0}")]
-    CommitFailed(String),
-    
-    #[error("Transaction rollback failed: {0}")]
-    RollbackFailed(String),
-    
-    #[error("Database error: {0}")]
-    DatabaseError(#[from] sqlx::Error),
+pub use crate::error::{TransactionError, TransactionResult};
+
+use crate::{Executor, Extensions, SessionStats};
+
+/// How a transaction ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    /// The transaction was committed.
+    Committed,
+    /// The transaction was rolled back, explicitly or after a failed commit.
+    RolledBack,
+    /// Ending the transaction failed, e.g. ROLLBACK on a lost connection.
+    Failed,
 }
 
-/// Result type for transaction-aware operations
-pub type TransactionResult<T> = Result<T, TransactionError>;
+/// Information about a completed transaction, passed to observers.
+///
+/// The session builds the context once when the transaction ends, so every
+/// observer of one transaction sees the same values.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct TransactionContext {
+    /// Identifier of the session that ran the transaction.
+    pub session_id: Uuid,
+    /// When the session began.
+    pub started_at: SystemTime,
+    /// When the transaction ended.
+    pub completed_at: SystemTime,
+    /// How long the transaction was open.
+    pub duration: Duration,
+    /// How the transaction ended.
+    pub outcome: TransactionOutcome,
+    /// Label given to the session, if any.
+    pub label: Option<String>,
+    /// Metadata attached to the session.
+    pub metadata: HashMap<String, String>,
+    /// Backend pid of the transaction's connection, if it was asked for with
+    /// `PostgresUnitOfWorkSession::backend_pid`.
+    pub backend_pid: Option<i32>,
+    /// Values stashed on the session with `PostgresUnitOfWorkSession::extensions`.
+    pub extensions: Extensions,
+    /// Work the session did through its Executor's helpers.
+    pub stats: SessionStats,
+    /// OpenTelemetry trace the session's span belongs to, as 32 lowercase hex
+    /// digits; None without the `otel` feature or an OpenTelemetry layer.
+    pub trace_id: Option<String>,
+}
 
 /// Trait for components that need to be notified of transaction lifecycle events.
 ///
@@ -22,17 +59,305 @@ pub type TransactionResult<T> = Result<T, TransactionError>;
 /// to receive callbacks when the transaction is committed or rolled back.
 /// This allows repositories and other components to perform cleanup operations,
 /// update caches, or handle other post-transaction tasks.
+///
+/// Several observers can be combined into one by implementing this trait on a
+/// `Vec<Arc<dyn TransactionAware>>` or a tuple of up to three observers.
 #[async_trait]
 pub trait TransactionAware: Send + Sync {
+    /// Called when the observer joins an active transaction.
+    ///
+    /// Session-level observers are called from `register_transaction_aware`;
+    /// default observers of a `PostgresUnitOfWork` are called from `begin()`.
+    /// Implementations can use the executor to prepare per-transaction state,
+    /// e.g. a temporary table. Returning an error rejects the registration.
+    async fn after_begin(&self, _executor: &Executor) -> TransactionResult<()> {
+        Ok(())
+    }
+    
+    /// Called inside the transaction just before it is committed.
+    ///
+    /// Implementations can use the executor to run SQL in the same transaction,
+    /// e.g. to flush buffered writes or check invariants over the staged data.
+    /// Returning an error vetoes the commit: the session rolls back instead and
+    /// `commit()` fails with `TransactionError::CommitVetoed`.
+    async fn before_commit(&self, _executor: &Executor) -> TransactionResult<()> {
+        Ok(())
+    }
+    
     /// Called after a successful transaction commit.
     ///
     /// Implementations should use this to finalize any pending operations,
     /// such as updating caches or flushing buffers.
     async fn on_commit(&self) -> TransactionResult<()>;
     
+    /// Called after a successful transaction commit with its context.
+    ///
+    /// Sessions call this variant; the default delegates to `on_commit`.
+    async fn on_commit_with(&self, _context: &TransactionContext) -> TransactionResult<()> {
+        self.on_commit().await
+    }
+    
     /// Called after a transaction rollback.
     ///
     /// Implementations should use this to revert any in-memory state changes
     /// that were made during the transaction.
     async fn on_rollback(&self) -> TransactionResult<()>;
+    
+    /// Called after a transaction rollback with its context.
+    ///
+    /// Sessions call this variant; the default delegates to `on_rollback`.
+    async fn on_rollback_with(&self, _context: &TransactionContext) -> TransactionResult<()> {
+        self.on_rollback().await
+    }
+    
+    /// Called when rolling back the transaction failed, e.g. because the
+    /// connection was lost.
+    ///
+    /// The server aborts a transaction whose connection dies, so reverting
+    /// in-memory state is usually still appropriate; the default does nothing.
+    async fn on_rollback_failure(&self, _error: &TransactionError) -> TransactionResult<()> {
+        Ok(())
+    }
+    
+    /// Called when rolling back the transaction failed, with its context.
+    ///
+    /// Sessions call this variant; the default delegates to `on_rollback_failure`.
+    async fn on_rollback_failure_with(
+        &self,
+        _context: &TransactionContext,
+        error: &TransactionError,
+    ) -> TransactionResult<()> {
+        self.on_rollback_failure(error).await
+    }
+    
+    /// Name identifying this observer in errors and diagnostics.
+    ///
+    /// Defaults to the implementing type's name; override it to tell apart
+    /// several instances of the same type.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+    
+    /// Whether this observer tolerates being registered and notified again
+    /// when a failed unit of work is retried.
+    ///
+    /// `run_with_retry` refuses to retry an attempt that registered
+    /// non-idempotent observers unless the retry policy allows it.
+    fn is_idempotent(&self) -> bool {
+        false
+    }
+}
+
+/// A shared observer forwards every callback, so `Arc`s of observers can be
+/// combined into composites.
+#[async_trait]
+impl<T: TransactionAware + ?Sized> TransactionAware for Arc<T> {
+    async fn after_begin(&self, executor: &Executor) -> TransactionResult<()> {
+        self.as_ref().after_begin(executor).await
+    }
+    
+    async fn before_commit(&self, executor: &Executor) -> TransactionResult<()> {
+        self.as_ref().before_commit(executor).await
+    }
+    
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.as_ref().on_commit().await
+    }
+    
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.as_ref().on_rollback().await
+    }
+    
+    async fn on_rollback_failure(&self, error: &TransactionError) -> TransactionResult<()> {
+        self.as_ref().on_rollback_failure(error).await
+    }
+    
+    async fn on_commit_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        self.as_ref().on_commit_with(context).await
+    }
+    
+    async fn on_rollback_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        self.as_ref().on_rollback_with(context).await
+    }
+    
+    async fn on_rollback_failure_with(
+        &self,
+        context: &TransactionContext,
+        error: &TransactionError,
+    ) -> TransactionResult<()> {
+        self.as_ref().on_rollback_failure_with(context, error).await
+    }
+    
+    fn name(&self) -> &str {
+        self.as_ref().name()
+    }
+    
+    fn is_idempotent(&self) -> bool {
+        self.as_ref().is_idempotent()
+    }
+}
+
+/// Run `callback` on each member of a composite observer in order, stopping
+/// at the first error.
+#[doc(hidden)]
+pub async fn forward_until_error<'a>(
+    members: &[&'a dyn TransactionAware],
+    callback: impl Fn(&'a dyn TransactionAware) -> BoxFuture<'a, TransactionResult<()>>,
+) -> TransactionResult<()> {
+    for member in members {
+        callback(*member).await?;
+    }
+    Ok(())
+}
+
+/// Run `callback` on every member of a composite observer in order,
+/// collecting failures into `TransactionError::ObserverErrors`.
+#[doc(hidden)]
+pub async fn forward_to_all<'a>(
+    members: &[&'a dyn TransactionAware],
+    callback: impl Fn(&'a dyn TransactionAware) -> BoxFuture<'a, TransactionResult<()>>,
+) -> TransactionResult<()> {
+    let mut errors = Vec::new();
+    for member in members {
+        if let Err(error) = callback(*member).await {
+            errors.push(TransactionError::ObserverFailed {
+                observer: member.name().to_string(),
+                source: Box::new(error),
+            });
+        }
+    }
+    
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(TransactionError::ObserverErrors(errors))
+    }
+}
+
+/// Implement `TransactionAware` for a composite observer by forwarding every
+/// callback to its members in order.
+///
+/// `after_begin` and `before_commit` stop at the first failing member, like
+/// they do across a session's observers; the completion callbacks notify
+/// every member and aggregate their errors. The composite is idempotent only
+/// if all of its members are.
+macro_rules! composite_observer {
+    (impl<$($param:ident),*> for $ty:ty, |$this:ident| $members:expr) => {
+        #[async_trait]
+        impl<$($param: TransactionAware),*> TransactionAware for $ty {
+            async fn after_begin(&self, executor: &Executor) -> TransactionResult<()> {
+                let $this = self;
+                forward_until_error(&$members, |member| member.after_begin(executor)).await
+            }
+            
+            async fn before_commit(&self, executor: &Executor) -> TransactionResult<()> {
+                let $this = self;
+                forward_until_error(&$members, |member| member.before_commit(executor)).await
+            }
+            
+            async fn on_commit(&self) -> TransactionResult<()> {
+                let $this = self;
+                forward_to_all(&$members, |member| member.on_commit()).await
+            }
+            
+            async fn on_rollback(&self) -> TransactionResult<()> {
+                let $this = self;
+                forward_to_all(&$members, |member| member.on_rollback()).await
+            }
+            
+            async fn on_rollback_failure(&self, error: &TransactionError) -> TransactionResult<()> {
+                let $this = self;
+                forward_to_all(&$members, |member| member.on_rollback_failure(error)).await
+            }
+            
+            async fn on_commit_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+                let $this = self;
+                forward_to_all(&$members, |member| member.on_commit_with(context)).await
+            }
+            
+            async fn on_rollback_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+                let $this = self;
+                forward_to_all(&$members, |member| member.on_rollback_with(context)).await
+            }
+            
+            async fn on_rollback_failure_with(
+                &self,
+                context: &TransactionContext,
+                error: &TransactionError,
+            ) -> TransactionResult<()> {
+                let $this = self;
+                forward_to_all(&$members, |member| member.on_rollback_failure_with(context, error)).await
+            }
+            
+            fn is_idempotent(&self) -> bool {
+                let $this = self;
+                $members.iter().all(|member| member.is_idempotent())
+            }
+        }
+    };
+}
+
+composite_observer!(impl<> for Vec<Arc<dyn TransactionAware>>, |this| this
+    .iter()
+    .map(|member| member.as_ref())
+    .collect::<Vec<&dyn TransactionAware>>());
+composite_observer!(impl<A, B> for (A, B), |this| [&this.0 as &dyn TransactionAware, &this.1]);
+composite_observer!(impl<A, B, C> for (A, B, C), |this| [&this.0 as &dyn TransactionAware, &this.1, &this.2]);
+
+/// Synchronous counterpart of `TransactionAware` for observers that only
+/// update in-memory state.
+///
+/// Wrap an implementation in `SyncAdapter` to register it. The callbacks run
+/// directly on the async runtime, so they must return quickly and never
+/// block on I/O or locks held across transactions.
+pub trait SyncTransactionAware: Send + Sync {
+    /// Called after a successful transaction commit.
+    fn on_commit(&self) -> TransactionResult<()>;
+    
+    /// Called after a transaction rollback.
+    fn on_rollback(&self) -> TransactionResult<()>;
+    
+    /// Called when rolling back the transaction failed; the default does nothing.
+    fn on_rollback_failure(&self, _error: &TransactionError) -> TransactionResult<()> {
+        Ok(())
+    }
+    
+    /// See `TransactionAware::is_idempotent`.
+    fn is_idempotent(&self) -> bool {
+        false
+    }
+}
+
+/// Bridges a `SyncTransactionAware` observer to `TransactionAware`.
+#[derive(Clone, Debug, Default)]
+pub struct SyncAdapter<T>(pub T);
+
+impl<T: SyncTransactionAware> SyncAdapter<T> {
+    /// Wrap `observer` for registration with a session.
+    pub fn new(observer: T) -> Arc<Self> {
+        Arc::new(Self(observer))
+    }
+}
+
+#[async_trait]
+impl<T: SyncTransactionAware> TransactionAware for SyncAdapter<T> {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.0.on_commit()
+    }
+    
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.0.on_rollback()
+    }
+    
+    async fn on_rollback_failure(&self, error: &TransactionError) -> TransactionResult<()> {
+        self.0.on_rollback_failure(error)
+    }
+    
+    fn name(&self) -> &str {
+        std::any::type_name::<T>()
+    }
+    
+    fn is_idempotent(&self) -> bool {
+        self.0.is_idempotent()
+    }
 }
\ No newline at end of file
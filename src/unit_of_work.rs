@@ -1,9 +1,44 @@
 use async_trait::async_trait;
-use parking_lot::RwLock;
-use sqlx::{PgPool, Postgres, Transaction};
-use std::sync::Arc;
+use futures::future::{join_all, BoxFuture};
+use futures::FutureExt;
+use parking_lot::{Mutex, RwLock};
+use sqlx::migrate::Migrator;
+use sqlx::postgres::{PgListener, PgTransactionManager};
+use sqlx::{PgPool, Postgres, Transaction, TransactionManager};
+use std::any::Any;
+#[cfg(feature = "backtrace")]
+use std::backtrace::Backtrace;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock, Weak};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::Mutex as AsyncMutex;
+use tokio::task::AbortHandle;
+use tracing::Instrument;
+use uuid::Uuid;
 
-use crate::{Executor, TransactionAware, TransactionResult};
+use crate::circuit_breaker::{CircuitBreaker, Transition};
+use crate::events::{EventBuffer, EventHandler};
+use crate::executor::SessionState;
+use crate::handle::{Completion, SessionRegistry};
+use crate::hooks::{ClosureHook, Compensations, HookTrigger, OnceObserver};
+use crate::idempotency::{self, IdempotencyOutcome};
+use crate::jobs::{self, JobWakeups, JOBS_CHANNEL};
+use crate::metrics::NoopMetrics;
+use crate::migrate::{self, MigrationReport};
+use crate::instrumentation::{guard_panic, SlowTransactionCallback, Watchdog};
+use crate::observer_registry::{ObserverRef, ObserverRegistry, Registered};
+use crate::pool_stats::AcquireWaits;
+use crate::sql_comment::SqlComment;
+use crate::statement_log::StatementLog;
+use crate::{
+    AsTransactionError, CircuitBreakerConfig, CircuitState, CommitReport, Executor, ExecutorMetrics, Extensions, HealthReport, LeakPolicy, NotificationStream, ObserverErrorPolicy, ObserverHandle, PoolSampler, PoolStats, QueryHook,
+    LogLevel, ReadOnlyExecutor, RetryPolicy, SessionHandle, SqlCommenter, SessionInterceptor, SessionInfo, SessionStats, ShutdownReport, SlowTransaction, TransactionAware, TransactionContext, TransactionError, TransactionListener,
+    TransactionOptions, TransactionOutcome, TransactionResult, ActiveSessionCount, BeginErrorKind, RollbackReason, UowMetrics,
+};
 
 /// Unit of Work pattern for managing database transactions.
 ///
@@ -28,7 +63,53 @@ pub trait UnitOfWorkSession: Send + Sync {
     fn executor(&self) -> &Executor;
     
     /// Register a component that needs to be notified of transaction events.
-    fn register_transaction_aware(&self, observer: Arc<dyn TransactionAware>);
+    ///
+    /// The observer's `after_begin` hook runs before it is registered; if it
+    /// fails, the observer is not registered and the error is returned. The
+    /// returned handle can deregister the observer before the session completes.
+    ///
+    /// Registering once the transaction has completed fails with
+    /// `TransactionError::TransactionAlreadyCompleted`, as does adding a
+    /// closure hook.
+    async fn register_transaction_aware(&self, observer: Arc<dyn TransactionAware>) -> TransactionResult<ObserverHandle> {
+        self.register_transaction_aware_with_priority(observer, 0).await
+    }
+    
+    /// Register a component with an explicit notification priority.
+    ///
+    /// Lower priorities are notified first before and after commit, and last
+    /// after rollback, mirroring setup/teardown order. Observers with the same
+    /// priority are notified in registration order. Plain registration uses
+    /// priority 0.
+    async fn register_transaction_aware_with_priority(
+        &self,
+        observer: Arc<dyn TransactionAware>,
+        priority: i32,
+    ) -> TransactionResult<ObserverHandle>;
+    
+    /// Register a component without keeping it alive.
+    ///
+    /// The session only holds a weak reference, and skips the observer if it
+    /// has been dropped by the time the session completes. Prefer this when
+    /// the observer itself holds the session or its Executor (like a
+    /// repository stored next to the session in a struct), which would
+    /// otherwise form a reference cycle; keep strong registration for
+    /// fire-and-forget observers that nothing else owns.
+    async fn register_transaction_aware_weak(
+        &self,
+        observer: Weak<dyn TransactionAware>,
+    ) -> TransactionResult<ObserverHandle>;
+    
+    /// Register a component that is notified of at most one completion event.
+    ///
+    /// The first commit, rollback or rollback failure notification consumes
+    /// the registration and releases the observer; any later notification of
+    /// the same entry does nothing. Use this for per-operation side effects,
+    /// such as sending one email after this commit.
+    async fn register_once(&self, observer: Arc<dyn TransactionAware>) -> TransactionResult<ObserverHandle> {
+        self.register_transaction_aware(Arc::new(OnceObserver::new(observer)))
+            .await
+    }
     
     /// Commit the transaction and notify all registered observers.
     async fn commit(self) -> TransactionResult<()>;
@@ -40,12 +121,821 @@ pub trait UnitOfWorkSession: Send + Sync {
 /// Default implementation of UnitOfWork for PostgreSQL.
 pub struct PostgresUnitOfWork {
     pool: Arc<PgPool>,
+    default_observers: RwLock<Vec<Arc<dyn TransactionAware>>>,
+    event_handlers: RwLock<Vec<EventHandler>>,
+    listeners: RwLock<Vec<Arc<dyn TransactionListener>>>,
+    interceptors: Vec<Arc<dyn SessionInterceptor>>,
+    observer_error_policy: ObserverErrorPolicy,
+    observer_timeout: Option<Duration>,
+    query_hook: Option<Arc<dyn QueryHook>>,
+    executor_metrics: Option<ExecutorMetrics>,
+    sql_commenter: Option<Arc<SqlCommenter>>,
+    statement_log: Option<StatementLog>,
+    metrics: Arc<dyn UowMetrics>,
+    slow_transaction_threshold: Option<Duration>,
+    on_slow_transaction: Option<SlowTransactionCallback>,
+    slow_commit_threshold: Option<Duration>,
+    watchdog: Option<Watchdog>,
+    leak_policy: LeakPolicy,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    sessions: Arc<SessionRegistry>,
+    acquire_waits: Arc<AcquireWaits>,
+    /// Set by `shutdown`, refusing new sessions.
+    shutting_down: AtomicBool,
 }
 
 impl PostgresUnitOfWork {
     /// Create a new PostgresUnitOfWork with the given connection pool.
     pub fn new(pool: Arc<PgPool>) -> Self {
-        Self { pool }
+        Self::builder(pool).build()
+    }
+    
+    /// Start configuring a PostgresUnitOfWork for the given connection pool.
+    pub fn builder(pool: Arc<PgPool>) -> PostgresUnitOfWorkBuilder {
+        PostgresUnitOfWorkBuilder {
+            pool,
+            interceptors: Vec::new(),
+            observer_error_policy: ObserverErrorPolicy::default(),
+            observer_timeout: None,
+            query_hook: None,
+            executor_metrics: None,
+            sql_commenter: None,
+            log_statements: None,
+            log_bind_values: false,
+            metrics: Arc::new(NoopMetrics),
+            slow_transaction_threshold: None,
+            on_slow_transaction: None,
+            slow_commit_threshold: None,
+            watchdog: None,
+            leak_policy: LeakPolicy::default(),
+            circuit_breaker: None,
+        }
+    }
+    
+    /// An Executor running statements directly on the pool, outside of any
+    /// transaction, for repositories used where no unit of work is begun.
+    ///
+    /// It reports to the configured query hook and executor metrics like the
+    /// Executors of sessions. See `Executor::from_pool`.
+    pub fn executor(&self) -> Executor {
+        let executor = Executor::from_pool(self.pool.clone());
+        self.instrument(&executor);
+        executor
+    }
+    
+    /// Register an observer with every session begun from now on.
+    ///
+    /// The observer's `after_begin` hook runs as part of `begin()`, and a
+    /// failure there fails the `begin()` call.
+    pub fn register_default_observer(&self, observer: Arc<dyn TransactionAware>) {
+        self.default_observers.write().push(observer);
+    }
+    
+    /// Add a listener that is told about every session begun from now on.
+    pub fn add_listener(&self, listener: Arc<dyn TransactionListener>) {
+        self.listeners.write().push(listener);
+    }
+    
+    /// Subscribe `handler` to domain events of type `E` staged on sessions
+    /// begun from now on.
+    ///
+    /// Events are delivered in staging order after a successful commit and
+    /// dropped on rollback. Handler failures are reported like observer
+    /// failures from `commit()`; the transaction is already committed then.
+    pub fn register_event_handler<E, F, Fut>(&self, handler: F)
+    where
+        E: Send + Sync + 'static,
+        F: Fn(Arc<E>) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.event_handlers.write().push(EventHandler::new(handler));
+    }
+    
+    /// The sessions begun by this unit of work that have not been dropped yet,
+    /// oldest first, e.g. for a dashboard or to wait for them on shutdown.
+    ///
+    /// The list does not keep the sessions alive: committing, rolling back or
+    /// dropping a session removes it.
+    pub fn active_sessions(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> = self.sessions.lock().values().map(SessionInfo::from).collect();
+        sessions.sort_by_key(|session| session.started_at);
+        sessions
+    }
+    
+    /// How many sessions `active_sessions` would list.
+    pub fn active_count(&self) -> usize {
+        self.sessions.lock().len()
+    }
+    
+    /// Checks that a working connection can be obtained, e.g. for a readiness
+    /// probe: acquires one from the pool, without beginning a transaction, and
+    /// runs `SELECT 1` on it.
+    ///
+    /// Gives up after `timeout`, also while waiting for a connection of an
+    /// exhausted pool, with `DatabaseError(sqlx::Error::PoolTimedOut)`.
+    pub async fn health_check(&self, timeout: Duration) -> TransactionResult<HealthReport> {
+        let started = Instant::now();
+        let check = async {
+            let mut conn = self.pool.acquire().await?;
+            sqlx::query("SELECT 1").execute(&mut *conn).await
+        };
+        tokio::time::timeout(timeout, check)
+            .await
+            .map_err(|_| TransactionError::DatabaseError(sqlx::Error::PoolTimedOut))??;
+        let latency = started.elapsed();
+        let stats = self.pool_stats();
+        Ok(HealthReport {
+            latency,
+            size: stats.size,
+            idle: stats.idle,
+            in_use: stats.in_use,
+        })
+    }
+    
+    /// The connections of the pool right now, and how long `begin` waited
+    /// for one lately.
+    pub fn pool_stats(&self) -> PoolStats {
+        PoolStats::of(&self.pool, &self.acquire_waits)
+    }
+    
+    /// Start reporting `pool_stats` to the unit of work's metrics every
+    /// `interval`, starting now, through `UowMetrics::observe_pool`.
+    ///
+    /// Runs on the current Tokio runtime until the returned sampler is shut
+    /// down or dropped.
+    pub fn spawn_pool_sampler(&self, interval: Duration) -> PoolSampler {
+        PoolSampler::spawn(self.pool.clone(), self.acquire_waits.clone(), self.metrics.clone(), interval)
+    }
+    
+    /// Stops beginning sessions and waits up to `grace` for the open ones to
+    /// end, then closes the pool, e.g. on SIGTERM before the process exits.
+    ///
+    /// `begin` fails with `TransactionError::ShuttingDown` from now on. Sessions
+    /// still open after `grace` are abandoned: they keep their connections
+    /// until they end, and the pool closes those connections then.
+    pub async fn shutdown(&self, grace: Duration) -> ShutdownReport {
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let deadline = tokio::time::Instant::now() + grace;
+        // Sessions whose begin was under way are registered a little later
+        let mut sessions: HashMap<Uuid, SessionHandle> = HashMap::new();
+        loop {
+            let open: Vec<SessionHandle> = self
+                .sessions
+                .lock()
+                .values()
+                .filter(|handle| !handle.is_completed())
+                .cloned()
+                .collect();
+            if open.is_empty() {
+                break;
+            }
+            sessions.extend(open.iter().map(|handle| (handle.id(), handle.clone())));
+            let ended = join_all(open.iter().map(SessionHandle::wait_for_completion));
+            if tokio::time::timeout_at(deadline, ended).await.is_err() {
+                break;
+            }
+        }
+        
+        let abandoned = sessions.values().filter(|handle| !handle.is_completed()).count();
+        if abandoned == 0 {
+            self.pool.close().await;
+        } else {
+            tracing::warn!(abandoned, "Shutting down with sessions still open");
+            let pool = self.pool.clone();
+            tokio::spawn(async move { pool.close().await });
+        }
+        ShutdownReport {
+            completed: sessions.len() - abandoned,
+            abandoned,
+        }
+    }
+    
+    /// Where the circuit breaker is, if one is configured, e.g. for a metrics
+    /// gauge.
+    pub fn circuit_state(&self) -> Option<CircuitState> {
+        self.circuit_breaker.as_ref().map(|breaker| breaker.state())
+    }
+    
+    /// Whether `shutdown` was called.
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutting_down.load(Ordering::SeqCst)
+    }
+    
+    /// Fails with `ShuttingDown` once `shutdown` was called.
+    fn ensure_accepting(&self) -> TransactionResult<()> {
+        if self.is_shutting_down() {
+            return Err(TransactionError::ShuttingDown);
+        }
+        Ok(())
+    }
+    
+    /// Apply the configured query hook, metrics, SQL comments and statement
+    /// log to `executor`, and have it report conflicts to the unit of work's
+    /// metrics.
+    fn instrument(&self, executor: &Executor) {
+        if let Some(hook) = &self.query_hook {
+            executor.set_query_hook(hook.clone());
+        }
+        if let Some(metrics) = &self.executor_metrics {
+            executor.set_metrics(metrics.clone());
+        }
+        if let Some(commenter) = &self.sql_commenter {
+            executor.set_sql_comment(SqlComment::new(commenter.clone()));
+        }
+        if let Some(log) = self.statement_log {
+            executor.set_statement_log(log);
+        }
+        executor.set_uow_metrics(self.metrics.clone());
+    }
+    
+    /// Tell the listeners about a change of the circuit breaker's state.
+    async fn report_circuit_transition(&self, transition: Option<Transition>) {
+        let Some((from, to)) = transition else {
+            return;
+        };
+        let listeners = self.listeners.read().clone();
+        for listener in &listeners {
+            listener.on_circuit_state_change(from, to).await;
+        }
+    }
+    
+    /// Begin a new transaction session configured with `options`.
+    ///
+    /// While the circuit breaker is open, fails right away with
+    /// `TransactionError::CircuitOpen`.
+    pub async fn begin_with(&self, options: TransactionOptions) -> TransactionResult<PostgresUnitOfWorkSession> {
+        // The session's span belongs to the caller's span, not to `uow.begin`
+        let parent = SpanParent::current();
+        let begin = self.begin_guarded(options, parent);
+        #[cfg(feature = "tracing")]
+        let begin = begin.instrument(tracing::info_span!(
+            "uow.begin",
+            db.system = "postgresql",
+            db.operation = "BEGIN",
+            db.statement = "BEGIN",
+            session_id = tracing::field::Empty,
+            isolation_level = tracing::field::Empty,
+        ));
+        match begin.await {
+            Ok(mut session) => {
+                guard_panic("Metrics", || self.metrics.record_begin());
+                session.metrics = self.metrics.clone();
+                Ok(session)
+            }
+            Err(error) => {
+                guard_panic("Metrics", || self.metrics.record_begin_error(BeginErrorKind::of(&error)));
+                Err(error)
+            }
+        }
+    }
+    
+    /// Begin a new session unless shutting down or the circuit breaker is
+    /// open.
+    async fn begin_guarded(
+        &self,
+        options: TransactionOptions,
+        parent: SpanParent,
+    ) -> TransactionResult<PostgresUnitOfWorkSession> {
+        self.ensure_accepting()?;
+        let Some(breaker) = &self.circuit_breaker else {
+            return self.begin_session(options, &parent).await;
+        };
+        let (permit, transition) = breaker.admit()?;
+        self.report_circuit_transition(transition).await;
+        let result = self.begin_session(options, &parent).await;
+        let transition = permit.finish(&result);
+        self.report_circuit_transition(transition).await;
+        result
+    }
+    
+    /// Begin a new session whose span is a child of `parent`, without
+    /// consulting the circuit breaker.
+    async fn begin_session(
+        &self,
+        options: TransactionOptions,
+        parent: &SpanParent,
+    ) -> TransactionResult<PostgresUnitOfWorkSession> {
+        let mut options = options;
+        for interceptor in &self.interceptors {
+            options = interceptor.before_begin(&options).await?;
+        }
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("isolation_level", options.isolation_level.map(crate::IsolationLevel::as_sql));
+        let acquiring = Instant::now();
+        let conn = self.pool.acquire().await?;
+        self.acquire_waits.record(acquiring.elapsed());
+        let mut tx = Transaction::begin(conn, None).await?;
+        for statement in options.setup_statements() {
+            sqlx::query(&statement).execute(&mut *tx).await?;
+        }
+        let mut session = PostgresUnitOfWorkSession::with_options(tx, options, parent);
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("session_id", tracing::field::display(session.id));
+        session.listeners = self.listeners.read().clone();
+        session.interceptors = self.interceptors.clone();
+        session.observer_error_policy = self.observer_error_policy.clone();
+        session.observer_timeout = self.observer_timeout;
+        session.executor.set_cancel_pool(self.pool.clone());
+        session.pool = Some(self.pool.clone());
+        self.instrument(&session.executor);
+        if let Some(commenter) = &self.sql_commenter {
+            session.executor.set_sql_comment(SqlComment::for_session(
+                commenter.clone(),
+                session.label.clone(),
+                session.metadata.clone(),
+                session.span.clone(),
+            ));
+        }
+        session.slow_transaction_threshold = self.slow_transaction_threshold;
+        session.on_slow_transaction = self.on_slow_transaction.clone();
+        session.slow_commit_threshold = self.slow_commit_threshold;
+        session.leak_policy = self.leak_policy;
+        session.circuit_breaker = self.circuit_breaker.clone();
+        if let Some(watchdog) = &self.watchdog {
+            session.start_watchdog(watchdog);
+        }
+        self.sessions.lock().insert(session.id, session.handle());
+        session.registry = Some(self.sessions.clone());
+        
+        let event_handlers = self.event_handlers.read().clone();
+        for handler in &event_handlers {
+            session.subscribe(handler);
+        }
+        let default_observers = self.default_observers.read().clone();
+        for observer in default_observers {
+            if let Err(error) = session.register_transaction_aware(observer).await {
+                // Roll back here rather than on drop, which would report the
+                // session as leaked
+                let _ = session.rollback_and_notify().await;
+                return Err(error);
+            }
+        }
+        for listener in &session.listeners {
+            listener.on_begin(session.id).await;
+        }
+        let info = session.info();
+        for interceptor in session.interceptors.iter().rev() {
+            interceptor.after_begin(&info).await;
+        }
+        Ok(session)
+    }
+    
+    /// Run `work` inside a new session, committing on success and rolling back
+    /// on error.
+    ///
+    /// If `work` panics, the session is rolled back and its observers notified
+    /// before the panic is resumed.
+    ///
+    /// `work` may fail with any error type that a `TransactionError` converts
+    /// into; begin and commit failures are converted the same way.
+    pub async fn run<F, T, E>(&self, work: F) -> Result<T, E>
+    where
+        F: for<'s> FnMut(&'s PostgresUnitOfWorkSession) -> BoxFuture<'s, Result<T, E>> + Send,
+        T: Send,
+        E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+    {
+        self.run_with_retry(&RetryPolicy::new(1), work).await
+    }
+    
+    /// Run several independent steps one after the other in a single new
+    /// session, so they commit or roll back together, and return their
+    /// outputs in order.
+    ///
+    /// The first step to fail stops the run and rolls back the work of all of
+    /// them. Observers registered by any step are notified once, by the one
+    /// commit or rollback, as with `run`.
+    pub async fn run_all<'a, T, E>(&self, steps: Vec<WorkStep<'a, T, E>>) -> Result<Vec<T>, E>
+    where
+        T: Send + 'a,
+        E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+    {
+        self.run_once(TransactionOptions::default(), |session, _: &&'a ()| {
+            Box::pin(async move {
+                let mut outputs = Vec::with_capacity(steps.len());
+                for step in steps {
+                    outputs.push((step.0)(session).await?);
+                }
+                Ok(outputs)
+            })
+        })
+        .await
+    }
+    
+    /// Run `work` against a READ ONLY transaction, for query-side code.
+    ///
+    /// The transaction is always rolled back afterwards, as there is nothing
+    /// to commit. No session is created: there are no observers, listeners or
+    /// events, and the transaction is not listed in `active_sessions`. Writes
+    /// fetched through `work`, such as `INSERT ... RETURNING`, are rejected by
+    /// the server.
+    pub async fn run_read_only<F, T, E>(&self, work: F) -> Result<T, E>
+    where
+        F: for<'e> FnOnce(&'e ReadOnlyExecutor) -> BoxFuture<'e, Result<T, E>> + Send,
+        T: Send,
+        E: From<TransactionError>,
+    {
+        self.ensure_accepting()?;
+        let mut tx = self.pool.begin().await.map_err(TransactionError::from)?;
+        sqlx::query("SET TRANSACTION READ ONLY")
+            .execute(&mut *tx)
+            .await
+            .map_err(TransactionError::from)?;
+        let executor = Executor::with_options(tx, TransactionOptions::default());
+        self.instrument(&executor);
+        
+        let result = work(&executor.read_only()).await;
+        // The rows are already read, so a failed rollback changes nothing
+        if let Ok(tx) = executor.take_transaction(SessionState::RollingBack).await {
+            let _ = tx.rollback().await;
+            executor.set_state(SessionState::RolledBack);
+        }
+        result
+    }
+    
+    /// Run `work` like [`run`](Self::run), and retry it with a fresh session
+    /// on transient failures.
+    ///
+    /// An attempt is retried when `policy` classifies its error (from `work` or
+    /// from commit) as retryable; errors that do not expose a
+    /// `TransactionError` through [`AsTransactionError`] are never retried.
+    /// Attempts that registered observers which are not idempotent fail with
+    /// `TransactionError::RetryUnsafe` instead, unless the policy allows
+    /// observer retries. If rolling back a failed attempt fails as well, the
+    /// error from `work` is the one reported.
+    pub async fn run_with_retry<F, T, E>(&self, policy: &RetryPolicy, mut work: F) -> Result<T, E>
+    where
+        F: for<'s> FnMut(&'s PostgresUnitOfWorkSession) -> BoxFuture<'s, Result<T, E>> + Send,
+        T: Send,
+        E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+    {
+        self.run_attempts(TransactionOptions::default(), policy, |session, _| work(session))
+            .await
+    }
+    
+    /// The attempts of `run_with_retry` and `#[transactional]` methods, each
+    /// in a session begun with `options`.
+    ///
+    /// The second argument of `work` only bounds the lifetime of the session
+    /// by the lifetime of what `work` borrows, so that the futures it returns
+    /// can hold on to both; `#[transactional]` bodies borrow the method's
+    /// arguments.
+    async fn run_attempts<'a, F, T, E>(&self, options: TransactionOptions, policy: &RetryPolicy, mut work: F) -> Result<T, E>
+    where
+        F: for<'s> FnMut(&'s PostgresUnitOfWorkSession, &'s &'a ()) -> BoxFuture<'s, Result<T, E>> + Send,
+        T: Send,
+        E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+    {
+        let mut attempt = 1;
+        loop {
+            let session = self.begin_with(options.clone()).await?;
+            let (result, non_idempotent) = Self::attempt(session, |session, scope| work(session, scope)).await;
+            let Err(error) = result else {
+                return result;
+            };
+
+            let retryable = error
+                .as_transaction_error()
+                .is_some_and(|transaction_error| policy.is_retryable(transaction_error));
+            if attempt >= policy.max_attempts || !retryable {
+                if retryable && policy.max_attempts > 1 {
+                    guard_panic("Metrics", || self.metrics.record_retry_exhausted(attempt));
+                }
+                return Err(error);
+            }
+            if non_idempotent > 0 && !policy.allow_observer_retry {
+                return Err(E::from(TransactionError::RetryUnsafe {
+                    source: Box::new(error),
+                    observers: non_idempotent,
+                }));
+            }
+
+            let sqlstate = error.as_transaction_error().and_then(TransactionError::sqlstate);
+            guard_panic("Metrics", || self.metrics.record_retry(attempt, sqlstate));
+            tokio::time::sleep(policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+    
+    /// A single attempt of `run_attempts`, for work that cannot be retried.
+    async fn run_once<'a, F, T, E>(&self, options: TransactionOptions, work: F) -> Result<T, E>
+    where
+        F: for<'s> FnOnce(&'s PostgresUnitOfWorkSession, &'s &'a ()) -> BoxFuture<'s, Result<T, E>> + Send,
+        T: Send,
+        E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+    {
+        let session = self.begin_with(options).await?;
+        Self::attempt(session, work).await.0
+    }
+    
+    /// Run `work` in `session`, committing on success and rolling back on
+    /// error or panic; also returns the number of observers that are not
+    /// idempotent, for deciding on a retry.
+    async fn attempt<'a, F, T, E>(session: PostgresUnitOfWorkSession, work: F) -> (Result<T, E>, usize)
+    where
+        F: for<'s> FnOnce(&'s PostgresUnitOfWorkSession, &'s &'a ()) -> BoxFuture<'s, Result<T, E>> + Send,
+        T: Send,
+        E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+    {
+        let result = match AssertUnwindSafe(work(&session, &&())).catch_unwind().await {
+            Ok(result) => result,
+            Err(panic) => {
+                // Roll back and notify observers before unwinding further,
+                // rather than leaving it to the session's drop
+                let _ = session.rollback().await;
+                std::panic::resume_unwind(panic);
+            }
+        };
+        let non_idempotent = session.non_idempotent_observers();
+
+        let result = match result {
+            Ok(value) => session.commit().await.map(|()| value).map_err(E::from),
+            Err(error) => {
+                let _ = session.rollback().await;
+                Err(error)
+            }
+        };
+        (result, non_idempotent)
+    }
+}
+
+/// Runs the body of a `#[transactional]` method once; not public API.
+#[doc(hidden)]
+pub async fn transactional<'a, F, T, E>(uow: &PostgresUnitOfWork, options: TransactionOptions, work: F) -> Result<T, E>
+where
+    F: for<'s> FnOnce(&'s PostgresUnitOfWorkSession, &'s &'a ()) -> BoxFuture<'s, Result<T, E>> + Send,
+    T: Send,
+    E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+{
+    uow.run_once(options, work).await
+}
+
+/// Runs the body of a `#[transactional(retry)]` method; not public API.
+#[doc(hidden)]
+pub async fn transactional_with_retry<'a, F, T, E>(
+    uow: &PostgresUnitOfWork,
+    options: TransactionOptions,
+    policy: RetryPolicy,
+    work: F,
+) -> Result<T, E>
+where
+    F: for<'s> FnMut(&'s PostgresUnitOfWorkSession, &'s &'a ()) -> BoxFuture<'s, Result<T, E>> + Send,
+    T: Send,
+    E: From<TransactionError> + AsTransactionError + std::error::Error + Send + Sync + 'static,
+{
+    uow.run_attempts(options, &policy, work).await
+}
+
+/// One step of `PostgresUnitOfWork::run_all`: self-contained work run
+/// against the session shared by all the steps.
+pub struct WorkStep<'a, T, E>(StepFn<'a, T, E>);
+
+type StepFn<'a, T, E> = Box<dyn for<'s> FnOnce(&'s PostgresUnitOfWorkSession) -> BoxFuture<'s, Result<T, E>> + Send + 'a>;
+
+impl<'a, T, E> WorkStep<'a, T, E> {
+    /// Wrap `work` as a step, e.g. an operation that otherwise runs in a
+    /// session of its own.
+    pub fn new<F>(work: F) -> Self
+    where
+        F: for<'s> FnOnce(&'s PostgresUnitOfWorkSession) -> BoxFuture<'s, Result<T, E>> + Send + 'a,
+    {
+        Self(Box::new(work))
+    }
+}
+
+/// Builder for a `PostgresUnitOfWork` with non-default configuration.
+pub struct PostgresUnitOfWorkBuilder {
+    pool: Arc<PgPool>,
+    interceptors: Vec<Arc<dyn SessionInterceptor>>,
+    observer_error_policy: ObserverErrorPolicy,
+    observer_timeout: Option<Duration>,
+    query_hook: Option<Arc<dyn QueryHook>>,
+    executor_metrics: Option<ExecutorMetrics>,
+    sql_commenter: Option<Arc<SqlCommenter>>,
+    log_statements: Option<LogLevel>,
+    log_bind_values: bool,
+    metrics: Arc<dyn UowMetrics>,
+    slow_transaction_threshold: Option<Duration>,
+    on_slow_transaction: Option<SlowTransactionCallback>,
+    slow_commit_threshold: Option<Duration>,
+    watchdog: Option<Watchdog>,
+    leak_policy: LeakPolicy,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+impl PostgresUnitOfWorkBuilder {
+    /// Wrap the lifecycle of every session in `interceptor`, after the
+    /// interceptors added before it; see `SessionInterceptor` for the order
+    /// its hooks run in.
+    pub fn interceptor(mut self, interceptor: Arc<dyn SessionInterceptor>) -> Self {
+        self.interceptors.push(interceptor);
+        self
+    }
+    
+    /// How sessions handle observer failures after the transaction ended.
+    ///
+    /// Defaults to `ObserverErrorPolicy::Propagate`.
+    pub fn observer_error_policy(mut self, policy: ObserverErrorPolicy) -> Self {
+        self.observer_error_policy = policy;
+        self
+    }
+    
+    /// Maximum time each observer callback may take before the observer is
+    /// treated as failed with `TransactionError::ObserverTimeout`.
+    ///
+    /// Notification then proceeds to the next observer. Unlimited by default;
+    /// sessions can override it per registration.
+    pub fn observer_timeout(mut self, timeout: Duration) -> Self {
+        self.observer_timeout = Some(timeout);
+        self
+    }
+    
+    /// Report every statement run through a session's Executor helpers to
+    /// `hook`, e.g. to log the SQL a unit of work executes.
+    pub fn query_hook(mut self, hook: Arc<dyn QueryHook>) -> Self {
+        self.query_hook = Some(hook);
+        self
+    }
+    
+    /// Report the latency of every statement run through a session's
+    /// Executor helpers, and warn about slow ones, as configured by `metrics`.
+    pub fn executor_metrics(mut self, metrics: ExecutorMetrics) -> Self {
+        self.executor_metrics = Some(metrics);
+        self
+    }
+    
+    /// Append a sqlcommenter comment with the tags of `commenter` to every
+    /// statement run through a session's Executor helpers, or through
+    /// `PostgresUnitOfWork::executor`; see `SqlCommenter`. Off by default.
+    pub fn sql_comments(mut self, commenter: SqlCommenter) -> Self {
+        self.sql_commenter = Some(Arc::new(commenter));
+        self
+    }
+    
+    /// Log every statement run through a session's Executor helpers, or
+    /// through `PostgresUnitOfWork::executor`, as a tracing event at `level`
+    /// with its elapsed time and outcome. Off by default.
+    ///
+    /// Bind parameters are logged by type only, as in
+    /// `$1=<redacted:uuid>, $2=<redacted:text>`, unless `log_bind_values`
+    /// allows their values. SQL longer than 2048 bytes is cut off.
+    pub fn log_statements(mut self, level: LogLevel) -> Self {
+        self.log_statements = Some(level);
+        self
+    }
+    
+    /// Whether the statement log shows the values of bind parameters, as in
+    /// `$1=42, $2='alice'`, rather than only their types. Values may hold
+    /// personal data or secrets, so this is off by default; long values are
+    /// cut off.
+    pub fn log_bind_values(mut self, log_bind_values: bool) -> Self {
+        self.log_bind_values = log_bind_values;
+        self
+    }
+    
+    /// Report every begin, commit and rollback of the unit of work's sessions,
+    /// and every failed begin, to `metrics`; see `UowMetrics`.
+    pub fn metrics(mut self, metrics: Arc<dyn UowMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+    
+    /// Warn about sessions whose transaction stays open longer than
+    /// `threshold`, however fast their individual statements are.
+    ///
+    /// The check runs when the session is committed, rolled back or dropped
+    /// without either; the warning names the session, its label, the number of
+    /// statements it ran and how long it took.
+    pub fn slow_transaction_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_transaction_threshold = Some(threshold);
+        self
+    }
+    
+    /// Also pass every slow transaction to `callback`, e.g. for metrics.
+    ///
+    /// Only called when a `slow_transaction_threshold` is set.
+    pub fn on_slow_transaction(mut self, callback: impl Fn(&SlowTransaction) + Send + Sync + 'static) -> Self {
+        self.on_slow_transaction = Some(Arc::new(callback));
+        self
+    }
+    
+    /// Warn about sessions whose COMMIT or ROLLBACK alone takes longer than
+    /// `threshold` to answer, as under synchronous replication waiting for a
+    /// standby, and report them to `UowMetrics::record_slow_commit` or
+    /// `record_slow_rollback`.
+    ///
+    /// Independent of `slow_transaction_threshold`; the round trip of COMMIT
+    /// is also in `CommitReport::commit_duration`.
+    pub fn slow_commit_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_commit_threshold = Some(threshold);
+        self
+    }
+    
+    /// Warn about sessions still open `threshold` after they began, while they
+    /// may still be holding locks, rather than only once they complete.
+    ///
+    /// Each session then runs a timer task that is stopped when it commits,
+    /// rolls back or is dropped. The warning names the session, its label
+    /// and metadata, the number of statements it ran so far and how long it
+    /// has been open.
+    pub fn long_transaction_threshold(mut self, threshold: Duration) -> Self {
+        self.watchdog.get_or_insert_with(Watchdog::default).threshold = Some(threshold);
+        self
+    }
+    
+    /// Repeat the long transaction warning every `interval` for as long as
+    /// the session stays open, instead of warning once.
+    ///
+    /// Only used when a `long_transaction_threshold` is set.
+    pub fn long_transaction_interval(mut self, interval: Duration) -> Self {
+        self.watchdog.get_or_insert_with(Watchdog::default).interval = Some(interval);
+        self
+    }
+    
+    /// Also pass every long transaction warning to `callback`. Its `outcome`
+    /// is always None, as the session is still open.
+    ///
+    /// Only called when a `long_transaction_threshold` is set.
+    pub fn on_long_transaction(mut self, callback: impl Fn(&SlowTransaction) + Send + Sync + 'static) -> Self {
+        self.watchdog.get_or_insert_with(Watchdog::default).callback = Some(Arc::new(callback));
+        self
+    }
+    
+    /// What sessions do when they are dropped without being committed or
+    /// rolled back, before rolling back their transaction.
+    ///
+    /// Defaults to `LeakPolicy::Warn`.
+    pub fn leak_policy(mut self, policy: LeakPolicy) -> Self {
+        self.leak_policy = policy;
+        self
+    }
+    
+    /// Fail `begin()` right away, instead of waiting for connection timeouts,
+    /// once begins and commits keep failing with connection errors, e.g.
+    /// while the database is down.
+    ///
+    /// After `config`'s threshold of consecutive failures the breaker opens,
+    /// and `begin()` fails with `TransactionError::CircuitOpen` for the
+    /// cool-down. Then one `begin()` is let through as a probe: the breaker
+    /// closes if it succeeds and opens again if it fails. Listeners are told
+    /// of every change in `on_circuit_state_change`. Off by default.
+    pub fn circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+    
+    /// Create the configured PostgresUnitOfWork.
+    pub fn build(self) -> PostgresUnitOfWork {
+        let sessions = Arc::default();
+        self.metrics.observe_active_sessions(ActiveSessionCount::new(&sessions));
+        PostgresUnitOfWork {
+            pool: self.pool,
+            default_observers: RwLock::new(Vec::new()),
+            event_handlers: RwLock::new(Vec::new()),
+            listeners: RwLock::new(Vec::new()),
+            interceptors: self.interceptors,
+            observer_error_policy: self.observer_error_policy,
+            observer_timeout: self.observer_timeout,
+            query_hook: self.query_hook,
+            executor_metrics: self.executor_metrics,
+            sql_commenter: self.sql_commenter,
+            statement_log: self.log_statements.map(|level| StatementLog {
+                level,
+                bind_values: self.log_bind_values,
+            }),
+            metrics: self.metrics,
+            slow_transaction_threshold: self.slow_transaction_threshold,
+            on_slow_transaction: self.on_slow_transaction,
+            slow_commit_threshold: self.slow_commit_threshold,
+            // Intervals and callbacks alone configure nothing
+            watchdog: self.watchdog.filter(|watchdog| watchdog.threshold.is_some()),
+            leak_policy: self.leak_policy,
+            circuit_breaker: self.circuit_breaker.map(|config| Arc::new(CircuitBreaker::new(config))),
+            sessions,
+            acquire_waits: Arc::default(),
+            shutting_down: AtomicBool::new(false),
+        }
+    }
+}
+
+impl fmt::Debug for PostgresUnitOfWorkBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PostgresUnitOfWorkBuilder")
+            .field("pool", &self.pool)
+            .field("interceptors", &self.interceptors.len())
+            .field("observer_error_policy", &self.observer_error_policy)
+            .field("observer_timeout", &self.observer_timeout)
+            .field("query_hook", &self.query_hook.is_some())
+            .field("executor_metrics", &self.executor_metrics)
+            .field("sql_commenter", &self.sql_commenter)
+            .field("log_statements", &self.log_statements)
+            .field("log_bind_values", &self.log_bind_values)
+            .field("slow_transaction_threshold", &self.slow_transaction_threshold)
+            .field("on_slow_transaction", &self.on_slow_transaction.is_some())
+            .field("slow_commit_threshold", &self.slow_commit_threshold)
+            .field("watchdog", &self.watchdog)
+            .field("leak_policy", &self.leak_policy)
+            .field("circuit_breaker", &self.circuit_breaker)
+            .finish()
     }
 }
 
@@ -54,64 +944,1288 @@ impl UnitOfWork for PostgresUnitOfWork {
     type Session = PostgresUnitOfWorkSession;
     
     async fn begin(&self) -> TransactionResult<Self::Session> {
-        let tx = self.pool.begin().await?;
-        Ok(PostgresUnitOfWorkSession::new(tx))
+        self.begin_with(TransactionOptions::default()).await
     }
 }
 
+/// What the span of a new session is a child of, captured when `begin()` is
+/// called.
+pub(crate) struct SpanParent {
+    span: tracing::Span,
+    /// The caller's OpenTelemetry context, joined if there is no tracing span.
+    #[cfg(feature = "otel")]
+    context: opentelemetry::Context,
+}
+
+impl SpanParent {
+    /// The caller's current span and context.
+    pub(crate) fn current() -> Self {
+        Self {
+            span: tracing::Span::current(),
+            #[cfg(feature = "otel")]
+            context: opentelemetry::Context::current(),
+        }
+    }
+}
+
+/// Span around committing or rolling back `$session`, as a child of its
+/// session span, with the fields `record_completion` fills in.
+#[cfg(feature = "tracing")]
+macro_rules! completion_span {
+    ($session:expr, $name:literal, $operation:literal) => {
+        tracing::info_span!(
+            parent: &$session.span,
+            $name,
+            db.system = "postgresql",
+            db.operation = $operation,
+            db.statement = $operation,
+            session_id = %$session.id,
+            label = tracing::field::Empty,
+            isolation_level = $session.executor.isolation_level().map(crate::IsolationLevel::as_sql),
+            outcome = tracing::field::Empty,
+            duration = tracing::field::Empty,
+            statements = tracing::field::Empty,
+        )
+    };
+}
+
+/// Record how a session ended on its `uow.commit` or `uow.rollback` span.
+#[cfg(feature = "tracing")]
+fn record_completion(span: &tracing::Span, context: &TransactionContext) {
+    span.record("label", context.label.as_deref());
+    span.record("outcome", tracing::field::debug(context.outcome));
+    span.record("duration", tracing::field::debug(context.duration));
+    span.record("statements", context.stats.statements);
+}
+
+/// Tell `metrics` how the session of `context` ended; `reason` is why it
+/// rolled back, if it did not commit.
+fn report_to_metrics(metrics: &dyn UowMetrics, context: &TransactionContext, reason: RollbackReason) {
+    guard_panic("Metrics", || match context.outcome {
+        TransactionOutcome::Committed => metrics.record_commit(context.duration, context.stats.statements),
+        TransactionOutcome::RolledBack | TransactionOutcome::Failed => metrics.record_rollback(context.duration, reason),
+    });
+}
+
 /// Default implementation of UnitOfWorkSession for PostgreSQL.
+///
+/// Dropping a session that was neither committed nor rolled back rolls its
+/// transaction back and notifies its observers, from a spawned task.
 pub struct PostgresUnitOfWorkSession {
     executor: Executor,
-    observers: Arc<RwLock<Vec<Arc<dyn TransactionAware>>>>,
+    observers: Arc<RwLock<ObserverRegistry>>,
+    id: Uuid,
+    /// Span carrying the session id, entered while completing the session.
+    span: tracing::Span,
+    started: Instant,
+    started_at: SystemTime,
+    label: Arc<Mutex<Option<String>>>,
+    metadata: Arc<Mutex<BTreeMap<String, String>>>,
+    /// Backend pid of the transaction's connection, once asked for.
+    backend_pid: Arc<OnceLock<i32>>,
+    /// Wakes the session's handles once it has ended.
+    handles: Arc<Completion>,
+    extensions: Extensions,
+    compensations: Arc<Compensations>,
+    /// Wakes the workers of the queues `enqueue_job` was used with.
+    job_wakeups: OnceLock<Arc<JobWakeups>>,
+    concurrent_notification: AtomicBool,
+    events: EventBuffer,
+    listeners: Vec<Arc<dyn TransactionListener>>,
+    interceptors: Vec<Arc<dyn SessionInterceptor>>,
+    completion: Mutex<Option<TransactionContext>>,
+    observer_error_policy: ObserverErrorPolicy,
+    observer_timeout: Option<Duration>,
+    slow_transaction_threshold: Option<Duration>,
+    on_slow_transaction: Option<SlowTransactionCallback>,
+    slow_commit_threshold: Option<Duration>,
+    /// Timer task warning about the session while it stays open.
+    watchdog: Option<AbortHandle>,
+    leak_policy: LeakPolicy,
+    /// Circuit breaker of the unit of work, counting commit failures.
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    metrics: Arc<dyn UowMetrics>,
+    /// Where the session was created, for reporting it if it leaks.
+    #[cfg(feature = "backtrace")]
+    created: Backtrace,
+    /// Pool the session was begun on, if known.
+    pool: Option<Arc<PgPool>>,
+    /// Open sessions of the unit of work that began this one.
+    registry: Option<Arc<SessionRegistry>>,
+    /// Connection listening to the channels passed to `listen`.
+    notifications: AsyncMutex<Option<PgListener>>,
 }
 
 impl PostgresUnitOfWorkSession {
     /// Create a new session from a PostgreSQL transaction.
     pub fn new(tx: Transaction<'static, Postgres>) -> Self {
+        Self::with_options(tx, TransactionOptions::default(), &SpanParent::current())
+    }
+    
+    /// Create a new session from a transaction that was started with `options`,
+    /// with its span a child of `parent`.
+    pub(crate) fn with_options(
+        tx: Transaction<'static, Postgres>,
+        options: TransactionOptions,
+        parent: &SpanParent,
+    ) -> Self {
+        let id = Uuid::new_v4();
+        let span = tracing::info_span!(
+            parent: &parent.span,
+            "unit_of_work_session",
+            session_id = %id,
+            metadata = tracing::field::Empty
+        );
+        #[cfg(feature = "otel")]
+        crate::otel::join(&span, &parent.span, &parent.context);
         Self {
-            executor: Executor::new(tx),
-            observers: Arc::new(RwLock::new(Vec::new())),
+            executor: Executor::with_options(tx, options),
+            observers: Arc::new(RwLock::new(ObserverRegistry::default())),
+            id,
+            span,
+            started: Instant::now(),
+            started_at: SystemTime::now(),
+            label: Arc::default(),
+            metadata: Arc::default(),
+            backend_pid: Arc::default(),
+            handles: Arc::default(),
+            extensions: Extensions::default(),
+            compensations: Arc::default(),
+            job_wakeups: OnceLock::new(),
+            concurrent_notification: AtomicBool::new(false),
+            events: EventBuffer::default(),
+            listeners: Vec::new(),
+            interceptors: Vec::new(),
+            completion: Mutex::new(None),
+            observer_error_policy: ObserverErrorPolicy::default(),
+            observer_timeout: None,
+            slow_transaction_threshold: None,
+            on_slow_transaction: None,
+            slow_commit_threshold: None,
+            watchdog: None,
+            leak_policy: LeakPolicy::default(),
+            circuit_breaker: None,
+            metrics: Arc::new(NoopMetrics),
+            #[cfg(feature = "backtrace")]
+            created: Backtrace::force_capture(),
+            pool: None,
+            registry: None,
+            notifications: AsyncMutex::new(None),
         }
     }
-}
-
-#[async_trait]
-impl UnitOfWorkSession for PostgresUnitOfWorkSession {
-    fn executor(&self) -> &Executor {
-        &self.executor
+    
+    /// Identifier of this session, as reported to transaction listeners and
+    /// observers and recorded on its tracing span.
+    pub fn id(&self) -> Uuid {
+        self.id
     }
     
-    fn register_transaction_aware(&self, observer: Arc<dyn TransactionAware>) {
-        self.observers.write().push(observer);
+    /// Wall-clock time the session began, as reported to observers in
+    /// `TransactionContext::started_at`.
+    pub fn started_at(&self) -> SystemTime {
+        self.started_at
     }
     
-    async fn commit(self) -> TransactionResult<()> {
+    /// How long the session has been open.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+    
+    /// Where the session is in its lifecycle.
+    pub fn state(&self) -> SessionState {
+        self.executor.session_state()
+    }
+    
+    /// Whether the session's transaction was committed or rolled back.
+    ///
+    /// `commit` and `rollback` consume the session, so code holding only a
+    /// clone of its Executor checks `Executor::session_state` instead.
+    pub fn is_completed(&self) -> bool {
+        matches!(self.state(), SessionState::Committed | SessionState::RolledBack)
+    }
+    
+    /// Tracing span of this session, with its id as the `session_id` field.
+    ///
+    /// The session enters it while committing or rolling back, so everything
+    /// logged by observers and listeners then carries the id. Instrument the
+    /// work done in the session with it to correlate the rest.
+    pub fn span(&self) -> &tracing::Span {
+        &self.span
+    }
+    
+    /// Process id of the server backend running the session's transaction,
+    /// for finding it in `pg_stat_activity` or `pg_locks`.
+    ///
+    /// The first call asks the server through the Executor's helpers; the pid
+    /// is then cached and also reported to observers and in slow and long
+    /// transaction warnings.
+    pub async fn backend_pid(&self) -> TransactionResult<i32> {
+        if let Some(pid) = self.backend_pid.get() {
+            return Ok(*pid);
+        }
+        let (pid,): (i32,) = self.executor.fetch_one_as(sqlx::query_as("SELECT pg_backend_pid()")).await?;
+        Ok(*self.backend_pid.get_or_init(|| pid))
+    }
+    
+    /// Claims `key` for this transaction, for processing a request at most
+    /// once: `IdempotencyOutcome::New` means go ahead,
+    /// `IdempotencyOutcome::Duplicate` that a committed transaction already
+    /// claimed the key and the request should be skipped.
+    ///
+    /// The key is written to the `uow_idempotency_keys` table (see
+    /// `IDEMPOTENCY_MIGRATION`) as part of the transaction, so a rollback
+    /// releases it again. After `ttl` the key may be claimed anew. A session
+    /// racing another one for the same key waits until the other one ends.
+    pub async fn idempotency_guard(&self, key: &str, ttl: Duration) -> TransactionResult<IdempotencyOutcome> {
+        idempotency::claim(&self.executor, key, ttl).await
+    }
+    
+    /// Enqueues a background job on `queue`, due at `run_at`, returning its id.
+    ///
+    /// The job is written to the `uow_jobs` table (see `JOBS_MIGRATION`) as
+    /// part of the transaction, so workers only see it once the session
+    /// commits; `claim_jobs` takes it. After the commit, `JOBS_CHANNEL` is
+    /// notified with the queue's name on a connection of the pool, once per
+    /// queue, so listening workers wake right away. Sessions not begun by a
+    /// `PostgresUnitOfWork` have no pool and send the notification from the
+    /// transaction instead, which delivers it on commit just the same.
+    pub async fn enqueue_job(&self, queue: &str, payload: &[u8], run_at: SystemTime) -> TransactionResult<i64> {
+        self.ensure_active()?;
+        let id = jobs::insert(&self.executor, queue, payload, run_at).await?;
+        match &self.pool {
+            Some(pool) => {
+                let wakeups = self.job_wakeups.get_or_init(|| {
+                    let wakeups = Arc::new(JobWakeups::new(pool.clone()));
+                    let observer: Arc<dyn TransactionAware> = wakeups.clone();
+                    self.observers.write().push(ObserverRef::Strong(observer), 0, None);
+                    wakeups
+                });
+                wakeups.push(queue);
+            }
+            None => {
+                self.executor
+                    .execute(sqlx::query("SELECT pg_notify($1, $2)").bind(JOBS_CHANNEL).bind(queue))
+                    .await?;
+            }
+        }
+        Ok(id)
+    }
+    
+    /// Applies the pending migrations of `migrator`, e.g. one built with
+    /// `sqlx::migrate!`, in the session's transaction, and reports which
+    /// versions were applied.
+    ///
+    /// The migrations commit or roll back with the session, all together.
+    /// They are checked like `Migrator::run` checks them, and a pending
+    /// migration opting out of transactions fails the call with
+    /// `MigrationNotTransactional` before any migration runs. Other sessions
+    /// running migrations wait until this one ends.
+    pub async fn run_migrations(&self, migrator: &Migrator) -> TransactionResult<MigrationReport> {
+        self.ensure_active()?;
+        let mut conn = self.executor.acquire().await?;
+        migrate::run(&mut conn, migrator).await
+    }
+    
+    /// Runs `future` with the session's Executor available through
+    /// `Executor::current`; see `Executor::attach`.
+    pub fn attach<F: Future>(&self, future: F) -> impl Future<Output = F::Output> {
+        self.executor.attach(future)
+    }
+    
+    /// A handle for following the session from elsewhere, e.g. to wait for it
+    /// to end during shutdown.
+    pub fn handle(&self) -> SessionHandle {
+        SessionHandle::new(
+            self.id,
+            self.started,
+            self.started_at,
+            self.label.clone(),
+            self.executor.shared_state(),
+            self.handles.clone(),
+        )
+    }
+    
+    /// A snapshot of the session, as passed to interceptors.
+    fn info(&self) -> SessionInfo {
+        SessionInfo::from(&self.handle())
+    }
+    
+    /// How much work the session has done through its Executor's helpers so far.
+    pub fn stats(&self) -> SessionStats {
+        self.executor.stats()
+    }
+    
+    /// Subscribes to notifications on `channel`, delivered through the stream
+    /// `commit_and_listen` returns.
+    ///
+    /// sqlx cannot receive notifications on the transaction's connection, so
+    /// the first call takes a second connection from the pool and listens on
+    /// it, starting right away: nothing sent between this call and the commit
+    /// is missed. That connection is held until the stream `commit_and_listen`
+    /// returns is dropped, so size the pool for two connections per listening
+    /// session. Waiting for it fails with `sqlx::Error::PoolTimedOut` after the
+    /// pool's acquire timeout, as `begin` does.
+    ///
+    /// The transaction's own NOTIFYs are delivered when it commits. Sessions
+    /// not begun by a `PostgresUnitOfWork` have no pool to listen with.
+    pub async fn listen(&self, channel: &str) -> TransactionResult<()> {
+        let pool = self.pool.as_ref().ok_or_else(|| {
+            TransactionError::DatabaseError(sqlx::Error::Configuration(
+                format!("session {} has no pool to listen on", self.id).into(),
+            ))
+        })?;
+        let mut notifications = self.notifications.lock().await;
+        let listener = match &mut *notifications {
+            Some(listener) => listener,
+            None => {
+                let acquire_timeout = pool.options().get_acquire_timeout();
+                let listener = tokio::time::timeout(acquire_timeout, PgListener::connect_with(pool))
+                    .await
+                    .unwrap_or(Err(sqlx::Error::PoolTimedOut))
+                    .map_err(|error| self.executor.classify_error(error))?;
+                notifications.insert(listener)
+            }
+        };
+        listener
+            .listen(channel)
+            .await
+            .map_err(|error| self.executor.classify_error(error))
+    }
+    
+    /// Like `commit`, but also reports what the session did, e.g. for logging
+    /// "committed 14 statements affecting 230 rows in 12ms".
+    pub async fn commit_with_report(self) -> TransactionResult<CommitReport> {
+        let span = self.span.clone();
+        let commit = async {
+            let result = self.commit_and_notify().await;
+            #[cfg(feature = "tracing")]
+            self.record_completion();
+            self.report_to_circuit_breaker(&result).await;
+            let reason = result.as_ref().err().map_or(RollbackReason::CommitFailed, RollbackReason::of_commit_error);
+            self.report_to_metrics(reason);
+            self.report_to_interceptors().await;
+            self.report_to_listeners(&result).await;
+            result
+        };
+        #[cfg(feature = "tracing")]
+        let commit = commit.instrument(completion_span!(self, "uow.commit", "COMMIT"));
+        commit.instrument(span).await
+    }
+    
+    /// Commits the transaction and returns the notifications received on the
+    /// channels passed to `listen`, starting with those sent before the commit.
+    ///
+    /// The stream is empty if `listen` was never called. If the commit fails,
+    /// the channels are unlistened and the commit error is returned.
+    pub async fn commit_and_listen(self) -> TransactionResult<NotificationStream> {
+        let listener = self.notifications.lock().await.take();
+        self.commit().await?;
+        Ok(NotificationStream::new(listener))
+    }
+    
+    /// Label the session, e.g. with the name of the business operation, for
+    /// observers to report through their `TransactionContext`.
+    pub fn set_label(&self, label: impl Into<String>) {
+        *self.label.lock() = Some(label.into());
+    }
+    
+    /// Attach `value` under `key`, e.g. the id of the user acting, replacing
+    /// any previous value.
+    ///
+    /// Unlike `extensions`, metadata is plain text meant to be logged: it is
+    /// passed to observers in the `TransactionContext`, included in the
+    /// `CommitReport` and recorded on the session's tracing span and in the
+    /// warning about a leaked session.
+    pub fn set_metadata(&self, key: impl Into<String>, value: impl Into<String>) {
+        let mut metadata = self.metadata.lock();
+        metadata.insert(key.into(), value.into());
+        self.span.record("metadata", tracing::field::debug(&*metadata));
+    }
+    
+    /// A snapshot of the metadata attached with `set_metadata`.
+    pub fn metadata(&self) -> HashMap<String, String> {
+        self.metadata.lock().clone().into_iter().collect()
+    }
+    
+    /// Values stashed on the session by type, e.g. by middleware, for
+    /// observers to read through their `TransactionContext`.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+    
+    /// Run `hook` after the transaction commits successfully.
+    ///
+    /// Hooks run once, in registration order together with the other
+    /// observers, and are dropped without running if the session rolls back.
+    /// An error returned by the hook is reported like an observer failure.
+    pub fn on_commit<F, Fut>(&self, hook: F) -> TransactionResult<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.push_hook(HookTrigger::Commit, None, move |_| hook())
+    }
+    
+    /// Like `on_commit`, naming the hook `label` in errors and diagnostics.
+    pub fn on_commit_named<F, Fut>(&self, label: impl Into<String>, hook: F) -> TransactionResult<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.push_hook(HookTrigger::Commit, Some(label.into()), move |_| hook())
+    }
+    
+    /// Run `hook` after the transaction rolls back, e.g. to compensate
+    /// in-memory changes.
+    ///
+    /// Hooks run once and are dropped without running if the session commits.
+    pub fn on_rollback<F, Fut>(&self, hook: F) -> TransactionResult<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.push_hook(HookTrigger::Rollback, None, move |_| hook())
+    }
+    
+    /// Like `on_rollback`, naming the hook `label` in errors and diagnostics.
+    pub fn on_rollback_named<F, Fut>(&self, label: impl Into<String>, hook: F) -> TransactionResult<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.push_hook(HookTrigger::Rollback, Some(label.into()), move |_| hook())
+    }
+    
+    /// Run `hook` once the transaction has ended, whatever the outcome.
+    ///
+    /// This is the place to release resources held for the duration of the
+    /// transaction, such as distributed locks.
+    pub fn on_complete<F, Fut>(&self, hook: F) -> TransactionResult<()>
+    where
+        F: FnOnce(TransactionOutcome) -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.push_hook(HookTrigger::Complete, None, hook)
+    }
+    
+    /// Like `on_complete`, naming the hook `label` in errors and diagnostics.
+    pub fn on_complete_named<F, Fut>(&self, label: impl Into<String>, hook: F) -> TransactionResult<()>
+    where
+        F: FnOnce(TransactionOutcome) -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.push_hook(HookTrigger::Complete, Some(label.into()), hook)
+    }
+    
+    /// Register a compensating action undoing a side effect outside of the
+    /// database, e.g. reserving inventory in another service, should the
+    /// transaction roll back.
+    ///
+    /// After the rollback, the session's compensations run one after another,
+    /// most recently registered first, as the observer named "compensations";
+    /// a failing, panicking or timed out one does not stop the rest, and
+    /// failures are reported as `ObserverErrors` naming each action. Each
+    /// action is given the observer timeout of its own. They are dropped unrun if the
+    /// session commits.
+    pub fn register_compensation<F, Fut>(&self, name: impl Into<String>, action: F) -> TransactionResult<()>
+    where
+        F: FnOnce() -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.ensure_active()?;
+        if self.compensations.push(name.into(), self.observer_timeout, action) {
+            // Each action is given the observer timeout on its own, rather
+            // than all of them together
+            let compensations: Arc<dyn TransactionAware> = self.compensations.clone();
+            self.observers.write().push(ObserverRef::Strong(compensations), 0, Some(Duration::MAX));
+        }
+        Ok(())
+    }
+    
+    /// Stage a domain event for delivery to the unit of work's handlers of
+    /// type `E` once the transaction commits.
+    ///
+    /// Staged events are discarded if the session rolls back.
+    pub fn stage_event<E: Send + Sync + 'static>(&self, event: E) {
+        self.events.lock().push(Arc::new(event));
+    }
+    
+    /// Register a component whose callbacks may each take at most `timeout`,
+    /// overriding the unit of work's default observer timeout.
+    pub async fn register_transaction_aware_with_timeout(
+        &self,
+        observer: Arc<dyn TransactionAware>,
+        timeout: Duration,
+    ) -> TransactionResult<ObserverHandle> {
+        self.register(ObserverRef::Strong(observer), 0, Some(timeout)).await
+    }
+    
+    /// Notify observers of the commit or rollback concurrently instead of one
+    /// after another.
+    ///
+    /// Useful when observers do independent I/O, e.g. publishing events. The
+    /// priority and registration order guarantees do not apply in this mode:
+    /// callbacks may start, interleave and finish in any order. Errors are
+    /// still collected from every observer. `after_begin` and `before_commit`
+    /// always run sequentially.
+    pub fn notify_observers_concurrently(&self, concurrent: bool) {
+        self.concurrent_notification.store(concurrent, Ordering::Relaxed);
+    }
+    
+    fn push_hook<F, Fut>(&self, trigger: HookTrigger, label: Option<String>, hook: F) -> TransactionResult<()>
+    where
+        F: FnOnce(TransactionOutcome) -> Fut + Send + 'static,
+        Fut: Future<Output = TransactionResult<()>> + Send + 'static,
+    {
+        self.ensure_active()?;
+        let hook: Arc<dyn TransactionAware> = Arc::new(ClosureHook::new(trigger, label, hook));
+        self.observers.write().push(ObserverRef::Strong(hook), 0, None);
+        Ok(())
+    }
+    
+    /// Add an observer delivering this session's events to `handler`.
+    fn subscribe(&self, handler: &EventHandler) {
+        let observer = handler.observer(self.events.clone());
+        self.observers.write().push(ObserverRef::Strong(observer), 0, None);
+    }
+    
+    /// Reject registrations once the transaction has completed, since the
+    /// observer would never be notified.
+    fn ensure_active(&self) -> TransactionResult<()> {
+        if self.executor.is_completed() {
+            return Err(TransactionError::TransactionAlreadyCompleted);
+        }
+        Ok(())
+    }
+    
+    /// Run the observer's `after_begin` hook and add it to the registry.
+    async fn register(
+        &self,
+        observer: ObserverRef,
+        priority: i32,
+        timeout: Option<Duration>,
+    ) -> TransactionResult<ObserverHandle> {
+        self.ensure_active()?;
+        if let Some(live) = observer.upgrade() {
+            let after_begin = live.after_begin(&self.executor);
+            call_observer(live.name(), timeout.or(self.observer_timeout), after_begin).await?;
+        }
+        let id = self.observers.write().push(observer, priority, timeout);
+        Ok(ObserverHandle::new(&self.observers, id))
+    }
+    
+    /// Roll back the transaction and notify observers of the rollback.
+    async fn rollback_and_notify(&self) -> TransactionResult<()> {
+        // Observer errors are not reported over the rollback error
+        let (rollback_result, observer_result) = self.rollback_notifying_observers().await;
+        rollback_result?;
+        observer_result
+    }
+    
+    /// Roll back the transaction and notify observers of the outcome,
+    /// returning the result of the rollback and that of notifying observers
+    /// under the observer error policy.
+    async fn rollback_notifying_observers(&self) -> (TransactionResult<()>, TransactionResult<()>) {
         // Take ownership of the transaction
-        let tx = self.executor.take_transaction().await?;
+        let tx = match self.executor.take_transaction(SessionState::RollingBack).await {
+            Ok(tx) => tx,
+            Err(error) => return (Err(error), Ok(())),
+        };
         
-        // Commit the transaction
-        tx.commit().await?;
+        // Rollback the transaction, telling observers if that failed
+        let observers = self.observers.read().rollback_order();
+        let started = Instant::now();
+        let rollback_result = tx.rollback().await;
+        self.report_if_slow_completion("ROLLBACK", started.elapsed());
+        if let Err(error) = rollback_result {
+            self.executor.set_state(SessionState::Poisoned);
+            self.handles.complete();
+            let context = self.finish(TransactionOutcome::Failed);
+            let error = self.executor.classify_error(error);
+            let notification = Notification::RollbackFailure(&error);
+            let notify_result = self.notify_observers(&observers, &context, notification).await;
+            let observer_result = self.apply_observer_error_policy(notify_result).map(drop);
+            return (Err(error), observer_result);
+        }
+        
+        self.executor.set_state(SessionState::RolledBack);
+        self.handles.complete();
+        let context = self.finish(TransactionOutcome::RolledBack);
+        
+        // Notify observers after successful rollback
+        let notify_result = self.notify_observers(&observers, &context, Notification::Rollback).await;
+        (Ok(()), self.apply_observer_error_policy(notify_result).map(drop))
+    }
+    
+    /// Commit the transaction, or roll it back if a statement was cancelled,
+    /// an observer vetoes or COMMIT fails, and notify observers of the outcome.
+    async fn commit_and_notify(&self) -> TransactionResult<CommitReport> {
+        // Postgres answers COMMIT of a transaction whose statement was
+        // cancelled with a rollback, so make that explicit
+        if self.executor.session_state() == SessionState::Poisoned {
+            // The cancellation is what the caller needs to hear about
+            let (rollback_result, observer_result) = self.rollback_notifying_observers().await;
+            if let Err(error) = rollback_result {
+                tracing::warn!(session_id = %self.id, error = %error, "Failed to roll back the cancelled transaction");
+            }
+            if let Err(error) = observer_result {
+                tracing::warn!(session_id = %self.id, error = %error, "Transaction observers failed after cancellation");
+            }
+            return Err(TransactionError::Cancelled);
+        }
+        
+        // Refuse to commit before the hooks below get to write anything
+        self.executor.check_completable()?;
+        
+        // Give interceptors and observers a chance to write or veto while the
+        // transaction is open
+        let previous_state = self.executor.session_state();
+        self.executor.set_state(SessionState::Committing);
+        let observers = self.observers.read().commit_order();
+        if let Err(veto) = self.run_before_commit(&observers).await {
+            let (rollback_result, observer_result) = self.rollback_notifying_observers().await;
+            return Err(TransactionError::CommitVetoed {
+                source: Box::new(veto),
+                rollback_error: rollback_result.err().map(Box::new),
+                observer_error: observer_result.err().map(Box::new),
+            });
+        }
+        
+        // Take ownership of the transaction
+        let mut tx = match self.executor.take_transaction(SessionState::Committing).await {
+            Ok(tx) => tx,
+            Err(error) => {
+                // The hooks may have written already, so undo that as a veto would
+                self.executor.set_state(previous_state);
+                let (rollback_result, observer_result) = self.rollback_notifying_observers().await;
+                if let Err(error) = rollback_result {
+                    tracing::warn!(session_id = %self.id, error = %error, "Failed to roll back the uncommittable transaction");
+                }
+                if let Err(error) = observer_result {
+                    tracing::warn!(session_id = %self.id, error = %error, "Transaction observers failed after a refused commit");
+                }
+                return Err(error);
+            }
+        };
+        
+        // Commit through the transaction manager rather than `Transaction::commit`
+        // so the transaction is still ours to roll back explicitly if COMMIT fails
+        let started = Instant::now();
+        let commit_result = PgTransactionManager::commit(&mut tx).await;
+        let commit_duration = started.elapsed();
+        self.report_if_slow_completion("COMMIT", commit_duration);
+        if let Err(commit_error) = commit_result {
+            self.executor.set_state(SessionState::RolledBack);
+            let started = Instant::now();
+            let rollback_result = tx.rollback().await;
+            self.report_if_slow_completion("ROLLBACK", started.elapsed());
+            self.handles.complete();
+            let context = self.finish(TransactionOutcome::RolledBack);
+            
+            // The transaction did not commit either way, so observers are told
+            // it rolled back even if the explicit ROLLBACK failed
+            let observers = self.observers.read().rollback_order();
+            let notify_result = self.notify_observers(&observers, &context, Notification::Rollback).await;
+            let observer_error = self.apply_observer_error_policy(notify_result).err();
+            let rollback_error = rollback_result.err().map(|error| self.executor.classify_error(error));
+            return Err(TransactionError::CommitFailedRolledBack {
+                commit_error: Box::new(self.executor.classify_error(commit_error)),
+                rollback_error: rollback_error.map(Box::new),
+                observer_error: observer_error.map(Box::new),
+            });
+        }
+        // The transaction manager closed the transaction, so dropping it is a no-op
+        drop(tx);
+        self.executor.set_state(SessionState::Committed);
+        self.handles.complete();
+        let context = self.finish(TransactionOutcome::Committed);
         
         // Notify observers after successful commit
-        let observers = self.observers.read().clone();
-        for observer in observers.iter() {
-            observer.on_commit().await?;
+        let observers = self.observers.read().commit_order();
+        let notify_result = self.notify_observers(&observers, &context, Notification::Commit).await;
+        let observer_errors = self.apply_observer_error_policy(notify_result)?;
+        Ok(CommitReport {
+            session_id: context.session_id,
+            label: context.label,
+            metadata: context.metadata,
+            duration: context.duration,
+            commit_duration,
+            stats: context.stats,
+            observers: observers
+                .iter()
+                .map(|registered| registered.observer.name().to_string())
+                .collect(),
+            observer_errors,
+        })
+    }
+    
+    /// Handle observer failures from a completion notification according to
+    /// the unit of work's policy, returning the failures it logged and ignored.
+    fn apply_observer_error_policy(&self, result: TransactionResult<()>) -> TransactionResult<Vec<TransactionError>> {
+        let Err(error) = result else {
+            return Ok(Vec::new());
+        };
+        match &self.observer_error_policy {
+            ObserverErrorPolicy::Propagate => Err(error),
+            ObserverErrorPolicy::LogAndIgnore => {
+                tracing::warn!(session_id = %self.id, error = %error, "Ignoring transaction observer failures");
+                match error {
+                    TransactionError::ObserverErrors(errors) => Ok(errors),
+                    error => Ok(vec![error]),
+                }
+            }
+            ObserverErrorPolicy::Callback(handler) => {
+                handler(&error);
+                Ok(Vec::new())
+            }
+        }
+    }
+    
+    /// Record how the transaction ended, building the context observers see.
+    fn finish(&self, outcome: TransactionOutcome) -> TransactionContext {
+        self.stop_watchdog();
+        let context = TransactionContext {
+            session_id: self.id,
+            started_at: self.started_at,
+            completed_at: SystemTime::now(),
+            duration: self.started.elapsed(),
+            outcome,
+            label: self.label.lock().clone(),
+            metadata: self.metadata(),
+            backend_pid: self.backend_pid.get().copied(),
+            extensions: self.extensions.clone(),
+            stats: self.executor.stats(),
+            #[cfg(feature = "otel")]
+            trace_id: crate::otel::trace_id(&self.span),
+            #[cfg(not(feature = "otel"))]
+            trace_id: None,
+        };
+        tracing::debug!(
+            session_id = %self.id,
+            outcome = ?context.outcome,
+            elapsed = ?context.duration,
+            "Transaction completed"
+        );
+        *self.completion.lock() = Some(context.clone());
+        context
+    }
+    
+    /// Count the commit's outcome with the circuit breaker, telling the
+    /// listeners if that changes its state.
+    ///
+    /// A commit that went through counts as a success even if observers
+    /// failed afterwards.
+    async fn report_to_circuit_breaker<T>(&self, result: &TransactionResult<T>) {
+        let Some(breaker) = &self.circuit_breaker else {
+            return;
+        };
+        let committed = matches!(&*self.completion.lock(), Some(context) if context.outcome == TransactionOutcome::Committed);
+        let transition = if committed { breaker.record(&Ok(())) } else { breaker.record(result) };
+        if let Some((from, to)) = transition {
+            for listener in &self.listeners {
+                listener.on_circuit_state_change(from, to).await;
+            }
+        }
+    }
+    
+    /// Tell the unit of work's metrics how the session ended; `reason` is why
+    /// it rolled back, if it did not commit.
+    fn report_to_metrics(&self, reason: RollbackReason) {
+        let Some(context) = self.completion.lock().clone() else {
+            return;
+        };
+        report_to_metrics(&*self.metrics, &context, reason);
+    }
+    
+    /// Run the interceptors' `after_commit` or `after_rollback` hooks for how
+    /// the session ended; a failed ROLLBACK still ends it.
+    async fn report_to_interceptors(&self) {
+        let Some(outcome) = self.completion.lock().as_ref().map(|context| context.outcome) else {
+            return;
+        };
+        let info = self.info();
+        for interceptor in self.interceptors.iter().rev() {
+            match outcome {
+                TransactionOutcome::Committed => interceptor.after_commit(&info).await,
+                TransactionOutcome::RolledBack | TransactionOutcome::Failed => interceptor.after_rollback(&info).await,
+            }
+        }
+    }
+    
+    /// Record how the session ended on the current `uow.commit` or
+    /// `uow.rollback` span.
+    #[cfg(feature = "tracing")]
+    fn record_completion(&self) {
+        if let Some(context) = &*self.completion.lock() {
+            record_completion(&tracing::Span::current(), context);
+        }
+    }
+    
+    /// Tell the unit of work's listeners how the session ended.
+    async fn report_to_listeners<T>(&self, result: &TransactionResult<T>) {
+        let completion = self.completion.lock().clone();
+        for listener in &self.listeners {
+            match &completion {
+                Some(context) if context.outcome == TransactionOutcome::Committed => {
+                    listener.on_commit(self.id, context.duration).await
+                }
+                Some(context) if context.outcome == TransactionOutcome::RolledBack => {
+                    listener.on_rollback(self.id, context.duration).await
+                }
+                _ => {}
+            }
+            if let Err(error) = result {
+                listener.on_error(self.id, error).await;
+            }
+        }
+    }
+    
+    /// Notify every observer of the given event; see `notify_observers`.
+    async fn notify_observers(
+        &self,
+        observers: &[Registered],
+        context: &TransactionContext,
+        notification: Notification<'_>,
+    ) -> TransactionResult<()> {
+        notify_observers(observers, context, notification, self.concurrent(), self.observer_timeout).await
+    }
+    
+    /// Run every interceptor's and then every observer's `before_commit` hook
+    /// in registration order, stopping at the first one that vetoes the commit.
+    async fn run_before_commit(&self, observers: &[Registered]) -> TransactionResult<()> {
+        if !self.interceptors.is_empty() {
+            let info = self.info();
+            for interceptor in &self.interceptors {
+                interceptor.before_commit(&info).await?;
+            }
+        }
+        for registered in observers {
+            let observer = &registered.observer;
+            let timeout = registered.timeout.or(self.observer_timeout);
+            call_observer(observer.name(), timeout, observer.before_commit(&self.executor)).await?;
         }
         Ok(())
     }
     
-    async fn rollback(self) -> TransactionResult<()> {
-        // Take ownership of the transaction
-        let tx = self.executor.take_transaction().await?;
+    /// Whether observers are notified concurrently.
+    fn concurrent(&self) -> bool {
+        self.concurrent_notification.load(Ordering::Relaxed)
+    }
+    
+    /// Number of registered observers that are not idempotent.
+    fn non_idempotent_observers(&self) -> usize {
+        self.observers.read().non_idempotent()
+    }
+    
+    /// Warn about the session if its transaction was open for longer than
+    /// the slow transaction threshold.
+    fn report_if_slow(&self) {
+        let Some(threshold) = self.slow_transaction_threshold else {
+            return;
+        };
+        let completion = self.completion.lock().clone();
+        let elapsed = completion.as_ref().map_or_else(|| self.started.elapsed(), |context| context.duration);
+        if elapsed <= threshold {
+            return;
+        }
         
-        // Rollback the transaction
-        tx.rollback().await?;
+        let report = SlowTransaction {
+            session_id: self.id,
+            label: self.label.lock().clone(),
+            metadata: self.metadata(),
+            backend_pid: self.backend_pid.get().copied(),
+            statements: self.executor.stats().statements,
+            elapsed,
+            outcome: completion.map(|context| context.outcome),
+        };
+        tracing::warn!(
+            session_id = %report.session_id,
+            label = ?report.label,
+            backend_pid = report.backend_pid,
+            statements = report.statements,
+            elapsed = ?report.elapsed,
+            outcome = ?report.outcome,
+            "Slow transaction"
+        );
+        if let Some(callback) = &self.on_slow_transaction {
+            guard_panic("Slow transaction callback", || callback(&report));
+        }
+    }
+    
+    /// Warn about the session, and tell the metrics, if its COMMIT or
+    /// ROLLBACK `statement` took longer than the slow commit threshold.
+    fn report_if_slow_completion(&self, statement: &'static str, round_trip: Duration) {
+        if self.slow_commit_threshold.is_none_or(|threshold| round_trip <= threshold) {
+            return;
+        }
+        tracing::warn!(
+            session_id = %self.id,
+            label = ?self.label.lock(),
+            statement,
+            elapsed = ?round_trip,
+            "Slow {}",
+            statement.to_lowercase()
+        );
+        let metrics = &self.metrics;
+        guard_panic("Metrics", || match statement {
+            "COMMIT" => metrics.record_slow_commit(round_trip),
+            _ => metrics.record_slow_rollback(round_trip),
+        });
+    }
+    
+    /// Spawn the timer task warning about the session while it stays open
+    /// longer than the watchdog's threshold.
+    fn start_watchdog(&mut self, watchdog: &Watchdog) {
+        let Some(threshold) = watchdog.threshold else {
+            return;
+        };
+        let watchdog = watchdog.clone();
+        let executor = self.executor.clone();
+        let (label, metadata, backend_pid) = (self.label.clone(), self.metadata.clone(), self.backend_pid.clone());
+        let (id, started) = (self.id, self.started);
+        let span = self.span.clone();
+        let task = tokio::spawn(
+            async move {
+                tokio::time::sleep(threshold).await;
+                // Completing the session stops the task, but it may already be awake
+                while !executor.is_completed() {
+                    let report = SlowTransaction {
+                        session_id: id,
+                        label: label.lock().clone(),
+                        metadata: metadata.lock().clone().into_iter().collect(),
+                        backend_pid: backend_pid.get().copied(),
+                        statements: executor.stats().statements,
+                        elapsed: started.elapsed(),
+                        outcome: None,
+                    };
+                    tracing::warn!(
+                        session_id = %report.session_id,
+                        label = ?report.label,
+                        metadata = ?metadata.lock(),
+                        backend_pid = report.backend_pid,
+                        statements = report.statements,
+                        elapsed = ?report.elapsed,
+                        "Long transaction still open"
+                    );
+                    if let Some(callback) = &watchdog.callback {
+                        guard_panic("Long transaction callback", || callback(&report));
+                    }
+                    let Some(interval) = watchdog.interval else {
+                        break;
+                    };
+                    tokio::time::sleep(interval).await;
+                }
+            }
+            .instrument(span),
+        );
+        self.watchdog = Some(task.abort_handle());
+    }
+    
+    /// Stop the watchdog's timer task, if any.
+    fn stop_watchdog(&self) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.abort();
+        }
+    }
+    
+    /// Report a session dropped while its transaction was open, according to
+    /// the unit of work's leak policy.
+    fn report_leak(&self) {
+        let panic = match self.leak_policy {
+            LeakPolicy::Ignore => return,
+            LeakPolicy::Warn => false,
+            LeakPolicy::PanicInDebug => cfg!(debug_assertions),
+        };
+        // Panicking again while unwinding would abort
+        if panic && !std::thread::panicking() {
+            panic!("Session {} dropped without commit or rollback", self.id);
+        }
+        #[cfg(feature = "backtrace")]
+        let backtrace = Some(self.created.to_string());
+        #[cfg(not(feature = "backtrace"))]
+        let backtrace: Option<String> = None;
+        tracing::warn!(
+            session_id = %self.id,
+            label = ?self.label.lock(),
+            metadata = ?self.metadata.lock(),
+            elapsed = ?self.started.elapsed(),
+            backtrace,
+            "Session dropped without commit or rollback"
+        );
+    }
+    
+    /// Roll back the transaction of a session dropped without committing or
+    /// rolling back, and tell observers and listeners, from a task spawned on
+    /// the current Tokio runtime.
+    ///
+    /// Outside of a runtime the transaction is only dropped, which has sqlx
+    /// roll it back on the connection's next use, and nobody is told.
+    fn rollback_if_open(&mut self) {
+        let Ok(mut tx) = self.executor.tx.try_lock() else {
+            self.report_leak();
+            tracing::warn!(
+                session_id = %self.id,
+                "Session dropped while its transaction is in use; observers are not told of the rollback"
+            );
+            self.handles.complete();
+            return;
+        };
+        // Already completed, or taken out of the Executor directly
+        let Some(tx) = tx.take() else {
+            self.handles.complete();
+            return;
+        };
+        self.report_leak();
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            self.executor.set_state(SessionState::RolledBack);
+            self.handles.complete();
+            let metrics = &self.metrics;
+            guard_panic("Metrics", || metrics.record_rollback(self.started.elapsed(), RollbackReason::Dropped));
+            tracing::warn!(
+                session_id = %self.id,
+                "Session dropped outside of a Tokio runtime; observers are not told of the rollback"
+            );
+            return;
+        };
         
-        // Notify observers after successful rollback
-        let observers = self.observers.read().clone();
-        for observer in observers.iter() {
-            observer.on_rollback().await?;
+        self.executor.set_state(SessionState::RollingBack);
+        let context = self.finish(TransactionOutcome::RolledBack);
+        let observers = self.observers.read().rollback_order();
+        let listeners = self.listeners.clone();
+        let interceptors = self.interceptors.clone();
+        let metrics = self.metrics.clone();
+        let handle = self.handle();
+        let executor = self.executor.clone();
+        let handles = self.handles.clone();
+        let (id, concurrent, observer_timeout) = (self.id, self.concurrent(), self.observer_timeout);
+        let span = self.span.clone();
+        let rollback = async move {
+            let (rolled_back, result) = match tx.rollback().await {
+                Ok(()) => {
+                    executor.set_state(SessionState::RolledBack);
+                    handles.complete();
+                    #[cfg(feature = "tracing")]
+                    record_completion(&tracing::Span::current(), &context);
+                    let notification = Notification::Rollback;
+                    (true, notify_observers(&observers, &context, notification, concurrent, observer_timeout).await)
+                }
+                Err(error) => {
+                    executor.set_state(SessionState::Poisoned);
+                    handles.complete();
+                    let error = executor.classify_error(error);
+                    let context = TransactionContext {
+                        outcome: TransactionOutcome::Failed,
+                        ..context.clone()
+                    };
+                    #[cfg(feature = "tracing")]
+                    record_completion(&tracing::Span::current(), &context);
+                    let notification = Notification::RollbackFailure(&error);
+                    let _ = notify_observers(&observers, &context, notification, concurrent, observer_timeout).await;
+                    (false, Err(error))
+                }
+            };
+            report_to_metrics(&*metrics, &context, RollbackReason::Dropped);
+            let info = SessionInfo::from(&handle);
+            for interceptor in interceptors.iter().rev() {
+                interceptor.after_rollback(&info).await;
+            }
+            for listener in &listeners {
+                if rolled_back {
+                    listener.on_rollback(id, context.duration).await;
+                }
+                if let Err(error) = &result {
+                    listener.on_error(id, error).await;
+                }
+            }
+            if let Err(error) = result {
+                tracing::warn!(session_id = %id, error = %error, "Rolling back a dropped session failed");
+            }
+        };
+        #[cfg(feature = "tracing")]
+        let rollback = rollback.instrument(completion_span!(self, "uow.rollback", "ROLLBACK"));
+        runtime.spawn(rollback.instrument(span));
+    }
+}
+
+impl Drop for PostgresUnitOfWorkSession {
+    fn drop(&mut self) {
+        self.stop_watchdog();
+        self.report_if_slow();
+        self.rollback_if_open();
+        if let Some(registry) = &self.registry {
+            registry.lock().remove(&self.id);
+        }
+    }
+}
+
+#[async_trait]
+impl UnitOfWorkSession for PostgresUnitOfWorkSession {
+    fn executor(&self) -> &Executor {
+        &self.executor
+    }
+    
+    async fn register_transaction_aware_with_priority(
+        &self,
+        observer: Arc<dyn TransactionAware>,
+        priority: i32,
+    ) -> TransactionResult<ObserverHandle> {
+        self.register(ObserverRef::Strong(observer), priority, None).await
+    }
+    
+    async fn register_transaction_aware_weak(
+        &self,
+        observer: Weak<dyn TransactionAware>,
+    ) -> TransactionResult<ObserverHandle> {
+        self.register(ObserverRef::Weak(observer), 0, None).await
+    }
+    
+    async fn commit(self) -> TransactionResult<()> {
+        self.commit_with_report().await.map(drop)
+    }
+    
+    async fn rollback(self) -> TransactionResult<()> {
+        let span = self.span.clone();
+        let rollback = async {
+            let result = self.rollback_and_notify().await;
+            #[cfg(feature = "tracing")]
+            self.record_completion();
+            self.report_to_metrics(RollbackReason::Requested);
+            self.report_to_interceptors().await;
+            self.report_to_listeners(&result).await;
+            result
+        };
+        #[cfg(feature = "tracing")]
+        let rollback = rollback.instrument(completion_span!(self, "uow.rollback", "ROLLBACK"));
+        rollback.instrument(span).await
+    }
+}
+
+/// Transaction lifecycle event delivered to observers.
+#[derive(Clone, Copy, Debug)]
+enum Notification<'a> {
+    Commit,
+    Rollback,
+    RollbackFailure(&'a TransactionError),
+}
+
+#[cfg(feature = "tracing")]
+impl Notification<'_> {
+    /// Name of the event, as recorded on observer notification events.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Notification::Commit => "commit",
+            Notification::Rollback => "rollback",
+            Notification::RollbackFailure(_) => "rollback_failure",
         }
+    }
+}
+
+/// Notify every observer of the given event.
+///
+/// A failing or panicking observer does not prevent the remaining observers
+/// from being notified; all failures are collected into
+/// `TransactionError::ObserverErrors`, wrapped in `ObserverFailed` with the
+/// observer's name. When notifying concurrently the callbacks are polled
+/// together and complete in no particular order.
+async fn notify_observers(
+    observers: &[Registered],
+    context: &TransactionContext,
+    notification: Notification<'_>,
+    concurrent: bool,
+    observer_timeout: Option<Duration>,
+) -> TransactionResult<()> {
+    let callbacks = observers.iter().map(|registered| async move {
+        let observer = &registered.observer;
+        let callback = async {
+            match notification {
+                Notification::Commit => observer.on_commit_with(context).await,
+                Notification::Rollback => observer.on_rollback_with(context).await,
+                Notification::RollbackFailure(error) => observer.on_rollback_failure_with(context, error).await,
+            }
+        };
+        let timeout = registered.timeout.or(observer_timeout);
+        let result = call_observer(observer.name(), timeout, callback).await.map_err(|error| match error {
+            TransactionError::ObserverPanicked { .. } | TransactionError::ObserverTimeout { .. } => error,
+            error => TransactionError::ObserverFailed {
+                observer: observer.name().to_string(),
+                source: Box::new(error),
+            },
+        });
+        #[cfg(feature = "tracing")]
+        match &result {
+            Ok(()) => tracing::debug!(
+                session_id = %context.session_id,
+                observer = observer.name(),
+                notification = notification.as_str(),
+                "Notified transaction observer"
+            ),
+            Err(error) => tracing::debug!(
+                session_id = %context.session_id,
+                observer = observer.name(),
+                notification = notification.as_str(),
+                error = %error,
+                "Transaction observer failed"
+            ),
+        }
+        result
+    });
+
+    let results = if concurrent {
+        join_all(callbacks).await
+    } else {
+        let mut results = Vec::with_capacity(observers.len());
+        for callback in callbacks {
+            results.push(callback.await);
+        }
+        results
+    };
+    let errors: Vec<_> = results.into_iter().filter_map(Result::err).collect();
+
+    if errors.is_empty() {
         Ok(())
+    } else {
+        Err(TransactionError::ObserverErrors(errors))
+    }
+}
+
+/// Await an observer callback, converting a panic into `ObserverPanicked`
+/// and giving up with `ObserverTimeout` once `timeout` has elapsed.
+pub(crate) async fn call_observer(
+    name: &str,
+    timeout: Option<Duration>,
+    callback: impl Future<Output = TransactionResult<()>>,
+) -> TransactionResult<()> {
+    let callback = AssertUnwindSafe(callback).catch_unwind();
+    let result = match timeout {
+        Some(timeout) => {
+            let started = Instant::now();
+            match tokio::time::timeout(timeout, callback).await {
+                Ok(result) => result,
+                Err(_) => {
+                    return Err(TransactionError::ObserverTimeout {
+                        observer: name.to_string(),
+                        elapsed: started.elapsed(),
+                    })
+                }
+            }
+        }
+        None => callback.await,
+    };
+    result.unwrap_or_else(|panic| {
+        Err(TransactionError::ObserverPanicked {
+            observer: name.to_string(),
+            message: panic_message(panic.as_ref()),
+        })
+    })
+}
+
+/// Extract a readable message from a panic payload.
+pub(crate) fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "non-string panic payload".to_string()
     }
 }
\ No newline at end of file
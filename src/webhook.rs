@@ -0,0 +1,175 @@
+use async_trait::async_trait;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{RetryPolicy, TransactionAware, TransactionContext, TransactionError, TransactionResult};
+
+/// Sends the requests of a `WebhookObserver`.
+///
+/// It is implemented for `reqwest::Client`, so the application's client, with
+/// its timeouts and TLS settings, can be passed as is; implement it to send
+/// webhooks through another HTTP client.
+#[async_trait]
+pub trait WebhookClient: Send + Sync {
+    /// POSTs `body` to `url` with `Content-Type: application/json`, returning
+    /// the response status, or why there was no response.
+    async fn post_json(&self, url: &str, body: &str) -> Result<u16, String>;
+}
+
+#[async_trait]
+impl WebhookClient for reqwest::Client {
+    async fn post_json(&self, url: &str, body: &str) -> Result<u16, String> {
+        let response = self
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .map_err(|error| error.to_string())?;
+        Ok(response.status().as_u16())
+    }
+}
+
+/// Builds the JSON body of a webhook from the committed session.
+pub type WebhookPayload = Arc<dyn Fn(&TransactionContext) -> String + Send + Sync>;
+
+/// Called with a webhook that could not be delivered, e.g. to store it for
+/// replaying later.
+pub type WebhookDeadLetter = Arc<dyn Fn(&FailedWebhook) + Send + Sync>;
+
+/// A webhook that was given up on, as passed to the dead-letter callback.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct FailedWebhook {
+    /// The endpoint the webhook was sent to.
+    pub url: String,
+    /// The JSON body built for the session.
+    pub body: String,
+    /// How many times delivery was tried.
+    pub attempts: u32,
+    /// The last response status, or why there was no response.
+    pub reason: String,
+}
+
+/// Observer calling an HTTP endpoint with a JSON payload once the transaction
+/// has committed; it does nothing on rollback.
+///
+/// Responses with a 2xx status count as delivered. Failed requests, 5xx and
+/// 429 responses are retried as configured by `retry_policy`, which defaults
+/// to three attempts; other statuses are not retried. A webhook that cannot be
+/// delivered is passed to the dead-letter callback and reported as a failure
+/// of this observer, `TransactionError::WebhookFailed`, subject to the
+/// session's observer error policy. The observer timeout covers all attempts.
+pub struct WebhookObserver {
+    client: Arc<dyn WebhookClient>,
+    url: String,
+    name: String,
+    payload: WebhookPayload,
+    retry_policy: RetryPolicy,
+    dead_letter: Option<WebhookDeadLetter>,
+}
+
+impl WebhookObserver {
+    /// Create an observer POSTing the body built by `payload` to `url`
+    /// through `client`.
+    pub fn new(
+        client: Arc<dyn WebhookClient>,
+        url: impl Into<String>,
+        payload: impl Fn(&TransactionContext) -> String + Send + Sync + 'static,
+    ) -> Self {
+        let url = url.into();
+        Self {
+            client,
+            name: format!("webhook {url}"),
+            url,
+            payload: Arc::new(payload),
+            retry_policy: RetryPolicy::default(),
+            dead_letter: None,
+        }
+    }
+
+    /// How many times to try delivering the webhook, and how long to wait
+    /// between attempts.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Pass webhooks that could not be delivered to `callback`.
+    pub fn on_dead_letter(mut self, callback: impl Fn(&FailedWebhook) + Send + Sync + 'static) -> Self {
+        self.dead_letter = Some(Arc::new(callback));
+        self
+    }
+
+    /// Sends `body`, retrying transient failures, and returns why it failed
+    /// along with the number of attempts made.
+    async fn deliver(&self, body: &str) -> Result<(), (u32, String)> {
+        let mut attempt = 1;
+        loop {
+            let (reason, retryable) = match self.client.post_json(&self.url, body).await {
+                Ok(status) if (200..300).contains(&status) => return Ok(()),
+                Ok(status) => (format!("status {status}"), status >= 500 || status == 429),
+                Err(reason) => (reason, true),
+            };
+            if !retryable || attempt >= self.retry_policy.max_attempts {
+                return Err((attempt, reason));
+            }
+            tracing::debug!(url = %self.url, attempt, %reason, "Retrying webhook");
+            tokio::time::sleep(self.retry_policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionAware for WebhookObserver {
+    /// The payload is built from the context, so sessions call
+    /// `on_commit_with` instead.
+    async fn on_commit(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_commit_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        let body = (self.payload)(context);
+        let Err((attempts, reason)) = self.deliver(&body).await else {
+            return Ok(());
+        };
+        let failed = FailedWebhook {
+            url: self.url.clone(),
+            body,
+            attempts,
+            reason,
+        };
+        if let Some(dead_letter) = &self.dead_letter {
+            dead_letter(&failed);
+        }
+        Err(TransactionError::WebhookFailed {
+            url: failed.url,
+            attempts: failed.attempts,
+            reason: failed.reason,
+        })
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Only a committed attempt calls the webhook, so retrying is safe.
+    fn is_idempotent(&self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for WebhookObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebhookObserver")
+            .field("url", &self.url)
+            .field("retry_policy", &self.retry_policy)
+            .field("dead_letter", &self.dead_letter.is_some())
+            .finish_non_exhaustive()
+    }
+}
\ No newline at end of file
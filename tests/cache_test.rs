@@ -0,0 +1,212 @@
+mod common;
+
+use async_trait::async_trait;
+use parking_lot::{Mutex, RwLock};
+use postgres_unit_of_work::{
+    Invalidation, InvalidationObserver, Invalidator, PostgresUnitOfWork, SharedCache, TransactionResult, TxCache,
+    UnitOfWork, UnitOfWorkSession,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use common::connect;
+
+type Shared = Arc<RwLock<HashMap<&'static str, String>>>;
+
+#[derive(Default)]
+struct RecordingInvalidator {
+    invalidations: Mutex<Vec<Invalidation>>,
+}
+
+#[async_trait]
+impl Invalidator for RecordingInvalidator {
+    async fn invalidate(&self, invalidation: Invalidation) -> TransactionResult<()> {
+        self.invalidations.lock().push(invalidation);
+        Ok(())
+    }
+}
+
+/// A shared cache holding `alice` and `bob`
+fn shared_cache() -> Shared {
+    let shared: Shared = Arc::default();
+    shared.write().insert("alice", "alice@example.com".to_string());
+    shared.write().insert("bob", "bob@example.com".to_string());
+    shared
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_overlay_masks_the_shared_cache() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let shared = shared_cache();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let cache = TxCache::new(shared.clone());
+    session
+        .register_transaction_aware(cache.clone())
+        .await
+        .expect("Failed to register cache");
+    cache.put("alice", "alice@example.org".to_string());
+    cache.put("carol", "carol@example.com".to_string());
+    cache.remove("bob");
+
+    // The transaction reads its own writes, everyone else the shared values
+    assert_eq!(cache.get(&"alice").await.as_deref(), Some("alice@example.org"));
+    assert_eq!(cache.get(&"carol").await.as_deref(), Some("carol@example.com"));
+    assert_eq!(cache.get(&"bob").await, None);
+    assert_eq!(cache.get(&"dave").await, None);
+    assert_eq!(shared.get(&"alice").await.as_deref(), Some("alice@example.com"));
+    assert_eq!(shared.get(&"bob").await.as_deref(), Some("bob@example.com"));
+    assert_eq!(shared.get(&"carol").await, None);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_commit_promotes_the_overlay() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let shared = shared_cache();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let cache = TxCache::new(shared.clone());
+    session
+        .register_transaction_aware(cache.clone())
+        .await
+        .expect("Failed to register cache");
+    cache.put("alice", "alice@example.org".to_string());
+    cache.put("carol", "carol@example.com".to_string());
+    // A removal tombstones the key, and writing it again revives it
+    cache.remove("bob");
+    cache.remove("carol");
+    cache.put("carol", "carol@example.net".to_string());
+    session.commit().await.expect("Failed to commit transaction");
+
+    let expected = HashMap::from([("alice", "alice@example.org".to_string()), ("carol", "carol@example.net".to_string())]);
+    assert_eq!(*shared.read(), expected);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_discards_the_overlay() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let shared = shared_cache();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let cache = TxCache::new(shared.clone());
+    session
+        .register_transaction_aware(cache.clone())
+        .await
+        .expect("Failed to register cache");
+    cache.put("alice", "alice@example.org".to_string());
+    cache.remove("bob");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert_eq!(*shared.read(), *shared_cache().read());
+    // Nothing is left in the overlay either
+    assert_eq!(cache.get(&"alice").await.as_deref(), Some("alice@example.com"));
+    assert_eq!(cache.get(&"bob").await.as_deref(), Some("bob@example.com"));
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_marked_keys_are_invalidated_once_after_commit() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let invalidator = Arc::new(RecordingInvalidator::default());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let observer = Arc::new(InvalidationObserver::new(invalidator.clone()));
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    observer.mark("user:2");
+    observer.mark("user:1");
+    observer.mark("user:2");
+    assert!(invalidator.invalidations.lock().is_empty(), "Nothing is invalidated before the commit");
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(
+        *invalidator.invalidations.lock(),
+        [Invalidation::Keys(vec!["user:1".to_string(), "user:2".to_string()])]
+    );
+
+    // A transaction that marked nothing invalidates nothing
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let observer = Arc::new(InvalidationObserver::new(invalidator.clone()));
+    session
+        .register_transaction_aware(observer)
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(invalidator.invalidations.lock().len(), 1);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_forgets_marked_keys() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let invalidator = Arc::new(RecordingInvalidator::default());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let observer = Arc::new(InvalidationObserver::new(invalidator.clone()));
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    observer.mark("user:1");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert!(invalidator.invalidations.lock().is_empty());
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_too_many_keys_invalidate_everything() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let invalidator = Arc::new(RecordingInvalidator::default());
+
+    // Up to the limit the keys are collected
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let observer = Arc::new(InvalidationObserver::new(invalidator.clone()).max_keys(3));
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    for key in ["user:1", "user:2", "user:3", "user:1"] {
+        observer.mark(key);
+    }
+    session.commit().await.expect("Failed to commit transaction");
+
+    // ... past it everything is invalidated
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let observer = Arc::new(InvalidationObserver::new(invalidator.clone()).max_keys(3));
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    for index in 0..10 {
+        observer.mark(format!("user:{index}"));
+    }
+    session.commit().await.expect("Failed to commit transaction");
+
+    let keys = ["user:1", "user:2", "user:3"].map(String::from).to_vec();
+    assert_eq!(*invalidator.invalidations.lock(), [Invalidation::Keys(keys), Invalidation::All]);
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,215 @@
+mod common;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    CircuitBreakerConfig, CircuitState, PostgresUnitOfWork, SessionInterceptor, TransactionError, TransactionListener,
+    TransactionOptions, TransactionResult, UnitOfWork, UnitOfWorkSession,
+};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::connect;
+
+const COOL_DOWN: Duration = Duration::from_millis(200);
+
+/// Interceptor failing begins like an unreachable database while `down` is set
+#[derive(Default)]
+struct FlakyDatabase {
+    down: AtomicBool,
+    /// Delay before each begin, in milliseconds
+    delay: AtomicU64,
+    begins: AtomicUsize,
+}
+
+#[async_trait]
+impl SessionInterceptor for FlakyDatabase {
+    async fn before_begin(&self, options: &TransactionOptions) -> TransactionResult<TransactionOptions> {
+        self.begins.fetch_add(1, Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(self.delay.load(Ordering::SeqCst))).await;
+        if self.down.load(Ordering::SeqCst) {
+            let refused = std::io::Error::new(std::io::ErrorKind::ConnectionRefused, "connection refused");
+            return Err(TransactionError::DatabaseError(sqlx::Error::Io(refused)));
+        }
+        Ok(options.clone())
+    }
+}
+
+#[derive(Default)]
+struct TransitionListener {
+    transitions: Mutex<Vec<(CircuitState, CircuitState)>>,
+}
+
+#[async_trait]
+impl TransactionListener for TransitionListener {
+    async fn on_circuit_state_change(&self, from: CircuitState, to: CircuitState) {
+        self.transitions.lock().push((from, to));
+    }
+}
+
+fn guarded_uow(pool: &PgPool) -> (PostgresUnitOfWork, Arc<FlakyDatabase>, Arc<TransitionListener>) {
+    let database = Arc::new(FlakyDatabase::default());
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .interceptor(database.clone())
+        .circuit_breaker(CircuitBreakerConfig::new().failure_threshold(2).cool_down(COOL_DOWN))
+        .build();
+    let listener = Arc::new(TransitionListener::default());
+    uow.add_listener(listener.clone());
+    (uow, database, listener)
+}
+
+/// Begin and commit a session, returning the begin's error
+async fn try_begin(uow: &PostgresUnitOfWork) -> Option<TransactionError> {
+    match uow.begin().await {
+        Ok(session) => {
+            session.commit().await.expect("Failed to commit transaction");
+            None
+        }
+        Err(error) => Some(error),
+    }
+}
+
+fn is_circuit_open(error: &Option<TransactionError>) -> bool {
+    matches!(error, Some(TransactionError::CircuitOpen { .. }))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_breaker_opens_probes_and_recovers() {
+    let pool = connect().await;
+    let (uow, database, listener) = guarded_uow(&pool);
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Closed));
+    assert!(try_begin(&uow).await.is_none());
+
+    // Consecutive connection failures open the breaker
+    database.down.store(true, Ordering::SeqCst);
+    for _ in 0..2 {
+        let error = try_begin(&uow).await.expect("The database is down");
+        assert!(error.is_connection_error(), "Unexpected error {error:?}");
+    }
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Open));
+
+    // ... so begins fail right away without trying the database
+    let begins = database.begins.load(Ordering::SeqCst);
+    match try_begin(&uow).await {
+        Some(TransactionError::CircuitOpen { retry_after }) => {
+            assert!(!retry_after.is_zero() && retry_after <= COOL_DOWN, "Unexpected retry_after {retry_after:?}")
+        }
+        other => panic!("Expected CircuitOpen, got {other:?}"),
+    }
+    assert_eq!(database.begins.load(Ordering::SeqCst), begins);
+
+    // After the cool-down a failing probe opens it again
+    tokio::time::sleep(COOL_DOWN).await;
+    assert!(!is_circuit_open(&try_begin(&uow).await), "The probe should be let through");
+    assert_eq!(database.begins.load(Ordering::SeqCst), begins + 1);
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Open));
+    assert!(is_circuit_open(&try_begin(&uow).await));
+
+    // ... and a successful one closes it
+    database.down.store(false, Ordering::SeqCst);
+    tokio::time::sleep(COOL_DOWN).await;
+    assert!(try_begin(&uow).await.is_none());
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Closed));
+
+    assert_eq!(
+        *listener.transitions.lock(),
+        [
+            (CircuitState::Closed, CircuitState::Open),
+            (CircuitState::Open, CircuitState::HalfOpen),
+            (CircuitState::HalfOpen, CircuitState::Open),
+            (CircuitState::Open, CircuitState::HalfOpen),
+            (CircuitState::HalfOpen, CircuitState::Closed),
+        ]
+    );
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_only_consecutive_connection_failures_count() {
+    let pool = connect().await;
+    let (uow, database, listener) = guarded_uow(&pool);
+
+    database.down.store(true, Ordering::SeqCst);
+    assert!(try_begin(&uow).await.is_some());
+    database.down.store(false, Ordering::SeqCst);
+    assert!(try_begin(&uow).await.is_none());
+    database.down.store(true, Ordering::SeqCst);
+    assert!(try_begin(&uow).await.is_some());
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Closed));
+
+    // Failing statements say nothing about the database being down
+    database.down.store(false, Ordering::SeqCst);
+    for _ in 0..3 {
+        let session = uow.begin().await.expect("Failed to begin transaction");
+        let error = session
+            .executor()
+            .execute(sqlx::query("SELECT * FROM circuit_breaker_missing_table"))
+            .await
+            .expect_err("The table does not exist");
+        assert!(!error.is_connection_error());
+        session.rollback().await.expect("Failed to rollback transaction");
+    }
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Closed));
+    assert!(listener.transitions.lock().is_empty());
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_half_open_breaker_lets_one_probe_through() {
+    let pool = connect().await;
+    let (uow, database, _listener) = guarded_uow(&pool);
+    let uow = Arc::new(uow);
+
+    database.down.store(true, Ordering::SeqCst);
+    for _ in 0..2 {
+        assert!(try_begin(&uow).await.is_some());
+    }
+    database.down.store(false, Ordering::SeqCst);
+    database.delay.store(200, Ordering::SeqCst);
+    tokio::time::sleep(COOL_DOWN).await;
+
+    let probe = tokio::spawn({
+        let uow = uow.clone();
+        async move { try_begin(&uow).await.map(|error| error.to_string()) }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(uow.circuit_state(), Some(CircuitState::HalfOpen));
+    match try_begin(&uow).await {
+        Some(TransactionError::CircuitOpen { retry_after }) => assert_eq!(retry_after, Duration::ZERO),
+        other => panic!("Expected CircuitOpen while probing, got {other:?}"),
+    }
+    assert_eq!(probe.await.expect("The probe panicked"), None);
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Closed));
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_unreachable_database_fails_fast_once_open() {
+    let pool = PgPoolOptions::new()
+        .acquire_timeout(Duration::from_millis(300))
+        .connect_lazy("postgres://postgres@127.0.0.1:1/unreachable")
+        .expect("Failed to configure pool");
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .circuit_breaker(CircuitBreakerConfig::new().failure_threshold(1).cool_down(Duration::from_secs(60)))
+        .build();
+
+    let error = try_begin(&uow).await.expect("The database is unreachable");
+    assert!(error.is_connection_error(), "Unexpected error {error:?}");
+    assert_eq!(uow.circuit_state(), Some(CircuitState::Open));
+
+    let started = Instant::now();
+    assert!(is_circuit_open(&try_begin(&uow).await));
+    assert!(started.elapsed() < Duration::from_millis(50), "An open breaker should fail right away");
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -1,7 +1,7 @@
 use uuid::Uuid;
 
 /// Sample User entity for testing
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
 pub struct User {
     pub id: Uuid,
     pub username: String,
@@ -19,7 +19,7 @@ impl User {
 }
 
 /// Sample Order entity for testing
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, sqlx::FromRow)]
 pub struct Order {
     pub id: Uuid,
     pub user_id: Uuid,
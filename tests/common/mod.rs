@@ -1,5 +1,11 @@
+#![allow(dead_code, unused_imports)]
+
+pub mod database;
 pub mod entities;
+pub mod observers;
 pub mod repositories;
 
+pub use database::{cleanup_database, connect, get_database_url, setup_database};
 pub use entities::{Order, User};
+pub use observers::{CallLog, RecordingObserver};
 pub use repositories::{OrderRepository, UserRepository};
\ No newline at end of file
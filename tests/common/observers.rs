@@ -0,0 +1,89 @@
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+use postgres_unit_of_work::{TransactionAware, TransactionError, TransactionResult};
+
+/// Shared log of observer callbacks, in invocation order
+pub type CallLog = Arc<Mutex<Vec<String>>>;
+
+/// What a RecordingObserver does after recording a callback
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Behaviour {
+    Succeed,
+    Fail,
+    Panic,
+}
+
+/// Transaction-aware observer that records its callbacks into a shared log
+pub struct RecordingObserver {
+    name: String,
+    log: CallLog,
+    behaviour: Behaviour,
+    idempotent: bool,
+}
+
+impl RecordingObserver {
+    pub fn new(name: &str, log: CallLog) -> Arc<Self> {
+        Self::with_behaviour(name, log, Behaviour::Succeed)
+    }
+
+    /// Create an observer whose callbacks record the call and then fail
+    pub fn failing(name: &str, log: CallLog) -> Arc<Self> {
+        Self::with_behaviour(name, log, Behaviour::Fail)
+    }
+
+    /// Create an observer whose callbacks record the call and then panic
+    pub fn panicking(name: &str, log: CallLog) -> Arc<Self> {
+        Self::with_behaviour(name, log, Behaviour::Panic)
+    }
+
+    /// Create an observer that declares itself safe to notify again on retry
+    pub fn idempotent(name: &str, log: CallLog) -> Arc<Self> {
+        Arc::new(Self {
+            idempotent: true,
+            ..Self::build(name, log, Behaviour::Succeed)
+        })
+    }
+
+    fn with_behaviour(name: &str, log: CallLog, behaviour: Behaviour) -> Arc<Self> {
+        Arc::new(Self::build(name, log, behaviour))
+    }
+
+    fn build(name: &str, log: CallLog, behaviour: Behaviour) -> Self {
+        Self {
+            name: name.to_string(),
+            log,
+            behaviour,
+            idempotent: false,
+        }
+    }
+
+    fn record(&self, event: &str) -> TransactionResult<()> {
+        self.log.lock().push(format!("{}:{}", self.name, event));
+        match self.behaviour {
+            Behaviour::Succeed => Ok(()),
+            Behaviour::Fail => Err(TransactionError::CommitFailed(format!("{} failed on {}", self.name, event))),
+            Behaviour::Panic => panic!("{} panicked on {}", self.name, event),
+        }
+    }
+}
+
+#[async_trait]
+impl TransactionAware for RecordingObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.record("commit")
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.record("rollback")
+    }
+
+    async fn on_rollback_failure(&self, _error: &TransactionError) -> TransactionResult<()> {
+        self.record("rollback_failure")
+    }
+
+    fn is_idempotent(&self) -> bool {
+        self.idempotent
+    }
+}
@@ -26,39 +26,22 @@ impl UserRepository {
     }
 
     pub async fn create(&self, user: &User) -> TransactionResult<()> {
-        let mut tx_guard = self.executor.tx.lock().await;
-        let tx = tx_guard.as_mut().ok_or(sqlx::Error::PoolClosed)?;
-        sqlx::query(
-            "INSERT INTO users (id, username, email) VALUES ($1, $2, $3)"
-        )
-        .bind(user.id)
-        .bind(&user.username)
-        .bind(&user.email)
-        .execute(&mut **tx)
-        .await?;
+        let query = sqlx::query("INSERT INTO users (id, username, email) VALUES ($1, $2, $3)")
+            .bind(user.id)
+            .bind(&user.username)
+            .bind(&user.email);
+        self.executor.execute(query).await?;
         Ok(())
     }
 
     pub async fn find_by_id(&self, id: Uuid) -> TransactionResult<Option<User>> {
-        let mut tx_guard = self.executor.tx.lock().await;
-        let tx = tx_guard.as_mut().ok_or(sqlx::Error::PoolClosed)?;
-        let row = sqlx::query(
-            "SELECT id, username, email FROM users WHERE id = $1"
-        )
-        .bind(id)
-        .fetch_optional(&mut **tx)
-        .await?;
-
-        Ok(row.map(|r| User {
-            id: r.get("id"),
-            username: r.get("username"),
-            email: r.get("email"),
-        }))
+        let query = sqlx::query_as("SELECT id, username, email FROM users WHERE id = $1").bind(id);
+        self.executor.fetch_optional_as::<User>(query).await
     }
 
     pub async fn count(&self) -> TransactionResult<i64> {
         let mut tx_guard = self.executor.tx.lock().await;
-        let tx = tx_guard.as_mut().ok_or(sqlx::Error::PoolClosed)?;
+        let tx = tx_guard.as_mut().ok_or_else(|| self.executor.completed_error())?;
         let row = sqlx::query("SELECT COUNT(*) as count FROM users")
             .fetch_one(&mut **tx)
             .await?;
@@ -104,30 +87,40 @@ impl OrderRepository {
         })
     }
 
-    pub async fn create(&self, order: &Order) -> TransactionResult<()> {
-        let mut tx_guard = self.executor.tx.lock().await;
-        let tx = tx_guard.as_mut().ok_or(sqlx::Error::PoolClosed)?;
-        sqlx::query(
-            "INSERT INTO orders (id, user_id, product_name, amount) VALUES ($1, $2, $3, $4)"
+    pub async fn create(&self, order: &Order) -> TransactionResult<Order> {
+        self.executor
+            .insert_returning(
+                "INSERT INTO orders (id, user_id, product_name, amount) VALUES ($1, $2, $3, $4) \
+                 RETURNING id, user_id, product_name, amount",
+                |query| {
+                    query
+                        .bind(order.id)
+                        .bind(order.user_id)
+                        .bind(&order.product_name)
+                        .bind(order.amount)
+                },
+            )
+            .await
+    }
+
+    pub async fn find_by_user(&self, user_id: Uuid) -> TransactionResult<Vec<Order>> {
+        let query = sqlx::query(
+            "SELECT id, user_id, product_name, amount FROM orders WHERE user_id = $1 ORDER BY amount"
         )
-        .bind(order.id)
-        .bind(order.user_id)
-        .bind(&order.product_name)
-        .bind(order.amount)
-        .execute(&mut **tx)
-        .await?;
-        Ok(())
+        .bind(user_id);
+        let rows = self.executor.fetch_all(query).await?;
+
+        Ok(rows.iter().map(|r| Order {
+            id: r.get("id"),
+            user_id: r.get("user_id"),
+            product_name: r.get("product_name"),
+            amount: r.get("amount"),
+        }).collect())
     }
 
     pub async fn find_by_id(&self, id: Uuid) -> TransactionResult<Option<Order>> {
-        let mut tx_guard = self.executor.tx.lock().await;
-        let tx = tx_guard.as_mut().ok_or(sqlx::Error::PoolClosed)?;
-        let row = sqlx::query(
-            "SELECT id, user_id, product_name, amount FROM orders WHERE id = $1"
-        )
-        .bind(id)
-        .fetch_optional(&mut **tx)
-        .await?;
+        let query = sqlx::query("SELECT id, user_id, product_name, amount FROM orders WHERE id = $1").bind(id);
+        let row = self.executor.fetch_optional(query).await?;
 
         Ok(row.map(|r| Order {
             id: r.get("id"),
@@ -138,11 +131,7 @@ impl OrderRepository {
     }
 
     pub async fn count(&self) -> TransactionResult<i64> {
-        let mut tx_guard = self.executor.tx.lock().await;
-        let tx = tx_guard.as_mut().ok_or(sqlx::Error::PoolClosed)?;
-        let row = sqlx::query("SELECT COUNT(*) as count FROM orders")
-            .fetch_one(&mut **tx)
-            .await?;
+        let row = self.executor.fetch_one(sqlx::query("SELECT COUNT(*) as count FROM orders")).await?;
         Ok(row.get("count"))
     }
 
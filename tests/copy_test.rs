@@ -0,0 +1,160 @@
+mod common;
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use postgres_unit_of_work::{
+    BinaryCopyWriter, CopyType, CopyValue, PostgresUnitOfWork, TransactionError, UnitOfWork, UnitOfWorkSession,
+};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use common::{cleanup_database, setup_database};
+
+type TypedRow = (
+    Option<i16>,
+    Option<i32>,
+    Option<i64>,
+    Option<f32>,
+    Option<f64>,
+    Option<bool>,
+    Option<String>,
+    Option<Uuid>,
+    Option<NaiveDateTime>,
+    Option<DateTime<Utc>>,
+);
+
+const COLUMNS: [CopyType; 10] = [
+    CopyType::Int2,
+    CopyType::Int4,
+    CopyType::Int8,
+    CopyType::Float4,
+    CopyType::Float8,
+    CopyType::Bool,
+    CopyType::Text,
+    CopyType::Uuid,
+    CopyType::Timestamp,
+    CopyType::Timestamptz,
+];
+
+const CREATE_TABLE: &str = "CREATE TEMP TABLE typed_rows (
+    position int4 GENERATED ALWAYS AS IDENTITY,
+    small int2, regular int4, big int8, single float4, double float8,
+    flag bool, label text, id uuid, local_time timestamp, instant timestamptz
+) ON COMMIT DROP";
+
+const COPY_STATEMENT: &str = "COPY typed_rows (small, regular, big, single, double, flag, label, id, local_time, instant) \
+    FROM STDIN WITH (FORMAT binary)";
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_binary_copy_round_trips_supported_types() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let local_time = NaiveDate::from_ymd_opt(1999, 12, 31)
+        .and_then(|date| date.and_hms_micro_opt(23, 59, 58, 123_456))
+        .expect("Valid timestamp");
+    let instant = DateTime::from_timestamp_micros(1_700_000_000_654_321).expect("Valid instant");
+    let full: TypedRow = (
+        Some(-7),
+        Some(42),
+        Some(i64::MAX),
+        Some(1.5),
+        Some(-2.25),
+        Some(true),
+        Some("zürich".to_string()),
+        Some(Uuid::new_v4()),
+        Some(local_time),
+        Some(instant),
+    );
+    let empty: TypedRow = (None, None, None, None, None, None, None, None, None, None);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor.execute(sqlx::query(CREATE_TABLE)).await.expect("Failed to create table");
+
+    let sink = executor.copy_in(COPY_STATEMENT).await.expect("Failed to start COPY");
+    let mut writer = BinaryCopyWriter::new(sink, &COLUMNS);
+    for row in [&full, &empty] {
+        let values: [&dyn CopyValue; 10] = [
+            &row.0, &row.1, &row.2, &row.3, &row.4, &row.5, &row.6, &row.7, &row.8, &row.9,
+        ];
+        writer.write_row(&values).await.expect("Failed to write row");
+    }
+    let label = "borrowed";
+    let values: [&dyn CopyValue; 10] = [
+        &1_i16, &2_i32, &3_i64, &4_f32, &5_f64, &false, &label, &None::<Uuid>, &local_time, &instant,
+    ];
+    writer.write_row(&values).await.expect("Failed to write row");
+    assert_eq!(writer.finish().await.expect("Failed to finish COPY"), 3);
+
+    let rows: Vec<TypedRow> = executor
+        .fetch_all_as(sqlx::query_as(
+            "SELECT small, regular, big, single, double, flag, label, id, local_time, instant \
+             FROM typed_rows ORDER BY position",
+        ))
+        .await
+        .expect("Failed to read rows");
+    let borrowed: TypedRow = (
+        Some(1),
+        Some(2),
+        Some(3),
+        Some(4.0),
+        Some(5.0),
+        Some(false),
+        Some(label.to_string()),
+        None,
+        Some(local_time),
+        Some(instant),
+    );
+    assert_eq!(rows, vec![full, empty, borrowed]);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_binary_copy_rejects_mismatched_values() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor.execute(sqlx::query(CREATE_TABLE)).await.expect("Failed to create table");
+    let sink = executor.copy_in(COPY_STATEMENT).await.expect("Failed to start COPY");
+    let mut writer = BinaryCopyWriter::new(sink, &COLUMNS);
+
+    let error = writer.write_row(&[&1_i16]).await.expect_err("Short rows should be rejected");
+    assert_eq!(error.to_string(), "Invalid binary COPY row: expected 10 values, got 1");
+
+    // NULLs are checked against the declared type too
+    let values: [&dyn CopyValue; 10] = [
+        &1_i16,
+        &None::<i64>,
+        &3_i64,
+        &4_f32,
+        &5_f64,
+        &true,
+        &"x",
+        &Uuid::nil(),
+        &None::<NaiveDateTime>,
+        &None::<DateTime<Utc>>,
+    ];
+    let error = writer
+        .write_row(&values)
+        .await
+        .expect_err("Mismatched types should be rejected");
+    match error {
+        TransactionError::InvalidCopyRow(message) => assert_eq!(message, "column 1 is int4 but the value is int8"),
+        other => panic!("Expected InvalidCopyRow, got {other:?}"),
+    }
+
+    // Rejected rows were never sent, so the copy can still complete
+    assert_eq!(writer.finish().await.expect("Failed to finish COPY"), 0);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,112 @@
+mod common;
+
+use postgres_unit_of_work::{PostgresUnitOfWork, TransactionError, UnitOfWork, UnitOfWorkSession};
+use sqlx::Row;
+use std::sync::Arc;
+
+use common::{cleanup_database, setup_database};
+
+/// Counts the cursors declared through `Executor::cursor`, leaving out the
+/// portal of the counting statement itself.
+const OPEN_CURSORS: &str = "SELECT COUNT(*) FROM pg_cursors WHERE name LIKE 'postgres_unit_of_work_cursor_%'";
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_cursor_pages_through_rows_in_order() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .execute_batch("CREATE TEMP TABLE numbers AS SELECT generate_series(1, 1000) AS n")
+        .await
+        .expect("Failed to create numbers");
+
+    let mut cursor = executor
+        .cursor("SELECT n FROM numbers WHERE n > $1 ORDER BY n", |query| query.bind(0))
+        .await
+        .expect("Failed to declare cursor");
+    let mut numbers = Vec::new();
+    let mut batches = 0;
+    loop {
+        let rows = cursor.fetch(100).await.expect("Failed to fetch rows");
+        if rows.is_empty() {
+            break;
+        }
+        assert_eq!(rows.len(), 100);
+        batches += 1;
+        numbers.extend(rows.iter().map(|row| row.get::<i32, _>("n")));
+        // Other statements can run between batches
+        executor
+            .execute(sqlx::query("SELECT 1"))
+            .await
+            .expect("Failed to run a statement between batches");
+    }
+    assert_eq!(batches, 10);
+    assert_eq!(numbers, (1..=1000).collect::<Vec<_>>());
+    assert!(cursor.is_exhausted());
+    assert!(cursor.fetch(100).await.expect("Failed to fetch rows").is_empty());
+
+    // A partial batch exhausts the cursor
+    let mut cursor = executor
+        .cursor("SELECT n FROM numbers WHERE n > $1 ORDER BY n", |query| query.bind(950))
+        .await
+        .expect("Failed to declare cursor");
+    assert_eq!(cursor.fetch(100).await.expect("Failed to fetch rows").len(), 50);
+    assert!(cursor.is_exhausted());
+    cursor.close().await.expect("Failed to close cursor");
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_cursors_are_named_uniquely_and_die_with_rollback() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    let mut first = executor
+        .cursor("SELECT generate_series(1, 10)", |query| query)
+        .await
+        .expect("Failed to declare cursor");
+    let second = executor
+        .cursor("SELECT generate_series(1, 10)", |query| query)
+        .await
+        .expect("Failed to declare cursor");
+    assert_ne!(first.name(), second.name());
+    let open: i64 = executor
+        .fetch_one(sqlx::query(OPEN_CURSORS))
+        .await
+        .expect("Failed to count cursors")
+        .get(0);
+    assert_eq!(open, 2);
+    second.close().await.expect("Failed to close cursor");
+    let open: i64 = executor
+        .fetch_one(sqlx::query(OPEN_CURSORS))
+        .await
+        .expect("Failed to count cursors")
+        .get(0);
+    assert_eq!(open, 1);
+    assert_eq!(first.fetch(3).await.expect("Failed to fetch rows").len(), 3);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+    let error = first.fetch(3).await.expect_err("The cursor should be gone after rollback");
+    assert!(matches!(error, TransactionError::AlreadyRolledBack), "Unexpected error {error:?}");
+
+    // Outside of a transaction there is nothing to declare a cursor in
+    let error = uow
+        .executor()
+        .cursor("SELECT 1", |query| query)
+        .await
+        .expect_err("A pool-backed Executor cannot declare a cursor");
+    assert_eq!(error.sqlstate(), Some("25P01"));
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
\ No newline at end of file
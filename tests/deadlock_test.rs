@@ -0,0 +1,76 @@
+#![cfg(feature = "deadlock-diagnostics")]
+
+mod common;
+
+use postgres_unit_of_work::{PgErrorKind, PostgresUnitOfWork, TransactionError, UnitOfWork, UnitOfWorkSession};
+use std::sync::Arc;
+
+use common::connect;
+
+#[tokio::test]
+async fn test_deadlock_carries_both_backends() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let (first_key, second_key) = (rand_key(), rand_key());
+
+    let first = uow.begin().await.expect("Failed to begin transaction");
+    let second = uow.begin().await.expect("Failed to begin transaction");
+    let first_pid = first.backend_pid().await.expect("Failed to read backend pid");
+    let second_pid = second.backend_pid().await.expect("Failed to read backend pid");
+    let lock = |key: i64| sqlx::query("SELECT pg_advisory_xact_lock($1)").bind(key);
+    first.executor().execute(lock(first_key)).await.expect("Failed to lock");
+    second.executor().execute(lock(second_key)).await.expect("Failed to lock");
+
+    // Each session waits for the other's lock until the server breaks the cycle
+    let (first_result, second_result) = tokio::join!(
+        first.executor().execute(lock(second_key)),
+        second.executor().execute(lock(first_key)),
+    );
+    let error = match (first_result, second_result) {
+        (Err(error), Ok(_)) | (Ok(_), Err(error)) => error,
+        results => panic!("Expected exactly one session to fail, got {results:?}"),
+    };
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::Deadlock));
+    let TransactionError::Deadlock { diagnostics, .. } = &error else {
+        panic!("Expected a deadlock, got {error:?}");
+    };
+    let diagnostics = diagnostics.as_ref().expect("Missing deadlock diagnostics");
+    assert!(diagnostics.pids.contains(&first_pid), "Missing pid {first_pid} in {diagnostics:?}");
+    assert!(diagnostics.pids.contains(&second_pid), "Missing pid {second_pid} in {diagnostics:?}");
+    for pid in [first_pid, second_pid] {
+        let backend = diagnostics.backends.iter().find(|backend| backend.pid == pid);
+        let query = backend.and_then(|backend| backend.query.as_deref()).unwrap_or_default();
+        assert!(query.contains("pg_advisory_xact_lock"), "Unexpected backend {backend:?}");
+    }
+    assert!(diagnostics.locks.iter().any(|lock| lock.locktype == "advisory" && lock.granted));
+    assert!(!diagnostics.truncated);
+
+    first.rollback().await.expect("Failed to rollback transaction");
+    second.rollback().await.expect("Failed to rollback transaction");
+}
+
+#[tokio::test]
+async fn test_deadlock_without_detail_has_no_pids() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let error = session
+        .executor()
+        .execute(sqlx::query("DO $$ BEGIN RAISE EXCEPTION USING ERRCODE = 'deadlock_detected'; END $$"))
+        .await
+        .expect_err("The statement fails");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let TransactionError::Deadlock { diagnostics: Some(diagnostics), .. } = &error else {
+        panic!("Expected a deadlock with diagnostics, got {error:?}");
+    };
+    assert!(diagnostics.pids.is_empty());
+    assert!(diagnostics.backends.is_empty());
+    assert!(diagnostics.locks.is_empty());
+}
+
+/// An advisory lock key no other test takes
+fn rand_key() -> i64 {
+    uuid::Uuid::new_v4().as_u64_pair().0 as i64
+}
@@ -0,0 +1,98 @@
+mod common;
+
+use postgres_unit_of_work::{DynSession, DynUnitOfWork, PostgresUnitOfWork};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use common::{cleanup_database, setup_database, Order, OrderRepository, User, UserRepository};
+
+/// The unit of work as a service would get it from a container
+fn container(pool: &PgPool) -> Arc<dyn DynUnitOfWork> {
+    Arc::new(PostgresUnitOfWork::new(Arc::new(pool.clone())))
+}
+
+/// Creates a user and an order for it in `session`, returning the repositories
+/// registered with the session
+async fn place_order(
+    session: &dyn DynSession,
+    user: &User,
+    order: &Order,
+) -> (Arc<UserRepository>, Arc<OrderRepository>) {
+    let user_repo = UserRepository::new(session.executor().clone());
+    let order_repo = OrderRepository::new(session.executor().clone());
+    session
+        .register_transaction_aware(user_repo.clone())
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(order_repo.clone())
+        .await
+        .expect("Failed to register observer");
+    user_repo.create(user).await.expect("Failed to create user");
+    order_repo.create(order).await.expect("Failed to create order");
+    (user_repo, order_repo)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_commit_through_dyn_unit_of_work() {
+    let pool = setup_database().await;
+    let uow = container(&pool);
+    let user = User::new("dyn_john".to_string(), "dyn_john@example.com".to_string());
+    let order = Order::new(user.id, "Laptop".to_string(), 1200);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let (user_repo, order_repo) = place_order(session.as_ref(), &user, &order).await;
+    session.commit().await.expect("Failed to commit transaction");
+    assert!(user_repo.is_committed(), "User repository should be committed");
+    assert!(order_repo.is_committed(), "Order repository should be committed");
+    assert!(!user_repo.is_rolled_back(), "User repository should not be rolled back");
+
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let persisted_user = UserRepository::new(verify_session.executor().clone())
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to find persisted user");
+    assert!(persisted_user.is_some(), "Persisted user not found");
+    let persisted_order = OrderRepository::new(verify_session.executor().clone())
+        .find_by_id(order.id)
+        .await
+        .expect("Failed to find persisted order");
+    assert!(persisted_order.is_some(), "Persisted order not found");
+    verify_session.commit().await.expect("Failed to commit verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_through_dyn_unit_of_work() {
+    let pool = setup_database().await;
+    let uow = container(&pool);
+    let user = User::new("dyn_jane".to_string(), "dyn_jane@example.com".to_string());
+    let order = Order::new(user.id, "Smartphone".to_string(), 800);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let (user_repo, order_repo) = place_order(session.as_ref(), &user, &order).await;
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert!(user_repo.is_rolled_back(), "User repository should be rolled back");
+    assert!(order_repo.is_rolled_back(), "Order repository should be rolled back");
+    assert!(!user_repo.is_committed(), "User repository should not be committed");
+
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let persisted_user = UserRepository::new(verify_session.executor().clone())
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to query user");
+    assert!(persisted_user.is_none(), "User should not exist after rollback");
+    let persisted_order = OrderRepository::new(verify_session.executor().clone())
+        .find_by_id(order.id)
+        .await
+        .expect("Failed to query order");
+    assert!(persisted_order.is_none(), "Order should not exist after rollback");
+    verify_session.rollback().await.expect("Failed to rollback verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,203 @@
+mod common;
+
+use postgres_unit_of_work::{
+    Executor, PgErrorKind, PostgresUnitOfWork, TransactionError, TransactionOptions, UnitOfWork,
+    UnitOfWorkSession,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{cleanup_database, setup_database, User, UserRepository};
+
+/// Run a raw statement on the executor's transaction, classifying errors with the session options
+async fn execute(executor: &Executor, sql: &str) -> Result<(), TransactionError> {
+    let mut tx_guard = executor.tx.lock().await;
+    let tx = tx_guard.as_mut().ok_or_else(|| executor.completed_error())?;
+    sqlx::query(sql)
+        .execute(&mut **tx)
+        .await
+        .map_err(|error| executor.classify_error(error))?;
+    Ok(())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_statement_timeout_is_classified() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    // Server-side timeout set by hand: the configured budget is unknown
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    execute(session.executor(), "SET LOCAL statement_timeout = 50")
+        .await
+        .expect("Failed to set statement_timeout");
+    let error = execute(session.executor(), "SELECT pg_sleep(1)")
+        .await
+        .expect_err("pg_sleep should be cancelled");
+    match error {
+        TransactionError::StatementTimeout { timeout, .. } => assert_eq!(timeout, None),
+        other => panic!("Expected StatementTimeout, got {other:?}"),
+    }
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    // Timeout configured through the session options
+    let options = TransactionOptions::new().statement_timeout(Duration::from_millis(50));
+    let session = uow.begin_with(options).await.expect("Failed to begin transaction");
+    let error = execute(session.executor(), "SELECT pg_sleep(1)")
+        .await
+        .expect_err("pg_sleep should be cancelled");
+    match error {
+        TransactionError::StatementTimeout { timeout, .. } => {
+            assert_eq!(timeout, Some(Duration::from_millis(50)))
+        }
+        other => panic!("Expected StatementTimeout, got {other:?}"),
+    }
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_lock_timeout_is_classified() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let setup_session = uow.begin().await.expect("Failed to begin setup transaction");
+    let user = User::new("locked".to_string(), "locked@example.com".to_string());
+    UserRepository::new(setup_session.executor().clone())
+        .create(&user)
+        .await
+        .expect("Failed to create user");
+    setup_session.commit().await.expect("Failed to commit setup transaction");
+
+    let lock_sql = format!("SELECT id FROM users WHERE id = '{}' FOR UPDATE", user.id);
+
+    // Hold the row lock in one session
+    let holder = uow.begin().await.expect("Failed to begin holder transaction");
+    execute(holder.executor(), &lock_sql).await.expect("Failed to lock row");
+
+    // Wait for it with a short lock_timeout in another
+    let options = TransactionOptions::new().lock_timeout(Duration::from_millis(100));
+    let waiter = uow.begin_with(options).await.expect("Failed to begin waiter transaction");
+    let error = execute(waiter.executor(), &lock_sql)
+        .await
+        .expect_err("Lock acquisition should time out");
+    match error {
+        TransactionError::LockTimeout { timeout, .. } => {
+            assert_eq!(timeout, Some(Duration::from_millis(100)))
+        }
+        other => panic!("Expected LockTimeout, got {other:?}"),
+    }
+
+    waiter.rollback().await.expect("Failed to rollback waiter transaction");
+    holder.rollback().await.expect("Failed to rollback holder transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[test]
+fn test_pg_error_kind_mapping() {
+    let cases = [
+        ("23505", PgErrorKind::UniqueViolation),
+        ("23503", PgErrorKind::ForeignKeyViolation),
+        ("23514", PgErrorKind::CheckViolation),
+        ("23502", PgErrorKind::NotNullViolation),
+        ("40001", PgErrorKind::SerializationFailure),
+        ("40P01", PgErrorKind::Deadlock),
+        ("55P03", PgErrorKind::LockNotAvailable),
+        ("57014", PgErrorKind::QueryCanceled),
+        ("42501", PgErrorKind::InsufficientPrivilege),
+        ("42P01", PgErrorKind::UndefinedTable),
+        ("08000", PgErrorKind::ConnectionError),
+        ("08003", PgErrorKind::ConnectionError),
+        ("08006", PgErrorKind::ConnectionError),
+        ("08P01", PgErrorKind::ConnectionError),
+        ("23000", PgErrorKind::Other("23000".to_string())),
+        ("40000", PgErrorKind::Other("40000".to_string())),
+        ("42601", PgErrorKind::Other("42601".to_string())),
+        ("P0001", PgErrorKind::Other("P0001".to_string())),
+    ];
+
+    for (code, expected) in cases {
+        assert_eq!(PgErrorKind::from_sqlstate(code), expected, "SQLSTATE {code}");
+    }
+}
+
+#[test]
+fn test_pg_kind_is_none_without_database_error() {
+    let error = TransactionError::CommitFailed("not a database error".to_string());
+    assert_eq!(error.pg_kind(), None);
+    assert_eq!(error.sqlstate(), None);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_unique_violation_kind() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let user = User::new("duplicate".to_string(), "duplicate@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    let error = user_repo.create(&user).await.expect_err("Duplicate insert should fail");
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::UniqueViolation));
+    assert_eq!(error.sqlstate(), Some("23505"));
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_database_error_details_are_reachable() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let user = User::new("details".to_string(), "details@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    let error = user_repo.create(&user).await.expect_err("Duplicate insert should fail");
+    assert_eq!(error.detail(), Some(format!("Key (id)=({}) already exists.", user.id).as_str()));
+    assert_eq!(error.table(), Some("users"));
+    assert_eq!(error.column(), None);
+
+    let source = std::error::Error::source(&error).expect("Database error should be the source");
+    assert!(source.downcast_ref::<sqlx::Error>().is_some(), "Source should be the sqlx error");
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let error = execute(
+        session.executor(),
+        "INSERT INTO users (id, username, email) VALUES (gen_random_uuid(), NULL, 'null@example.com')",
+    )
+    .await
+    .expect_err("NULL username should fail");
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::NotNullViolation));
+    assert_eq!(error.table(), Some("users"));
+    assert_eq!(error.column(), Some("username"));
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let error = execute(session.executor(), "SELECT usernam FROM users")
+        .await
+        .expect_err("Misspelled column should fail");
+    assert_eq!(
+        error.hint(),
+        Some("Perhaps you meant to reference the column \"users.username\".")
+    );
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use parking_lot::Mutex;
+use postgres_unit_of_work::{PostgresUnitOfWork, TransactionError, UnitOfWork, UnitOfWorkSession};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use common::connect;
+
+#[derive(Debug, PartialEq)]
+struct OrderPlaced {
+    order: u32,
+}
+
+#[derive(Debug, PartialEq)]
+struct UserRegistered {
+    name: &'static str,
+}
+
+type Delivered = Arc<Mutex<Vec<String>>>;
+
+/// Unit of work whose event handlers record every delivered event
+fn recording_uow(pool: &PgPool, delivered: &Delivered) -> PostgresUnitOfWork {
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log = delivered.clone();
+    uow.register_event_handler(move |event: Arc<OrderPlaced>| {
+        let log = log.clone();
+        async move {
+            log.lock().push(format!("order:{}", event.order));
+            Ok(())
+        }
+    });
+    let log = delivered.clone();
+    uow.register_event_handler(move |event: Arc<UserRegistered>| {
+        let log = log.clone();
+        async move {
+            log.lock().push(format!("user:{}", event.name));
+            Ok(())
+        }
+    });
+    uow
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_staged_events_delivered_after_commit() {
+    let pool = connect().await;
+    let delivered: Delivered = Arc::new(Mutex::new(Vec::new()));
+    let uow = recording_uow(&pool, &delivered);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.stage_event(OrderPlaced { order: 1 });
+    session.stage_event(UserRegistered { name: "alice" });
+    session.stage_event(OrderPlaced { order: 2 });
+    assert!(delivered.lock().is_empty(), "Events must not be delivered before commit");
+
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(*delivered.lock(), vec!["order:1", "order:2", "user:alice"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_staged_events_dropped_on_rollback() {
+    let pool = connect().await;
+    let delivered: Delivered = Arc::new(Mutex::new(Vec::new()));
+    let uow = recording_uow(&pool, &delivered);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.stage_event(OrderPlaced { order: 1 });
+    session.stage_event(UserRegistered { name: "bob" });
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert!(delivered.lock().is_empty(), "Rolled back events were delivered: {:?}", delivered.lock());
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_event_handler_failure_reported_as_observer_failure() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    uow.register_event_handler(|_: Arc<OrderPlaced>| async {
+        Err(TransactionError::CommitFailed("broker unavailable".to_string()))
+    });
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.stage_event(OrderPlaced { order: 1 });
+    let error = session.commit().await.expect_err("Commit should report the handler failure");
+
+    match error {
+        TransactionError::ObserverErrors(errors) => match &errors[..] {
+            [TransactionError::ObserverFailed { observer, .. }] => {
+                assert!(observer.ends_with("::OrderPlaced"), "Unexpected observer {observer}")
+            }
+            other => panic!("Expected one ObserverFailed, got {other:?}"),
+        },
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,1537 @@
+mod common;
+
+use futures::StreamExt;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    BindRow, BulkInsertOptions, Executor, ExecutorState, PgErrorKind, PostgresUnitOfWork, TransactionError,
+    TransactionOptions, UnitOfWork, UnitOfWorkSession,
+};
+use sqlx::postgres::PgArguments;
+use sqlx::query::Query;
+use sqlx::{Postgres, Row};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use common::{
+    cleanup_database, setup_database, CallLog, Order, OrderRepository, RecordingObserver, User, UserRepository,
+};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_execute_returns_rows_affected() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    for name in ["alice", "bob"] {
+        let user = User::new(name.to_string(), format!("{name}@example.com"));
+        user_repo.create(&user).await.expect("Failed to create user");
+    }
+
+    let result = session
+        .executor()
+        .execute(sqlx::query("UPDATE users SET email = $1").bind("renamed@example.com"))
+        .await
+        .expect("Failed to update users");
+    assert_eq!(result.rows_affected(), 2);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_execute_after_completion_fails() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    session.commit().await.expect("Failed to commit transaction");
+    let error = executor
+        .execute(sqlx::query("SELECT 1"))
+        .await
+        .expect_err("Execute should fail after commit");
+    assert!(matches!(error, TransactionError::AlreadyCommitted), "Unexpected error {error:?}");
+
+    // A transaction taken out of the executor directly has no recorded outcome
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let tx = executor.tx.lock().await.take().expect("Transaction should be active");
+    let error = executor
+        .execute(sqlx::query("SELECT 1"))
+        .await
+        .expect_err("Execute should fail without a transaction");
+    assert!(matches!(error, TransactionError::TransactionAlreadyCompleted), "Unexpected error {error:?}");
+    tx.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_fetch_helpers_back_order_repository() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let order_repo = OrderRepository::new(session.executor().clone());
+    let user = User::new("carol".to_string(), "carol@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+    let book = Order::new(user.id, "Book".to_string(), 1500);
+    let lamp = Order::new(user.id, "Lamp".to_string(), 4200);
+    order_repo.create(&lamp).await.expect("Failed to create order");
+    order_repo.create(&book).await.expect("Failed to create order");
+
+    assert_eq!(order_repo.find_by_id(book.id).await.expect("Failed to find order"), Some(book.clone()));
+    assert_eq!(order_repo.find_by_id(uuid::Uuid::new_v4()).await.expect("Failed to find order"), None);
+    assert_eq!(order_repo.find_by_user(user.id).await.expect("Failed to list orders"), vec![book, lamp]);
+    assert_eq!(order_repo.count().await.expect("Failed to count orders"), 2);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_fetch_one_without_rows_reports_row_not_found() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let error = executor
+        .fetch_one(sqlx::query("SELECT id FROM users WHERE id = $1").bind(uuid::Uuid::new_v4()))
+        .await
+        .expect_err("fetch_one should fail without rows");
+    assert!(
+        matches!(error, TransactionError::DatabaseError(sqlx::Error::RowNotFound)),
+        "Unexpected error {error:?}"
+    );
+
+    session.rollback().await.expect("Failed to rollback transaction");
+    let error = executor
+        .fetch_all(sqlx::query("SELECT 1"))
+        .await
+        .expect_err("fetch_all should fail after rollback");
+    assert!(matches!(error, TransactionError::AlreadyRolledBack), "Unexpected error {error:?}");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_typed_fetch_maps_rows() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let alice = User::new("alice".to_string(), "alice@example.com".to_string());
+    let bob = User::new("bob".to_string(), "bob@example.com".to_string());
+    user_repo.create(&alice).await.expect("Failed to create user");
+    user_repo.create(&bob).await.expect("Failed to create user");
+
+    assert_eq!(user_repo.find_by_id(bob.id).await.expect("Failed to find user"), Some(bob.clone()));
+    assert_eq!(user_repo.find_by_id(uuid::Uuid::new_v4()).await.expect("Failed to find user"), None);
+
+    let executor = session.executor();
+    let users = executor
+        .fetch_all_as::<User>(sqlx::query_as("SELECT id, username, email FROM users ORDER BY username"))
+        .await
+        .expect("Failed to list users");
+    assert_eq!(users, vec![alice.clone(), bob]);
+    let (count,) = executor
+        .fetch_one_as::<(i64,)>(sqlx::query_as("SELECT COUNT(*) FROM users WHERE id <> $1").bind(alice.id))
+        .await
+        .expect("Failed to count users");
+    assert_eq!(count, 1);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_fetch_stream_pulls_rows_lazily() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    executor
+        .execute(sqlx::query(
+            "CREATE TEMP TABLE export_rows ON COMMIT DROP AS SELECT n FROM generate_series(1, 10000) AS n",
+        ))
+        .await
+        .expect("Failed to create export table");
+
+    let mut rows = Box::pin(executor.fetch_stream(sqlx::query("SELECT n FROM export_rows ORDER BY n")));
+    let mut count = 0;
+    let mut sum = 0_i64;
+    while let Some(row) = rows.next().await {
+        let n: i32 = row.expect("Failed to stream row").get("n");
+        count += 1;
+        sum += i64::from(n);
+        if count == 1 {
+            let error = executor
+                .execute(sqlx::query("SELECT 1"))
+                .await
+                .expect_err("Executor should be busy while streaming");
+            assert!(matches!(error, TransactionError::ExecutorBusy { .. }), "Unexpected error {error:?}");
+        }
+    }
+    assert_eq!(count, 10_000);
+    assert_eq!(sum, 50_005_000);
+    drop(rows);
+
+    let row = executor
+        .fetch_one(sqlx::query("SELECT COUNT(*) AS count FROM export_rows"))
+        .await
+        .expect("Executor should be usable after the stream is dropped");
+    assert_eq!(row.get::<i64, _>("count"), 10_000);
+
+    session.commit().await.expect("Failed to commit transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_with_tx_serializes_access() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    executor
+        .execute(sqlx::query("CREATE TEMP TABLE counters ON COMMIT DROP AS SELECT 1 AS value"))
+        .await
+        .expect("Failed to create counters table");
+
+    let locked = Arc::new(tokio::sync::Notify::new());
+    let finished = Arc::new(AtomicBool::new(false));
+    let (notify, done) = (locked.clone(), finished.clone());
+    let read_modify_write = executor.with_tx(move |tx| {
+        Box::pin(async move {
+            let row = sqlx::query("SELECT value FROM counters").fetch_one(&mut **tx).await?;
+            let value: i32 = row.get("value");
+            notify.notify_one();
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            sqlx::query("UPDATE counters SET value = $1").bind(value + 1).execute(&mut **tx).await?;
+            done.store(true, Ordering::SeqCst);
+            Ok(value)
+        })
+    });
+
+    let other = executor.clone();
+    let waiter = tokio::spawn(async move {
+        locked.notified().await;
+        other
+            .execute(sqlx::query("UPDATE counters SET value = value * 10"))
+            .await
+            .expect("Failed to update counter");
+        finished.load(Ordering::SeqCst)
+    });
+
+    assert_eq!(read_modify_write.await.expect("Critical section failed"), 1);
+    assert!(waiter.await.expect("Waiter panicked"), "Query ran inside the critical section");
+    let row = executor
+        .fetch_one(sqlx::query("SELECT value FROM counters"))
+        .await
+        .expect("Failed to read counter");
+    assert_eq!(row.get::<i32, _>("value"), 20);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_try_with_tx_reports_lock_holder() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let locked = Arc::new(tokio::sync::Notify::new());
+    let notify = locked.clone();
+    let holder = executor.clone();
+    let critical_section = tokio::spawn(async move {
+        holder
+            .with_tx(move |_| {
+                Box::pin(async move {
+                    notify.notify_one();
+                    tokio::time::sleep(Duration::from_millis(200)).await;
+                    Ok(())
+                })
+            })
+            .await
+    });
+
+    locked.notified().await;
+    let error = executor
+        .try_with_tx(|_| Box::pin(async { Ok(()) }))
+        .await
+        .expect_err("Executor should be busy");
+    match error {
+        TransactionError::ExecutorBusy { holder } => {
+            assert!(holder.starts_with("with_tx since "), "Unexpected holder {holder}")
+        }
+        other => panic!("Expected ExecutorBusy, got {other:?}"),
+    }
+    critical_section
+        .await
+        .expect("Critical section panicked")
+        .expect("Critical section failed");
+
+    let mut rows = Box::pin(executor.fetch_stream(sqlx::query("SELECT generate_series(1, 3)")));
+    rows.next().await.expect("Stream ended early").expect("Failed to stream row");
+    let error = executor
+        .try_with_tx(|_| Box::pin(async { Ok(()) }))
+        .await
+        .expect_err("Executor should be busy while streaming");
+    assert!(error.to_string().contains("held by fetch_stream since"), "Unexpected error {error}");
+    drop(rows);
+
+    let value = executor
+        .try_with_tx(|tx| {
+            Box::pin(async move {
+                let row = sqlx::query("SELECT 7 AS value").fetch_one(&mut **tx).await?;
+                Ok(row.get::<i32, _>("value"))
+            })
+        })
+        .await
+        .expect("Executor should be free");
+    assert_eq!(value, 7);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_acquire_runs_sqlx_queries_directly() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user = User::new("dave".to_string(), "dave@example.com".to_string());
+    {
+        let mut conn = session.executor().acquire().await.expect("Failed to acquire connection");
+        sqlx::query("INSERT INTO users (id, username, email) VALUES ($1, $2, $3)")
+            .bind(user.id)
+            .bind(&user.username)
+            .bind(&user.email)
+            .execute(&mut conn)
+            .await
+            .expect("Failed to insert user");
+        let found = sqlx::query!("SELECT username, email FROM users WHERE id = $1", user.id)
+            .fetch_one(&mut conn)
+            .await
+            .expect("Failed to find user");
+        assert_eq!((found.username, found.email), (user.username.clone(), user.email.clone()));
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM users")
+            .fetch_one(&mut *conn)
+            .await
+            .expect("Failed to count users");
+        assert_eq!(count, Some(1));
+    }
+
+    // The connection was released, and its writes belong to the session
+    let user_repo = UserRepository::new(session.executor().clone());
+    assert_eq!(user_repo.find_by_id(user.id).await.expect("Failed to find user"), Some(user));
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// CSV lines for `count` users, split across several chunks
+fn user_csv_chunks(count: usize) -> Vec<String> {
+    let lines: Vec<String> = (0..count)
+        .map(|i| format!("{},user{i},user{i}@example.com\n", uuid::Uuid::new_v4()))
+        .collect();
+    lines.chunks(500).map(|chunk| chunk.concat()).collect()
+}
+
+async fn copy_users(executor: &Executor, count: usize) -> u64 {
+    let mut chunks = user_csv_chunks(count);
+    let last = chunks.pop().expect("At least one chunk");
+    let mut sink = executor
+        .copy_in("COPY users (id, username, email) FROM STDIN WITH (FORMAT csv)")
+        .await
+        .expect("Failed to start COPY");
+    for chunk in chunks {
+        sink.send(chunk).await.expect("Failed to send chunk");
+    }
+    sink.read_from(last.as_bytes()).await.expect("Failed to copy from reader");
+    sink.finish().await.expect("Failed to finish COPY")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_copy_in_participates_in_transaction() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let persisted = || async {
+        sqlx::query_scalar::<_, i64>("SELECT COUNT(*) FROM users")
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to count users")
+    };
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    assert_eq!(copy_users(session.executor(), 3000).await, 3000);
+    let user_repo = UserRepository::new(session.executor().clone());
+    assert_eq!(user_repo.count().await.expect("Failed to count users"), 3000);
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert_eq!(persisted().await, 0);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    assert_eq!(copy_users(session.executor(), 3000).await, 3000);
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(persisted().await, 3000);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_copy_in_reports_bad_data() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let error = match session.executor().copy_in("COPY missing_table FROM STDIN").await {
+        Err(error) => error,
+        Ok(_) => panic!("COPY into a missing table should fail"),
+    };
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::UndefinedTable));
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let mut sink = session
+        .executor()
+        .copy_in("COPY users (id, username, email) FROM STDIN WITH (FORMAT csv)")
+        .await
+        .expect("Failed to start COPY");
+    sink.send("not-a-uuid,alice,alice@example.com\n").await.expect("Failed to send chunk");
+    let error = sink.finish().await.expect_err("Malformed rows should fail the COPY");
+    assert_eq!(error.sqlstate(), Some("22P02"));
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_copy_out_sees_uncommitted_writes() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    for name in ["erin", "frank"] {
+        let user = User::new(name.to_string(), format!("{name}@example.com"));
+        user_repo.create(&user).await.expect("Failed to create user");
+    }
+
+    let executor = session.executor();
+    let mut chunks = executor
+        .copy_out("COPY (SELECT username, email FROM users ORDER BY username) TO STDOUT WITH (FORMAT csv)")
+        .await
+        .expect("Failed to start COPY");
+    let error = executor
+        .execute(sqlx::query("SELECT 1"))
+        .await
+        .expect_err("Executor should be busy while copying");
+    assert!(error.to_string().contains("held by copy_out since"), "Unexpected error {error}");
+    let mut exported = Vec::new();
+    while let Some(chunk) = chunks.next().await {
+        exported.extend_from_slice(&chunk.expect("Failed to copy chunk"));
+    }
+    drop(chunks);
+    assert_eq!(
+        String::from_utf8(exported).expect("CSV should be UTF-8"),
+        "erin,erin@example.com\nfrank,frank@example.com\n"
+    );
+    assert_eq!(user_repo.count().await.expect("Failed to count users"), 2);
+
+    // A failure while copying surfaces from copy_out or from the stream
+    let failed = async {
+        let mut chunks = executor
+            .copy_out("COPY (SELECT 1 / (n - 2) FROM generate_series(1, 3) AS n) TO STDOUT")
+            .await?;
+        while let Some(chunk) = chunks.next().await {
+            chunk?;
+        }
+        Ok::<_, TransactionError>(())
+    };
+    let error = failed.await.expect_err("Division by zero should fail the copy");
+    assert_eq!(error.sqlstate(), Some("22012"));
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_execute_batch_runs_statements_in_order() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .execute_batch(
+            "CREATE TEMP TABLE fixtures (name text NOT NULL) ON COMMIT DROP;
+             CREATE INDEX fixtures_name ON fixtures (name);
+             INSERT INTO fixtures VALUES ('alpha'), ('beta');",
+        )
+        .await
+        .expect("Failed to run batch");
+    let row = executor
+        .fetch_one(sqlx::query(
+            "SELECT COUNT(*) AS count, bool_or(indexname = 'fixtures_name') AS indexed \
+             FROM fixtures, pg_indexes WHERE tablename = 'fixtures'",
+        ))
+        .await
+        .expect("Failed to inspect batch effects");
+    assert_eq!(row.get::<i64, _>("count"), 2);
+    assert!(row.get::<bool, _>("indexed"));
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_execute_batch_reports_failing_statement() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let error = session
+        .executor()
+        .execute_batch(
+            "CREATE TEMP TABLE fixtures (name text) ON COMMIT DROP;
+             INSERT INTO missing_fixtures VALUES ('alpha');
+             INSERT INTO fixtures VALUES ('beta');",
+        )
+        .await
+        .expect_err("The middle statement should fail");
+    match &error {
+        TransactionError::BatchStatementFailed { index, .. } => assert_eq!(*index, 1),
+        other => panic!("Expected BatchStatementFailed, got {other:?}"),
+    }
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::UndefinedTable));
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_execute_pipelined_returns_results_in_order() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .execute(sqlx::query("CREATE TEMP TABLE ingest (n int4 PRIMARY KEY) ON COMMIT DROP"))
+        .await
+        .expect("Failed to create table");
+
+    let results = executor
+        .execute_pipelined(vec![
+            sqlx::query("INSERT INTO ingest VALUES ($1), ($2)").bind(1).bind(2),
+            sqlx::query("INSERT INTO ingest VALUES ($1)").bind(3),
+            sqlx::query("UPDATE ingest SET n = n + 10 WHERE n > $1").bind(100),
+        ])
+        .await
+        .expect("Failed to run pipelined queries");
+    let affected: Vec<u64> = results.iter().map(|result| result.rows_affected()).collect();
+    assert_eq!(affected, vec![2, 1, 0]);
+
+    let error = executor
+        .execute_pipelined(vec![
+            sqlx::query("INSERT INTO ingest VALUES ($1)").bind(4),
+            sqlx::query("INSERT INTO ingest VALUES ($1)").bind(1),
+            sqlx::query("INSERT INTO ingest VALUES ($1)").bind(5),
+        ])
+        .await
+        .expect_err("The duplicate key should fail");
+    match &error {
+        TransactionError::BatchStatementFailed { index, .. } => assert_eq!(*index, 1),
+        other => panic!("Expected BatchStatementFailed, got {other:?}"),
+    }
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::UniqueViolation));
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// Number of statements prepared on the executor's connection whose SQL contains `marker`
+async fn prepared_statements(executor: &Executor, marker: &str) -> i64 {
+    let query = sqlx::query("SELECT COUNT(*) FROM pg_prepared_statements WHERE strpos(statement, $1) > 0")
+        .bind(marker)
+        .persistent(false);
+    let row = executor.fetch_one(query).await.expect("Failed to count prepared statements");
+    row.get(0)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_helpers_cache_statements_unless_the_query_opts_out() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .fetch_one(sqlx::query("SELECT $1::int4 AS cached_probe").bind(1))
+        .await
+        .expect("Failed to select");
+    executor
+        .fetch_one(sqlx::query("SELECT $1::int4 AS one_off_probe").bind(1).persistent(false))
+        .await
+        .expect("Failed to select");
+
+    assert_eq!(prepared_statements(executor, "cached_probe").await, 1);
+    assert_eq!(prepared_statements(executor, "one_off_probe").await, 0);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_session_without_statement_cache_prepares_nothing() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let options = TransactionOptions::new().statement_cache(false);
+    let session = uow.begin_with(options).await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    let user_repo = UserRepository::new(executor.clone());
+    let user = User::new("uncached".to_string(), "uncached@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+    assert!(user_repo.find_by_id(user.id).await.expect("Failed to find user").is_some());
+    for n in 0..20 {
+        let sql = format!("SELECT $1::int4 AS dynamic_probe_{n}");
+        executor.fetch_all(sqlx::query(&sql).bind(n)).await.expect("Failed to select");
+    }
+    let rows: Vec<_> = executor
+        .fetch_stream(sqlx::query("SELECT username FROM users WHERE id = $1").bind(user.id))
+        .collect()
+        .await;
+    assert_eq!(rows.len(), 1);
+
+    assert_eq!(prepared_statements(executor, "INSERT INTO users").await, 0);
+    assert_eq!(prepared_statements(executor, "FROM users").await, 0);
+    assert_eq!(prepared_statements(executor, "dynamic_probe").await, 0);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_pool_executor_auto_commits() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let user_repo = UserRepository::new(uow.executor());
+    let user = User::new("pooled".to_string(), "pooled@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    // Visible to other connections without any commit
+    let row: (String,) = sqlx::query_as("SELECT username FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&pool)
+        .await
+        .expect("The insert should be committed");
+    assert_eq!(row.0, "pooled");
+
+    let found = user_repo.find_by_id(user.id).await.expect("Failed to find user");
+    assert_eq!(found.map(|user| user.email), Some("pooled@example.com".to_string()));
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_pool_executor_with_tx_and_fetch_stream() {
+    let pool = setup_database().await;
+    let executor = Executor::from_pool(Arc::new(pool.clone()));
+
+    let user = User::new("atomic".to_string(), "atomic@example.com".to_string());
+    let error = executor
+        .with_tx(|tx| {
+            Box::pin(async move {
+                sqlx::query("INSERT INTO users (id, username, email) VALUES ($1, $2, $3)")
+                    .bind(user.id)
+                    .bind(&user.username)
+                    .bind(&user.email)
+                    .execute(&mut **tx)
+                    .await?;
+                Err::<(), _>(TransactionError::CommitFailed("abandoned".to_string()))
+            })
+        })
+        .await
+        .expect_err("The closure failed");
+    assert!(matches!(error, TransactionError::CommitFailed(_)), "Unexpected error {error:?}");
+    let row = executor
+        .fetch_one(sqlx::query("SELECT COUNT(*) FROM users"))
+        .await
+        .expect("Failed to count users");
+    assert_eq!(row.get::<i64, _>(0), 0);
+
+    let streamed: Vec<_> = executor
+        .fetch_stream(sqlx::query("SELECT generate_series(1, 3)"))
+        .collect()
+        .await;
+    assert_eq!(streamed.len(), 3);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_executor_state_follows_the_transaction() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    assert_eq!(executor.state(), ExecutorState::Active);
+    assert!(executor.is_active());
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(executor.state(), ExecutorState::Completed);
+    assert!(!executor.is_active());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert_eq!(executor.state(), ExecutorState::Completed);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let tx = executor.tx.lock().await.take().expect("The transaction should be open");
+    assert_eq!(executor.state(), ExecutorState::Completed);
+    tx.rollback().await.expect("Failed to rollback transaction");
+    drop(session);
+
+    let executor = uow.executor();
+    assert_eq!(executor.state(), ExecutorState::PoolMode);
+    assert!(!executor.is_active());
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_reentrant_helper_call_fails_instead_of_deadlocking() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let user_repo = UserRepository::new(executor.clone());
+    let nested = executor.with_tx(|_tx| {
+        Box::pin(async move {
+            let user = User::new("nested".to_string(), "nested@example.com".to_string());
+            user_repo.create(&user).await
+        })
+    });
+    let error = tokio::time::timeout(Duration::from_secs(5), nested)
+        .await
+        .expect("The nested call should fail rather than deadlock")
+        .expect_err("The nested call should fail");
+    match &error {
+        TransactionError::ReentrantExecutorUse { holder } => {
+            assert!(holder.starts_with("with_tx since"), "Unexpected holder {holder}")
+        }
+        other => panic!("Expected ReentrantExecutorUse, got {other:?}"),
+    }
+
+    // The lock was released, so the executor is still usable
+    let row = executor
+        .fetch_one(sqlx::query("SELECT 1"))
+        .await
+        .expect("Failed to select");
+    assert_eq!(row.get::<i32, _>(0), 1);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_helpers_joined_in_one_task_take_turns() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let first_repo = UserRepository::new(executor.clone());
+    let second_repo = UserRepository::new(executor.clone());
+    let first = User::new("first".to_string(), "first@example.com".to_string());
+    let second = User::new("second".to_string(), "second@example.com".to_string());
+    let joined = async { tokio::join!(first_repo.create(&first), second_repo.create(&second)) };
+    let (first_result, second_result) = tokio::time::timeout(Duration::from_secs(5), joined)
+        .await
+        .expect("The joined calls should not deadlock");
+    first_result.expect("Failed to create first user");
+    second_result.expect("Failed to create second user");
+
+    let row = executor
+        .fetch_one(sqlx::query("SELECT COUNT(*) FROM users WHERE id = ANY($1)").bind(vec![first.id, second.id]))
+        .await
+        .expect("Failed to count users");
+    assert_eq!(row.get::<i64, _>(0), 2);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_executor_guard_excludes_other_helpers() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let mut guard = executor.lock().await.expect("Failed to lock executor");
+    guard
+        .execute(sqlx::query("CREATE TEMP TABLE guarded (n int4)"))
+        .await
+        .expect("Failed to create table");
+
+    let released = Arc::new(AtomicBool::new(false));
+    let guard_released = released.clone();
+    let other = executor.clone();
+    let waiter = tokio::spawn(async move {
+        let row = other
+            .fetch_one(sqlx::query("SELECT COUNT(*) FROM guarded"))
+            .await
+            .expect("Failed to count rows");
+        (guard_released.load(Ordering::SeqCst), row.get::<i64, _>(0))
+    });
+
+    for n in 0..10 {
+        guard
+            .execute(sqlx::query("INSERT INTO guarded VALUES ($1)").bind(n))
+            .await
+            .expect("Failed to insert row");
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let tx = guard.transaction().expect("A session guard holds the transaction");
+    sqlx::query("INSERT INTO guarded VALUES (10)")
+        .execute(&mut **tx)
+        .await
+        .expect("Failed to insert row");
+    let error = executor
+        .fetch_one(sqlx::query("SELECT 1"))
+        .await
+        .expect_err("The task holding the guard cannot use the helpers");
+    assert!(matches!(error, TransactionError::ReentrantExecutorUse { .. }), "Unexpected error {error:?}");
+    assert!(!waiter.is_finished(), "A helper ran while the guard was held");
+    released.store(true, Ordering::SeqCst);
+    drop(guard);
+
+    let (ran_after_release, count) = waiter.await.expect("Waiter panicked");
+    assert!(ran_after_release, "A helper ran while the guard was held");
+    assert_eq!(count, 11);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_read_only_executor_fetches_in_the_transaction() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let user = User::new("alice".to_string(), "alice@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    // The uncommitted user is visible through the read-only view
+    let reader = session.executor().read_only();
+    assert!(reader.is_active());
+    let fetched: User = reader
+        .fetch_one_as(sqlx::query_as("SELECT * FROM users WHERE id = $1").bind(user.id))
+        .await
+        .expect("Failed to fetch user");
+    assert_eq!(fetched, user);
+    let rows = reader
+        .fetch_all(sqlx::query("SELECT id FROM users"))
+        .await
+        .expect("Failed to fetch users");
+    assert_eq!(rows.len(), 1);
+    let streamed: Vec<_> = reader.fetch_stream(sqlx::query("SELECT id FROM users")).collect().await;
+    assert_eq!(streamed.len(), 1);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert_eq!(reader.state(), ExecutorState::Completed);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[test]
+fn test_read_only_executor_cannot_write() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_statement_with_timeout_is_cancelled() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let user = User::new("alice".to_string(), "alice@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    let started = Instant::now();
+    let error = session
+        .executor()
+        .fetch_one_with_timeout(sqlx::query("SELECT pg_sleep(5)"), Duration::from_millis(200))
+        .await
+        .expect_err("The statement should time out");
+    assert!(started.elapsed() < Duration::from_secs(2), "Timed out after {:?}", started.elapsed());
+    match &error {
+        TransactionError::StatementTimeout { timeout, .. } => assert_eq!(*timeout, Some(Duration::from_millis(200))),
+        other => panic!("Expected StatementTimeout, got {other:?}"),
+    }
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::QueryCanceled));
+
+    // The transaction is still usable, and keeps the work done before
+    let row = session
+        .executor()
+        .fetch_one_with_timeout(sqlx::query("SELECT COUNT(*) FROM users"), Duration::from_secs(5))
+        .await
+        .expect("Failed to count users");
+    assert_eq!(row.get::<i64, _>(0), 1);
+    session.commit().await.expect("Failed to commit transaction");
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to count users");
+    assert_eq!(count, 1);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_pool_executor_statement_with_timeout_is_cancelled() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let executor = uow.executor();
+
+    let started = Instant::now();
+    let error = executor
+        .execute_with_timeout(sqlx::query("SELECT pg_sleep(5)"), Duration::from_millis(200))
+        .await
+        .expect_err("The statement should time out");
+    assert!(started.elapsed() < Duration::from_secs(2), "Timed out after {:?}", started.elapsed());
+    assert!(matches!(error, TransactionError::StatementTimeout { .. }), "Unexpected error {error:?}");
+
+    let row = executor
+        .fetch_one(sqlx::query("SELECT 1::int4"))
+        .await
+        .expect("Failed to run a query after the timeout");
+    assert_eq!(row.get::<i32, _>(0), 1);
+    let active: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM pg_stat_activity WHERE query = 'SELECT pg_sleep(5)' AND state = 'active'")
+            .fetch_one(&pool)
+            .await
+            .expect("Failed to query pg_stat_activity");
+    assert_eq!(active, 0, "The timed out statement is still running");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_cancelled_statement_leaves_transaction_to_roll_back() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let user = User::new("alice".to_string(), "alice@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        canceller.cancel();
+    });
+    let started = Instant::now();
+    let error = session
+        .executor()
+        .fetch_one_with_cancellation(sqlx::query("SELECT pg_sleep(5)"), &token)
+        .await
+        .expect_err("The statement should be cancelled");
+    assert!(started.elapsed() < Duration::from_secs(2), "Cancelled after {:?}", started.elapsed());
+    assert!(matches!(error, TransactionError::Cancelled), "Unexpected error {error:?}");
+
+    // A token cancelled beforehand stops the helper before it runs anything
+    let error = session
+        .executor()
+        .execute_with_cancellation(sqlx::query("DELETE FROM users"), &token)
+        .await
+        .expect_err("The token is already cancelled");
+    assert!(matches!(error, TransactionError::Cancelled), "Unexpected error {error:?}");
+
+    let error = session.commit().await.expect_err("Commit should fail after cancellation");
+    assert!(matches!(error, TransactionError::Cancelled), "Unexpected error {error:?}");
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to count users");
+    assert_eq!(count, 0);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_cancelled_commit_reports_the_cancellation_over_observer_failures() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(RecordingObserver::failing("audit", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        canceller.cancel();
+    });
+    session
+        .executor()
+        .execute_with_cancellation(sqlx::query("SELECT pg_sleep(5)"), &token)
+        .await
+        .expect_err("The statement should be cancelled");
+
+    let error = session.commit().await.expect_err("Commit should fail after cancellation");
+    assert!(matches!(error, TransactionError::Cancelled), "Unexpected error {error:?}");
+    assert_eq!(*log.lock(), vec!["audit:rollback"]);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_pool_executor_cancelled_statement() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let executor = uow.executor();
+
+    let token = CancellationToken::new();
+    let canceller = token.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        canceller.cancel();
+    });
+    let started = Instant::now();
+    let error = executor
+        .fetch_all_with_cancellation(sqlx::query("SELECT pg_sleep(5)"), &token)
+        .await
+        .expect_err("The statement should be cancelled");
+    assert!(started.elapsed() < Duration::from_secs(2), "Cancelled after {:?}", started.elapsed());
+    assert!(matches!(error, TransactionError::Cancelled), "Unexpected error {error:?}");
+
+    // Statements outside of a transaction are unaffected by the cancellation
+    let row = executor
+        .fetch_one_with_cancellation(sqlx::query("SELECT 1::int4"), &CancellationToken::new())
+        .await
+        .expect("Failed to run a query after the cancellation");
+    assert_eq!(row.get::<i32, _>(0), 1);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_status_reports_the_lock_holder() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let status = executor.status();
+    assert!(!status.locked);
+    assert_eq!(status.holder, None);
+    assert!(!status.transaction_taken);
+
+    let sleeper = executor.clone();
+    let sleep = tokio::spawn(async move { sleeper.fetch_one(sqlx::query("SELECT pg_sleep(0.5)")).await });
+    let first = loop {
+        let status = executor.status();
+        if status.locked {
+            break status;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    };
+    assert_eq!(first.holder, Some("fetch_one"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let second = executor.status();
+    assert_eq!(second.holder, Some("fetch_one"));
+    assert!(second.held_for > first.held_for, "{second:?} should be held longer than {first:?}");
+    assert!(format!("{executor:?}").contains("fetch_one"));
+
+    sleep.await.expect("Sleeper panicked").expect("Failed to sleep");
+    let status = executor.status();
+    assert!(!status.locked);
+    assert_eq!(status.held_for, None);
+    assert_eq!(status.statements, 1);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert!(executor.status().transaction_taken);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_insert_returning_checks_the_returned_rows() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let order_repo = OrderRepository::new(session.executor().clone());
+    let user = User::new("dave".to_string(), "dave@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+    let lamp = Order::new(user.id, "Lamp".to_string(), 4200);
+    assert_eq!(order_repo.create(&lamp).await.expect("Failed to create order"), lamp);
+
+    let executor = session.executor();
+    let upsert = "INSERT INTO orders (id, user_id, product_name, amount) VALUES ($1, $2, $3, $4) \
+                  ON CONFLICT (id) DO NOTHING RETURNING id";
+    let skipped: Option<(uuid::Uuid,)> = executor
+        .insert_returning_optional(upsert, |query| query.bind(lamp.id).bind(user.id).bind("Lamp").bind(1_i64))
+        .await
+        .expect("Failed to insert order");
+    assert_eq!(skipped, None);
+    let error = executor
+        .insert_returning::<(uuid::Uuid,), _>(upsert, |query| query.bind(lamp.id).bind(user.id).bind("Lamp").bind(1_i64))
+        .await
+        .expect_err("An insert skipped by the conflict returns no row");
+    match error {
+        TransactionError::UnexpectedRowCount { expected, rows } => {
+            assert_eq!(expected, "exactly one");
+            assert_eq!(rows, 0);
+        }
+        other => panic!("Expected UnexpectedRowCount, got {other:?}"),
+    }
+
+    let error = executor
+        .insert_returning_optional::<(uuid::Uuid,), _>(
+            "INSERT INTO orders (id, user_id, product_name, amount) \
+             SELECT gen_random_uuid(), $1, 'Pen', 100 FROM generate_series(1, 2) RETURNING id",
+            |query| query.bind(user.id),
+        )
+        .await
+        .expect_err("Two returned rows are more than at most one");
+    assert!(
+        matches!(error, TransactionError::UnexpectedRowCount { expected: "at most one", rows: 2 }),
+        "Unexpected error {error:?}"
+    );
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_execute_many_matches_per_call_inserts() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let users: Vec<User> = (0..1000)
+        .map(|n| User::new(format!("user{n}"), format!("user{n}@example.com")))
+        .collect();
+    let insert = "INSERT INTO users (id, username, email) VALUES ($1, $2, $3)";
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    let rows_affected = executor
+        .execute_many(insert, users.iter().map(|user| (user.id, &user.username, &user.email)))
+        .await
+        .expect("Failed to insert users");
+    assert_eq!(rows_affected, 1000);
+    let many: Vec<User> = executor
+        .fetch_all_as(sqlx::query_as("SELECT * FROM users ORDER BY username"))
+        .await
+        .expect("Failed to list users");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    for user in &users {
+        executor
+            .execute(sqlx::query(insert).bind(user.id).bind(&user.username).bind(&user.email))
+            .await
+            .expect("Failed to insert user");
+    }
+    let single: Vec<User> = executor
+        .fetch_all_as(sqlx::query_as("SELECT * FROM users ORDER BY username"))
+        .await
+        .expect("Failed to list users");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert_eq!(many.len(), 1000);
+    assert_eq!(many, single);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_execute_many_reports_the_failing_row() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let duplicate = uuid::Uuid::new_v4();
+    let ids = [uuid::Uuid::new_v4(), duplicate, uuid::Uuid::new_v4(), duplicate, uuid::Uuid::new_v4()];
+    let error = session
+        .executor()
+        .execute_many(
+            "INSERT INTO users (id, username, email) VALUES ($1, $2, $3)",
+            ids.iter().enumerate().map(|(n, id)| (*id, format!("user{n}"), format!("user{n}@example.com"))),
+        )
+        .await
+        .expect_err("The duplicate id should fail");
+    match error {
+        TransactionError::BatchStatementFailed { index, source } => {
+            assert_eq!(index, 3);
+            assert_eq!(source.pg_kind(), Some(PgErrorKind::UniqueViolation));
+        }
+        other => panic!("Expected BatchStatementFailed, got {other:?}"),
+    }
+    assert_eq!(session.stats().statements, 4);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_bulk_insert_inserts_all_rows_in_one_statement() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let users: Vec<User> = (0..10_000)
+        .map(|n| User::new(format!("user{n}"), format!("user{n}@example.com")))
+        .collect();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let inserted = session
+        .executor()
+        .bulk_insert(
+            "users",
+            &["id", "username", "email"],
+            users.iter().map(|user| (user.id, &user.username, &user.email)),
+        )
+        .await
+        .expect("Failed to insert users");
+    assert_eq!(inserted, 10_000);
+    assert_eq!(session.stats().statements, 1);
+    let (count,): (i64,) = session
+        .executor()
+        .fetch_one_as(sqlx::query_as("SELECT count(*) FROM users"))
+        .await
+        .expect("Failed to count users");
+    assert_eq!(count, 10_000);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_bulk_insert_chunks_rows_under_the_parameter_limit() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .execute(sqlx::query(
+            "CREATE TEMP TABLE bulk_items (id INT PRIMARY KEY, quantity INT, name TEXT, sku TEXT) ON COMMIT DROP",
+        ))
+        .await
+        .expect("Failed to create table");
+    // 20000 rows of 4 values are 80000 parameters, more than one statement takes
+    let inserted = executor
+        .bulk_insert(
+            "bulk_items",
+            &["id", "quantity", "name", "sku"],
+            (0..20_000).map(|n| (n, n % 7, format!("item {n}"), format!("sku-{n}"))),
+        )
+        .await
+        .expect("Failed to insert items");
+    assert_eq!(inserted, 20_000);
+    assert_eq!(session.stats().statements, 3);
+    let (count, sum): (i64, i64) = executor
+        .fetch_one_as(sqlx::query_as("SELECT count(*), sum(id) FROM bulk_items"))
+        .await
+        .expect("Failed to count items");
+    assert_eq!(count, 20_000);
+    assert_eq!(sum, (0..20_000i64).sum::<i64>());
+
+    // Conflicting rows are skipped with the option, and fail the insert without
+    let options = BulkInsertOptions::new().on_conflict_do_nothing();
+    let inserted = executor
+        .bulk_insert_with("bulk_items", &["id", "quantity"], [(19_999, 0), (20_000, 0), (20_001, 0)], options)
+        .await
+        .expect("Failed to insert items");
+    assert_eq!(inserted, 2);
+    let error = executor
+        .bulk_insert("bulk_items", &["id", "quantity"], [(30_000, 0), (0, 0)])
+        .await
+        .expect_err("The duplicate id should fail");
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::UniqueViolation));
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_bulk_insert_rejects_rows_not_matching_the_columns() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .execute(sqlx::query("CREATE TEMP TABLE bulk_items (id INT, quantity INT, name TEXT) ON COMMIT DROP"))
+        .await
+        .expect("Failed to create table");
+    let statements = session.stats().statements;
+
+    // Two values per row would put every row after the first off by a column
+    let error = executor
+        .bulk_insert("bulk_items", &["id", "quantity", "name"], [(1, 2), (3, 4), (5, 6)])
+        .await
+        .expect_err("Rows binding too few values should fail");
+    assert!(
+        matches!(&error, TransactionError::DatabaseError(sqlx::Error::Configuration(message))
+            if message.to_string().contains("row 0 bound 2 values, expected one for each of 3 column(s)")),
+        "Unexpected error {error:?}"
+    );
+    let columns = vec!["id"; 65_536];
+    let error = executor
+        .bulk_insert("bulk_items", &columns, [(1,)])
+        .await
+        .expect_err("More columns than bind parameters should fail");
+    assert!(
+        matches!(&error, TransactionError::DatabaseError(sqlx::Error::Configuration(_))),
+        "Unexpected error {error:?}"
+    );
+    assert_eq!(session.stats().statements, statements, "Nothing should be sent");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// A row of any number of values
+struct Values(Vec<i32>);
+
+impl<'q> BindRow<'q> for Values {
+    fn bind_to(self, query: Query<'q, Postgres, PgArguments>) -> Query<'q, Postgres, PgArguments> {
+        self.0.into_iter().fold(query, |query, value| query.bind(value))
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_bulk_insert_checks_the_values_of_each_row() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .execute(sqlx::query("CREATE TEMP TABLE bulk_items (id INT, quantity INT, price INT) ON COMMIT DROP"))
+        .await
+        .expect("Failed to create table");
+    let statements = session.stats().statements;
+
+    // The second row's missing value and the third row's extra one add up to
+    // the values of three rows
+    let rows = [Values(vec![1, 2, 3]), Values(vec![4, 5]), Values(vec![6, 7, 8, 9])];
+    let error = executor
+        .bulk_insert("bulk_items", &["id", "quantity", "price"], rows)
+        .await
+        .expect_err("A row binding too few values should fail");
+    assert!(
+        matches!(&error, TransactionError::DatabaseError(sqlx::Error::Configuration(message))
+            if message.to_string().contains("row 1 bound 2 values")),
+        "Unexpected error {error:?}"
+    );
+    assert_eq!(session.stats().statements, statements, "Nothing should be sent");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_decode_error_names_the_column_and_types() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    let sql = "SELECT 'Lamp' AS product_name, 4200::int8 AS amount";
+    let error = executor
+        .fetch_one_as(sqlx::query_as::<_, (String, i32)>(sql))
+        .await
+        .expect_err("An int8 column does not decode into an i32");
+    match &error {
+        TransactionError::Decode { column, rust_type, pg_type, sql: statement, .. } => {
+            assert_eq!(column, "amount");
+            assert_eq!(rust_type.as_deref(), Some("i32"));
+            assert_eq!(pg_type, "INT8");
+            assert_eq!(statement, sql);
+        }
+        other => panic!("Expected Decode, got {other:?}"),
+    }
+    assert!(error.to_string().contains("amount"), "Unexpected message {error}");
+
+    let (amount,): (i64,) = executor
+        .fetch_one_as(sqlx::query_as("SELECT 4200::int8"))
+        .await
+        .expect("A decode error leaves the transaction usable");
+    assert_eq!(amount, 4200);
+
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// An argument-free repository function running on the attached session
+async fn current_backend_pid() -> Result<i32, TransactionError> {
+    let (pid,): (i32,) = Executor::current()?
+        .fetch_one_as(sqlx::query_as("SELECT pg_backend_pid()"))
+        .await?;
+    Ok(pid)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_attached_session_is_the_current_executor() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let outer = uow.begin().await.expect("Failed to begin transaction");
+    let inner = uow.begin().await.expect("Failed to begin transaction");
+    let outer_pid = outer.backend_pid().await.expect("Failed to get backend pid");
+    let inner_pid = inner.backend_pid().await.expect("Failed to get backend pid");
+
+    let error = current_backend_pid().await.expect_err("No session is attached yet");
+    assert!(matches!(error, TransactionError::NoCurrentSession), "Unexpected error {error:?}");
+
+    outer
+        .attach(async {
+            assert_eq!(current_backend_pid().await.expect("Outer session is attached"), outer_pid);
+
+            // The innermost attachment wins until it ends
+            inner
+                .attach(async {
+                    assert_eq!(current_backend_pid().await.expect("Inner session is attached"), inner_pid);
+                })
+                .await;
+            assert_eq!(current_backend_pid().await.expect("Outer session is attached again"), outer_pid);
+
+            // Spawned tasks do not inherit the attachment
+            let spawned = tokio::spawn(current_backend_pid()).await.expect("Spawned task panicked");
+            assert!(
+                matches!(spawned, Err(TransactionError::NoCurrentSession)),
+                "Unexpected result {spawned:?}"
+            );
+        })
+        .await;
+
+    let error = current_backend_pid().await.expect_err("The attachment has ended");
+    assert!(matches!(error, TransactionError::NoCurrentSession), "Unexpected error {error:?}");
+
+    inner.rollback().await.expect("Failed to rollback transaction");
+    outer.rollback().await.expect("Failed to rollback transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,131 @@
+mod common;
+
+use postgres_unit_of_work::idempotency::{purge_expired_keys, IDEMPOTENCY_MIGRATION};
+use postgres_unit_of_work::{IdempotencyOutcome, PostgresUnitOfWork, UnitOfWork, UnitOfWorkSession};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use common::connect;
+
+const TTL: Duration = Duration::from_secs(3600);
+
+async fn setup_idempotency_keys() -> PgPool {
+    let pool = connect().await;
+    sqlx::raw_sql(IDEMPOTENCY_MIGRATION)
+        .execute(&pool)
+        .await
+        .expect("Failed to create the idempotency keys");
+    sqlx::query("DELETE FROM uow_idempotency_keys")
+        .execute(&pool)
+        .await
+        .expect("Failed to clean the idempotency keys");
+    pool
+}
+
+/// Claim `key` in a unit of work of its own, committing it
+async fn claim(uow: &PostgresUnitOfWork, key: &str, ttl: Duration) -> IdempotencyOutcome {
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let outcome = session.idempotency_guard(key, ttl).await.expect("Failed to claim key");
+    session.commit().await.expect("Failed to commit transaction");
+    outcome
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_second_claim_of_a_key_is_a_duplicate() {
+    let pool = setup_idempotency_keys().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let before = SystemTime::now() - Duration::from_secs(1);
+
+    assert_eq!(claim(&uow, "order-1", TTL).await, IdempotencyOutcome::New);
+    match claim(&uow, "order-1", TTL).await {
+        IdempotencyOutcome::Duplicate { first_seen } => {
+            assert!(first_seen >= before && first_seen <= SystemTime::now(), "Unexpected first_seen {first_seen:?}")
+        }
+        other => panic!("Expected Duplicate, got {other:?}"),
+    }
+    assert_eq!(claim(&uow, "order-2", TTL).await, IdempotencyOutcome::New);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_releases_the_key() {
+    let pool = setup_idempotency_keys().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let outcome = session.idempotency_guard("order-1", TTL).await.expect("Failed to claim key");
+    assert_eq!(outcome, IdempotencyOutcome::New);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert_eq!(claim(&uow, "order-1", TTL).await, IdempotencyOutcome::New);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_expired_keys_can_be_claimed_again_and_purged() {
+    let pool = setup_idempotency_keys().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    assert_eq!(claim(&uow, "order-1", Duration::ZERO).await, IdempotencyOutcome::New);
+    assert_eq!(claim(&uow, "order-1", Duration::ZERO).await, IdempotencyOutcome::New);
+    assert_eq!(claim(&uow, "order-2", TTL).await, IdempotencyOutcome::New);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let purged = purge_expired_keys(session.executor()).await.expect("Failed to purge keys");
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(purged, 1);
+    assert!(claim(&uow, "order-2", TTL).await.is_duplicate());
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial_test::serial]
+async fn test_concurrent_sessions_race_for_a_key() {
+    let pool = setup_idempotency_keys().await;
+    let uow = Arc::new(PostgresUnitOfWork::new(Arc::new(pool.clone())));
+
+    // The second session waits for the first and sees its commit
+    let first = uow.begin().await.expect("Failed to begin transaction");
+    assert_eq!(first.idempotency_guard("order-1", TTL).await.expect("Failed to claim key"), IdempotencyOutcome::New);
+    let racing = tokio::spawn({
+        let uow = uow.clone();
+        async move { claim(&uow, "order-1", TTL).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!racing.is_finished(), "The second session should wait for the first");
+    first.commit().await.expect("Failed to commit transaction");
+    assert!(racing.await.expect("The racing session panicked").is_duplicate());
+
+    // ... and claims the key when the first rolls back
+    let first = uow.begin().await.expect("Failed to begin transaction");
+    assert_eq!(first.idempotency_guard("order-2", TTL).await.expect("Failed to claim key"), IdempotencyOutcome::New);
+    let racing = tokio::spawn({
+        let uow = uow.clone();
+        async move { claim(&uow, "order-2", TTL).await }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    first.rollback().await.expect("Failed to rollback transaction");
+    assert_eq!(racing.await.expect("The racing session panicked"), IdempotencyOutcome::New);
+
+    // Of many sessions claiming the same key at once, exactly one wins
+    let claims = (0..8).map(|_| {
+        let uow = uow.clone();
+        tokio::spawn(async move { claim(&uow, "order-3", TTL).await })
+    });
+    let outcomes = futures::future::join_all(claims).await;
+    let new = outcomes
+        .into_iter()
+        .map(|outcome| outcome.expect("A claiming session panicked"))
+        .filter(|outcome| *outcome == IdempotencyOutcome::New)
+        .count();
+    assert_eq!(new, 1);
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,810 @@
+mod common;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    ExecutorMetrics, LeakPolicy, ObserverErrorPolicy, PgErrorKind, PostgresUnitOfWork, QueryHook, SessionStats,
+    SlowTransaction, TransactionAware, TransactionContext, TransactionError, TransactionOutcome, TransactionResult,
+    UnitOfWork, UnitOfWorkSession,
+};
+use std::fmt::{self, Write};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::field::Field;
+use tracing::span;
+
+use sqlx::PgPool;
+
+use common::{
+    cleanup_database, setup_database, CallLog, Order, OrderRepository, RecordingObserver, User, UserRepository,
+};
+
+/// A statement seen by the recording hook
+#[derive(Debug)]
+struct Recorded {
+    sql: String,
+    duration: Duration,
+    result: Result<u64, Option<PgErrorKind>>,
+}
+
+#[derive(Default)]
+struct RecordingHook {
+    queries: Mutex<Vec<Recorded>>,
+}
+
+impl QueryHook for RecordingHook {
+    fn on_query(&self, sql: &str, duration: Duration, result: Result<u64, &TransactionError>) {
+        self.queries.lock().push(Recorded {
+            sql: sql.to_string(),
+            duration,
+            result: result.map_err(TransactionError::pg_kind),
+        });
+    }
+}
+
+struct PanickingHook;
+
+impl QueryHook for PanickingHook {
+    fn on_query(&self, _sql: &str, _duration: Duration, _result: Result<u64, &TransactionError>) {
+        panic!("query hook failure");
+    }
+}
+
+/// Collects the fields of warnings this crate logs on the current thread
+#[derive(Clone, Default)]
+struct WarningCollector(Arc<Mutex<Vec<String>>>);
+
+impl tracing::Subscriber for WarningCollector {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        *metadata.level() == tracing::Level::WARN && metadata.target().starts_with("postgres_unit_of_work")
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = String::new();
+        event.record(&mut |field: &Field, value: &dyn fmt::Debug| {
+            let _ = write!(fields, "{}={:?} ", field.name(), value);
+        });
+        self.0.lock().push(fields);
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+/// Records the fields of the spans this crate creates, and for every warning
+/// the span it was logged in
+#[derive(Clone, Default)]
+struct SpanCollector {
+    spans: Arc<Mutex<Vec<String>>>,
+    entered: Arc<Mutex<Vec<u64>>>,
+    warnings: Arc<Mutex<Vec<Warning>>>,
+}
+
+/// A warning seen by the span collector
+#[derive(Debug)]
+struct Warning {
+    span: Option<u64>,
+    fields: String,
+}
+
+impl tracing::Subscriber for SpanCollector {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        if metadata.is_span() {
+            // The `tracing` feature's lifecycle spans are covered by tracing_test
+            metadata.target().starts_with("postgres_unit_of_work") && !metadata.name().starts_with("uow.")
+        } else {
+            *metadata.level() == tracing::Level::WARN
+        }
+    }
+
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        let mut fields = format!("{} ", span.metadata().name());
+        span.record(&mut |field: &Field, value: &dyn fmt::Debug| {
+            let _ = write!(fields, "{}={:?} ", field.name(), value);
+        });
+        let mut spans = self.spans.lock();
+        spans.push(fields);
+        span::Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = String::new();
+        event.record(&mut |field: &Field, value: &dyn fmt::Debug| {
+            let _ = write!(fields, "{}={:?} ", field.name(), value);
+        });
+        let span = self.entered.lock().last().copied();
+        self.warnings.lock().push(Warning { span, fields });
+    }
+
+    fn enter(&self, span: &span::Id) {
+        self.entered.lock().push(span.into_u64());
+    }
+
+    fn exit(&self, _span: &span::Id) {
+        self.entered.lock().pop();
+    }
+}
+
+/// Logs a warning whenever it is notified
+struct WarningObserver;
+
+#[async_trait]
+impl TransactionAware for WarningObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        tracing::warn!("Observer notified of the commit");
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        tracing::warn!("Observer notified of the rollback");
+        Ok(())
+    }
+}
+
+/// Keeps the context of the rollback it is notified of
+#[derive(Default)]
+struct ContextObserver {
+    context: Mutex<Option<TransactionContext>>,
+}
+
+#[async_trait]
+impl TransactionAware for ContextObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_rollback_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        *self.context.lock() = Some(context.clone());
+        Ok(())
+    }
+}
+
+/// A unit of work reporting transactions open for longer than 50ms
+fn slow_transaction_uow(pool: &PgPool) -> (PostgresUnitOfWork, Arc<Mutex<Vec<SlowTransaction>>>) {
+    let reports: Arc<Mutex<Vec<SlowTransaction>>> = Arc::default();
+    let recorded = reports.clone();
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .slow_transaction_threshold(Duration::from_millis(50))
+        .on_slow_transaction(move |report| recorded.lock().push(report.clone()))
+        .build();
+    (uow, reports)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_query_hook_sees_repository_statements_in_order() {
+    let pool = setup_database().await;
+    let hook = Arc::new(RecordingHook::default());
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .query_hook(hook.clone())
+        .build();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let order_repo = OrderRepository::new(session.executor().clone());
+
+    let user = User::new("hooked".to_string(), "hooked@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+    order_repo
+        .create(&Order::new(user.id, "Widget".to_string(), 10))
+        .await
+        .expect("Failed to create order");
+    order_repo
+        .create(&Order::new(user.id, "Gadget".to_string(), 20))
+        .await
+        .expect("Failed to create order");
+    let orders = order_repo.find_by_user(user.id).await.expect("Failed to find orders");
+    assert_eq!(orders.len(), 2);
+    assert!(user_repo.find_by_id(user.id).await.expect("Failed to find user").is_some());
+    let error = user_repo.create(&user).await.expect_err("Duplicate user should fail");
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::UniqueViolation));
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let queries = std::mem::take(&mut *hook.queries.lock());
+    let statements: Vec<_> = queries.iter().map(|query| query.sql.split_whitespace().next().unwrap()).collect();
+    assert_eq!(statements, ["INSERT", "INSERT", "INSERT", "SELECT", "SELECT", "INSERT"]);
+    assert!(queries[0].sql.contains("INTO users"), "Unexpected SQL {}", queries[0].sql);
+    assert!(queries[1].sql.contains("INTO orders"), "Unexpected SQL {}", queries[1].sql);
+    assert!(queries[3].sql.contains("FROM orders"), "Unexpected SQL {}", queries[3].sql);
+    assert!(queries[4].sql.contains("FROM users"), "Unexpected SQL {}", queries[4].sql);
+    let results: Vec<_> = queries.iter().map(|query| query.result.clone()).collect();
+    assert_eq!(results, [Ok(1), Ok(1), Ok(1), Ok(2), Ok(1), Err(Some(PgErrorKind::UniqueViolation))]);
+    for query in &queries {
+        assert!(query.duration > Duration::ZERO, "Implausible duration {query:?}");
+        assert!(query.duration < Duration::from_secs(5), "Implausible duration {query:?}");
+    }
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_query_hook_reports_batches_and_streams() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    let hook = Arc::new(RecordingHook::default());
+    executor.set_query_hook(hook.clone());
+
+    executor
+        .execute_batch("CREATE TEMP TABLE hooked (n int); INSERT INTO hooked SELECT generate_series(1, 3)")
+        .await
+        .expect("Failed to run batch");
+    let rows = executor.fetch_stream(sqlx::query("SELECT n FROM hooked")).count().await;
+    assert_eq!(rows, 3);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let queries = std::mem::take(&mut *hook.queries.lock());
+    assert_eq!(queries.len(), 2, "Unexpected queries {queries:?}");
+    assert!(queries[0].sql.starts_with("CREATE TEMP TABLE"), "Unexpected SQL {}", queries[0].sql);
+    assert_eq!(queries[0].result, Ok(3));
+    assert_eq!(queries[1].sql, "SELECT n FROM hooked");
+    assert_eq!(queries[1].result, Ok(3));
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_panicking_query_hook_does_not_fail_the_query() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .query_hook(Arc::new(PanickingHook))
+        .build();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    let user = User::new("panicky".to_string(), "panicky@example.com".to_string());
+    user_repo.create(&user).await.expect("A panicking hook should not fail the query");
+    session.commit().await.expect("Failed to commit transaction");
+
+    let row: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to count users");
+    assert_eq!(row.0, 1);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_slow_query_threshold_warns_and_timing_sees_every_query() {
+    let pool = setup_database().await;
+    let timings: Arc<Mutex<Vec<(String, Duration)>>> = Arc::default();
+    let recorded = timings.clone();
+    let metrics = ExecutorMetrics::default()
+        .on_query_timing(move |fingerprint, elapsed| recorded.lock().push((fingerprint.to_string(), elapsed)))
+        .slow_query_threshold(Duration::from_millis(100));
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .executor_metrics(metrics)
+        .build();
+    let warnings = WarningCollector::default();
+    let _subscriber = tracing::subscriber::set_default(warnings.clone());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    executor
+        .fetch_one(sqlx::query("SELECT pg_sleep(0.2)"))
+        .await
+        .expect("Failed to sleep");
+    executor
+        .fetch_one(sqlx::query("SELECT 1"))
+        .await
+        .expect("Failed to select");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let timings = std::mem::take(&mut *timings.lock());
+    assert_eq!(timings.len(), 2, "Unexpected timings {timings:?}");
+    assert_eq!(timings[0].0, "SELECT pg_sleep(?)");
+    assert!(timings[0].1 >= Duration::from_millis(200), "Implausible elapsed {:?}", timings[0].1);
+    assert_eq!(timings[1].0, "SELECT ?");
+    assert!(timings[1].1 < Duration::from_millis(100), "Implausible elapsed {:?}", timings[1].1);
+
+    let warnings = std::mem::take(&mut *warnings.0.lock());
+    assert_eq!(warnings.len(), 1, "Unexpected warnings {warnings:?}");
+    assert!(warnings[0].contains("Slow query"), "Unexpected warning {}", warnings[0]);
+    assert!(warnings[0].contains("sql=SELECT pg_sleep(0.2)"), "Unexpected warning {}", warnings[0]);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_query_timing_fingerprint_strips_literals() {
+    let pool = setup_database().await;
+    let fingerprints: Arc<Mutex<Vec<String>>> = Arc::default();
+    let recorded = fingerprints.clone();
+    let metrics = ExecutorMetrics::default()
+        .on_query_timing(move |fingerprint, _| recorded.lock().push(fingerprint.to_string()));
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .executor_metrics(metrics)
+        .build();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let query = sqlx::query("SELECT  'it''s' AS \"col 1\",\n  42, 1.5, $1::int4 AS n2").bind(7);
+    session.executor().fetch_one(query).await.expect("Failed to select");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert_eq!(*fingerprints.lock(), ["SELECT ? AS \"col 1\", ?, ?, $1::int4 AS n2"]);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_slow_transaction_is_reported_at_commit() {
+    let pool = setup_database().await;
+    let (uow, reports) = slow_transaction_uow(&pool);
+    let warnings = WarningCollector::default();
+    let _subscriber = tracing::subscriber::set_default(warnings.clone());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session.set_label("monthly-report");
+    let user_repo = UserRepository::new(session.executor().clone());
+    user_repo
+        .create(&User::new("slow".to_string(), "slow@example.com".to_string()))
+        .await
+        .expect("Failed to create user");
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    user_repo
+        .create(&User::new("slower".to_string(), "slower@example.com".to_string()))
+        .await
+        .expect("Failed to create user");
+    session.commit().await.expect("Failed to commit transaction");
+
+    let reports = std::mem::take(&mut *reports.lock());
+    assert_eq!(reports.len(), 1, "Unexpected reports {reports:?}");
+    let report = &reports[0];
+    assert_eq!(report.session_id, id);
+    assert_eq!(report.label.as_deref(), Some("monthly-report"));
+    assert_eq!(report.statements, 2);
+    assert!(report.elapsed >= Duration::from_millis(80), "Implausible elapsed {:?}", report.elapsed);
+    assert_eq!(report.outcome, Some(TransactionOutcome::Committed));
+
+    let warnings = std::mem::take(&mut *warnings.0.lock());
+    assert_eq!(warnings.len(), 1, "Unexpected warnings {warnings:?}");
+    assert!(warnings[0].contains("Slow transaction"), "Unexpected warning {}", warnings[0]);
+    assert!(warnings[0].contains(&format!("session_id={id}")), "Unexpected warning {}", warnings[0]);
+    assert!(warnings[0].contains("statements=2"), "Unexpected warning {}", warnings[0]);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_slow_transaction_is_reported_when_dropped() {
+    let pool = setup_database().await;
+    let (uow, reports) = slow_transaction_uow(&pool);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session
+        .executor()
+        .execute(sqlx::query("SELECT 1"))
+        .await
+        .expect("Failed to select");
+    tokio::time::sleep(Duration::from_millis(80)).await;
+    drop(session);
+
+    let reports = std::mem::take(&mut *reports.lock());
+    assert_eq!(reports.len(), 1, "Unexpected reports {reports:?}");
+    assert_eq!(reports[0].session_id, id);
+    assert_eq!(reports[0].label, None);
+    assert_eq!(reports[0].statements, 1);
+    assert!(reports[0].elapsed >= Duration::from_millis(80), "Implausible elapsed {:?}", reports[0].elapsed);
+    assert_eq!(reports[0].outcome, None);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_fast_transaction_is_not_reported() {
+    let pool = setup_database().await;
+    let (uow, reports) = slow_transaction_uow(&pool);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .executor()
+        .execute(sqlx::query("SELECT 1"))
+        .await
+        .expect("Failed to select");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let reports = std::mem::take(&mut *reports.lock());
+    assert!(reports.is_empty(), "Unexpected reports {reports:?}");
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_slow_commit_and_rollback_are_reported_past_the_threshold() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .slow_commit_threshold(Duration::ZERO)
+        .build();
+    let warnings = WarningCollector::default();
+    let _subscriber = tracing::subscriber::set_default(warnings.clone());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let committed = session.id();
+    session.set_label("checkout");
+    session.commit().await.expect("Failed to commit transaction");
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let rolled_back = session.id();
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let logged = std::mem::take(&mut *warnings.0.lock());
+    assert_eq!(logged.len(), 2, "Unexpected warnings {logged:?}");
+    assert!(logged[0].contains("Slow commit"), "Unexpected warning {}", logged[0]);
+    assert!(logged[0].contains(&format!("session_id={committed}")), "Unexpected warning {}", logged[0]);
+    assert!(logged[0].contains("label=Some(\"checkout\")"), "Unexpected warning {}", logged[0]);
+    assert!(logged[0].contains("statement=\"COMMIT\""), "Unexpected warning {}", logged[0]);
+    assert!(logged[1].contains("Slow rollback"), "Unexpected warning {}", logged[1]);
+    assert!(logged[1].contains(&format!("session_id={rolled_back}")), "Unexpected warning {}", logged[1]);
+
+    // The slow transaction threshold is independent of it
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .slow_transaction_threshold(Duration::ZERO)
+        .build();
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.commit().await.expect("Failed to commit transaction");
+    let logged = std::mem::take(&mut *warnings.0.lock());
+    assert_eq!(logged.len(), 1, "Unexpected warnings {logged:?}");
+    assert!(logged[0].contains("Slow transaction"), "Unexpected warning {}", logged[0]);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_session_stats_count_every_helper() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let observer = Arc::new(ContextObserver::default());
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    let executor = session.executor().clone();
+    let user_repo = UserRepository::new(executor.clone());
+    assert_eq!(session.stats(), SessionStats::default());
+
+    // 2 statements, 2 rows affected
+    for name in ["alice", "bob"] {
+        let user = User::new(name.to_string(), format!("{name}@example.com"));
+        user_repo.create(&user).await.expect("Failed to create user");
+    }
+    // 1 statement, 2 rows affected
+    executor
+        .execute(sqlx::query("UPDATE users SET email = lower(email)"))
+        .await
+        .expect("Failed to update users");
+    // 2 statements, 2 rows fetched
+    let users = executor
+        .fetch_all(sqlx::query("SELECT id FROM users"))
+        .await
+        .expect("Failed to fetch users");
+    assert_eq!(users.len(), 2);
+    let missing = executor
+        .fetch_optional(sqlx::query("SELECT id FROM users WHERE username = 'nobody'"))
+        .await
+        .expect("Failed to fetch user");
+    assert!(missing.is_none());
+    // 2 statements, 3 rows affected
+    executor
+        .execute_batch("CREATE TEMP TABLE counted (n int4); INSERT INTO counted VALUES (1), (2), (3)")
+        .await
+        .expect("Failed to run batch");
+    // 2 statements, 2 rows affected
+    executor
+        .execute_pipelined(vec![
+            sqlx::query("INSERT INTO counted VALUES ($1)").bind(4),
+            sqlx::query("INSERT INTO counted VALUES ($1)").bind(5),
+        ])
+        .await
+        .expect("Failed to run pipelined queries");
+    // 1 statement, 4 rows affected
+    let mut sink = executor
+        .copy_in("COPY counted (n) FROM STDIN WITH (FORMAT csv)")
+        .await
+        .expect("Failed to start copy");
+    sink.send("6\n7\n8\n9\n").await.expect("Failed to send rows");
+    assert_eq!(sink.finish().await.expect("Failed to finish copy"), 4);
+    // 1 statement, 9 rows fetched
+    let streamed = executor.fetch_stream(sqlx::query("SELECT n FROM counted")).count().await;
+    assert_eq!(streamed, 9);
+    // 1 statement; the copied data is not counted as rows
+    let chunks: Vec<_> = executor
+        .copy_out("COPY counted TO STDOUT")
+        .await
+        .expect("Failed to start copy")
+        .collect()
+        .await;
+    assert!(chunks.iter().all(Result::is_ok));
+    // 1 failed statement
+    executor
+        .execute(sqlx::query("SELECT 1 / 0"))
+        .await
+        .expect_err("Division by zero should fail");
+
+    let stats = session.stats();
+    assert_eq!((stats.statements, stats.rows_affected, stats.rows_fetched), (13, 13, 11));
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let context = observer.context.lock().clone().expect("The observer should see the rollback");
+    assert_eq!(context.stats, stats);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_session_id_is_stable_and_tags_its_span() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let collector = SpanCollector::default();
+    let _subscriber = tracing::subscriber::set_default(collector.clone());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session
+        .register_transaction_aware(Arc::new(WarningObserver))
+        .await
+        .expect("Failed to register observer");
+    UserRepository::new(session.executor().clone())
+        .create(&User::new("traced".to_string(), "traced@example.com".to_string()))
+        .await
+        .expect("Failed to create user");
+    assert_eq!(session.id(), id);
+    let other = uow.begin().await.expect("Failed to begin transaction");
+    let other_id = other.id();
+    assert_ne!(other_id, id);
+    other
+        .register_transaction_aware(Arc::new(WarningObserver))
+        .await
+        .expect("Failed to register observer");
+    other.rollback().await.expect("Failed to rollback transaction");
+    session.commit().await.expect("Failed to commit transaction");
+
+    let spans = std::mem::take(&mut *collector.spans.lock());
+    assert_eq!(
+        spans,
+        [
+            format!("unit_of_work_session session_id={id} "),
+            format!("unit_of_work_session session_id={other_id} "),
+        ]
+    );
+    let warnings = std::mem::take(&mut *collector.warnings.lock());
+    assert_eq!(warnings.len(), 2, "Unexpected warnings {warnings:?}");
+    assert_eq!(warnings[0].span, Some(2), "The rollback runs in the other session's span");
+    assert!(warnings[0].fields.contains("rollback"), "Unexpected warning {}", warnings[0].fields);
+    assert_eq!(warnings[1].span, Some(1), "The commit runs in the session's span");
+    assert!(warnings[1].fields.contains("commit"), "Unexpected warning {}", warnings[1].fields);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_leaked_session_is_reported() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let warnings = WarningCollector::default();
+    let _subscriber = tracing::subscriber::set_default(warnings.clone());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session.set_label("forgotten");
+    session.set_metadata("user_id", "u-1");
+    drop(session);
+    // Completed sessions are not leaks
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.commit().await.expect("Failed to commit transaction");
+
+    let warnings = std::mem::take(&mut *warnings.0.lock());
+    assert_eq!(warnings.len(), 1, "Unexpected warnings {warnings:?}");
+    assert!(warnings[0].contains("Session dropped without commit or rollback"), "Unexpected warning {}", warnings[0]);
+    assert!(warnings[0].contains(&format!("session_id={id}")), "Unexpected warning {}", warnings[0]);
+    assert!(warnings[0].contains("label=Some(\"forgotten\")"), "Unexpected warning {}", warnings[0]);
+    assert!(warnings[0].contains("metadata={\"user_id\": \"u-1\"}"), "Unexpected warning {}", warnings[0]);
+    assert!(warnings[0].contains("elapsed="), "Unexpected warning {}", warnings[0]);
+
+    // The Ignore policy drops sessions silently
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .leak_policy(LeakPolicy::Ignore)
+        .build();
+    let warnings = WarningCollector::default();
+    let _subscriber = tracing::subscriber::set_default(warnings.clone());
+    drop(uow.begin().await.expect("Failed to begin transaction"));
+    assert!(warnings.0.lock().is_empty(), "Unexpected warnings {:?}", warnings.0.lock());
+
+    cleanup_database(&pool).await;
+}
+
+#[cfg(debug_assertions)]
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+#[should_panic(expected = "dropped without commit or rollback")]
+async fn test_leaked_session_panics_in_debug() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .leak_policy(LeakPolicy::PanicInDebug)
+        .build();
+
+    drop(uow.begin().await.expect("Failed to begin transaction"));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_commit_report_describes_the_session() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .observer_error_policy(ObserverErrorPolicy::LogAndIgnore)
+        .build();
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session.set_label("signup");
+    session
+        .on_commit_named("flush-cache", || async { Ok(()) })
+        .expect("Failed to register hook");
+    session
+        .register_transaction_aware(RecordingObserver::failing("audit", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    let user_repo = UserRepository::new(session.executor().clone());
+    for name in ["alice", "bob"] {
+        user_repo
+            .create(&User::new(name.to_string(), format!("{name}@example.com")))
+            .await
+            .expect("Failed to create user");
+    }
+    let users = session
+        .executor()
+        .fetch_all(sqlx::query("SELECT * FROM users"))
+        .await
+        .expect("Failed to fetch users");
+    assert_eq!(users.len(), 2);
+    let report = session.commit_with_report().await.expect("Failed to commit transaction");
+
+    assert_eq!(report.session_id, id);
+    assert_eq!(report.label.as_deref(), Some("signup"));
+    assert!(report.duration > Duration::ZERO);
+    assert!(
+        report.commit_duration > Duration::ZERO && report.commit_duration < report.duration,
+        "Implausible commit duration {:?} of {:?}",
+        report.commit_duration,
+        report.duration
+    );
+    assert_eq!(report.stats.statements, 3);
+    assert_eq!(report.stats.rows_affected, 2);
+    assert_eq!(report.stats.rows_fetched, 2);
+    assert_eq!(report.observers.len(), 2, "Unexpected observers {:?}", report.observers);
+    assert_eq!(report.observers[0], "flush-cache");
+    assert!(report.observers[1].ends_with("RecordingObserver"), "Unexpected observers {:?}", report.observers);
+    assert_eq!(report.observer_errors.len(), 1, "Unexpected errors {:?}", report.observer_errors);
+    assert!(
+        matches!(&report.observer_errors[0], TransactionError::ObserverFailed { observer, .. } if observer.ends_with("RecordingObserver")),
+        "Unexpected error {:?}",
+        report.observer_errors[0]
+    );
+    assert_eq!(*log.lock(), vec!["audit:commit"]);
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_long_transaction_watchdog_fires_while_open() {
+    let pool = setup_database().await;
+    let reports: Arc<Mutex<Vec<SlowTransaction>>> = Arc::default();
+    let recorded = reports.clone();
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .long_transaction_threshold(Duration::from_millis(50))
+        .on_long_transaction(move |report| recorded.lock().push(report.clone()))
+        .build();
+
+    // A fast session completes before the watchdog fires
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.commit().await.expect("Failed to commit transaction");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(reports.lock().is_empty(), "Unexpected reports {:?}", reports.lock());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session.set_label("nightly-import");
+    session.set_metadata("job", "import");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    {
+        let reports = reports.lock();
+        assert_eq!(reports.len(), 1, "Unexpected reports {reports:?}");
+        assert_eq!(reports[0].session_id, id);
+        assert_eq!(reports[0].label.as_deref(), Some("nightly-import"));
+        assert_eq!(reports[0].metadata.get("job").map(String::as_str), Some("import"));
+        assert!(reports[0].elapsed >= Duration::from_millis(50), "Implausible elapsed {:?}", reports[0].elapsed);
+        assert_eq!(reports[0].outcome, None);
+    }
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    // With an interval the warning repeats until the session completes
+    let reports: Arc<Mutex<Vec<SlowTransaction>>> = Arc::default();
+    let recorded = reports.clone();
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .long_transaction_threshold(Duration::from_millis(50))
+        .long_transaction_interval(Duration::from_millis(50))
+        .on_long_transaction(move |report| recorded.lock().push(report.clone()))
+        .build();
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    session.commit().await.expect("Failed to commit transaction");
+    let fired = reports.lock().len();
+    assert!(fired >= 2, "The warning should repeat, got {fired}");
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(reports.lock().len(), fired, "The watchdog should stop at commit");
+
+    cleanup_database(&pool).await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_backend_pid_matches_pg_stat_activity() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let observer = Arc::new(ContextObserver::default());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    let application_name = format!("uow-{}", session.id().simple());
+    session
+        .executor()
+        .execute(sqlx::query(&format!("SET LOCAL application_name = '{application_name}'")))
+        .await
+        .expect("Failed to set application_name");
+    let pid = session.backend_pid().await.expect("Failed to get the backend pid");
+    let statements = session.stats().statements;
+    assert_eq!(session.backend_pid().await.expect("Failed to get the backend pid"), pid);
+    assert_eq!(session.stats().statements, statements, "The pid should be cached");
+
+    let active: i32 = sqlx::query_scalar("SELECT pid FROM pg_stat_activity WHERE application_name = $1")
+        .bind(&application_name)
+        .fetch_one(&pool)
+        .await
+        .expect("The session's connection should be in pg_stat_activity");
+    assert_eq!(active, pid);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let context = observer.context.lock().clone().expect("The observer should see the rollback");
+    assert_eq!(context.backend_pid, Some(pid));
+
+    cleanup_database(&pool).await;
+}
\ No newline at end of file
@@ -0,0 +1,278 @@
+mod common;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    PostgresUnitOfWork, PostgresUnitOfWorkSession, SessionInfo, SessionInterceptor, TransactionError, TransactionOptions,
+    TransactionResult, UnitOfWork, UnitOfWorkSession,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{cleanup_database, setup_database, CallLog, User, UserRepository};
+
+/// Interceptor that records its hooks into a shared log
+#[derive(Default)]
+struct RecordingInterceptor {
+    name: &'static str,
+    log: CallLog,
+    /// Setting forced onto every transaction
+    setting: Option<(&'static str, &'static str)>,
+    refuse_begin: bool,
+    refuse_commit: bool,
+}
+
+impl RecordingInterceptor {
+    fn new(name: &'static str, log: CallLog) -> Self {
+        Self {
+            name,
+            log,
+            ..Self::default()
+        }
+    }
+
+    fn record(&self, hook: &str) {
+        self.log.lock().push(format!("{}:{hook}", self.name));
+    }
+}
+
+#[async_trait]
+impl SessionInterceptor for RecordingInterceptor {
+    async fn before_begin(&self, options: &TransactionOptions) -> TransactionResult<TransactionOptions> {
+        self.record("before_begin");
+        if self.refuse_begin {
+            return Err(TransactionError::ShuttingDown);
+        }
+        Ok(match self.setting {
+            Some((name, value)) => options.clone().setting(name, value),
+            None => options.clone(),
+        })
+    }
+
+    async fn after_begin(&self, _session: &SessionInfo) {
+        self.record("after_begin");
+    }
+
+    async fn before_commit(&self, _session: &SessionInfo) -> TransactionResult<()> {
+        self.record("before_commit");
+        if self.refuse_commit {
+            return Err(TransactionError::CommitFailed("outside of the maintenance window".to_string()));
+        }
+        Ok(())
+    }
+
+    async fn after_commit(&self, _session: &SessionInfo) {
+        self.record("after_commit");
+    }
+
+    async fn after_rollback(&self, _session: &SessionInfo) {
+        self.record("after_rollback");
+    }
+}
+
+fn intercepted_uow(pool: &PgPool, outer: RecordingInterceptor, inner: RecordingInterceptor) -> PostgresUnitOfWork {
+    PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .interceptor(Arc::new(outer))
+        .interceptor(Arc::new(inner))
+        .build()
+}
+
+fn take(log: &CallLog) -> Vec<String> {
+    std::mem::take(&mut *log.lock())
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_interceptors_wrap_the_session_in_order() {
+    let pool = setup_database().await;
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let uow = intercepted_uow(
+        &pool,
+        RecordingInterceptor::new("outer", log.clone()),
+        RecordingInterceptor::new("inner", log.clone()),
+    );
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    assert_eq!(
+        take(&log),
+        ["outer:before_begin", "inner:before_begin", "inner:after_begin", "outer:after_begin"]
+    );
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(
+        take(&log),
+        ["outer:before_commit", "inner:before_commit", "inner:after_commit", "outer:after_commit"]
+    );
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    take(&log);
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert_eq!(take(&log), ["inner:after_rollback", "outer:after_rollback"]);
+
+    // Dropped sessions are rolled back from a spawned task
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    take(&log);
+    drop(session);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(take(&log), ["inner:after_rollback", "outer:after_rollback"]);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// Kill the connection of `session` from another connection, waiting until it is gone
+async fn terminate_backend(pool: &PgPool, session: &PostgresUnitOfWorkSession) {
+    let pid = session.backend_pid().await.expect("Failed to read backend pid");
+    let terminated: bool = sqlx::query_scalar("SELECT pg_terminate_backend($1, 5000)")
+        .bind(pid)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to terminate backend");
+    assert!(terminated, "Backend should be terminated");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_interceptors_see_the_session_end_when_rollback_fails() {
+    let pool = setup_database().await;
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let uow = intercepted_uow(
+        &pool,
+        RecordingInterceptor::new("outer", log.clone()),
+        RecordingInterceptor::new("inner", log.clone()),
+    );
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    terminate_backend(&pool, &session).await;
+    take(&log);
+    session.rollback().await.expect_err("Rollback on a dead connection should fail");
+    assert_eq!(take(&log), ["inner:after_rollback", "outer:after_rollback"]);
+
+    // Also when a dropped session fails to roll back
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    terminate_backend(&pool, &session).await;
+    take(&log);
+    drop(session);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(take(&log), ["inner:after_rollback", "outer:after_rollback"]);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_before_begin_changes_the_options() {
+    let pool = setup_database().await;
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let uow = intercepted_uow(
+        &pool,
+        RecordingInterceptor {
+            setting: Some(("application_name", "overridden")),
+            ..RecordingInterceptor::new("outer", log.clone())
+        },
+        RecordingInterceptor {
+            setting: Some(("application_name", "o'brien's service")),
+            ..RecordingInterceptor::new("inner", log.clone())
+        },
+    );
+
+    // The last interceptor has the final say, and the caller's options are kept
+    let options = TransactionOptions::new().setting("app.tenant_id", "acme");
+    let session = uow.begin_with(options).await.expect("Failed to begin transaction");
+    let (application_name, tenant): (String, String) = session
+        .executor()
+        .fetch_one_as(sqlx::query_as("SELECT current_setting('application_name'), current_setting('app.tenant_id')"))
+        .await
+        .expect("Failed to read settings");
+    assert_eq!(application_name, "o'brien's service");
+    assert_eq!(tenant, "acme");
+    session.commit().await.expect("Failed to commit transaction");
+
+    // The settings only last for the transaction
+    let application_name: String = sqlx::query_scalar("SELECT current_setting('application_name')")
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to read settings");
+    assert_ne!(application_name, "o'brien's service");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_failing_before_begin_aborts_the_begin() {
+    let pool = setup_database().await;
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let uow = intercepted_uow(
+        &pool,
+        RecordingInterceptor {
+            refuse_begin: true,
+            ..RecordingInterceptor::new("outer", log.clone())
+        },
+        RecordingInterceptor::new("inner", log.clone()),
+    );
+
+    match uow.begin().await {
+        Err(TransactionError::ShuttingDown) => {}
+        Err(other) => panic!("Expected the interceptor's error, got {other:?}"),
+        Ok(_) => panic!("The interceptor should refuse the session"),
+    }
+    assert_eq!(take(&log), ["outer:before_begin"]);
+    assert_eq!(uow.active_count(), 0);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_failing_before_commit_rolls_back() {
+    let pool = setup_database().await;
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let uow = intercepted_uow(
+        &pool,
+        RecordingInterceptor::new("outer", log.clone()),
+        RecordingInterceptor {
+            refuse_commit: true,
+            ..RecordingInterceptor::new("inner", log.clone())
+        },
+    );
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user = User::new("intercepted".to_string(), "intercepted@example.com".to_string());
+    UserRepository::new(session.executor().clone())
+        .create(&user)
+        .await
+        .expect("Failed to create user");
+    take(&log);
+
+    let error = session.commit().await.expect_err("Commit should be vetoed");
+    match error {
+        TransactionError::CommitVetoed {
+            source,
+            rollback_error,
+            observer_error,
+        } => {
+            assert!(matches!(*source, TransactionError::CommitFailed(_)), "Unexpected veto {source:?}");
+            assert!(rollback_error.is_none(), "Rollback should succeed, got {rollback_error:?}");
+            assert!(observer_error.is_none(), "Observers should succeed, got {observer_error:?}");
+        }
+        other => panic!("Expected CommitVetoed, got {other:?}"),
+    }
+    assert_eq!(
+        take(&log),
+        ["outer:before_commit", "inner:before_commit", "inner:after_rollback", "outer:after_rollback"]
+    );
+
+    let found: Option<(String,)> = sqlx::query_as("SELECT username FROM users WHERE id = $1")
+        .bind(user.id)
+        .fetch_optional(&pool)
+        .await
+        .expect("Failed to query user");
+    assert!(found.is_none(), "User should not exist after the veto");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,158 @@
+mod common;
+
+use postgres_unit_of_work::jobs::{claim_jobs, JOBS_CHANNEL, JOBS_MIGRATION};
+use postgres_unit_of_work::{PostgresUnitOfWork, PostgresUnitOfWorkSession, UnitOfWork, UnitOfWorkSession};
+use sqlx::postgres::{PgListener, PgNotification};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use common::connect;
+
+async fn setup_jobs() -> PgPool {
+    let pool = connect().await;
+    sqlx::raw_sql(JOBS_MIGRATION)
+        .execute(&pool)
+        .await
+        .expect("Failed to create the jobs table");
+    sqlx::query("DELETE FROM uow_jobs")
+        .execute(&pool)
+        .await
+        .expect("Failed to clean the jobs table");
+    pool
+}
+
+async fn listen(pool: &PgPool) -> PgListener {
+    let mut listener = PgListener::connect_with(pool).await.expect("Failed to connect listener");
+    listener.listen(JOBS_CHANNEL).await.expect("Failed to listen");
+    listener
+}
+
+/// Wait briefly for the next notification, if any
+async fn next_notification(listener: &mut PgListener) -> Option<PgNotification> {
+    tokio::time::timeout(Duration::from_millis(200), listener.recv())
+        .await
+        .ok()
+        .map(|notification| notification.expect("Failed to receive notification"))
+}
+
+async fn queued(pool: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM uow_jobs")
+        .fetch_one(pool)
+        .await
+        .expect("Failed to count jobs")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_committed_jobs_wake_listening_workers() {
+    let pool = setup_jobs().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let mut listener = listen(&pool).await;
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let now = SystemTime::now();
+    session.enqueue_job("emails", b"welcome alice", now).await.expect("Failed to enqueue job");
+    session.enqueue_job("emails", b"welcome bob", now).await.expect("Failed to enqueue job");
+    session.enqueue_job("invoices", b"invoice 7", now).await.expect("Failed to enqueue job");
+    assert!(next_notification(&mut listener).await.is_none(), "Workers are not woken before the commit");
+    session.commit().await.expect("Failed to commit transaction");
+
+    // One wake-up per queue
+    let mut queues = Vec::new();
+    while let Some(notification) = next_notification(&mut listener).await {
+        assert_eq!(notification.channel(), JOBS_CHANNEL);
+        queues.push(notification.payload().to_string());
+    }
+    assert_eq!(queues, ["emails", "invoices"]);
+    assert_eq!(queued(&pool).await, 3);
+
+    drop(listener);
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rolled_back_jobs_are_not_enqueued() {
+    let pool = setup_jobs().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let mut listener = listen(&pool).await;
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .enqueue_job("emails", b"welcome alice", SystemTime::now())
+        .await
+        .expect("Failed to enqueue job");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert!(next_notification(&mut listener).await.is_none());
+    assert_eq!(queued(&pool).await, 0);
+
+    drop(listener);
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_sessions_without_a_pool_notify_on_commit() {
+    let pool = setup_jobs().await;
+    let mut listener = listen(&pool).await;
+
+    let session = PostgresUnitOfWorkSession::new(pool.begin().await.expect("Failed to begin transaction"));
+    session
+        .enqueue_job("emails", b"welcome alice", SystemTime::now())
+        .await
+        .expect("Failed to enqueue job");
+    assert!(next_notification(&mut listener).await.is_none());
+    session.commit().await.expect("Failed to commit transaction");
+
+    let notification = next_notification(&mut listener).await.expect("The workers should be woken");
+    assert_eq!(notification.payload(), "emails");
+
+    drop(listener);
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_claim_jobs_skips_locked_and_future_jobs() {
+    let pool = setup_jobs().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let now = SystemTime::now();
+    let mut ids = Vec::new();
+    for index in 0..4 {
+        let payload = format!("email {index}");
+        ids.push(session.enqueue_job("emails", payload.as_bytes(), now).await.expect("Failed to enqueue job"));
+    }
+    session
+        .enqueue_job("emails", b"later", now + Duration::from_secs(3600))
+        .await
+        .expect("Failed to enqueue job");
+    session.enqueue_job("invoices", b"invoice 7", now).await.expect("Failed to enqueue job");
+    session.commit().await.expect("Failed to commit transaction");
+
+    // Workers side by side get different jobs, earliest first
+    let first = uow.begin().await.expect("Failed to begin transaction");
+    let second = uow.begin().await.expect("Failed to begin transaction");
+    let claimed = claim_jobs(first.executor(), "emails", 3).await.expect("Failed to claim jobs");
+    assert_eq!(claimed.iter().map(|job| job.id).collect::<Vec<_>>(), ids[..3]);
+    assert_eq!(claimed[0].payload, b"email 0");
+    assert!(claimed.iter().all(|job| job.queue == "emails"));
+    let rest = claim_jobs(second.executor(), "emails", 3).await.expect("Failed to claim jobs");
+    assert_eq!(rest.iter().map(|job| job.id).collect::<Vec<_>>(), ids[3..]);
+
+    // A rolled back claim puts the jobs back
+    first.rollback().await.expect("Failed to rollback transaction");
+    second.commit().await.expect("Failed to commit transaction");
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let claimed = claim_jobs(session.executor(), "emails", 10).await.expect("Failed to claim jobs");
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(claimed.iter().map(|job| job.id).collect::<Vec<_>>(), ids[..3]);
+
+    // Only the future email and the invoice are left
+    assert_eq!(queued(&pool).await, 2);
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,170 @@
+mod common;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    PostgresUnitOfWork, TransactionError, TransactionListener, UnitOfWork, UnitOfWorkSession,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+use common::{connect, RecordingObserver};
+
+/// A lifecycle event seen by the recording listener
+#[derive(Debug, PartialEq)]
+enum Event {
+    Begin(Uuid),
+    Commit(Uuid, Duration),
+    Rollback(Uuid, Duration),
+    Error(Uuid, String),
+}
+
+#[derive(Default)]
+struct RecordingListener {
+    events: Mutex<Vec<Event>>,
+}
+
+#[async_trait]
+impl TransactionListener for RecordingListener {
+    async fn on_begin(&self, session_id: Uuid) {
+        self.events.lock().push(Event::Begin(session_id));
+    }
+
+    async fn on_commit(&self, session_id: Uuid, duration: Duration) {
+        self.events.lock().push(Event::Commit(session_id, duration));
+    }
+
+    async fn on_rollback(&self, session_id: Uuid, duration: Duration) {
+        self.events.lock().push(Event::Rollback(session_id, duration));
+    }
+
+    async fn on_error(&self, session_id: Uuid, error: &TransactionError) {
+        self.events.lock().push(Event::Error(session_id, error.to_string()));
+    }
+}
+
+fn listening_uow(pool: &PgPool) -> (PostgresUnitOfWork, Arc<RecordingListener>) {
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let listener = Arc::new(RecordingListener::default());
+    uow.add_listener(listener.clone());
+    (uow, listener)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_listener_sees_begin_and_commit() {
+    let pool = connect().await;
+    let (uow, listener) = listening_uow(&pool);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    assert_eq!(*listener.events.lock(), vec![Event::Begin(id)]);
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    session.commit().await.expect("Failed to commit transaction");
+
+    let events = std::mem::take(&mut *listener.events.lock());
+    assert_eq!(events.len(), 2, "Unexpected events {events:?}");
+    match events[1] {
+        Event::Commit(session_id, duration) => {
+            assert_eq!(session_id, id);
+            assert!(duration >= Duration::from_millis(20), "Implausible duration {duration:?}");
+            assert!(duration < Duration::from_secs(5), "Implausible duration {duration:?}");
+        }
+        ref other => panic!("Expected Commit, got {other:?}"),
+    }
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_listener_sees_begin_and_rollback() {
+    let pool = connect().await;
+    let (uow, listener) = listening_uow(&pool);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let events = std::mem::take(&mut *listener.events.lock());
+    assert_eq!(events.len(), 2, "Unexpected events {events:?}");
+    assert_eq!(events[0], Event::Begin(id));
+    match events[1] {
+        Event::Rollback(session_id, duration) => {
+            assert_eq!(session_id, id);
+            assert!(duration >= Duration::from_millis(20), "Implausible duration {duration:?}");
+        }
+        ref other => panic!("Expected Rollback, got {other:?}"),
+    }
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_listener_sees_commit_errors() {
+    let pool = connect().await;
+    let (uow, listener) = listening_uow(&pool);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session
+        .register_transaction_aware(RecordingObserver::failing("observer", Arc::default()))
+        .await
+        .expect("Failed to register observer");
+    let error = session.commit().await.expect_err("Commit should report the observer failure");
+
+    let events = std::mem::take(&mut *listener.events.lock());
+    assert_eq!(events.len(), 3, "Unexpected events {events:?}");
+    assert!(matches!(events[1], Event::Commit(session_id, _) if session_id == id));
+    assert_eq!(events[2], Event::Error(id, error.to_string()));
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_listener_sees_every_session() {
+    let pool = connect().await;
+    let (uow, listener) = listening_uow(&pool);
+
+    let first = uow.begin().await.expect("Failed to begin transaction");
+    let second = uow.begin().await.expect("Failed to begin transaction");
+    assert_ne!(first.id(), second.id());
+    let (first_id, second_id) = (first.id(), second.id());
+    second.rollback().await.expect("Failed to rollback transaction");
+    first.commit().await.expect("Failed to commit transaction");
+
+    let events = std::mem::take(&mut *listener.events.lock());
+    assert_eq!(events.len(), 4, "Unexpected events {events:?}");
+    assert!(matches!(events[2], Event::Rollback(session_id, _) if session_id == second_id));
+    assert!(matches!(events[3], Event::Commit(session_id, _) if session_id == first_id));
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_session_elapsed_is_reported_at_completion() {
+    let pool = connect().await;
+    let (uow, listener) = listening_uow(&pool);
+
+    let before = SystemTime::now();
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    assert!(session.started_at() >= before, "Implausible start {:?}", session.started_at());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let elapsed = session.elapsed();
+    assert!(elapsed >= Duration::from_millis(100), "Implausible elapsed {elapsed:?}");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let events = std::mem::take(&mut *listener.events.lock());
+    match events[1] {
+        Event::Rollback(_, duration) => assert!(duration >= elapsed, "Implausible duration {duration:?}"),
+        ref other => panic!("Expected Rollback, got {other:?}"),
+    }
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,307 @@
+mod common;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    BeginErrorKind, Executor, PostgresUnitOfWork, RetryPolicy, RollbackReason, TransactionAware,
+    TransactionError, TransactionResult, UnitOfWork, UnitOfWorkSession, UowMetrics,
+};
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::connect;
+
+/// Metrics counting every call, by reason and kind
+#[derive(Default)]
+struct CountingMetrics {
+    counts: Mutex<Counts>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct Counts {
+    begins: u64,
+    commits: u64,
+    statements: u64,
+    rollbacks: BTreeMap<&'static str, u64>,
+    begin_errors: BTreeMap<&'static str, u64>,
+    slow_commits: Vec<Duration>,
+    slow_rollbacks: Vec<Duration>,
+    retries: Vec<(u32, Option<String>)>,
+    retries_exhausted: Vec<u32>,
+    conflicts: Vec<String>,
+}
+
+impl UowMetrics for CountingMetrics {
+    fn record_begin(&self) {
+        self.counts.lock().begins += 1;
+    }
+
+    fn record_commit(&self, _duration: Duration, statements: u64) {
+        let mut counts = self.counts.lock();
+        counts.commits += 1;
+        counts.statements += statements;
+    }
+
+    fn record_rollback(&self, _duration: Duration, reason: RollbackReason) {
+        *self.counts.lock().rollbacks.entry(reason.as_str()).or_default() += 1;
+    }
+
+    fn record_slow_commit(&self, round_trip: Duration) {
+        self.counts.lock().slow_commits.push(round_trip);
+    }
+
+    fn record_slow_rollback(&self, round_trip: Duration) {
+        self.counts.lock().slow_rollbacks.push(round_trip);
+    }
+
+    fn record_begin_error(&self, kind: BeginErrorKind) {
+        *self.counts.lock().begin_errors.entry(kind.as_str()).or_default() += 1;
+    }
+
+    fn record_retry(&self, attempt: u32, sqlstate: Option<&str>) {
+        self.counts.lock().retries.push((attempt, sqlstate.map(str::to_string)));
+    }
+
+    fn record_retry_exhausted(&self, attempts: u32) {
+        self.counts.lock().retries_exhausted.push(attempts);
+    }
+
+    fn record_conflict(&self, sqlstate: &str) {
+        self.counts.lock().conflicts.push(sqlstate.to_string());
+    }
+}
+
+/// Observer refusing every session
+struct Refusing;
+
+#[async_trait]
+impl TransactionAware for Refusing {
+    async fn after_begin(&self, _executor: &Executor) -> TransactionResult<()> {
+        Err(TransactionError::CommitFailed("not today".to_string()))
+    }
+
+    async fn on_commit(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+}
+
+/// Observer vetoing every commit
+struct Vetoing;
+
+#[async_trait]
+impl TransactionAware for Vetoing {
+    async fn before_commit(&self, _executor: &Executor) -> TransactionResult<()> {
+        Err(TransactionError::CommitFailed("not today".to_string()))
+    }
+
+    async fn on_commit(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+}
+
+/// Fail the statement with the condition `condition`, e.g. `serialization_failure`
+async fn raise(executor: &Executor, condition: &str) -> TransactionResult<()> {
+    let sql = format!("DO $$ BEGIN RAISE EXCEPTION 'simulated conflict' USING ERRCODE = '{condition}'; END $$");
+    executor.execute(sqlx::query(&sql)).await?;
+    Ok(())
+}
+
+fn measured_uow(pool: &PgPool) -> (PostgresUnitOfWork, Arc<CountingMetrics>) {
+    let metrics = Arc::new(CountingMetrics::default());
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .metrics(metrics.clone())
+        .build();
+    (uow, metrics)
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_every_ending_is_counted_once() {
+    let pool = connect().await;
+    let (uow, metrics) = measured_uow(&pool);
+
+    for statements in [1, 2] {
+        let session = uow.begin().await.expect("Failed to begin transaction");
+        for _ in 0..statements {
+            session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+        }
+        session.commit().await.expect("Failed to commit transaction");
+    }
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    // A commit whose observers fail afterwards still committed
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .on_commit(|| async { Err(TransactionError::CommitFailed("late".to_string())) })
+        .expect("Failed to add hook");
+    session.commit().await.expect_err("The hook fails after the commit");
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(Arc::new(Vetoing))
+        .await
+        .expect("Failed to register observer");
+    match session.commit().await {
+        Err(TransactionError::CommitVetoed { .. }) => {}
+        other => panic!("Expected CommitVetoed, got {other:?}"),
+    }
+
+    // Dropped sessions are counted by the task rolling them back
+    drop(uow.begin().await.expect("Failed to begin transaction"));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    uow.shutdown(Duration::ZERO).await;
+    match uow.begin().await {
+        Err(TransactionError::ShuttingDown) => {}
+        Err(other) => panic!("Expected ShuttingDown, got {other:?}"),
+        Ok(_) => panic!("Begin should fail once shutting down"),
+    }
+
+    assert_eq!(
+        *metrics.counts.lock(),
+        Counts {
+            begins: 6,
+            commits: 3,
+            statements: 3,
+            rollbacks: BTreeMap::from([("dropped", 1), ("requested", 1), ("vetoed", 1)]),
+            begin_errors: BTreeMap::from([("shutting_down", 1)]),
+            ..Counts::default()
+        }
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_refused_begins_are_not_counted_as_sessions() {
+    let pool = connect().await;
+    let (uow, metrics) = measured_uow(&pool);
+    uow.register_default_observer(Arc::new(Refusing));
+
+    for _ in 0..2 {
+        match uow.begin().await {
+            Err(TransactionError::CommitFailed(_)) => {}
+            Err(other) => panic!("Expected the observer's error, got {other:?}"),
+            Ok(_) => panic!("The observer should refuse the session"),
+        }
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(
+        *metrics.counts.lock(),
+        Counts {
+            begin_errors: BTreeMap::from([("rejected", 2)]),
+            ..Counts::default()
+        }
+    );
+
+    pool.close().await;
+
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_slow_commits_and_rollbacks_are_recorded() {
+    let pool = connect().await;
+    let metrics = Arc::new(CountingMetrics::default());
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .metrics(metrics.clone())
+        .slow_commit_threshold(Duration::ZERO)
+        .build();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let report = session.commit_with_report().await.expect("Failed to commit transaction");
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let counts = std::mem::take(&mut *metrics.counts.lock());
+    assert_eq!(counts.slow_commits, [report.commit_duration]);
+    assert_eq!(counts.slow_rollbacks.len(), 1);
+    assert!(counts.slow_rollbacks[0] > Duration::ZERO);
+
+    // Nothing is slow without a threshold
+    let (uow, metrics) = measured_uow(&pool);
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.commit().await.expect("Failed to commit transaction");
+    assert!(metrics.counts.lock().slow_commits.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_retries_and_conflicts_are_recorded() {
+    let pool = connect().await;
+    let (uow, metrics) = measured_uow(&pool);
+    let policy = RetryPolicy::new(3).initial_backoff(Duration::from_millis(1));
+
+    // Two conflicts, retried, then a commit
+    let attempts = AtomicU32::new(0);
+    uow.run_with_retry(&policy, |session| {
+        let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+        Box::pin(async move {
+            match attempt {
+                0 => raise(session.executor(), "serialization_failure").await,
+                1 => raise(session.executor(), "deadlock_detected").await,
+                _ => Ok(()),
+            }
+        })
+    })
+    .await
+    .expect("Retry should succeed");
+
+    let counts = std::mem::take(&mut *metrics.counts.lock());
+    assert_eq!(
+        counts.retries,
+        [(1, Some("40001".to_string())), (2, Some("40P01".to_string()))]
+    );
+    assert_eq!(counts.conflicts, ["40001", "40P01"]);
+    assert!(counts.retries_exhausted.is_empty());
+
+    // Conflicts on every attempt exhaust the policy
+    let result = uow
+        .run_with_retry(&policy, |session| Box::pin(raise(session.executor(), "serialization_failure")))
+        .await;
+    assert_eq!(result.expect_err("Every attempt fails").sqlstate(), Some("40001"));
+
+    let counts = std::mem::take(&mut *metrics.counts.lock());
+    assert_eq!(
+        counts.retries,
+        [(1, Some("40001".to_string())), (2, Some("40001".to_string()))]
+    );
+    assert_eq!(counts.retries_exhausted, [3]);
+    assert_eq!(counts.conflicts, ["40001", "40001", "40001"]);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_conflicts_are_recorded_without_retries() {
+    let pool = connect().await;
+    let (uow, metrics) = measured_uow(&pool);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    raise(session.executor(), "serialization_failure")
+        .await
+        .expect_err("The statement fails");
+    session.rollback().await.expect("Failed to rollback transaction");
+    let result = uow.run(|session| Box::pin(raise(session.executor(), "deadlock_detected"))).await;
+    assert_eq!(result.expect_err("The attempt fails").sqlstate(), Some("40P01"));
+    // Other errors are no conflicts
+    uow.executor()
+        .execute(sqlx::query("SELECT 1 / 0"))
+        .await
+        .expect_err("Division by zero succeeded");
+
+    let counts = std::mem::take(&mut *metrics.counts.lock());
+    assert_eq!(counts.conflicts, ["40001", "40P01"]);
+    assert!(counts.retries.is_empty());
+    assert!(counts.retries_exhausted.is_empty());
+}
@@ -0,0 +1,121 @@
+mod common;
+
+use postgres_unit_of_work::{PostgresUnitOfWork, TransactionError, UnitOfWork, UnitOfWorkSession};
+use sqlx::migrate::Migrator;
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use common::connect;
+
+static WIDGETS: Migrator = sqlx::migrate!("tests/migrations/widgets");
+static CONCURRENT_INDEX: Migrator = sqlx::migrate!("tests/migrations/concurrent_index");
+
+/// Connect with no migrations applied
+async fn drop_migrated_tables() -> PgPool {
+    let pool = connect().await;
+    sqlx::raw_sql("DROP TABLE IF EXISTS migration_widgets; DROP TABLE IF EXISTS _sqlx_migrations")
+        .execute(&pool)
+        .await
+        .expect("Failed to drop the migrated tables");
+    pool
+}
+
+async fn table_exists(pool: &PgPool, table: &str) -> bool {
+    sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+        .bind(table)
+        .fetch_one(pool)
+        .await
+        .expect("Failed to look up table")
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_run_migrations_applies_pending_migrations_once() {
+    let pool = drop_migrated_tables().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let report = session.run_migrations(&WIDGETS).await.expect("Failed to run migrations");
+    assert_eq!(report.applied, [1, 2]);
+    assert!(report.already_applied.is_empty());
+    session.commit().await.expect("Failed to commit transaction");
+
+    let (color,): (String,) = sqlx::query_as("INSERT INTO migration_widgets (name) VALUES ('bolt') RETURNING color")
+        .fetch_one(&pool)
+        .await
+        .expect("The migrated table should exist");
+    assert_eq!(color, "grey");
+
+    // Running them again applies nothing, and sqlx agrees they are applied
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let report = session.run_migrations(&WIDGETS).await.expect("Failed to run migrations");
+    assert!(report.applied.is_empty());
+    assert_eq!(report.already_applied, [1, 2]);
+    session.commit().await.expect("Failed to commit transaction");
+    WIDGETS.run(&pool).await.expect("sqlx should see the migrations as applied");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rolled_back_migrations_leave_the_schema_untouched() {
+    let pool = drop_migrated_tables().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let report = session.run_migrations(&WIDGETS).await.expect("Failed to run migrations");
+    assert_eq!(report.applied, [1, 2]);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert!(!table_exists(&pool, "migration_widgets").await);
+    assert!(!table_exists(&pool, "_sqlx_migrations").await);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_migrations_without_a_transaction_are_refused() {
+    let pool = drop_migrated_tables().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let error = session
+        .run_migrations(&CONCURRENT_INDEX)
+        .await
+        .expect_err("CREATE INDEX CONCURRENTLY cannot run in a transaction");
+    match error {
+        TransactionError::MigrationNotTransactional { version, description } => {
+            assert_eq!(version, 1);
+            assert_eq!(description, "index migration widgets");
+        }
+        other => panic!("Expected MigrationNotTransactional, got {other:?}"),
+    }
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_modified_migrations_are_reported() {
+    let pool = drop_migrated_tables().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.run_migrations(&WIDGETS).await.expect("Failed to run migrations");
+    session
+        .executor()
+        .execute(sqlx::query("UPDATE _sqlx_migrations SET checksum = '\\x00' WHERE version = 2"))
+        .await
+        .expect("Failed to change checksum");
+    let error = session.run_migrations(&WIDGETS).await.expect_err("The changed migration should be reported");
+    assert!(
+        matches!(&error, TransactionError::MigrationFailed(source) if matches!(**source, sqlx::migrate::MigrateError::VersionMismatch(2))),
+        "Unexpected error {error:?}"
+    );
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,2 @@
+-- no-transaction
+CREATE INDEX CONCURRENTLY migration_widgets_name ON migration_widgets (name);
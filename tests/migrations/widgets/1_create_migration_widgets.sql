@@ -0,0 +1,4 @@
+CREATE TABLE migration_widgets (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL
+);
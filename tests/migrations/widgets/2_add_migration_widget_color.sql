@@ -0,0 +1 @@
+ALTER TABLE migration_widgets ADD COLUMN color TEXT NOT NULL DEFAULT 'grey';
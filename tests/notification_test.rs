@@ -0,0 +1,127 @@
+mod common;
+
+use futures::StreamExt;
+use postgres_unit_of_work::{PostgresUnitOfWork, TransactionError, UnitOfWork, UnitOfWorkSession};
+use sqlx::postgres::PgPoolOptions;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{cleanup_database, get_database_url, setup_database};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_notifications_are_delivered_around_commit() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.listen("jobs").await.expect("Failed to listen on jobs");
+    session.listen("audit").await.expect("Failed to listen on audit");
+
+    // Sent by another connection while the transaction is still open
+    sqlx::query("SELECT pg_notify('jobs', 'before commit')")
+        .execute(&pool)
+        .await
+        .expect("Failed to notify");
+    // Held back by Postgres until the transaction commits
+    session
+        .executor()
+        .execute(sqlx::query("SELECT pg_notify('audit', 'in transaction')"))
+        .await
+        .expect("Failed to notify");
+
+    let notifications = session.commit_and_listen().await.expect("Failed to commit transaction");
+    sqlx::query("SELECT pg_notify('jobs', 'after commit')")
+        .execute(&pool)
+        .await
+        .expect("Failed to notify");
+
+    let received: Vec<_> = tokio::time::timeout(Duration::from_secs(5), notifications.take(3).collect::<Vec<_>>())
+        .await
+        .expect("Timed out waiting for notifications")
+        .into_iter()
+        .map(|notification| {
+            let notification = notification.expect("Failed to receive notification");
+            (notification.channel().to_string(), notification.payload().to_string())
+        })
+        .collect();
+    assert_eq!(
+        received,
+        vec![
+            ("jobs".to_string(), "before commit".to_string()),
+            ("audit".to_string(), "in transaction".to_string()),
+            ("jobs".to_string(), "after commit".to_string()),
+        ]
+    );
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_dropped_stream_unlistens() {
+    let pool = setup_database().await;
+    // Two connections: one for the transaction, one the session listens on
+    let listen_pool = PgPoolOptions::new()
+        .max_connections(2)
+        .connect(&get_database_url())
+        .await
+        .expect("Failed to connect to database");
+    let uow = PostgresUnitOfWork::new(Arc::new(listen_pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.listen("jobs").await.expect("Failed to listen on jobs");
+    let notifications = session.commit_and_listen().await.expect("Failed to commit transaction");
+    drop(notifications);
+
+    // Acquiring both connections waits for the listening one to be unlistened
+    // and returned
+    let mut conns = Vec::new();
+    for _ in 0..2 {
+        conns.push(listen_pool.acquire().await.expect("Failed to acquire connection"));
+    }
+    for conn in &mut conns {
+        let channels: Vec<String> = sqlx::query_scalar("SELECT pg_listening_channels()")
+            .fetch_all(&mut **conn)
+            .await
+            .expect("Failed to list channels");
+        assert!(channels.is_empty(), "Still listening on {channels:?}");
+    }
+    drop(conns);
+
+    // A session committed without listening returns an empty stream
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let notifications = session.commit_and_listen().await.expect("Failed to commit transaction");
+    assert_eq!(notifications.count().await, 0);
+
+    listen_pool.close().await;
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_listen_gives_up_on_an_exhausted_pool() {
+    // The transaction takes the only connection, leaving none to listen on
+    let pool = PgPoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_millis(200))
+        .connect(&get_database_url())
+        .await
+        .expect("Failed to connect to database");
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let error = tokio::time::timeout(Duration::from_secs(5), session.listen("jobs"))
+        .await
+        .expect("listen should give up after the acquire timeout")
+        .expect_err("No connection is left to listen on");
+    assert!(
+        matches!(error, TransactionError::DatabaseError(sqlx::Error::PoolTimedOut)),
+        "Unexpected error {error:?}"
+    );
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    pool.close().await;
+}
\ No newline at end of file
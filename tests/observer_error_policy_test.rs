@@ -0,0 +1,108 @@
+mod common;
+
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    ObserverErrorPolicy, PostgresUnitOfWork, TransactionError, UnitOfWork, UnitOfWorkSession,
+};
+use sqlx::PgPool;
+use std::sync::Arc;
+
+use common::{connect, CallLog, RecordingObserver};
+
+/// Unit of work whose observer failures are routed to the returned log
+fn uow_with_callback(pool: &PgPool) -> (PostgresUnitOfWork, Arc<Mutex<Vec<String>>>) {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let handler_seen = seen.clone();
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .observer_error_policy(ObserverErrorPolicy::callback(move |error| {
+            handler_seen.lock().push(error.to_string())
+        }))
+        .build();
+    (uow, seen)
+}
+
+async fn register_failing(session: &impl UnitOfWorkSession, log: &CallLog) {
+    session
+        .register_transaction_aware(RecordingObserver::failing("failing", log.clone()))
+        .await
+        .expect("Failed to register observer");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_propagate_policy_returns_observer_errors() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .observer_error_policy(ObserverErrorPolicy::Propagate)
+        .build();
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_failing(&session, &log).await;
+    let error = session.commit().await.expect_err("Commit should report the observer failure");
+
+    assert!(matches!(error, TransactionError::ObserverErrors(_)), "Unexpected error {error:?}");
+    assert_eq!(*log.lock(), vec!["failing:commit"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_log_and_ignore_policy_reports_success() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .observer_error_policy(ObserverErrorPolicy::LogAndIgnore)
+        .build();
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_failing(&session, &log).await;
+    session.commit().await.expect("Commit should ignore the observer failure");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_failing(&session, &log).await;
+    session.rollback().await.expect("Rollback should ignore the observer failure");
+
+    assert_eq!(*log.lock(), vec!["failing:commit", "failing:rollback"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_callback_policy_receives_commit_errors() {
+    let pool = connect().await;
+    let (uow, seen) = uow_with_callback(&pool);
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_failing(&session, &log).await;
+    session.commit().await.expect("Commit should hand the observer failure to the callback");
+
+    assert_eq!(*seen.lock(), vec!["1 transaction observer(s) failed"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_callback_policy_receives_rollback_errors() {
+    let pool = connect().await;
+    let (uow, seen) = uow_with_callback(&pool);
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_failing(&session, &log).await;
+    session.rollback().await.expect("Rollback should hand the observer failure to the callback");
+
+    assert_eq!(seen.lock().len(), 1);
+    assert_eq!(*log.lock(), vec!["failing:rollback"]);
+
+    // Successful notification does not reach the handler
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(seen.lock().len(), 1);
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,1470 @@
+mod common;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    Executor, LeakPolicy, PostgresUnitOfWork, PostgresUnitOfWorkSession, SessionState, SyncAdapter, SyncTransactionAware,
+    TransactionAware, TransactionContext, TransactionError, TransactionOutcome, TransactionResult, UnitOfWork,
+    UnitOfWorkSession,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use common::{cleanup_database, connect, setup_database, CallLog, RecordingObserver, User, UserRepository};
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_commit_notifies_all_observers_when_one_fails() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(RecordingObserver::new("first", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(RecordingObserver::failing("second", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(RecordingObserver::new("third", log.clone()))
+        .await
+        .expect("Failed to register observer");
+
+    let error = session.commit().await.expect_err("Commit should report the observer failure");
+
+    assert_eq!(*log.lock(), vec!["first:commit", "second:commit", "third:commit"]);
+    match error {
+        TransactionError::ObserverErrors(errors) => assert_eq!(errors.len(), 1),
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_notifies_all_observers_when_one_fails() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(RecordingObserver::new("first", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(RecordingObserver::failing("second", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(RecordingObserver::new("third", log.clone()))
+        .await
+        .expect("Failed to register observer");
+
+    let error = session.rollback().await.expect_err("Rollback should report the observer failure");
+
+    assert_eq!(*log.lock(), vec!["first:rollback", "second:rollback", "third:rollback"]);
+    match error {
+        TransactionError::ObserverErrors(errors) => assert_eq!(errors.len(), 1),
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_commit_converts_observer_panic_into_error() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    session
+        .register_transaction_aware(RecordingObserver::panicking("first", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(RecordingObserver::new("second", log.clone()))
+        .await
+        .expect("Failed to register observer");
+
+    let user = User::new("panic_user".to_string(), "panic@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    let error = session.commit().await.expect_err("Commit should report the observer panic");
+
+    assert_eq!(*log.lock(), vec!["first:commit", "second:commit"]);
+    match error {
+        TransactionError::ObserverErrors(errors) => {
+            assert_eq!(errors.len(), 1);
+            match &errors[0] {
+                TransactionError::ObserverPanicked { message, .. } => {
+                    assert_eq!(message, "first panicked on commit")
+                }
+                other => panic!("Expected ObserverPanicked, got {other:?}"),
+            }
+        }
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+
+    // The transaction itself was committed before observers were notified
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let verify_user_repo = UserRepository::new(verify_session.executor().clone());
+    let persisted_user = verify_user_repo
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to find persisted user");
+    assert!(persisted_user.is_some(), "User should persist despite the observer panic");
+    verify_session.commit().await.expect("Failed to commit verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// Observer that rejects every commit after recording its callbacks
+struct VetoingObserver {
+    log: CallLog,
+}
+
+#[async_trait]
+impl TransactionAware for VetoingObserver {
+    async fn before_commit(&self, _executor: &Executor) -> TransactionResult<()> {
+        self.log.lock().push("veto:before_commit".to_string());
+        Err(TransactionError::CommitFailed("invariant violated".to_string()))
+    }
+
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.log.lock().push("veto:commit".to_string());
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.log.lock().push("veto:rollback".to_string());
+        Ok(())
+    }
+}
+
+/// Observer that buffers users and writes them just before commit
+struct FlushingObserver {
+    pending: Mutex<Vec<User>>,
+}
+
+#[async_trait]
+impl TransactionAware for FlushingObserver {
+    async fn before_commit(&self, executor: &Executor) -> TransactionResult<()> {
+        let pending = std::mem::take(&mut *self.pending.lock());
+        let user_repo = UserRepository::new(executor.clone());
+        for user in &pending {
+            user_repo.create(user).await?;
+        }
+        Ok(())
+    }
+
+    async fn on_commit(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_before_commit_veto_rolls_back() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let user_repo = UserRepository::new(session.executor().clone());
+    session
+        .register_transaction_aware(RecordingObserver::new("recorder", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(Arc::new(VetoingObserver { log: log.clone() }))
+        .await
+        .expect("Failed to register observer");
+
+    let user = User::new("vetoed".to_string(), "vetoed@example.com".to_string());
+    user_repo.create(&user).await.expect("Failed to create user");
+
+    let error = session.commit().await.expect_err("Commit should be vetoed");
+    match error {
+        TransactionError::CommitVetoed {
+            source,
+            rollback_error,
+            observer_error,
+        } => {
+            assert!(matches!(*source, TransactionError::CommitFailed(_)), "Unexpected veto {source:?}");
+            assert!(rollback_error.is_none(), "Rollback should succeed, got {rollback_error:?}");
+            assert!(observer_error.is_none(), "Observers should succeed, got {observer_error:?}");
+        }
+        other => panic!("Expected CommitVetoed, got {other:?}"),
+    }
+    assert_eq!(*log.lock(), vec!["veto:before_commit", "recorder:rollback", "veto:rollback"]);
+
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let found = UserRepository::new(verify_session.executor().clone())
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to query user");
+    assert!(found.is_none(), "User should not exist after the veto");
+    verify_session.commit().await.expect("Failed to commit verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_veto_reports_observer_failures_apart_from_rollback() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(RecordingObserver::failing("failing", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(Arc::new(VetoingObserver { log: log.clone() }))
+        .await
+        .expect("Failed to register observer");
+
+    let error = session.commit().await.expect_err("Commit should be vetoed");
+    match error {
+        TransactionError::CommitVetoed {
+            rollback_error,
+            observer_error,
+            ..
+        } => {
+            assert!(rollback_error.is_none(), "Rollback should succeed, got {rollback_error:?}");
+            match observer_error.as_deref() {
+                Some(TransactionError::ObserverErrors(errors)) => assert_eq!(errors.len(), 1),
+                other => panic!("Expected the observer failure, got {other:?}"),
+            }
+        }
+        other => panic!("Expected CommitVetoed, got {other:?}"),
+    }
+    assert_eq!(*log.lock(), vec!["veto:before_commit", "failing:rollback", "veto:rollback"]);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_busy_commit_fails_before_before_commit_writes() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let user = User::new("flushed".to_string(), "flushed@example.com".to_string());
+    let flushing = Arc::new(FlushingObserver {
+        pending: Mutex::new(vec![user]),
+    });
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(flushing.clone())
+        .await
+        .expect("Failed to register observer");
+    let executor = session.executor().clone();
+    let mut rows = Box::pin(executor.fetch_stream(sqlx::query("SELECT generate_series(1, 3)")));
+    rows.next().await.expect("Stream ended early").expect("Failed to stream row");
+
+    let error = session.commit().await.expect_err("Commit should fail while streaming");
+    assert!(matches!(error, TransactionError::ExecutorBusy { .. }), "Unexpected error {error:?}");
+    assert_eq!(flushing.pending.lock().len(), 1, "The observer should not have flushed");
+    drop(rows);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_before_commit_writes_are_committed() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let user = User::new("flushed".to_string(), "flushed@example.com".to_string());
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.register_transaction_aware(Arc::new(FlushingObserver {
+        pending: Mutex::new(vec![user.clone()]),
+    }))
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("Failed to commit transaction");
+
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let found = UserRepository::new(verify_session.executor().clone())
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to query user")
+        .expect("Flushed user should be persisted");
+    assert_eq!(found, user);
+    verify_session.commit().await.expect("Failed to commit verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// Observer that stages audit rows in a temp table and counts them before commit
+struct AuditObserver {
+    log: CallLog,
+    audited: Mutex<Option<i64>>,
+}
+
+#[async_trait]
+impl TransactionAware for AuditObserver {
+    async fn after_begin(&self, executor: &Executor) -> TransactionResult<()> {
+        self.log.lock().push("audit:after_begin".to_string());
+        let mut tx_guard = executor.tx.lock().await;
+        let tx = tx_guard.as_mut().ok_or_else(|| executor.completed_error())?;
+        sqlx::query("CREATE TEMP TABLE audit_staging (entry TEXT NOT NULL) ON COMMIT DROP")
+            .execute(&mut **tx)
+            .await?;
+        Ok(())
+    }
+
+    async fn before_commit(&self, executor: &Executor) -> TransactionResult<()> {
+        let mut tx_guard = executor.tx.lock().await;
+        let tx = tx_guard.as_mut().ok_or_else(|| executor.completed_error())?;
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM audit_staging")
+            .fetch_one(&mut **tx)
+            .await?;
+        *self.audited.lock() = Some(count);
+        Ok(())
+    }
+
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.log.lock().push("audit:commit".to_string());
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.log.lock().push("audit:rollback".to_string());
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_after_begin_prepares_transaction_state() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let audit = Arc::new(AuditObserver {
+        log: log.clone(),
+        audited: Mutex::new(None),
+    });
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(audit.clone())
+        .await
+        .expect("Failed to register observer");
+    {
+        let mut tx_guard = session.executor().tx.lock().await;
+        let tx = tx_guard.as_mut().expect("Transaction should be active");
+        sqlx::query("INSERT INTO audit_staging (entry) VALUES ('created'), ('updated')")
+            .execute(&mut **tx)
+            .await
+            .expect("Temp table should exist after registration");
+    }
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(*audit.audited.lock(), Some(2));
+    assert_eq!(*log.lock(), vec!["audit:after_begin", "audit:commit"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_default_observer_after_begin_runs_at_begin() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    uow.register_default_observer(Arc::new(AuditObserver {
+        log: log.clone(),
+        audited: Mutex::new(None),
+    }));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    assert_eq!(*log.lock(), vec!["audit:after_begin"]);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.commit().await.expect("Failed to commit transaction");
+    assert_eq!(
+        *log.lock(),
+        vec!["audit:after_begin", "audit:rollback", "audit:after_begin", "audit:commit"]
+    );
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_failing_default_observer_rolls_back_the_begin() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .leak_policy(LeakPolicy::PanicInDebug)
+        .build();
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    // The second observer fails to create the temp table the first one made
+    for _ in 0..2 {
+        uow.register_default_observer(Arc::new(AuditObserver {
+            log: log.clone(),
+            audited: Mutex::new(None),
+        }));
+    }
+
+    if uow.begin().await.is_ok() {
+        panic!("The failing observer should fail the begin");
+    }
+
+    assert_eq!(*log.lock(), vec!["audit:after_begin", "audit:after_begin", "audit:rollback"]);
+    assert_eq!(uow.active_count(), 0);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_failing_after_begin_rejects_registration() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    {
+        // Make the observer's CREATE TEMP TABLE fail
+        let mut tx_guard = session.executor().tx.lock().await;
+        let tx = tx_guard.as_mut().expect("Transaction should be active");
+        sqlx::query("CREATE TEMP TABLE audit_staging (entry TEXT NOT NULL) ON COMMIT DROP")
+            .execute(&mut **tx)
+            .await
+            .expect("Failed to create temp table");
+    }
+    session
+        .register_transaction_aware(Arc::new(AuditObserver {
+            log: log.clone(),
+            audited: Mutex::new(None),
+        }))
+        .await
+        .expect_err("Registration should fail");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert_eq!(*log.lock(), vec!["audit:after_begin"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_on_commit_closures_run_in_order() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    for name in ["first", "second"] {
+        let log = log.clone();
+        session
+            .on_commit(move || async move {
+                log.lock().push(name.to_string());
+                Ok(())
+            })
+            .expect("Failed to register hook");
+    }
+    assert!(log.lock().is_empty(), "Hooks should not run before commit");
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(*log.lock(), vec!["first", "second"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_on_commit_closure_skipped_on_rollback() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let hook_log = log.clone();
+    session
+        .on_commit(move || async move {
+            hook_log.lock().push("committed".to_string());
+            Ok(())
+        })
+        .expect("Failed to register hook");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert!(log.lock().is_empty(), "Hook should not run after rollback");
+
+    pool.close().await;
+}
+
+/// Register on_rollback and on_complete hooks that record into `log`
+fn register_outcome_hooks(session: &PostgresUnitOfWorkSession, log: &CallLog) {
+    let rollback_log = log.clone();
+    session
+        .on_rollback(move || async move {
+            rollback_log.lock().push("on_rollback".to_string());
+            Ok(())
+        })
+        .expect("Failed to register hook");
+    let complete_log = log.clone();
+    session
+        .on_complete(move |outcome| async move {
+            complete_log.lock().push(format!("on_complete:{outcome:?}"));
+            Ok(())
+        })
+        .expect("Failed to register hook");
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_outcome_hooks_on_commit() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_outcome_hooks(&session, &log);
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(*log.lock(), vec!["on_complete:Committed"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_outcome_hooks_on_rollback() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_outcome_hooks(&session, &log);
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert_eq!(*log.lock(), vec!["on_rollback", "on_complete:RolledBack"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_observer_priority_order() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    for commit in [true, false] {
+        let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+        let session = uow.begin().await.expect("Failed to begin transaction");
+        for (name, priority) in [("cache", 10), ("index", 0), ("audit", -5), ("search", 0)] {
+            session
+                .register_transaction_aware_with_priority(RecordingObserver::new(name, log.clone()), priority)
+                .await
+                .expect("Failed to register observer");
+        }
+
+        if commit {
+            session.commit().await.expect("Failed to commit transaction");
+            assert_eq!(
+                *log.lock(),
+                vec!["audit:commit", "index:commit", "search:commit", "cache:commit"]
+            );
+        } else {
+            session.rollback().await.expect("Failed to rollback transaction");
+            assert_eq!(
+                *log.lock(),
+                vec!["cache:rollback", "index:rollback", "search:rollback", "audit:rollback"]
+            );
+        }
+    }
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_deregistered_observer_is_not_notified() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(RecordingObserver::new("kept", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    let cancelled = session
+        .register_transaction_aware(RecordingObserver::new("cancelled", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    let late = cancelled.clone();
+
+    assert!(cancelled.deregister(), "Registration should be removed");
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(*log.lock(), vec!["kept:commit"]);
+    assert!(!late.deregister(), "Deregistering after completion should be a no-op");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_weak_observer_skipped_after_drop() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let dropped: Arc<dyn TransactionAware> = RecordingObserver::new("dropped", log.clone());
+    let kept: Arc<dyn TransactionAware> = RecordingObserver::new("kept", log.clone());
+    session
+        .register_transaction_aware_weak(Arc::downgrade(&dropped))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware_weak(Arc::downgrade(&kept))
+        .await
+        .expect("Failed to register observer");
+
+    drop(dropped);
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(*log.lock(), vec!["kept:commit"]);
+
+    pool.close().await;
+}
+
+
+type Spans = Arc<Mutex<Vec<(Instant, Instant)>>>;
+
+/// Observer that sleeps on commit and rollback, recording when it ran
+struct SlowObserver {
+    spans: Spans,
+}
+
+impl SlowObserver {
+    fn new(spans: Spans) -> Arc<Self> {
+        Arc::new(Self { spans })
+    }
+
+    async fn record(&self) -> TransactionResult<()> {
+        let start = Instant::now();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.spans.lock().push((start, Instant::now()));
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TransactionAware for SlowObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        self.record().await
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        self.record().await
+    }
+}
+
+async fn register_slow_observers(session: &PostgresUnitOfWorkSession, spans: &Spans, count: usize) {
+    for _ in 0..count {
+        session
+            .register_transaction_aware(SlowObserver::new(spans.clone()))
+            .await
+            .expect("Failed to register observer");
+    }
+}
+
+fn all_overlap(spans: &[(Instant, Instant)]) -> bool {
+    spans
+        .iter()
+        .all(|(start, _)| spans.iter().all(|(_, end)| start < end))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_concurrent_notification_overlaps_callbacks() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let spans: Spans = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.notify_observers_concurrently(true);
+    register_slow_observers(&session, &spans, 3).await;
+    session.commit().await.expect("Failed to commit transaction");
+
+    let commit_spans = std::mem::take(&mut *spans.lock());
+    assert_eq!(commit_spans.len(), 3);
+    assert!(all_overlap(&commit_spans), "commit callbacks ran one after another");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.notify_observers_concurrently(true);
+    register_slow_observers(&session, &spans, 3).await;
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let rollback_spans = spans.lock().clone();
+    assert_eq!(rollback_spans.len(), 3);
+    assert!(all_overlap(&rollback_spans), "rollback callbacks ran one after another");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_sequential_notification_by_default() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let spans: Spans = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_slow_observers(&session, &spans, 2).await;
+    session.commit().await.expect("Failed to commit transaction");
+
+    let spans = spans.lock().clone();
+    assert_eq!(spans.len(), 2);
+    assert!(spans[0].1 <= spans[1].0, "commit callbacks overlapped");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_concurrent_notification_aggregates_errors() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.notify_observers_concurrently(true);
+    for observer in [
+        RecordingObserver::failing("first", log.clone()),
+        RecordingObserver::new("second", log.clone()),
+        RecordingObserver::panicking("third", log.clone()),
+    ] {
+        session
+            .register_transaction_aware(observer)
+            .await
+            .expect("Failed to register observer");
+    }
+
+    let result = session.commit().await;
+    match result {
+        Err(TransactionError::ObserverErrors(errors)) => assert_eq!(errors.len(), 2),
+        other => panic!("Expected ObserverErrors, got {:?}", other),
+    }
+    let mut calls = log.lock().clone();
+    calls.sort();
+    assert_eq!(calls, vec!["first:commit", "second:commit", "third:commit"]);
+
+    pool.close().await;
+}
+
+/// Observer that fails on commit under a custom name
+struct NamedObserver;
+
+#[async_trait]
+impl TransactionAware for NamedObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        Err(TransactionError::CommitFailed("cache unavailable".to_string()))
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "inventory-cache"
+    }
+}
+
+fn failed_observer_names(error: TransactionError) -> Vec<String> {
+    match error {
+        TransactionError::ObserverErrors(errors) => errors
+            .into_iter()
+            .map(|error| match error {
+                TransactionError::ObserverFailed { observer, .. } => observer,
+                other => panic!("Expected ObserverFailed, got {other:?}"),
+            })
+            .collect(),
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_observer_name_defaults_to_type_name() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let observer = RecordingObserver::failing("first", log.clone());
+    assert!(observer.name().ends_with("::RecordingObserver"), "Unexpected name {}", observer.name());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(observer)
+        .await
+        .expect("Failed to register observer");
+    let error = session.commit().await.expect_err("Commit should report the observer failure");
+
+    let names = failed_observer_names(error);
+    assert_eq!(names.len(), 1);
+    assert!(names[0].ends_with("::RecordingObserver"), "Unexpected name {}", names[0]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_custom_observer_name_in_errors() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(Arc::new(NamedObserver))
+        .await
+        .expect("Failed to register observer");
+    session
+        .on_commit_named("publish-events", || async {
+            Err(TransactionError::CommitFailed("broker unavailable".to_string()))
+        })
+        .expect("Failed to register hook");
+    session
+        .on_commit(|| async { Err(TransactionError::CommitFailed("unlabelled".to_string())) })
+        .expect("Failed to register hook");
+    let error = session.commit().await.expect_err("Commit should report the observer failures");
+
+    let TransactionError::ObserverErrors(errors) = &error else {
+        panic!("Expected ObserverErrors, got {error:?}");
+    };
+    assert_eq!(
+        errors[0].to_string(),
+        "Transaction observer inventory-cache failed: Transaction commit failed: cache unavailable"
+    );
+    assert_eq!(
+        failed_observer_names(error),
+        vec!["inventory-cache", "publish-events", "on_commit hook"]
+    );
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_composite_observer_forwards_in_order() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let members: Vec<Arc<dyn TransactionAware>> = vec![
+        RecordingObserver::new("first", log.clone()),
+        RecordingObserver::new("second", log.clone()),
+    ];
+    let pair = (
+        RecordingObserver::new("third", log.clone()),
+        RecordingObserver::new("fourth", log.clone()),
+    );
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(Arc::new(members))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(Arc::new(pair))
+        .await
+        .expect("Failed to register observer");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    assert_eq!(
+        *log.lock(),
+        vec!["first:rollback", "second:rollback", "third:rollback", "fourth:rollback"]
+    );
+
+    pool.close().await;
+}
+
+#[tokio::test]
+async fn test_composite_observer_aggregates_errors() {
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let composite = (
+        RecordingObserver::failing("first", log.clone()),
+        NamedObserver,
+        SlowObserver::new(Arc::new(Mutex::new(Vec::new()))),
+    );
+
+    let error = composite.on_commit().await.expect_err("Composite should report member failures");
+
+    assert_eq!(*log.lock(), vec!["first:commit"]);
+    let names = failed_observer_names(error);
+    assert_eq!(names.len(), 2);
+    assert!(names[0].ends_with("::RecordingObserver"), "Unexpected name {}", names[0]);
+    assert_eq!(names[1], "inventory-cache");
+    assert!(!composite.is_idempotent());
+}
+
+/// Synchronous observer flipping in-memory flags, like the test repositories
+#[derive(Default)]
+struct SyncFlags {
+    committed: AtomicBool,
+    rolled_back: AtomicBool,
+}
+
+impl SyncTransactionAware for SyncFlags {
+    fn on_commit(&self) -> TransactionResult<()> {
+        self.committed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn on_rollback(&self) -> TransactionResult<()> {
+        self.rolled_back.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_sync_observer_notified_on_both_outcomes() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let committed = SyncAdapter::new(SyncFlags::default());
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(committed.clone())
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("Failed to commit transaction");
+    assert!(committed.0.committed.load(Ordering::SeqCst));
+    assert!(!committed.0.rolled_back.load(Ordering::SeqCst));
+    assert!(committed.name().ends_with("::SyncFlags"), "Unexpected name {}", committed.name());
+
+    let rolled_back = SyncAdapter::new(SyncFlags::default());
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(rolled_back.clone())
+        .await
+        .expect("Failed to register observer");
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert!(!rolled_back.0.committed.load(Ordering::SeqCst));
+    assert!(rolled_back.0.rolled_back.load(Ordering::SeqCst));
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_register_once_fires_once_after_commit_failure() {
+    let pool = connect().await;
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS once_codes (code INT NOT NULL, UNIQUE (code) DEFERRABLE INITIALLY DEFERRED)",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to create once_codes table");
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let once = RecordingObserver::new("once", log.clone());
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_once(once.clone())
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(RecordingObserver::new("always", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    {
+        let mut tx_guard = session.executor().tx.lock().await;
+        let tx = tx_guard.as_mut().expect("Transaction should be active");
+        sqlx::query("INSERT INTO once_codes (code) VALUES (1), (1)")
+            .execute(&mut **tx)
+            .await
+            .expect("Deferred constraint should not fail the insert");
+    }
+
+    session.commit().await.expect_err("Commit should fail on the deferred constraint");
+
+    // The one-shot entry was consumed by the rollback notification and released
+    assert_eq!(*log.lock(), vec!["once:rollback", "always:rollback"]);
+    assert_eq!(Arc::strong_count(&once), 1);
+
+    sqlx::query("DROP TABLE IF EXISTS once_codes")
+        .execute(&pool)
+        .await
+        .expect("Failed to drop once_codes table");
+    pool.close().await;
+}
+
+/// Observer keeping the context of every completion it is notified of
+#[derive(Default)]
+struct ContextObserver {
+    contexts: Mutex<Vec<TransactionContext>>,
+}
+
+#[async_trait]
+impl TransactionAware for ContextObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        unreachable!("sessions call on_commit_with")
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        unreachable!("sessions call on_rollback_with")
+    }
+
+    async fn on_commit_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        self.contexts.lock().push(context.clone());
+        Ok(())
+    }
+
+    async fn on_rollback_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        self.contexts.lock().push(context.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_observers_share_transaction_context() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+    let first = Arc::new(ContextObserver::default());
+    let second = Arc::new(ContextObserver::default());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    session.set_label("checkout");
+    session
+        .register_transaction_aware(first.clone())
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(second.clone())
+        .await
+        .expect("Failed to register observer");
+    // Observers implementing only the plain callbacks are still notified
+    session
+        .register_transaction_aware(RecordingObserver::new("plain", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("Failed to commit transaction");
+
+    let first_context = first.contexts.lock()[0].clone();
+    let second_context = second.contexts.lock()[0].clone();
+    assert_eq!(first_context.session_id, id);
+    assert_eq!(second_context.session_id, id);
+    assert_eq!(first_context.completed_at, second_context.completed_at);
+    assert_eq!(first_context.outcome, TransactionOutcome::Committed);
+    assert_eq!(first_context.label.as_deref(), Some("checkout"));
+    assert!(first_context.started_at <= first_context.completed_at);
+    assert_eq!(*log.lock(), vec!["plain:commit"]);
+
+    let observer = Arc::new(ContextObserver::default());
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    let rollback_id = session.id();
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let context = observer.contexts.lock()[0].clone();
+    assert_eq!(context.outcome, TransactionOutcome::RolledBack);
+    assert_eq!(context.session_id, rollback_id);
+    assert_ne!(rollback_id, id);
+    assert_eq!(context.label, None);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_registration_rejected_after_completion() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    // Code holding the session's executor completes the transaction behind its back
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let tx = session
+        .executor()
+        .tx
+        .lock()
+        .await
+        .take()
+        .expect("Transaction should be active");
+    tx.commit().await.expect("Failed to commit transaction");
+
+    let error = session
+        .register_transaction_aware(RecordingObserver::new("late", log.clone()))
+        .await
+        .expect_err("Registration after completion should fail");
+    assert!(
+        matches!(error, TransactionError::TransactionAlreadyCompleted),
+        "Expected TransactionAlreadyCompleted, got {error:?}"
+    );
+    let error = session
+        .on_commit(|| async { Ok(()) })
+        .expect_err("Hook registration after completion should fail");
+    assert!(
+        matches!(error, TransactionError::TransactionAlreadyCompleted),
+        "Expected TransactionAlreadyCompleted, got {error:?}"
+    );
+    let error = session.commit().await.expect_err("Commit after completion should fail");
+    assert!(
+        matches!(error, TransactionError::TransactionAlreadyCompleted),
+        "Expected TransactionAlreadyCompleted, got {error:?}"
+    );
+    assert!(log.lock().is_empty(), "Late observer was notified");
+
+    pool.close().await;
+}
+
+/// Observer whose commit callback sleeps for `delay`, e.g. on a dead cache server
+struct HangingObserver {
+    delay: Duration,
+}
+
+#[async_trait]
+impl TransactionAware for HangingObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        tokio::time::sleep(self.delay).await;
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "hanging"
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_observer_timeout_bounds_commit() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .observer_timeout(Duration::from_millis(100))
+        .build();
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(Arc::new(HangingObserver { delay: Duration::from_secs(30) }))
+        .await
+        .expect("Failed to register observer");
+    session
+        .register_transaction_aware(RecordingObserver::new("next", log.clone()))
+        .await
+        .expect("Failed to register observer");
+
+    let started = Instant::now();
+    let error = session.commit().await.expect_err("Commit should report the timeout");
+
+    assert!(started.elapsed() < Duration::from_secs(5), "Commit took {:?}", started.elapsed());
+    match error {
+        TransactionError::ObserverErrors(errors) => match &errors[..] {
+            [TransactionError::ObserverTimeout { observer, elapsed }] => {
+                assert_eq!(observer, "hanging");
+                assert!(*elapsed >= Duration::from_millis(100), "Timed out after {elapsed:?}");
+            }
+            other => panic!("Expected one ObserverTimeout, got {other:?}"),
+        },
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+    assert_eq!(*log.lock(), vec!["next:commit"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_observer_timeout_overridden_per_registration() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .observer_timeout(Duration::from_millis(50))
+        .build();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware_with_timeout(
+            Arc::new(HangingObserver { delay: Duration::from_millis(150) }),
+            Duration::from_secs(5),
+        )
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("Slow observer should finish within its own timeout");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_dropped_session_rolls_back_and_notifies_observers() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor().clone();
+    session
+        .register_transaction_aware(RecordingObserver::new("dropped", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    UserRepository::new(executor.clone())
+        .create(&User::new("dropped".to_string(), "dropped@example.com".to_string()))
+        .await
+        .expect("Failed to create user");
+    drop(session);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    assert_eq!(*log.lock(), vec!["dropped:rollback"]);
+    assert_eq!(executor.session_state(), SessionState::RolledBack);
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+        .fetch_one(&pool)
+        .await
+        .expect("Failed to count users");
+    assert_eq!(count, 0);
+
+    // A completed session is not notified again when it is dropped
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(RecordingObserver::new("committed", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("Failed to commit transaction");
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(*log.lock(), vec!["dropped:rollback", "committed:commit"]);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+/// Request data the application stashes on a session
+#[derive(Debug, PartialEq)]
+struct RequestInfo {
+    request_id: String,
+    user: String,
+}
+
+/// Observer reading the request data from the context it is notified with
+#[derive(Default)]
+struct RequestObserver {
+    seen: Mutex<Option<Arc<RequestInfo>>>,
+}
+
+#[async_trait]
+impl TransactionAware for RequestObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        unreachable!("sessions call on_commit_with")
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        unreachable!("sessions call on_rollback_with")
+    }
+
+    async fn on_commit_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        *self.seen.lock() = context.extensions.get::<RequestInfo>();
+        Ok(())
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_observer_reads_session_extensions() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let observer = Arc::new(RequestObserver::default());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    let extensions = session.extensions();
+    assert!(extensions.insert(7_u32).is_none());
+    assert_eq!(extensions.insert(8_u32).as_deref(), Some(&7));
+    assert_eq!(extensions.remove::<u32>().as_deref(), Some(&8));
+    assert!(!extensions.contains::<u32>());
+    extensions.insert(RequestInfo {
+        request_id: "req-42".to_string(),
+        user: "alice".to_string(),
+    });
+    assert_eq!(extensions.len(), 1);
+    session.commit().await.expect("Failed to commit transaction");
+
+    let seen = observer.seen.lock().clone().expect("The observer should see the request");
+    assert_eq!(
+        *seen,
+        RequestInfo {
+            request_id: "req-42".to_string(),
+            user: "alice".to_string(),
+        }
+    );
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_observer_sees_session_metadata() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let observer = Arc::new(ContextObserver::default());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    session.set_metadata("user_id", "u-1");
+    session.set_metadata("tenant", "acme");
+    session.set_metadata("user_id", "u-2");
+    let expected: HashMap<String, String> = [("user_id", "u-2"), ("tenant", "acme")]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    assert_eq!(session.metadata(), expected);
+    let report = session.commit_with_report().await.expect("Failed to commit transaction");
+
+    assert_eq!(observer.contexts.lock()[0].metadata, expected);
+    assert_eq!(report.metadata, expected);
+
+    pool.close().await;
+}
+
+/// Register three compensations that record into `log`, the middle one failing
+fn register_compensations(session: &PostgresUnitOfWorkSession, log: &CallLog) {
+    for (name, fails) in [("release inventory", false), ("refund payment", true), ("cancel shipment", false)] {
+        let log = log.clone();
+        session
+            .register_compensation(name, move || async move {
+                log.lock().push(name.to_string());
+                if fails {
+                    return Err(TransactionError::RollbackFailed(format!("{name} failed")));
+                }
+                Ok(())
+            })
+            .expect("Failed to register compensation");
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_compensations_run_in_reverse_on_rollback() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_compensations(&session, &log);
+    let error = session.rollback().await.expect_err("The failed compensation should be reported");
+
+    assert_eq!(*log.lock(), vec!["cancel shipment", "refund payment", "release inventory"]);
+    let TransactionError::ObserverErrors(errors) = error else {
+        panic!("Expected ObserverErrors, got {error:?}");
+    };
+    let [TransactionError::ObserverFailed { observer, source }] = errors.as_slice() else {
+        panic!("Expected the compensations to fail, got {errors:?}");
+    };
+    assert_eq!(observer, "compensations");
+    let TransactionError::ObserverErrors(failures) = source.as_ref() else {
+        panic!("Expected ObserverErrors, got {source:?}");
+    };
+    let failed: Vec<_> = failures
+        .iter()
+        .map(|failure| match failure {
+            TransactionError::ObserverFailed { observer, .. } => observer.as_str(),
+            other => panic!("Expected ObserverFailed, got {other:?}"),
+        })
+        .collect();
+    assert_eq!(failed, vec!["refund payment"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_compensations_discarded_on_commit() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    register_compensations(&session, &log);
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert!(log.lock().is_empty(), "Compensations should not run after commit");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_panicking_compensation_does_not_stop_the_rest() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    for (name, panics) in [("release inventory", false), ("refund payment", true), ("cancel shipment", false)] {
+        let log = log.clone();
+        session
+            .register_compensation(name, move || async move {
+                if panics {
+                    panic!("{name} panicked");
+                }
+                log.lock().push(name.to_string());
+                Ok(())
+            })
+            .expect("Failed to register compensation");
+    }
+    let error = session.rollback().await.expect_err("The panic should be reported");
+
+    assert_eq!(*log.lock(), vec!["cancel shipment", "release inventory"]);
+    let TransactionError::ObserverErrors(errors) = error else {
+        panic!("Expected ObserverErrors, got {error:?}");
+    };
+    let [TransactionError::ObserverFailed { source, .. }] = errors.as_slice() else {
+        panic!("Expected the compensations to fail, got {errors:?}");
+    };
+    let TransactionError::ObserverErrors(failures) = source.as_ref() else {
+        panic!("Expected ObserverErrors, got {source:?}");
+    };
+    let [TransactionError::ObserverPanicked { observer, message }] = failures.as_slice() else {
+        panic!("Expected the panic, got {failures:?}");
+    };
+    assert_eq!(observer, "refund payment");
+    assert_eq!(message, "refund payment panicked");
+
+    pool.close().await;
+}
\ No newline at end of file
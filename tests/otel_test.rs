@@ -0,0 +1,176 @@
+#![cfg(feature = "otel")]
+
+mod common;
+
+use async_trait::async_trait;
+use opentelemetry::trace::{
+    SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState, TracerProvider as _,
+};
+use opentelemetry::{Context, KeyValue, Value};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    PostgresUnitOfWork, TransactionAware, TransactionContext, TransactionResult, UnitOfWork, UnitOfWorkSession,
+};
+use std::sync::{Arc, OnceLock};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+
+use common::connect;
+
+static EXPORTER: OnceLock<InMemorySpanExporter> = OnceLock::new();
+
+/// The exporter of the global OpenTelemetry layer, with everything exported
+/// so far forgotten
+fn exporter() -> &'static InMemorySpanExporter {
+    let exporter = EXPORTER.get_or_init(|| {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("otel_test"));
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::set_global_default(subscriber).expect("Failed to install subscriber");
+        exporter
+    });
+    exporter.reset();
+    exporter
+}
+
+/// The only exported span named `name`
+fn span(spans: &[SpanData], name: &str) -> SpanData {
+    let mut matching = spans.iter().filter(|span| span.name == name);
+    let span = matching.next().unwrap_or_else(|| panic!("No {name} span was exported in {spans:?}"));
+    assert!(matching.next().is_none(), "More than one {name} span was exported");
+    span.clone()
+}
+
+/// The value of the attribute `key` of `span`
+fn attribute(span: &SpanData, key: &str) -> Option<Value> {
+    span.attributes
+        .iter()
+        .find(|KeyValue { key: k, .. }| k.as_str() == key)
+        .map(|attribute| attribute.value.clone())
+}
+
+/// Observer keeping the trace id of the transactions it is notified of
+#[derive(Default)]
+struct TraceIdObserver(Mutex<Vec<Option<String>>>);
+
+#[async_trait]
+impl TransactionAware for TraceIdObserver {
+    async fn on_commit(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+
+    async fn on_commit_with(&self, context: &TransactionContext) -> TransactionResult<()> {
+        self.0.lock().push(context.trace_id.clone());
+        Ok(())
+    }
+
+    async fn on_rollback(&self) -> TransactionResult<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_session_spans_are_children_of_the_request_span() {
+    let exporter = exporter();
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let observer = Arc::new(TraceIdObserver::default());
+
+    let request = tracing::info_span!("request");
+    async {
+        let session = uow.begin().await.expect("Failed to begin transaction");
+        session
+            .register_transaction_aware(observer.clone())
+            .await
+            .expect("Failed to register observer");
+        session.commit().await.expect("Failed to commit transaction");
+    }
+    .instrument(request.clone())
+    .await;
+    drop(request);
+
+    let spans = exporter.get_finished_spans().expect("Failed to read spans");
+    let request = span(&spans, "request");
+    let trace_id = request.span_context.trace_id();
+    let session = span(&spans, "unit_of_work_session");
+    assert_eq!(session.span_context.trace_id(), trace_id);
+    assert_eq!(session.parent_span_id, request.span_context.span_id());
+    assert_eq!(attribute(&session, "db.system"), Some("postgresql".into()));
+
+    let begin = span(&spans, "uow.begin");
+    assert_eq!(begin.parent_span_id, request.span_context.span_id());
+    assert_eq!(attribute(&begin, "db.operation"), Some("BEGIN".into()));
+
+    let commit = span(&spans, "uow.commit");
+    assert_eq!(commit.span_context.trace_id(), trace_id);
+    assert_eq!(commit.parent_span_id, session.span_context.span_id());
+    assert_eq!(attribute(&commit, "db.system"), Some("postgresql".into()));
+    assert_eq!(attribute(&commit, "db.operation"), Some("COMMIT".into()));
+    assert_eq!(attribute(&commit, "db.statement"), Some("COMMIT".into()));
+
+    assert_eq!(*observer.0.lock(), [Some(trace_id.to_string())]);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_session_joins_the_attached_context_without_a_tracing_span() {
+    let exporter = exporter();
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let observer = Arc::new(TraceIdObserver::default());
+
+    // As if extracted from the `traceparent` header of an incoming request
+    let trace_id = TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").unwrap();
+    let remote_parent = SpanId::from_hex("00f067aa0ba902b7").unwrap();
+    let remote = SpanContext::new(trace_id, remote_parent, TraceFlags::SAMPLED, true, TraceState::default());
+    let context = Context::new().with_remote_span_context(remote);
+
+    let guard = context.attach();
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    drop(guard);
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    assert_eq!(session.span().context().span().span_context().trace_id(), trace_id);
+    session.rollback().await.expect("Failed to roll back transaction");
+
+    let spans = exporter.get_finished_spans().expect("Failed to read spans");
+    let session = span(&spans, "unit_of_work_session");
+    assert_eq!(session.span_context.trace_id(), trace_id);
+    assert_eq!(session.parent_span_id, remote_parent);
+    let rollback = span(&spans, "uow.rollback");
+    assert_eq!(rollback.span_context.trace_id(), trace_id);
+    assert_eq!(rollback.parent_span_id, session.span_context.span_id());
+    assert_eq!(attribute(&rollback, "db.operation"), Some("ROLLBACK".into()));
+    // Only commits are recorded by the observer
+    assert!(observer.0.lock().is_empty());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_trace_id_is_reported_to_observers() {
+    let exporter = exporter();
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let observer = Arc::new(TraceIdObserver::default());
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(observer.clone())
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("Failed to commit transaction");
+
+    // A session begun outside any trace starts its own
+    let spans = exporter.get_finished_spans().expect("Failed to read spans");
+    let session = span(&spans, "unit_of_work_session");
+    assert_eq!(session.parent_span_id, SpanId::INVALID);
+    let trace_id = observer.0.lock()[0].clone().expect("Missing trace id");
+    assert_eq!(trace_id, session.span_context.trace_id().to_string());
+    assert_eq!(trace_id.len(), 32);
+}
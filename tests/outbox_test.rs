@@ -0,0 +1,183 @@
+mod common;
+
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use postgres_unit_of_work::outbox::{enqueue, OUTBOX_MIGRATION};
+use postgres_unit_of_work::{
+    OutboxMessage, OutboxPublisher, OutboxRelay, OutboxRelayConfig, PostgresUnitOfWork, TransactionError,
+    TransactionResult, UnitOfWork, UnitOfWorkSession,
+};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::connect;
+
+async fn setup_outbox() -> PgPool {
+    let pool = connect().await;
+    sqlx::raw_sql(OUTBOX_MIGRATION)
+        .execute(&pool)
+        .await
+        .expect("Failed to create the outbox");
+    sqlx::query("DELETE FROM uow_outbox")
+        .execute(&pool)
+        .await
+        .expect("Failed to clean the outbox");
+    pool
+}
+
+/// Publisher recording every batch it is handed, failing the first `failures`
+#[derive(Default)]
+struct RecordingPublisher {
+    batches: Mutex<Vec<Vec<OutboxMessage>>>,
+    failures: AtomicU32,
+}
+
+impl RecordingPublisher {
+    fn failing(failures: u32) -> Arc<Self> {
+        Arc::new(Self {
+            failures: AtomicU32::new(failures),
+            ..Self::default()
+        })
+    }
+
+    fn published(&self) -> Vec<OutboxMessage> {
+        self.batches.lock().iter().flatten().cloned().collect()
+    }
+
+    /// Wait until `count` messages were handed over, including failed batches
+    async fn wait_for(&self, count: usize) {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while self.published().len() < count {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("The relay should publish the messages");
+    }
+}
+
+#[async_trait]
+impl OutboxPublisher for RecordingPublisher {
+    async fn publish(&self, messages: &[OutboxMessage]) -> TransactionResult<()> {
+        self.batches.lock().push(messages.to_vec());
+        let failures = self.failures.load(Ordering::SeqCst);
+        if failures > 0 {
+            self.failures.store(failures - 1, Ordering::SeqCst);
+            return Err(TransactionError::CommitFailed("broker unavailable".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// Enqueue `(topic, payload)` messages in one committed unit of work
+async fn enqueue_all(uow: &PostgresUnitOfWork, messages: &[(&str, &str)]) -> Vec<i64> {
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let mut ids = Vec::new();
+    for (topic, payload) in messages {
+        let id = enqueue(session.executor(), topic, payload.as_bytes())
+            .await
+            .expect("Failed to enqueue message");
+        ids.push(id);
+    }
+    session.commit().await.expect("Failed to commit transaction");
+    ids
+}
+
+async fn unpublished(pool: &PgPool) -> i64 {
+    sqlx::query_scalar("SELECT count(*) FROM uow_outbox WHERE published_at IS NULL")
+        .fetch_one(pool)
+        .await
+        .expect("Failed to count unpublished messages")
+}
+
+fn config() -> OutboxRelayConfig {
+    OutboxRelayConfig::new()
+        .batch_size(2)
+        .poll_interval(Duration::from_millis(10))
+        .initial_backoff(Duration::from_millis(10))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_relay_publishes_in_batches_and_order() {
+    let pool = setup_outbox().await;
+    let uow = Arc::new(PostgresUnitOfWork::new(Arc::new(pool.clone())));
+    let ids = enqueue_all(
+        &uow,
+        &[("orders", "o1"), ("payments", "p1"), ("orders", "o2"), ("payments", "p2"), ("orders", "o3")],
+    )
+    .await;
+
+    // Rolled back messages are never published
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    enqueue(session.executor(), "orders", b"discarded").await.expect("Failed to enqueue message");
+    session.rollback().await.expect("Failed to rollback transaction");
+
+    let publisher = Arc::new(RecordingPublisher::default());
+    let relay = OutboxRelay::spawn(uow.clone(), publisher.clone(), config());
+    publisher.wait_for(5).await;
+    relay.shutdown().await;
+
+    let sizes: Vec<usize> = publisher.batches.lock().iter().map(Vec::len).collect();
+    assert_eq!(sizes, [2, 2, 1]);
+    let published = publisher.published();
+    assert_eq!(published.iter().map(|message| message.id).collect::<Vec<_>>(), ids);
+    let orders: Vec<&[u8]> = published
+        .iter()
+        .filter(|message| message.topic == "orders")
+        .map(|message| message.payload.as_slice())
+        .collect();
+    assert_eq!(orders, [b"o1", b"o2", b"o3"]);
+    assert_eq!(unpublished(&pool).await, 0);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+#[serial_test::serial]
+async fn test_relay_retries_after_publisher_failure() {
+    let pool = setup_outbox().await;
+    let uow = Arc::new(PostgresUnitOfWork::new(Arc::new(pool.clone())));
+    let ids = enqueue_all(&uow, &[("orders", "o1"), ("orders", "o2")]).await;
+
+    let publisher = RecordingPublisher::failing(1);
+    let relay = OutboxRelay::spawn(uow.clone(), publisher.clone(), config());
+    publisher.wait_for(4).await;
+    relay.shutdown().await;
+
+    // The failed batch stays in the outbox and is handed over again
+    let batches = publisher.batches.lock().clone();
+    assert_eq!(batches.len(), 2);
+    assert_eq!(batches[0], batches[1]);
+    assert_eq!(batches[1].iter().map(|message| message.id).collect::<Vec<_>>(), ids);
+    assert_eq!(unpublished(&pool).await, 0);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[serial_test::serial]
+async fn test_concurrent_relays_do_not_publish_twice() {
+    let pool = setup_outbox().await;
+    let uow = Arc::new(PostgresUnitOfWork::new(Arc::new(pool.clone())));
+    let messages: Vec<(String, String)> = (0..40).map(|index| (format!("topic-{}", index % 3), index.to_string())).collect();
+    let messages: Vec<(&str, &str)> = messages.iter().map(|(topic, payload)| (topic.as_str(), payload.as_str())).collect();
+    let ids = enqueue_all(&uow, &messages).await;
+
+    let publisher = Arc::new(RecordingPublisher::default());
+    let first = OutboxRelay::spawn(uow.clone(), publisher.clone(), config());
+    let second = OutboxRelay::spawn(uow.clone(), publisher.clone(), config());
+    publisher.wait_for(ids.len()).await;
+    first.shutdown().await;
+    second.shutdown().await;
+
+    let published: Vec<i64> = publisher.published().iter().map(|message| message.id).collect();
+    assert_eq!(published.len(), ids.len(), "No message should be published twice");
+    assert_eq!(published.into_iter().collect::<HashSet<_>>(), ids.into_iter().collect::<HashSet<_>>());
+    assert_eq!(unpublished(&pool).await, 0);
+
+    pool.close().await;
+}
\ No newline at end of file
@@ -0,0 +1,118 @@
+mod common;
+
+use parking_lot::Mutex;
+use postgres_unit_of_work::{PoolStats, PostgresUnitOfWork, UnitOfWork, UnitOfWorkSession, UowMetrics};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::get_database_url;
+
+/// Metrics keeping every pool sample
+#[derive(Default)]
+struct PoolSamples(Mutex<Vec<PoolStats>>);
+
+impl UowMetrics for PoolSamples {
+    fn observe_pool(&self, stats: &PoolStats) {
+        self.0.lock().push(*stats);
+    }
+}
+
+async fn connect(max_connections: u32) -> PgPool {
+    PgPoolOptions::new()
+        .max_connections(max_connections)
+        .connect(&get_database_url())
+        .await
+        .expect("Failed to connect to database")
+}
+
+/// The connections in use once those released settle back into the pool,
+/// which sqlx does on a task of its own
+async fn settled_in_use(uow: &PostgresUnitOfWork, expected: u32) -> u32 {
+    for _ in 0..100 {
+        if uow.pool_stats().in_use == expected {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    uow.pool_stats().in_use
+}
+
+#[tokio::test]
+async fn test_pool_stats_follow_open_sessions() {
+    let pool = connect(4).await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let stats = uow.pool_stats();
+    assert_eq!(stats.max, 4);
+    assert_eq!(stats.in_use, 0);
+    assert_eq!(stats.acquire_wait_p50, None);
+    assert_eq!(stats.acquire_wait_p99, None);
+
+    let first = uow.begin().await.expect("Failed to begin transaction");
+    let second = uow.begin().await.expect("Failed to begin transaction");
+    let stats = uow.pool_stats();
+    assert_eq!(stats.in_use, 2);
+    assert_eq!(stats.size, stats.idle + stats.in_use);
+    assert!(stats.size >= 2 && stats.size <= 4, "Unexpected size {}", stats.size);
+    assert!(stats.acquire_wait_p50.is_some());
+    assert!(stats.acquire_wait_p99 >= stats.acquire_wait_p50);
+
+    first.commit().await.expect("Failed to commit transaction");
+    assert_eq!(settled_in_use(&uow, 1).await, 1);
+    second.rollback().await.expect("Failed to rollback transaction");
+    assert_eq!(settled_in_use(&uow, 0).await, 0);
+}
+
+#[tokio::test]
+async fn test_acquire_wait_includes_waiting_for_a_busy_pool() {
+    let pool = connect(1).await;
+    let uow = Arc::new(PostgresUnitOfWork::new(Arc::new(pool.clone())));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let waiting = tokio::spawn({
+        let uow = uow.clone();
+        async move {
+            let session = uow.begin().await.expect("Failed to begin transaction");
+            session.commit().await.expect("Failed to commit transaction");
+        }
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    session.commit().await.expect("Failed to commit transaction");
+    waiting.await.expect("Waiting session panicked");
+
+    let stats = uow.pool_stats();
+    let p99 = stats.acquire_wait_p99.expect("Missing acquire wait");
+    assert!(p99 >= Duration::from_millis(100), "Implausible p99 {p99:?}");
+    assert!(stats.acquire_wait_p50 <= stats.acquire_wait_p99);
+}
+
+#[tokio::test]
+async fn test_sampler_reports_until_shut_down() {
+    let pool = connect(4).await;
+    let samples = Arc::new(PoolSamples::default());
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .metrics(samples.clone())
+        .build();
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let sampler = uow.spawn_pool_sampler(Duration::from_millis(10));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    session.commit().await.expect("Failed to commit transaction");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    sampler.shutdown().await;
+
+    let taken = std::mem::take(&mut *samples.0.lock());
+    assert!(taken.len() >= 5, "Too few samples {taken:?}");
+    assert_eq!(taken[0].in_use, 1);
+    assert_eq!(taken.last().map(|stats| stats.in_use), Some(0));
+    assert!(taken.iter().all(|stats| stats.max == 4));
+
+    // Nothing is sampled once shut down, nor once dropped
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(samples.0.lock().is_empty());
+    drop(uow.spawn_pool_sampler(Duration::from_millis(10)));
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(samples.0.lock().len() <= 1);
+}
@@ -0,0 +1,127 @@
+#![cfg(feature = "prometheus")]
+
+mod common;
+
+use postgres_unit_of_work::{
+    PostgresUnitOfWork, PrometheusUowMetrics, RetryPolicy, TransactionError, UnitOfWork, UnitOfWorkSession,
+};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::connect;
+
+/// The lines of `text` for the series of `metric`, without comments
+fn series<'a>(text: &'a str, metric: &str) -> Vec<&'a str> {
+    text.lines()
+        .filter(|line| !line.starts_with('#') && line.starts_with(metric))
+        .collect()
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_exposition_counts_the_transactions() {
+    let pool = connect().await;
+    let metrics = Arc::new(PrometheusUowMetrics::with_buckets(vec![60.0, 0.0, 60.0]));
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .metrics(metrics.clone())
+        .build();
+
+    // A serialization failure, rolled back and retried, then a commit
+    let attempts = AtomicU32::new(0);
+    let policy = RetryPolicy::new(2).initial_backoff(Duration::from_millis(1));
+    uow.run_with_retry(&policy, |session| {
+        let first = attempts.fetch_add(1, Ordering::SeqCst) == 0;
+        Box::pin(async move {
+            let sql = if first {
+                "DO $$ BEGIN RAISE EXCEPTION 'simulated conflict' USING ERRCODE = 'serialization_failure'; END $$"
+            } else {
+                "SELECT 1"
+            };
+            session.executor().execute(sqlx::query(sql)).await?;
+            Ok::<_, TransactionError>(())
+        })
+    })
+    .await
+    .expect("Retry should succeed");
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    for _ in 0..2 {
+        session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+    }
+    session.commit().await.expect("Failed to commit transaction");
+
+    let open = uow.begin().await.expect("Failed to begin transaction");
+    let text = metrics.render();
+    assert_eq!(
+        series(&text, "uow_transactions_total"),
+        [
+            "uow_transactions_total{outcome=\"committed\"} 2",
+            "uow_transactions_total{outcome=\"rolled_back\"} 1",
+        ]
+    );
+    assert!(text.contains("uow_rollbacks_total{reason=\"requested\"} 1\n"), "Unexpected exposition:\n{text}");
+    assert!(text.contains("uow_rollbacks_total{reason=\"dropped\"} 0\n"), "Unexpected exposition:\n{text}");
+    assert!(text.contains("uow_begin_errors_total{kind=\"shutting_down\"} 0\n"), "Unexpected exposition:\n{text}");
+    assert_eq!(series(&text, "uow_statements_total"), ["uow_statements_total 3"]);
+    assert_eq!(series(&text, "uow_active_sessions"), ["uow_active_sessions 1"]);
+    assert_eq!(
+        series(&text, "uow_retries_total"),
+        [
+            "uow_retries_total{reason=\"serialization_failure\"} 1",
+            "uow_retries_total{reason=\"deadlock\"} 0",
+            "uow_retries_total{reason=\"other\"} 0",
+        ]
+    );
+    assert_eq!(series(&text, "uow_retries_exhausted_total"), ["uow_retries_exhausted_total 0"]);
+    assert_eq!(
+        series(&text, "uow_conflicts_total"),
+        [
+            "uow_conflicts_total{sqlstate=\"40001\"} 1",
+            "uow_conflicts_total{sqlstate=\"40P01\"} 0",
+        ]
+    );
+    assert_eq!(
+        series(&text, "uow_slow_completions_total"),
+        [
+            "uow_slow_completions_total{statement=\"commit\"} 0",
+            "uow_slow_completions_total{statement=\"rollback\"} 0",
+        ]
+    );
+    // The buckets are sorted and deduplicated
+    let histogram = series(&text, "uow_transaction_duration_seconds");
+    assert_eq!(
+        histogram[..3],
+        [
+            "uow_transaction_duration_seconds_bucket{le=\"0\"} 0",
+            "uow_transaction_duration_seconds_bucket{le=\"60\"} 3",
+            "uow_transaction_duration_seconds_bucket{le=\"+Inf\"} 3",
+        ]
+    );
+    assert!(histogram[3].starts_with("uow_transaction_duration_seconds_sum 0."), "Unexpected sum {}", histogram[3]);
+    assert_eq!(histogram[4], "uow_transaction_duration_seconds_count 3");
+    assert!(text.contains("# TYPE uow_transaction_duration_seconds histogram\n"));
+    assert!(text.contains("# TYPE uow_active_sessions gauge\n"));
+
+    open.commit().await.expect("Failed to commit transaction");
+    uow.shutdown(Duration::ZERO).await;
+    let _ = uow.begin().await;
+    let text = metrics.render();
+    assert_eq!(series(&text, "uow_active_sessions"), ["uow_active_sessions 0"]);
+    assert!(text.contains("uow_transactions_total{outcome=\"committed\"} 3\n"), "Unexpected exposition:\n{text}");
+    assert!(text.contains("uow_begin_errors_total{kind=\"shutting_down\"} 1\n"), "Unexpected exposition:\n{text}");
+}
+
+#[test]
+fn test_default_buckets_follow_the_prometheus_client() {
+    let text = PrometheusUowMetrics::new().render();
+    let bounds: Vec<&str> = series(&text, "uow_transaction_duration_seconds_bucket")
+        .iter()
+        .filter_map(|line| line.split('"').nth(1))
+        .collect();
+    assert_eq!(
+        bounds,
+        ["0.005", "0.01", "0.025", "0.05", "0.1", "0.25", "0.5", "1", "2.5", "5", "10", "+Inf"]
+    );
+    assert_eq!(series(&text, "uow_active_sessions"), ["uow_active_sessions 0"]);
+}
\ No newline at end of file
@@ -0,0 +1,350 @@
+mod common;
+
+use futures::FutureExt;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    AsTransactionError, Executor, PgErrorKind, PostgresUnitOfWork, RetryPolicy, TransactionError,
+    TransactionResult, UnitOfWork, UnitOfWorkSession,
+};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::{cleanup_database, connect, setup_database, CallLog, RecordingObserver, User, UserRepository};
+
+/// Raise a serialization failure inside the executor's transaction
+async fn raise_serialization_failure(executor: &Executor) -> TransactionResult<()> {
+    let mut tx_guard = executor.tx.lock().await;
+    let tx = tx_guard.as_mut().ok_or_else(|| executor.completed_error())?;
+    sqlx::query(
+        "DO $$ BEGIN RAISE EXCEPTION 'simulated conflict' USING ERRCODE = 'serialization_failure'; END $$",
+    )
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+/// Application error type wrapping transaction failures
+#[derive(Debug, thiserror::Error)]
+enum AppError {
+    #[error("insufficient funds")]
+    InsufficientFunds,
+
+    #[error(transparent)]
+    Transaction(#[from] TransactionError),
+}
+
+impl AsTransactionError for AppError {
+    fn as_transaction_error(&self) -> Option<&TransactionError> {
+        match self {
+            AppError::Transaction(error) => Some(error),
+            AppError::InsufficientFunds => None,
+        }
+    }
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::new(3).initial_backoff(Duration::from_millis(1))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_retry_succeeds_after_serialization_failure() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let attempts = Arc::new(AtomicU32::new(0));
+
+    let result = uow
+        .run_with_retry(&policy(), |session| {
+            let attempts = attempts.clone();
+            Box::pin(async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    raise_serialization_failure(session.executor()).await?;
+                }
+                Ok::<_, TransactionError>("done")
+            })
+        })
+        .await
+        .expect("Retry should succeed");
+
+    assert_eq!(result, "done");
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_retry_refused_with_non_idempotent_observer() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let attempts = Arc::new(AtomicU32::new(0));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let error = uow
+        .run_with_retry(&policy(), |session| {
+            let attempts = attempts.clone();
+            let log = log.clone();
+            Box::pin(async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                session.register_transaction_aware(RecordingObserver::new("mailer", log)).await?;
+                raise_serialization_failure(session.executor()).await
+            })
+        })
+        .await
+        .expect_err("Retry should be refused");
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::SerializationFailure));
+    match error {
+        TransactionError::RetryUnsafe { observers, .. } => assert_eq!(observers, 1),
+        other => panic!("Expected RetryUnsafe, got {other:?}"),
+    }
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_retry_allowed_with_idempotent_observer() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let attempts = Arc::new(AtomicU32::new(0));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    uow.run_with_retry(&policy(), |session| {
+        let attempts = attempts.clone();
+        let log = log.clone();
+        Box::pin(async move {
+            session.register_transaction_aware(RecordingObserver::idempotent("cache", log)).await?;
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                raise_serialization_failure(session.executor()).await?;
+            }
+            Ok::<_, TransactionError>(())
+        })
+    })
+    .await
+    .expect("Retry should succeed");
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(*log.lock(), vec!["cache:rollback", "cache:commit"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_retry_policy_override_allows_observer_retry() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let attempts = Arc::new(AtomicU32::new(0));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    uow.run_with_retry(&policy().allow_observer_retry(true), |session| {
+        let attempts = attempts.clone();
+        let log = log.clone();
+        Box::pin(async move {
+            session.register_transaction_aware(RecordingObserver::new("mailer", log)).await?;
+            if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                raise_serialization_failure(session.executor()).await?;
+            }
+            Ok::<_, TransactionError>(())
+        })
+    })
+    .await
+    .expect("Retry should succeed");
+
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    assert_eq!(*log.lock(), vec!["mailer:rollback", "mailer:commit"]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_run_rolls_back_on_domain_error() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let user = User::new("broke".to_string(), "broke@example.com".to_string());
+
+    let error = uow
+        .run(|session| {
+            let user = user.clone();
+            Box::pin(async move {
+                UserRepository::new(session.executor().clone()).create(&user).await?;
+                Err::<(), _>(AppError::InsufficientFunds)
+            })
+        })
+        .await
+        .expect_err("Domain error should be returned");
+    assert!(matches!(error, AppError::InsufficientFunds), "Expected InsufficientFunds, got {error:?}");
+
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let found = UserRepository::new(verify_session.executor().clone())
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to query user");
+    assert!(found.is_none(), "User should not exist after the domain error");
+    verify_session.commit().await.expect("Failed to commit verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_retry_with_custom_error_type() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let attempts = Arc::new(AtomicU32::new(0));
+
+    // Serialization failures wrapped in the application error are retried
+    let result = uow
+        .run_with_retry(&policy(), |session| {
+            let attempts = attempts.clone();
+            Box::pin(async move {
+                if attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                    raise_serialization_failure(session.executor()).await?;
+                }
+                Ok::<_, AppError>(42)
+            })
+        })
+        .await
+        .expect("Retry should succeed");
+    assert_eq!(result, 42);
+    assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+    // Domain failures are not retried
+    attempts.store(0, Ordering::SeqCst);
+    let error = uow
+        .run_with_retry(&policy(), |_session| {
+            let attempts = attempts.clone();
+            Box::pin(async move {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err::<(), _>(AppError::InsufficientFunds)
+            })
+        })
+        .await
+        .expect_err("Domain error should be returned");
+    assert!(matches!(error, AppError::InsufficientFunds), "Expected InsufficientFunds, got {error:?}");
+    assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+    pool.close().await;
+}
+
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_run_commits_on_success() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let user = User::new("runner".to_string(), "runner@example.com".to_string());
+
+    let id = uow
+        .run(|session| {
+            let user = user.clone();
+            Box::pin(async move {
+                UserRepository::new(session.executor().clone()).create(&user).await?;
+                Ok::<_, TransactionError>(user.id)
+            })
+        })
+        .await
+        .expect("Work should succeed");
+    assert_eq!(id, user.id);
+
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let found = UserRepository::new(verify_session.executor().clone())
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to query user");
+    assert!(found.is_some(), "User should exist after the commit");
+    verify_session.commit().await.expect("Failed to commit verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_run_rolls_back_when_work_panics() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let user = User::new("panicky".to_string(), "panicky@example.com".to_string());
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let run = uow.run(|session| {
+        let user = user.clone();
+        let log = log.clone();
+        Box::pin(async move {
+            session.register_transaction_aware(RecordingObserver::new("audit", log)).await?;
+            UserRepository::new(session.executor().clone()).create(&user).await?;
+            if user.username == "panicky" {
+                panic!("work panicked");
+            }
+            Ok::<_, TransactionError>(())
+        })
+    });
+    let panic = AssertUnwindSafe(run).catch_unwind().await.expect_err("The panic should be resumed");
+    assert_eq!(panic.downcast_ref::<&str>(), Some(&"work panicked"));
+    assert!(
+        log.lock().contains(&"audit:rollback".to_string()),
+        "Observers should be told of the rollback: {:?}",
+        log.lock()
+    );
+
+    let verify_session = uow.begin().await.expect("Failed to begin verify transaction");
+    let found = UserRepository::new(verify_session.executor().clone())
+        .find_by_id(user.id)
+        .await
+        .expect("Failed to query user");
+    assert!(found.is_none(), "User should not exist after the panic");
+    verify_session.commit().await.expect("Failed to commit verify transaction");
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_run_read_only_reads_but_cannot_write() {
+    let pool = setup_database().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let user = User::new("reader".to_string(), "reader@example.com".to_string());
+    uow.run(|session| {
+        let user = user.clone();
+        Box::pin(async move { UserRepository::new(session.executor().clone()).create(&user).await })
+    })
+    .await
+    .expect("Failed to create user");
+
+    let username = uow
+        .run_read_only(|executor| {
+            Box::pin(async move {
+                let (username,): (String,) = executor
+                    .fetch_one_as(sqlx::query_as("SELECT username FROM users WHERE id = $1").bind(user.id))
+                    .await?;
+                Ok::<_, TransactionError>(username)
+            })
+        })
+        .await
+        .expect("Read should succeed");
+    assert_eq!(username, "reader");
+
+    let error = uow
+        .run_read_only(|executor| {
+            Box::pin(async move {
+                executor
+                    .fetch_one(sqlx::query("DELETE FROM users RETURNING id"))
+                    .await
+                    .map(drop)
+            })
+        })
+        .await
+        .expect_err("Writes should be rejected");
+    assert_eq!(error.pg_kind(), Some(PgErrorKind::Other("25006".to_string())), "Unexpected error {error:?}");
+    assert_eq!(uow.active_count(), 0);
+
+    cleanup_database(&pool).await;
+    pool.close().await;
+}
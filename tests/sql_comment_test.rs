@@ -0,0 +1,172 @@
+mod common;
+
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    PostgresUnitOfWork, QueryHook, SqlCommenter, TransactionError, UnitOfWork, UnitOfWorkSession,
+};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+
+use common::connect;
+
+/// Query hook keeping the SQL of every statement, as sent to the server
+#[derive(Default)]
+struct SqlLog(Mutex<Vec<String>>);
+
+impl QueryHook for SqlLog {
+    fn on_query(&self, sql: &str, _duration: Duration, _result: Result<u64, &TransactionError>) {
+        self.0.lock().push(sql.to_string());
+    }
+}
+
+impl SqlLog {
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+fn unit_of_work(pool: &PgPool, commenter: SqlCommenter) -> (PostgresUnitOfWork, Arc<SqlLog>) {
+    let log = Arc::new(SqlLog::default());
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .query_hook(log.clone())
+        .sql_comments(commenter)
+        .build();
+    (uow, log)
+}
+
+#[tokio::test]
+async fn test_statements_carry_the_session_tags() {
+    let pool = connect().await;
+    let commenter = SqlCommenter::new()
+        .application("checkout api")
+        .metadata_key("route")
+        .traceparent(false);
+    let (uow, log) = unit_of_work(&pool, commenter);
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+    session.set_label("place_order");
+    session.set_metadata("route", "/orders/{id}");
+    session.set_metadata("user", "alice@example.com");
+    let (value,): (i32,) = session
+        .executor()
+        .fetch_one_as(sqlx::query_as("SELECT $1::int").bind(7))
+        .await
+        .expect("Failed to fetch value");
+    assert_eq!(value, 7);
+    let row = session
+        .executor()
+        .fetch_one(sqlx::query("SELECT 2;"))
+        .await
+        .expect("Failed to fetch row");
+    assert_eq!(row.get::<i32, _>(0), 2);
+    session.commit().await.expect("Failed to commit transaction");
+
+    let tags = "application='checkout%20api',controller='place_order',route='%2Forders%2F%7Bid%7D'";
+    assert_eq!(
+        log.take(),
+        [
+            "SELECT 1 /*application='checkout%20api'*/".to_string(),
+            format!("SELECT $1::int /*{tags}*/"),
+            format!("SELECT 2 /*{tags}*/;"),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_tag_values_cannot_end_the_comment() {
+    let pool = connect().await;
+    let (uow, log) = unit_of_work(&pool, SqlCommenter::new().traceparent(false));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.set_label("it's done */ DROP TABLE users; --");
+    session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(
+        log.take(),
+        ["SELECT 1 /*controller='it%27s%20done%20%2A%2F%20DROP%20TABLE%20users%3B%20--'*/"]
+    );
+}
+
+#[tokio::test]
+async fn test_statements_can_opt_out() {
+    let pool = connect().await;
+    let (uow, log) = unit_of_work(&pool, SqlCommenter::new().application("billing").traceparent(false));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let executor = session.executor();
+    executor
+        .execute(sqlx::query("SELECT /* hand-written */ 1"))
+        .await
+        .expect("Failed to run statement");
+    executor
+        .without_sql_comments()
+        .execute(sqlx::query("SELECT 2"))
+        .await
+        .expect("Failed to run statement");
+    // Only the returned Executor opts out
+    executor.execute(sqlx::query("SELECT 3")).await.expect("Failed to run statement");
+    session.commit().await.expect("Failed to commit transaction");
+
+    // Executors outside of sessions have no label to tag
+    uow.executor().execute(sqlx::query("SELECT 4")).await.expect("Failed to run statement");
+
+    assert_eq!(
+        log.take(),
+        [
+            "SELECT /* hand-written */ 1",
+            "SELECT 2",
+            "SELECT 3 /*application='billing'*/",
+            "SELECT 4 /*application='billing'*/",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_statements_are_left_alone_without_tags() {
+    let pool = connect().await;
+    let (uow, log) = unit_of_work(&pool, SqlCommenter::new().traceparent(false));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(log.take(), ["SELECT 1"]);
+}
+
+#[cfg(feature = "otel")]
+#[tokio::test]
+async fn test_statements_carry_the_traceparent_of_the_current_span() {
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use tracing::Instrument;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    let provider = SdkTracerProvider::builder().build();
+    let layer = tracing_opentelemetry::layer().with_tracer(provider.tracer("sql_comment_test"));
+    let _subscriber = tracing::subscriber::set_default(tracing_subscriber::registry().with(layer));
+
+    let pool = connect().await;
+    let (uow, log) = unit_of_work(&pool, SqlCommenter::new());
+    let request = tracing::info_span!("request");
+    let span_context = request.context().span().span_context().clone();
+    async {
+        let session = uow.begin().await.expect("Failed to begin transaction");
+        session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+        session.commit().await.expect("Failed to commit transaction");
+    }
+    .instrument(request)
+    .await;
+
+    assert_eq!(
+        log.take(),
+        [format!(
+            "SELECT 1 /*traceparent='00-{}-{}-01'*/",
+            span_context.trace_id(),
+            span_context.span_id()
+        )]
+    );
+}
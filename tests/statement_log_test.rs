@@ -0,0 +1,159 @@
+mod common;
+
+use parking_lot::Mutex;
+use postgres_unit_of_work::{LogLevel, PostgresUnitOfWork, UnitOfWork, UnitOfWorkSession};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::Arc;
+use tracing::field::Field;
+use tracing_core::span;
+use uuid::Uuid;
+
+use common::connect;
+
+/// A statement logged by the unit of work
+#[derive(Debug)]
+struct Logged {
+    level: tracing::Level,
+    fields: BTreeMap<String, String>,
+}
+
+/// Records the statements this crate logs
+#[derive(Clone, Default)]
+struct StatementCollector(Arc<Mutex<Vec<Logged>>>);
+
+impl tracing::Subscriber for StatementCollector {
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        metadata.is_event() && metadata.target().starts_with("postgres_unit_of_work")
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut |field: &Field, value: &dyn fmt::Debug| {
+            fields.insert(field.name().to_string(), format!("{value:?}"));
+        });
+        if fields.get("message").is_some_and(|message| message == "Statement") {
+            self.0.lock().push(Logged {
+                level: *event.metadata().level(),
+                fields,
+            });
+        }
+    }
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+impl StatementCollector {
+    fn take(&self) -> Vec<Logged> {
+        std::mem::take(&mut *self.0.lock())
+    }
+}
+
+const SQL: &str = "SELECT $1::uuid, $2::text, $3::int8, $4::int4, $5::bool";
+
+#[tokio::test]
+async fn test_bind_values_are_redacted_by_default() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .log_statements(LogLevel::Debug)
+        .build();
+    let collector = StatementCollector::default();
+    let _subscriber = tracing::subscriber::set_default(collector.clone());
+
+    let id = Uuid::new_v4();
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .executor()
+        .fetch_one(sqlx::query(SQL).bind(id).bind("alice").bind(42_i64).bind(None::<i32>).bind(true))
+        .await
+        .expect("Failed to run statement");
+    session.commit().await.expect("Failed to commit transaction");
+
+    let logged = collector.take();
+    assert_eq!(logged.len(), 1, "Unexpected statements {logged:?}");
+    assert_eq!(logged[0].level, tracing::Level::DEBUG);
+    assert_eq!(logged[0].fields["sql"], SQL);
+    assert_eq!(
+        logged[0].fields["parameters"],
+        "$1=<redacted:uuid>, $2=<redacted:text>, $3=<redacted:int8>, $4=<redacted:int4>, $5=<redacted:bool>"
+    );
+    assert_eq!(logged[0].fields["rows"], "1");
+    let everything = format!("{logged:?}");
+    assert!(!everything.contains(&id.to_string()), "Bind value logged in {everything}");
+    assert!(!everything.contains("alice"), "Bind value logged in {everything}");
+}
+
+#[tokio::test]
+async fn test_bind_values_are_logged_when_allowed() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .log_statements(LogLevel::Info)
+        .log_bind_values(true)
+        .build();
+    let collector = StatementCollector::default();
+    let _subscriber = tracing::subscriber::set_default(collector.clone());
+
+    let id = Uuid::new_v4();
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .executor()
+        .fetch_one(sqlx::query(SQL).bind(id).bind("it's").bind(42_i64).bind(None::<i32>).bind(true))
+        .await
+        .expect("Failed to run statement");
+    let error = session
+        .executor()
+        .execute(sqlx::query("SELECT 1 / $1").bind(0))
+        .await
+        .expect_err("Division by zero succeeded");
+    session.rollback().await.expect("Failed to roll back transaction");
+
+    let logged = collector.take();
+    assert_eq!(logged.len(), 2, "Unexpected statements {logged:?}");
+    assert_eq!(logged[0].level, tracing::Level::INFO);
+    assert_eq!(
+        logged[0].fields["parameters"],
+        format!("$1='{id}', $2='it''s', $3=42, $4=NULL, $5=true")
+    );
+    assert_eq!(logged[1].fields["parameters"], "$1=0");
+    assert_eq!(logged[1].fields["error"], error.to_string());
+    assert!(!logged[1].fields.contains_key("rows"));
+}
+
+#[tokio::test]
+async fn test_long_statements_are_truncated() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::builder(Arc::new(pool.clone()))
+        .log_statements(LogLevel::Info)
+        .log_bind_values(true)
+        .build();
+    let collector = StatementCollector::default();
+    let _subscriber = tracing::subscriber::set_default(collector.clone());
+
+    let sql = format!("SELECT '{}', $1::text", "x".repeat(4000));
+    let value = "y".repeat(1000);
+    uow.executor()
+        .fetch_one(sqlx::query(&sql).bind(&value))
+        .await
+        .expect("Failed to run statement");
+
+    let logged = collector.take();
+    assert_eq!(logged.len(), 1, "Unexpected statements {logged:?}");
+    assert_eq!(
+        logged[0].fields["sql"],
+        format!("{}... ({} bytes)", &sql[..2048], sql.len())
+    );
+    assert_eq!(
+        logged[0].fields["parameters"],
+        format!("$1='{}... (1000 bytes)'", "y".repeat(128))
+    );
+}
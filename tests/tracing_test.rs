@@ -0,0 +1,281 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use parking_lot::Mutex;
+use postgres_unit_of_work::{IsolationLevel, PostgresUnitOfWork, TransactionOptions, UnitOfWork, UnitOfWorkSession};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread::ThreadId;
+use std::time::Duration;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing_core::span::Current;
+use tracing::{Event, Instrument, Metadata, Subscriber};
+
+use common::{connect, CallLog, RecordingObserver};
+
+/// A span seen by the capturing subscriber
+#[derive(Clone, Debug)]
+struct CapturedSpan {
+    name: &'static str,
+    metadata: &'static Metadata<'static>,
+    parent: Option<u64>,
+    fields: HashMap<&'static str, String>,
+}
+
+/// An event seen by the capturing subscriber, with its message among the fields
+#[derive(Clone, Debug)]
+struct CapturedEvent {
+    fields: HashMap<&'static str, String>,
+}
+
+#[derive(Default)]
+struct Captured {
+    spans: HashMap<u64, CapturedSpan>,
+    events: Vec<CapturedEvent>,
+    /// Spans entered on each thread, innermost last
+    entered: HashMap<ThreadId, Vec<u64>>,
+}
+
+/// Subscriber keeping every span and event, installed globally so the
+/// sessions' spawned tasks report to it too
+#[derive(Clone, Default)]
+struct CapturingSubscriber {
+    next_id: Arc<AtomicU64>,
+    captured: Arc<Mutex<Captured>>,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<&'static str, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0.insert(field.name(), format!("{value:?}"));
+    }
+}
+
+impl Subscriber for CapturingSubscriber {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let mut captured = self.captured.lock();
+        let parent = if attributes.is_contextual() {
+            captured
+                .entered
+                .get(&std::thread::current().id())
+                .and_then(|entered| entered.last().copied())
+        } else {
+            attributes.parent().map(Id::into_u64)
+        };
+        let mut fields = HashMap::new();
+        attributes.record(&mut FieldVisitor(&mut fields));
+        let span = CapturedSpan {
+            name: attributes.metadata().name(),
+            metadata: attributes.metadata(),
+            parent,
+            fields,
+        };
+        captured.spans.insert(id, span);
+        Id::from_u64(id)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        if let Some(span) = self.captured.lock().spans.get_mut(&span.into_u64()) {
+            values.record(&mut FieldVisitor(&mut span.fields));
+        }
+    }
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = HashMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        self.captured.lock().events.push(CapturedEvent { fields });
+    }
+
+    fn enter(&self, span: &Id) {
+        let mut captured = self.captured.lock();
+        captured
+            .entered
+            .entry(std::thread::current().id())
+            .or_default()
+            .push(span.into_u64());
+    }
+
+    fn current_span(&self) -> Current {
+        let captured = self.captured.lock();
+        let current = captured
+            .entered
+            .get(&std::thread::current().id())
+            .and_then(|entered| entered.last())
+            .and_then(|id| Some((*id, captured.spans.get(id)?.metadata)));
+        match current {
+            Some((id, metadata)) => Current::new(Id::from_u64(id), metadata),
+            None => Current::none(),
+        }
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut captured = self.captured.lock();
+        if let Some(entered) = captured.entered.get_mut(&std::thread::current().id()) {
+            if let Some(position) = entered.iter().rposition(|id| *id == span.into_u64()) {
+                entered.remove(position);
+            }
+        }
+    }
+}
+
+static SUBSCRIBER: OnceLock<CapturingSubscriber> = OnceLock::new();
+
+/// The global capturing subscriber, with everything captured so far forgotten
+fn subscriber() -> &'static CapturingSubscriber {
+    let subscriber = SUBSCRIBER.get_or_init(|| {
+        let subscriber = CapturingSubscriber::default();
+        tracing::subscriber::set_global_default(subscriber.clone()).expect("Failed to install subscriber");
+        subscriber
+    });
+    let mut captured = subscriber.captured.lock();
+    captured.spans.clear();
+    captured.events.clear();
+    drop(captured);
+    subscriber
+}
+
+impl CapturingSubscriber {
+    /// The only span named `name` captured so far, with its id
+    fn span(&self, name: &str) -> (u64, CapturedSpan) {
+        let captured = self.captured.lock();
+        let mut spans = captured.spans.iter().filter(|(_, span)| span.name == name);
+        let (id, span) = spans.next().unwrap_or_else(|| panic!("No {name} span was created"));
+        assert!(spans.next().is_none(), "More than one {name} span was created");
+        (*id, span.clone())
+    }
+
+    /// The events whose message is `message`
+    fn events(&self, message: &str) -> Vec<CapturedEvent> {
+        let captured = self.captured.lock();
+        captured
+            .events
+            .iter()
+            .filter(|event| event.fields.get("message").is_some_and(|text| text == message))
+            .cloned()
+            .collect()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_commit_is_traced() {
+    let subscriber = subscriber();
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let id = async {
+        let options = TransactionOptions::new().isolation_level(IsolationLevel::Serializable);
+        let session = uow.begin_with(options).await.expect("Failed to begin transaction");
+        session.set_label("checkout");
+        session
+            .register_transaction_aware(RecordingObserver::new("mailer", log.clone()))
+            .await
+            .expect("Failed to register observer");
+        for _ in 0..2 {
+            session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+        }
+        let id = session.id();
+        session.commit().await.expect("Failed to commit transaction");
+        id
+    }
+    .instrument(tracing::info_span!("request"))
+    .await;
+
+    // The session belongs to the caller's span, next to the begin span
+    let (request, _) = subscriber.span("request");
+    let (_, begin) = subscriber.span("uow.begin");
+    assert_eq!(begin.parent, Some(request));
+    assert_eq!(begin.fields["session_id"], id.to_string());
+    assert_eq!(begin.fields["isolation_level"], "SERIALIZABLE");
+    let (session, session_span) = subscriber.span("unit_of_work_session");
+    assert_eq!(session_span.parent, Some(request));
+
+    let (_, commit) = subscriber.span("uow.commit");
+    assert_eq!(commit.parent, Some(session));
+    assert_eq!(commit.fields["session_id"], id.to_string());
+    assert_eq!(commit.fields["label"], "checkout");
+    assert_eq!(commit.fields["isolation_level"], "SERIALIZABLE");
+    assert_eq!(commit.fields["outcome"], "Committed");
+    assert_eq!(commit.fields["statements"], "2");
+    assert!(commit.fields.contains_key("duration"), "Missing duration in {commit:?}");
+
+    let notified = subscriber.events("Notified transaction observer");
+    assert_eq!(notified.len(), 1, "Unexpected events {notified:?}");
+    assert_eq!(notified[0].fields["notification"], "commit");
+    assert_eq!(notified[0].fields["session_id"], id.to_string());
+    assert!(notified[0].fields["observer"].contains("RecordingObserver"));
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_is_traced() {
+    let subscriber = subscriber();
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let log: CallLog = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(RecordingObserver::failing("mailer", log.clone()))
+        .await
+        .expect("Failed to register observer");
+    session.executor().execute(sqlx::query("SELECT 1")).await.expect("Failed to run statement");
+    let id = session.id();
+    session.rollback().await.expect_err("The observer fails");
+
+    let (session, _) = subscriber.span("unit_of_work_session");
+    let (_, rollback) = subscriber.span("uow.rollback");
+    assert_eq!(rollback.parent, Some(session));
+    assert_eq!(rollback.fields["session_id"], id.to_string());
+    assert_eq!(rollback.fields["outcome"], "RolledBack");
+    assert_eq!(rollback.fields["statements"], "1");
+    assert!(!rollback.fields.contains_key("label"));
+    assert!(!rollback.fields.contains_key("isolation_level"));
+
+    let failed = subscriber.events("Transaction observer failed");
+    assert_eq!(failed.len(), 1, "Unexpected events {failed:?}");
+    assert_eq!(failed[0].fields["notification"], "rollback");
+    assert!(failed[0].fields.contains_key("error"));
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_of_a_dropped_session_is_traced() {
+    let subscriber = subscriber();
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let id = session.id();
+    drop(session);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let (session, _) = subscriber.span("unit_of_work_session");
+    let (_, rollback) = subscriber.span("uow.rollback");
+    assert_eq!(rollback.parent, Some(session));
+    assert_eq!(rollback.fields["session_id"], id.to_string());
+    assert_eq!(rollback.fields["outcome"], "RolledBack");
+
+    pool.close().await;
+}
\ No newline at end of file
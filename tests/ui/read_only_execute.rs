@@ -0,0 +1,8 @@
+use postgres_unit_of_work::Executor;
+
+async fn report(executor: &Executor) {
+    let reader = executor.read_only();
+    let _ = reader.execute(sqlx::query("DELETE FROM users")).await;
+}
+
+fn main() {}
\ No newline at end of file
@@ -0,0 +1,208 @@
+#![cfg(feature = "http")]
+
+mod common;
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::post;
+use axum::Router;
+use parking_lot::Mutex;
+use postgres_unit_of_work::{
+    FailedWebhook, PostgresUnitOfWork, RetryPolicy, TransactionError, UnitOfWork, UnitOfWorkSession, WebhookObserver,
+};
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use common::connect;
+
+/// A request received by the test server: its content type and body
+type Received = (Option<String>, String);
+
+#[derive(Default)]
+struct Script {
+    received: Mutex<Vec<Received>>,
+    statuses: Mutex<VecDeque<u16>>,
+}
+
+/// Local HTTP server recording every webhook, answering with scripted
+/// statuses and 200 once they run out
+struct TestServer {
+    url: String,
+    script: Arc<Script>,
+    task: JoinHandle<()>,
+}
+
+impl TestServer {
+    async fn answering(statuses: impl IntoIterator<Item = u16>) -> Self {
+        let script = Arc::new(Script {
+            statuses: Mutex::new(statuses.into_iter().collect()),
+            ..Script::default()
+        });
+        let app = Router::new().route("/orders", post(receive)).with_state(script.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("Failed to bind test server");
+        let address = listener.local_addr().expect("Failed to read test server address");
+        let task = tokio::spawn(async move {
+            axum::serve(listener, app).await.expect("Test server failed");
+        });
+        Self {
+            url: format!("http://{address}/orders"),
+            script,
+            task,
+        }
+    }
+
+    fn received(&self) -> Vec<Received> {
+        self.script.received.lock().clone()
+    }
+
+    fn bodies(&self) -> Vec<String> {
+        self.received().into_iter().map(|(_, body)| body).collect()
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn receive(State(script): State<Arc<Script>>, headers: HeaderMap, body: String) -> StatusCode {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    script.received.lock().push((content_type, body));
+    let status = script.statuses.lock().pop_front().unwrap_or(200);
+    StatusCode::from_u16(status).expect("Invalid scripted status")
+}
+
+fn observer(server: &TestServer) -> WebhookObserver {
+    WebhookObserver::new(Arc::new(reqwest::Client::new()), &server.url, |context| {
+        format!(r#"{{"session":"{}"}}"#, context.session_id)
+    })
+    .retry_policy(RetryPolicy::new(3).initial_backoff(Duration::from_millis(1)))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_webhook_is_sent_after_commit() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let server = TestServer::answering([]).await;
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let session_id = session.id();
+    session
+        .register_transaction_aware(Arc::new(observer(&server)))
+        .await
+        .expect("Failed to register observer");
+    assert!(server.received().is_empty(), "Nothing is sent before the commit");
+    session.commit().await.expect("Failed to commit transaction");
+
+    assert_eq!(
+        server.received(),
+        [(Some("application/json".to_string()), format!(r#"{{"session":"{session_id}"}}"#))]
+    );
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_webhook_retries_server_errors() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let server = TestServer::answering([500, 500, 204]).await;
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(Arc::new(observer(&server)))
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect("The webhook should be delivered on the third attempt");
+
+    let bodies = server.bodies();
+    assert_eq!(bodies.len(), 3);
+    assert!(bodies.iter().all(|body| body == &bodies[0]), "Every attempt sends the same body");
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_undeliverable_webhook_is_dead_lettered() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let server = TestServer::answering([503, 503, 503]).await;
+    let dead_letters: Arc<Mutex<Vec<FailedWebhook>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    let recorded = dead_letters.clone();
+    let observer = observer(&server).on_dead_letter(move |failed| recorded.lock().push(failed.clone()));
+    session
+        .register_transaction_aware(Arc::new(observer))
+        .await
+        .expect("Failed to register observer");
+    let error = session.commit().await.expect_err("The webhook failure should be reported");
+
+    match error {
+        TransactionError::ObserverErrors(errors) => match errors.as_slice() {
+            [TransactionError::ObserverFailed { observer, source }] => {
+                assert_eq!(observer, &format!("webhook {}", server.url));
+                assert!(
+                    matches!(source.as_ref(), TransactionError::WebhookFailed { attempts: 3, reason, .. } if reason == "status 503"),
+                    "Unexpected error {source:?}"
+                );
+            }
+            other => panic!("Expected one ObserverFailed, got {other:?}"),
+        },
+        other => panic!("Expected ObserverErrors, got {other:?}"),
+    }
+    let dead_letters = dead_letters.lock().clone();
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].url, server.url);
+    assert_eq!(dead_letters[0].attempts, 3);
+    assert_eq!(dead_letters[0].body, server.bodies()[0]);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_client_errors_are_not_retried() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let server = TestServer::answering([400]).await;
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(Arc::new(observer(&server)))
+        .await
+        .expect("Failed to register observer");
+    session.commit().await.expect_err("The rejected webhook should be reported");
+    assert_eq!(server.bodies().len(), 1);
+
+    pool.close().await;
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+#[serial_test::serial]
+async fn test_rollback_sends_no_webhook() {
+    let pool = connect().await;
+    let uow = PostgresUnitOfWork::new(Arc::new(pool.clone()));
+    let server = TestServer::answering([]).await;
+
+    let session = uow.begin().await.expect("Failed to begin transaction");
+    session
+        .register_transaction_aware(Arc::new(observer(&server)))
+        .await
+        .expect("Failed to register observer");
+    session.rollback().await.expect("Failed to rollback transaction");
+    assert!(server.received().is_empty());
+
+    pool.close().await;
+}